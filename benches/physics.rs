@@ -0,0 +1,57 @@
+use std::hint::black_box;
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use macroquad::prelude::Vec2;
+
+use jetman::level::Level;
+use jetman::physics::{Body, resolve_link};
+use jetman::terrain::check_collision;
+use jetman::ui::InputState;
+use jetman::world::World;
+
+/// Step a world crowded with items tumbling onto long, rough ground
+fn step(c: &mut Criterion) {
+    let mut world = World::from_level(Level::stress(0, 200, 2000));
+    let input = InputState::default();
+    c.bench_function("step crowded world", |b| {
+        b.iter(|| world.step(black_box(&input), World::FIXED_STEP))
+    });
+}
+
+/// Collide a body falling into ground of thousands of segments
+fn collision(c: &mut Criterion) {
+    let level = Level::stress(0, 0, 2000);
+    let ground = &level.terrain[0];
+    let position = Vec2::new(8000.0, 470.0);
+    c.bench_function("collide with rough ground", |b| {
+        b.iter_batched_ref(
+            || {
+                let mut body = Body::new(position, 1.0);
+                body.velocity = Vec2::new(0.5, 2.0);
+                body
+            },
+            |body| check_collision(body, black_box(ground)),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+/// Keep two bodies pulling apart at the length of a link
+fn link(c: &mut Criterion) {
+    c.bench_function("resolve link", |b| {
+        b.iter_batched_ref(
+            || {
+                let mut pod = Body::new(Vec2::ZERO, 1.0);
+                let mut item = Body::new(Vec2::new(120.0, 40.0), 2.0);
+                pod.velocity = Vec2::new(-1.0, 0.5);
+                item.velocity = Vec2::new(1.0, 1.0);
+                (pod, item)
+            },
+            |(pod, item)| resolve_link(pod, item, black_box(100.0)),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, step, collision, link);
+criterion_main!(benches);
@@ -0,0 +1,60 @@
+/* C interface to headless Jetman simulations, implemented in src/ffi.rs */
+#ifndef JETMAN_H
+#define JETMAN_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/* the number of values in an observation */
+#define JETMAN_OBSERVATION_SIZE 30
+
+typedef struct JetmanSimulation JetmanSimulation;
+
+/* what the jet pod is told to do during a frame */
+typedef struct {
+    bool thrust;
+    bool turn_left;
+    bool turn_right;
+    bool sever;
+    bool reel_in;
+    bool reel_out;
+    bool fire;
+} JetmanControls;
+
+/* how much reward the agent gets for what happens, negative for penalties */
+typedef struct {
+    float delivery;   /* for each cargo item delivered */
+    float defusal;    /* for each bomb disposed of */
+    float detonation; /* for each bomb going off */
+    float fuel;       /* for each unit of fuel burnt */
+    float crash;      /* for each pod lost */
+    float completion; /* for completing the objectives */
+    float time;       /* for each second passing */
+} JetmanRewards;
+
+/* what a step led to */
+typedef struct {
+    float reward;
+    bool done;
+} JetmanOutcome;
+
+/* start simulating a level file, or get null if it can't be loaded */
+JetmanSimulation *jetman_simulation_new(const char *path, uint64_t seed);
+/* stop a simulation and free it */
+void jetman_simulation_free(JetmanSimulation *simulation);
+/* start the level over with a new seed */
+void jetman_simulation_reset(JetmanSimulation *simulation, uint64_t seed);
+/* reward the agent as given from now on */
+void jetman_simulation_set_rewards(JetmanSimulation *simulation, JetmanRewards rewards);
+/* advance by one frame at 60 frames per second */
+JetmanOutcome jetman_simulation_step(JetmanSimulation *simulation, JetmanControls controls);
+/* write up to length values of the world's state, returning the size of a full observation:
+ * position (2), velocity (2), heading, fuel, health, towing, tractor beam length,
+ * terrain distances along 8 rays clockwise from ahead as fractions of 400,
+ * the way to the nearest item, goal, disposal and waypoint (2 each),
+ * items delivered, bombs defused, lives, objectives complete and time */
+size_t jetman_simulation_observe(const JetmanSimulation *simulation, float *observation,
+                                 size_t length);
+
+#endif
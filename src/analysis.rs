@@ -0,0 +1,217 @@
+use std::fs;
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+use macroquad::prelude::*;
+
+use crate::headless::Report;
+use crate::level::Level;
+
+/// Figures summing up many runs of a level
+#[derive(Clone, PartialEq, Debug)]
+pub struct Statistics {
+    pub runs: usize,
+    /// The number of runs completing the objectives
+    pub completed: usize,
+    /// The average time in seconds of the completed runs
+    pub average_time: Option<f32>,
+    /// The fastest time in seconds of the completed runs
+    pub best_time: Option<f32>,
+    /// The average number of pods lost per run
+    pub average_deaths: f32,
+    /// The average reward collected per run
+    pub average_reward: f32,
+}
+
+impl Statistics {
+    /// Sum up the reports of a batch of runs
+    pub fn new(reports: &[Report]) -> Self {
+        let runs = reports.len();
+        let per_run = |total: f32| if runs == 0 { 0.0 } else { total / runs as f32 };
+        let times = reports
+            .iter()
+            .filter(|report| report.complete)
+            .map(|report| report.time)
+            .collect::<Vec<_>>();
+        Statistics {
+            runs,
+            completed: times.len(),
+            average_time: (!times.is_empty())
+                .then(|| times.iter().sum::<f32>() / times.len() as f32),
+            best_time: times.iter().copied().min_by(f32::total_cmp),
+            average_deaths: per_run(reports.iter().map(|report| report.deaths as f32).sum()),
+            average_reward: per_run(reports.iter().map(|report| report.reward).sum()),
+        }
+    }
+
+    /// The fraction of runs completing the objectives
+    pub fn completion_rate(&self) -> f32 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.completed as f32 / self.runs as f32
+        }
+    }
+}
+
+/// Counts of how often something happened in each part of a level, like pods crashing
+pub struct Heatmap {
+    /// The part of the level covered
+    bounds: Rect,
+    /// The size of each cell of the map in the world
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    counts: Vec<u32>,
+}
+
+impl Heatmap {
+    /// The size of the cells the analyzer counts crashes in
+    pub const CELL_SIZE: f32 = 16.0;
+
+    /// An empty heatmap covering a level's terrain and spawn point,
+    /// in cells of the given size
+    pub fn new(level: &Level, cell_size: f32) -> Self {
+        let points = level
+            .terrain
+            .iter()
+            .flat_map(|terrain| terrain.outline())
+            .flat_map(|(a, b)| [a, b]);
+        Self::covering(level.spawn, points, cell_size)
+    }
+
+    /// A heatmap of the given positions, covering just them, in cells of the given size
+    pub fn of(positions: &[Vec2], cell_size: f32) -> Self {
+        let first = positions.first().copied().unwrap_or_default();
+        let mut heatmap = Self::covering(first, positions.iter().copied(), cell_size);
+        for &position in positions {
+            heatmap.add(position);
+        }
+        heatmap
+    }
+
+    /// An empty heatmap covering a point and all the others
+    fn covering(point: Vec2, others: impl Iterator<Item = Vec2>, cell_size: f32) -> Self {
+        let (min, max) = others.fold((point, point), |(min, max), point| {
+            (min.min(point), max.max(point))
+        });
+        let bounds = Rect::new(min.x, min.y, max.x - min.x, max.y - min.y);
+        let columns = (bounds.w / cell_size).ceil().max(1.0) as usize;
+        let rows = (bounds.h / cell_size).ceil().max(1.0) as usize;
+        Heatmap {
+            bounds,
+            cell_size,
+            columns,
+            rows,
+            counts: vec![0; columns * rows],
+        }
+    }
+
+    /// Count something happening at a position in the level.
+    /// Positions outside the map count towards the nearest cell.
+    pub fn add(&mut self, position: Vec2) {
+        let cell = (position - self.bounds.point()) / self.cell_size;
+        let column = (cell.x.max(0.0) as usize).min(self.columns - 1);
+        let row = (cell.y.max(0.0) as usize).min(self.rows - 1);
+        self.counts[row * self.columns + column] += 1;
+    }
+
+    /// The highest count of any cell
+    pub fn peak(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    /// The center of the cell with the highest count and that count,
+    /// or `None` if nothing has been counted
+    pub fn hotspot(&self) -> Option<(Vec2, u32)> {
+        let (index, &count) = self
+            .counts
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, count)| **count)
+            .filter(|(_, count)| **count > 0)?;
+        let cell = vec2((index % self.columns) as f32, (index / self.columns) as f32);
+        Some((self.bounds.point() + (cell + 0.5) * self.cell_size, count))
+    }
+
+    /// Draw the heatmap over the level in world coordinates, the cells shaded
+    /// by their count from faint red to bright yellow
+    pub fn draw(&self) {
+        let peak = self.peak().max(1) as f32;
+        for (index, &count) in self.counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let heat = count as f32 / peak;
+            let cell = vec2((index % self.columns) as f32, (index / self.columns) as f32);
+            let corner = self.bounds.point() + cell * self.cell_size;
+            draw_rectangle(
+                corner.x,
+                corner.y,
+                self.cell_size,
+                self.cell_size,
+                Color::new(1.0, heat, 0.25 * heat, 0.2 + 0.5 * heat),
+            );
+        }
+    }
+
+    /// Save the heatmap as a PNG image, one pixel per cell, with the level's terrain
+    /// outlined in gray and the counts shaded from dark red to bright yellow
+    pub fn save(&self, path: impl AsRef<Path>, level: &Level) -> Result<(), String> {
+        let mut image =
+            RgbaImage::from_pixel(self.columns as u32, self.rows as u32, Rgba([0, 0, 0, 255]));
+        for (a, b) in level.terrain.iter().flat_map(|terrain| terrain.outline()) {
+            let steps = ((b - a).length() / self.cell_size * 2.0).ceil().max(1.0) as usize;
+            for step in 0..=steps {
+                let point = a.lerp(b, step as f32 / steps as f32);
+                let cell = (point - self.bounds.point()) / self.cell_size;
+                let (x, y) = (cell.x as u32, cell.y as u32);
+                if x < image.width() && y < image.height() {
+                    image.put_pixel(x, y, Rgba([90, 90, 90, 255]));
+                }
+            }
+        }
+        let peak = self.peak().max(1) as f32;
+        for (index, &count) in self.counts.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            let heat = count as f32 / peak;
+            let pixel = Rgba([
+                (128.0 + 127.0 * heat) as u8,
+                (255.0 * heat) as u8,
+                (64.0 * heat) as u8,
+                255,
+            ]);
+            image.put_pixel(
+                (index % self.columns) as u32,
+                (index / self.columns) as u32,
+                pixel,
+            );
+        }
+        image.save(path).map_err(|error| error.to_string())
+    }
+}
+
+/// Read the crash positions saved by the level analyzer, one `x,y` line each
+pub fn load_crashes(path: impl AsRef<Path>) -> Result<Vec<Vec2>, String> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)
+        .map_err(|error| format!("can't read {}: {error}", path.display()))?;
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let (x, y) = line
+                .split_once(',')
+                .ok_or(format!("line {}: expected 'x,y'", index + 1))?;
+            let number = |text: &str| {
+                text.trim()
+                    .parse::<f32>()
+                    .map_err(|_| format!("line {}: '{text}' is not a number", index + 1))
+            };
+            Ok(vec2(number(x)?, number(y)?))
+        })
+        .collect()
+}
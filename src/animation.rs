@@ -0,0 +1,119 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::assets;
+
+/// A sequence of frames laid out side by side in a sprite sheet
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Animation {
+    /// The name of the sprite sheet
+    pub sheet: &'static str,
+    /// The number of frames in the sheet
+    pub frames: usize,
+    /// The frames shown per second
+    pub fps: f32,
+    /// Whether the animation starts over once it's done, or stops on its last frame
+    pub looping: bool,
+}
+
+impl Animation {
+    /// The length of one pass through the frames, in seconds
+    pub fn duration(&self) -> f32 {
+        self.frames as f32 / self.fps
+    }
+
+    /// The frame to show after the given time in seconds
+    pub fn frame(&self, time: f32) -> usize {
+        let frame = (time.max(0.0) * self.fps) as usize;
+        if self.looping {
+            frame % self.frames
+        } else {
+            frame.min(self.frames - 1)
+        }
+    }
+
+    /// Draw the frame shown after the given time, centered on a position, scaled to the
+    /// given size and rotated clockwise by the given angle in radians.
+    /// Returns false without drawing anything if the sprite sheet is missing.
+    pub fn draw(&self, time: f32, center: Vec2, size: Vec2, rotation: f32) -> bool {
+        let Some(sheet) = assets::texture(self.sheet) else {
+            return false;
+        };
+        let width = sheet.width() / self.frames as f32;
+        let source = Rect::new(width * self.frame(time) as f32, 0.0, width, sheet.height());
+        draw_texture_ex(
+            sheet,
+            center.x - size.x / 2.0,
+            center.y - size.y / 2.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(size),
+                source: Some(source),
+                rotation,
+                ..Default::default()
+            },
+        );
+        true
+    }
+}
+
+/// The animations of the game
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Clip {
+    /// The glow of a goal teleporter pulsing
+    GoalPulse,
+    /// The glow of a disposal teleporter pulsing
+    DisposalPulse,
+    /// The flickering flame of Jetman's thruster
+    Thrust,
+    /// The spinning blades of a drone
+    Drone,
+    /// A bomb going off
+    Explosion,
+}
+
+impl Clip {
+    /// The animation played for the clip
+    pub fn animation(&self) -> Animation {
+        let (sheet, frames, fps, looping) = match self {
+            Clip::GoalPulse => ("goal_pulse", 4, 6.0, true),
+            Clip::DisposalPulse => ("disposal_pulse", 4, 6.0, true),
+            Clip::Thrust => ("thrust", 3, 15.0, true),
+            Clip::Drone => ("drone", 4, 12.0, true),
+            Clip::Explosion => ("explosion", 6, 10.0, false),
+        };
+        Animation {
+            sheet,
+            frames,
+            fps,
+            looping,
+        }
+    }
+}
+
+/// The component of an entity playing an animation
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Animator {
+    pub clip: Clip,
+    /// The time in seconds the animation has been playing
+    pub time: f32,
+}
+
+impl Animator {
+    /// Start playing a clip from its first frame
+    pub fn new(clip: Clip) -> Self {
+        Animator { clip, time: 0.0 }
+    }
+
+    /// Advance the animation
+    pub fn update(&mut self, seconds: f32) {
+        self.time += seconds;
+    }
+
+    /// Draw the current frame, returning false if the sprite sheet is missing
+    pub fn draw(&self, center: Vec2, size: Vec2, rotation: f32) -> bool {
+        self.clip
+            .animation()
+            .draw(self.time, center, size, rotation)
+    }
+}
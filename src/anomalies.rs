@@ -0,0 +1,139 @@
+use std::f32::consts::TAU;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The kinds of places where the laws of physics don't quite hold
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum AnomalyKind {
+    /// Pulls every body in, the harder the closer it gets, and swallows
+    /// whatever crosses its event horizon
+    BlackHole,
+    /// A bubble inside which there is no gravity
+    AntiGravity,
+}
+
+/// The component of an entity that bends the physics around it
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub position: Vec2,
+    /// The radius of a black hole's event horizon, or of an anti-grav bubble
+    pub radius: f32,
+    /// How hard a black hole pulls: the acceleration it causes at a distance of 1
+    pub pull: f32,
+    /// The angle the swirl around the anomaly has turned to
+    pub spin: f32,
+}
+
+impl Anomaly {
+    /// How hard a black hole pulls unless the level says otherwise
+    pub const PULL: f32 = 200.0;
+    /// How fast the swirl around an anomaly turns, in radians per update
+    const SPIN: f32 = 0.02;
+
+    /// Create a black hole with an event horizon of the given radius
+    pub fn black_hole(position: Vec2, radius: f32, pull: f32) -> Self {
+        Anomaly {
+            kind: AnomalyKind::BlackHole,
+            position,
+            radius,
+            pull,
+            spin: 0.0,
+        }
+    }
+
+    /// Create an anti-grav bubble of the given radius
+    pub fn anti_gravity(position: Vec2, radius: f32) -> Self {
+        Anomaly {
+            kind: AnomalyKind::AntiGravity,
+            position,
+            radius,
+            pull: 0.0,
+            spin: 0.0,
+        }
+    }
+
+    /// The acceleration with which a black hole pulls in a body at the given position,
+    /// falling off with the square of the distance
+    pub fn pull(&self, position: Vec2) -> Vec2 {
+        if self.kind != AnomalyKind::BlackHole {
+            return Vec2::ZERO;
+        }
+        let delta = self.position - position;
+        // no stronger than at the horizon, so nothing gets flung about by it
+        let distance = delta.length().max(self.radius);
+        delta.normalize_or_zero() * self.pull / (distance * distance)
+    }
+
+    /// Check whether a body at the given position floats inside an anti-grav bubble
+    pub fn lifts(&self, position: Vec2) -> bool {
+        self.kind == AnomalyKind::AntiGravity && (self.position - position).length() < self.radius
+    }
+
+    /// Check whether a body at the given position has crossed a black hole's event horizon
+    pub fn swallows(&self, position: Vec2) -> bool {
+        self.kind == AnomalyKind::BlackHole && (self.position - position).length() < self.radius
+    }
+
+    /// Turn the swirl around the anomaly
+    pub fn update(&mut self, dt: f32) {
+        let direction = match self.kind {
+            AnomalyKind::BlackHole => 1.0,
+            AnomalyKind::AntiGravity => -0.5,
+        };
+        self.spin = (self.spin + Self::SPIN * direction * dt).rem_euclid(TAU);
+    }
+
+    /// Draw a black hole as a dark disc inside a swirling ring of light bent around it,
+    /// or an anti-grav bubble as a shimmering sphere with motes floating up inside it
+    pub fn draw(&self) {
+        let Vec2 { x, y } = self.position;
+        match self.kind {
+            AnomalyKind::BlackHole => {
+                for ring in (1..=4).rev() {
+                    let radius = self.radius * (1.0 + ring as f32 * 0.5);
+                    let alpha = 0.25 - ring as f32 * 0.05;
+                    draw_circle(x, y, radius, Color::new(0.5, 0.2, 0.8, alpha));
+                }
+                for arm in 0..3 {
+                    let start = self.spin + arm as f32 * TAU / 3.0;
+                    let mut from = self.position + Vec2::from_angle(start) * self.radius * 2.5;
+                    for step in 1..=8 {
+                        let along = step as f32 / 8.0;
+                        let angle = start + along * 2.0;
+                        let distance = self.radius * (2.5 - along * 1.4);
+                        let to = self.position + Vec2::from_angle(angle) * distance;
+                        draw_line(
+                            from.x,
+                            from.y,
+                            to.x,
+                            to.y,
+                            2.0,
+                            Color::new(1.0, 0.7, 0.3, along),
+                        );
+                        from = to;
+                    }
+                }
+                draw_circle(x, y, self.radius, BLACK);
+                draw_circle_lines(x, y, self.radius, 1.5, ORANGE);
+            }
+            AnomalyKind::AntiGravity => {
+                draw_circle(x, y, self.radius, Color::new(0.6, 1.0, 0.8, 0.08));
+                let shimmer = 0.4 + 0.2 * self.spin.sin();
+                draw_circle_lines(x, y, self.radius, 2.0, Color::new(0.6, 1.0, 0.8, shimmer));
+                for mote in 0..6 {
+                    let offset = mote as f32 / 6.0;
+                    // the motes rise through the bubble, wrapping around at the top
+                    let rise = ((self.spin / TAU + offset) * 3.0).fract();
+                    let across = (offset * 2.0 - 1.0 + 1.0 / 6.0) * 0.8;
+                    let height = 1.0 - rise * 2.0;
+                    let width = (1.0 - height * height).max(0.0).sqrt();
+                    let mx = x + across * width * self.radius;
+                    let my = y + height * self.radius * 0.9;
+                    draw_circle(mx, my, 2.0, Color::new(0.8, 1.0, 0.9, 0.7));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use macroquad::prelude::*;
+
+use crate::mods::Mod;
+
+/// The directory the sprites are loaded from
+const SPRITE_DIR: &str = "assets/sprites";
+
+/// The sprites and sprite sheets loaded at startup, each named after its file
+/// without the extension
+const SPRITES: [&str; 10] = [
+    "jetman",
+    "cargo",
+    "bomb",
+    "goal",
+    "disposal",
+    "goal_pulse",
+    "disposal_pulse",
+    "thrust",
+    "drone",
+    "explosion",
+];
+
+/// The textures loaded at startup
+static ASSETS: OnceLock<Assets> = OnceLock::new();
+
+/// The textures the game draws with. Anything missing is drawn with primitives instead.
+pub struct Assets {
+    textures: HashMap<&'static str, Texture2D>,
+}
+
+impl Assets {
+    /// The texture of the given name, if it could be loaded
+    pub fn texture(&self, name: &str) -> Option<&Texture2D> {
+        self.textures.get(name)
+    }
+}
+
+/// Load the sprites, skipping any that are missing or broken. The last of the given
+/// mods that has a sprite replaces the game's own.
+/// Call this once at startup, before drawing anything.
+pub async fn load(mods: &[Mod]) {
+    let mut textures = HashMap::new();
+    for name in SPRITES {
+        let path = mods
+            .iter()
+            .rev()
+            .find_map(|found| found.sprite(name))
+            .unwrap_or_else(|| format!("{SPRITE_DIR}/{name}.png").into());
+        if let Ok(texture) = load_texture(&path.to_string_lossy()).await {
+            // the sprites are pixel art, which blurs when smoothed
+            texture.set_filter(FilterMode::Nearest);
+            textures.insert(name, texture);
+        }
+    }
+    let _ = ASSETS.set(Assets { textures });
+}
+
+/// The texture of the given name, if the assets have been loaded and it was among them
+pub fn texture(name: &str) -> Option<&'static Texture2D> {
+    ASSETS.get()?.texture(name)
+}
+
+/// Draw a sprite centered on a position, scaled to the given size
+/// and rotated clockwise by the given angle in radians
+pub fn draw_sprite(texture: &Texture2D, center: Vec2, size: Vec2, rotation: f32) {
+    draw_texture_ex(
+        texture,
+        center.x - size.x / 2.0,
+        center.y - size.y / 2.0,
+        WHITE,
+        DrawTextureParams {
+            dest_size: Some(size),
+            rotation,
+            ..Default::default()
+        },
+    );
+}
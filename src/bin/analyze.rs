@@ -0,0 +1,135 @@
+use std::fs;
+use std::process::ExitCode;
+
+use jetman::analysis::{Heatmap, Statistics};
+use jetman::headless::{Batch, RandomPilot, Run};
+use jetman::hud;
+use jetman::level_file;
+
+const USAGE: &str = "usage: analyze LEVEL [--runs N] [--frames N] [--seed N] [--threads N] \
+[--heatmap IMAGE.png] [--crashes CRASHES.csv]
+
+Flies a level many times at random without a window and reports how the runs went,
+to help balancing it. Optionally saves a heatmap of where pods crashed, and the
+crash positions themselves, one `x,y` line each.";
+
+/// What to analyze and where to put the results
+struct Options {
+    level: String,
+    runs: usize,
+    frames: usize,
+    seed: u64,
+    threads: Option<usize>,
+    heatmap: Option<String>,
+    crashes: Option<String>,
+}
+
+/// Read the options from the command line arguments
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        level: String::new(),
+        runs: 100,
+        frames: Batch::MAX_FRAMES,
+        seed: 0,
+        threads: None,
+        heatmap: None,
+        crashes: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        let number = |value: String| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{arg} needs a number, not '{value}'"))
+        };
+        match arg.as_str() {
+            "--runs" => options.runs = number(value()?)? as usize,
+            "--frames" => options.frames = number(value()?)? as usize,
+            "--seed" => options.seed = number(value()?)?,
+            "--threads" => options.threads = Some(number(value()?)? as usize),
+            "--heatmap" => options.heatmap = Some(value()?),
+            "--crashes" => options.crashes = Some(value()?),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ => options.level = arg,
+        }
+    }
+    if options.level.is_empty() {
+        return Err("no level given".to_string());
+    }
+    Ok(options)
+}
+
+fn analyze(options: Options) -> Result<(), String> {
+    let level = level_file::load(&options.level, options.seed)?;
+    let mut batch = Batch::new(level.clone()).with_max_frames(options.frames);
+    if let Some(threads) = options.threads {
+        batch = batch.with_threads(threads);
+    }
+    let runs = (0..options.runs as u64)
+        .map(|run| {
+            let seed = options.seed.wrapping_add(run);
+            Run::new(seed, RandomPilot::new(seed))
+        })
+        .collect();
+    let reports = batch.run(runs);
+
+    let statistics = Statistics::new(&reports);
+    let time = |time: Option<f32>| time.map_or("-".to_string(), hud::format_time);
+    println!("{}: {} runs", level.name, statistics.runs);
+    println!(
+        "completed:      {} ({:.0}%)",
+        statistics.completed,
+        statistics.completion_rate() * 100.0
+    );
+    println!("average time:   {}", time(statistics.average_time));
+    println!("best time:      {}", time(statistics.best_time));
+    println!("average deaths: {:.2}", statistics.average_deaths);
+    println!("average reward: {:.2}", statistics.average_reward);
+
+    let crashes = reports
+        .iter()
+        .flat_map(|report| &report.crashes)
+        .collect::<Vec<_>>();
+    let mut heatmap = Heatmap::new(&level, Heatmap::CELL_SIZE);
+    for crash in &crashes {
+        heatmap.add(**crash);
+    }
+    if let Some((position, count)) = heatmap.hotspot() {
+        println!(
+            "worst hotspot:  {count} crashes around ({:.0}, {:.0})",
+            position.x, position.y
+        );
+    }
+    if let Some(path) = options.heatmap {
+        heatmap.save(&path, &level)?;
+    }
+    if let Some(path) = options.crashes {
+        let lines = crashes
+            .iter()
+            .map(|crash| format!("{},{}\n", crash.x, crash.y))
+            .collect::<String>();
+        fs::write(&path, lines).map_err(|error| format!("can't write {path}: {error}"))?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            if !error.is_empty() {
+                eprintln!("{error}");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match analyze(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use jetman::level_file;
+use jetman::net::{self, Server};
+
+const USAGE: &str = "usage: server LEVEL [--port N] [--seed N]
+
+Runs a level for up to 4 players to fly together over the network, starting it over
+whenever a run is won or lost. Players join from the game's main menu, with
+JETMAN_SERVER set to this machine's address and port.";
+
+/// The frames the server steps per second
+const FRAME_RATE: u32 = 60;
+
+/// What to run and where
+struct Options {
+    level: String,
+    port: u16,
+    seed: u64,
+}
+
+/// Read the options from the command line arguments
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        level: String::new(),
+        port: net::PORT,
+        seed: 0,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        let number = |value: String| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{arg} needs a number, not '{value}'"))
+        };
+        match arg.as_str() {
+            "--port" => {
+                let port = number(value()?)?;
+                options.port = u16::try_from(port).map_err(|_| format!("no port {port}"))?;
+            }
+            "--seed" => options.seed = number(value()?)?,
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ => options.level = arg,
+        }
+    }
+    if options.level.is_empty() {
+        return Err("no level given".to_string());
+    }
+    Ok(options)
+}
+
+fn serve(options: Options) -> Result<(), String> {
+    let level = level_file::load(&options.level, options.seed)?;
+    let name = level.name.clone();
+    let mut server = Server::bind(("0.0.0.0", options.port), level)
+        .map_err(|error| format!("can't listen on port {}: {error}", options.port))?;
+    println!("serving {name} on port {}", options.port);
+
+    let frame = Duration::from_secs(1) / FRAME_RATE;
+    let mut players = 0;
+    let mut runs = 0;
+    let mut next = Instant::now();
+    loop {
+        // trouble with one client doesn't stop the game for the others
+        for problem in server.update(frame.as_secs_f32()) {
+            eprintln!("{problem}");
+        }
+        if server.players() != players {
+            players = server.players();
+            println!("{players} players");
+        }
+        if server.runs() != runs {
+            runs = server.runs();
+            println!("run {runs} is over, starting {name} again");
+        }
+        next += frame;
+        thread::sleep(next.saturating_duration_since(Instant::now()));
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            if !error.is_empty() {
+                eprintln!("{error}");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match serve(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
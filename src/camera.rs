@@ -0,0 +1,227 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The area of the world the camera shows at the least, in world units. Windows of
+/// any size scale it up or down to fit, so a bigger window shows the same world larger
+/// rather than more of it.
+pub const VIRTUAL_RESOLUTION: Vec2 = Vec2::new(800.0, 600.0);
+
+/// The pixels per world unit for a view of the given size in pixels, so the virtual
+/// resolution just fits into it. Views of another shape show more to the sides,
+/// or above and below.
+pub fn pixels_per_unit(view: Vec2) -> f32 {
+    (view / VIRTUAL_RESOLUTION).min_element()
+}
+
+/// How the player likes the camera to show the world
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CameraOptions {
+    /// How far the camera zooms in, 1.0 showing the world at its natural size
+    pub zoom: f32,
+    /// Whether impacts shake the camera
+    pub shake: bool,
+    /// Whether the camera pulls back while towing, to keep the cargo in view
+    pub auto_zoom: bool,
+    /// Whether the world looks like it's shown on an old CRT
+    pub crt: bool,
+    /// Whether bright things like thruster flames and teleporters glow
+    pub bloom: bool,
+    /// Whether explosions flash the screen
+    pub flash: bool,
+}
+
+impl Default for CameraOptions {
+    fn default() -> Self {
+        CameraOptions {
+            zoom: 1.0,
+            shake: true,
+            auto_zoom: true,
+            crt: false,
+            bloom: true,
+            flash: true,
+        }
+    }
+}
+
+/// Shakes the camera after impacts. Hard landings, explosions and hits add trauma,
+/// which wears off over time; the shake grows with the square of the trauma,
+/// so small knocks barely register while big blasts rattle the screen.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CameraShake {
+    /// How shaken the camera is, from 0 to 1
+    trauma: f32,
+    /// The time in seconds the shake has been running, driving its wobble
+    time: f32,
+}
+
+impl CameraShake {
+    /// The trauma worn off per second
+    const DECAY: f32 = 1.2;
+    /// The furthest the camera moves off target at full trauma, in pixels
+    const MAX_OFFSET: f32 = 16.0;
+    /// The furthest the camera tilts at full trauma, in degrees
+    const MAX_ROTATION: f32 = 4.0;
+    /// The impact speed above which a landing counts as hard
+    pub const HARD_LANDING: f32 = 1.5;
+
+    /// Create a camera shake at rest
+    pub fn new() -> Self {
+        CameraShake {
+            trauma: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// Add trauma from an impact, up to full trauma
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    /// The current trauma, from 0 to 1
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Wear the trauma off
+    pub fn update(&mut self, seconds: f32) {
+        self.trauma = (self.trauma - Self::DECAY * seconds).max(0.0);
+        self.time += seconds;
+    }
+
+    /// Move and tilt the camera by the current shake. The camera's target
+    /// is expected to have been set this frame, as the offset is added to it.
+    pub fn apply(&self, camera: &mut Camera2D) {
+        let shake = self.trauma * self.trauma;
+        // overlapping waves of unrelated frequencies make for a jitter that doesn't look regular,
+        // without drawing from the world's random generator
+        let t = self.time;
+        let wobble = |a: f32, b: f32| ((t * a).sin() + (t * b).sin()) / 2.0;
+        camera.target += vec2(wobble(41.0, 29.0), wobble(37.0, 23.0)) * Self::MAX_OFFSET * shake;
+        camera.rotation = wobble(31.0, 19.0) * Self::MAX_ROTATION * shake;
+    }
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        CameraShake::new()
+    }
+}
+
+/// Frames the pod and the receiver of the towed item together on the final approach.
+/// The camera follows the pod, or both pods in co-op, until the item comes within range of its receiver,
+/// then eases over to the midpoint of the two, pulling back far enough to show both,
+/// and eases back to following the pod once the item is delivered or taken away again.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CameraFraming {
+    /// The receiver being framed, kept while easing back to follow the pod
+    receiver: Option<Vec2>,
+    /// How far the camera has eased into framing, from 0 (following the pod) to 1
+    blend: f32,
+}
+
+impl CameraFraming {
+    /// The distance from its receiver within which the towed item counts as on final approach
+    pub const RANGE: f32 = 250.0;
+    /// The space kept around the pod and the receiver, in pixels
+    const MARGIN: f32 = 120.0;
+    /// The part of the way between following and framing the camera eases per second
+    const SPEED: f32 = 1.5;
+
+    /// Create a framing following the pod
+    pub fn new() -> Self {
+        CameraFraming {
+            receiver: None,
+            blend: 0.0,
+        }
+    }
+
+    /// Ease towards framing the given receiver, or back to following the pod if there is none
+    pub fn update(&mut self, seconds: f32, receiver: Option<Vec2>) {
+        let target = if receiver.is_some() { 1.0 } else { 0.0 };
+        let step = Self::SPEED * seconds;
+        self.blend += (target - self.blend).clamp(-step, step);
+        if receiver.is_some() {
+            self.receiver = receiver;
+        } else if self.blend <= 0.0 {
+            self.receiver = None;
+        }
+    }
+
+    /// Point the camera at the pods, or between them and the receiver, and zoom out
+    /// as far as it takes to show them all if it may. The camera's zoom is expected to
+    /// have been set for the view of the given size this frame, as it is scaled down
+    /// from there.
+    pub fn apply(&self, camera: &mut Camera2D, pods: Rect, view: Vec2, zoom_out: bool) {
+        // how far the camera has to zoom out to show an area with some space around it
+        let fit = |area: Rect| {
+            let needed = area.size() + Vec2::splat(2.0 * Self::MARGIN);
+            (needed / view).max_element().max(1.0)
+        };
+        camera.target = pods.center();
+        let mut scale = fit(pods);
+        if let Some(receiver) = self.receiver {
+            // ease in and out rather than moving at a constant rate
+            let blend = self.blend * self.blend * (3.0 - 2.0 * self.blend);
+            let both = pods.combine_with(Rect::new(receiver.x, receiver.y, 0.0, 0.0));
+            camera.target = camera.target.lerp(both.center(), blend);
+            scale += (fit(both) - scale) * blend;
+        }
+        if zoom_out {
+            camera.zoom /= scale;
+        }
+    }
+}
+
+impl Default for CameraFraming {
+    fn default() -> Self {
+        CameraFraming::new()
+    }
+}
+
+/// Pulls the camera back while towing whenever the cargo swings close to the edge of
+/// the view, so it never leaves the screen, and eases back in once it hangs closer again
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AutoZoom {
+    /// How far the camera is pulled back, 1.0 not at all and 2.0 showing twice as much
+    scale: f32,
+}
+
+impl AutoZoom {
+    /// The space kept between the cargo and the edge of the view, in world units
+    const MARGIN: f32 = 60.0;
+    /// How quickly the camera pulls back, as the part of the way covered per second
+    const WIDEN: f32 = 4.0;
+    /// How quickly the camera eases back in, as the part of the way covered per second
+    const NARROW: f32 = 0.8;
+
+    /// Create an auto zoom that doesn't pull the camera back
+    pub fn new() -> Self {
+        AutoZoom { scale: 1.0 }
+    }
+
+    /// Pull the camera back or ease it in towards showing the cargo at the given offset
+    /// from the pod, if one is towed, in a view of the given size in world units
+    pub fn update(&mut self, seconds: f32, cargo: Option<Vec2>, view: Vec2) {
+        let target = cargo.map_or(1.0, |offset| {
+            let needed = offset.abs() + Vec2::splat(Self::MARGIN);
+            (needed / (view / 2.0)).max_element().max(1.0)
+        });
+        let rate = if target > self.scale {
+            Self::WIDEN
+        } else {
+            Self::NARROW
+        };
+        self.scale += (target - self.scale) * (rate * seconds).min(1.0);
+    }
+
+    /// How far the camera is pulled back, 1.0 not at all
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+}
+
+impl Default for AutoZoom {
+    fn default() -> Self {
+        AutoZoom::new()
+    }
+}
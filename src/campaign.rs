@@ -0,0 +1,252 @@
+use std::path::{Path, PathBuf};
+
+use macroquad::prelude::*;
+
+use crate::hud::format_time;
+use crate::level_file;
+use crate::mods::{self, Mod};
+use crate::persistence;
+use crate::pickups::Inventory;
+use crate::shop::Upgrades;
+use crate::world::World;
+
+/// How a level of a campaign went, shown between levels
+#[derive(Clone, Debug)]
+pub struct LevelSummary {
+    /// The name of the level
+    pub level: String,
+    /// The time taken to complete the level, in seconds
+    pub time: f32,
+    /// The fuel burnt during the level
+    pub fuel_used: f32,
+    /// The hull damage taken during the level, in hulls
+    pub damage_taken: f32,
+    /// The number of fragile items delivered without any damage
+    pub intact: u32,
+    /// The number of fragile items broken on the way
+    pub wrecked: u32,
+    /// The number of gems collected
+    pub gems: u32,
+    /// The score earned for the level
+    pub score: u32,
+}
+
+impl LevelSummary {
+    /// Sum up a completed level: fast runs, collected gems and fragile items delivered
+    /// intact earn a bonus, while damage, fuel and broken items cost points
+    pub fn new(world: &World) -> Self {
+        let time = world.time();
+        let fuel_used = world.jetman.fuel_used;
+        let damage_taken = world.jetman.damage_taken;
+        let intact = world.progress.intact;
+        let wrecked = world.progress.wrecked;
+        let gems = world.inventory.gems;
+        let score = 1000.0 + (180.0 - time).max(0.0) * 5.0 - damage_taken * 200.0 - fuel_used
+            + intact as f32 * 150.0
+            - wrecked as f32 * 100.0
+            + (gems * Inventory::GEM_SCORE) as f32;
+        LevelSummary {
+            level: world.level_name().to_string(),
+            time,
+            fuel_used,
+            damage_taken,
+            intact,
+            wrecked,
+            gems,
+            score: score.max(0.0) as u32,
+        }
+    }
+}
+
+/// An ordered list of levels played one after the other, carrying over
+/// the score and the remaining lives from one level to the next
+pub struct Campaign {
+    /// The level files, in the order they are played
+    levels: Vec<PathBuf>,
+    /// The index of the level being played
+    current: usize,
+    /// The seed the levels are generated from
+    seed: u64,
+    /// The score earned so far
+    pub score: u32,
+    /// The jet pods left for the rest of the campaign
+    pub lives: u32,
+    /// The mods whose levels replace the campaign's, in load order
+    mods: Vec<Mod>,
+    /// Whether every level so far was completed without taking damage or losing a pod
+    flawless: bool,
+    /// Whether every level so far counts, flown by the players alone
+    counts: bool,
+    /// Whether losing a single pod ends the whole campaign
+    hardcore: bool,
+    /// The upgrades the jet pod comes with on every level
+    upgrades: Upgrades,
+}
+
+impl Campaign {
+    /// The campaign file shipped with the game
+    pub const PATH: &str = "levels/campaign.txt";
+    /// The number of jet pods a campaign starts out with
+    const LIVES: u32 = 3;
+
+    /// Create a campaign playing the given level files in order
+    pub fn new(levels: Vec<PathBuf>, seed: u64) -> Self {
+        Campaign {
+            levels,
+            current: 0,
+            seed,
+            score: 0,
+            lives: Self::LIVES,
+            mods: vec![],
+            flawless: true,
+            counts: true,
+            hardcore: false,
+            upgrades: Upgrades::default(),
+        }
+    }
+
+    /// Play the levels the given mods replace in place of the campaign's own,
+    /// the last mod winning where several replace the same level
+    pub fn with_mods(mut self, mods: Vec<Mod>) -> Self {
+        self.mods = mods;
+        self
+    }
+
+    /// Play the campaign in hardcore mode, where a single lost pod ends it
+    pub fn with_hardcore(mut self, hardcore: bool) -> Self {
+        self.hardcore = hardcore;
+        self
+    }
+
+    /// Fly the levels to come with the jet pod upgraded as given
+    pub fn with_upgrades(mut self, upgrades: Upgrades) -> Self {
+        self.upgrades = upgrades;
+        self
+    }
+
+    /// Check whether a single lost pod ends the campaign
+    pub fn is_hardcore(&self) -> bool {
+        self.hardcore
+    }
+
+    /// Load a campaign file listing one level file per line, relative to the campaign file.
+    /// Empty lines and lines starting with `#` are skipped.
+    pub fn load(path: impl AsRef<Path>, seed: u64) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = persistence::read_to_string(path)
+            .map_err(|error| format!("can't read {}: {error}", path.display()))?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let levels = source
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| directory.join(line))
+            .collect::<Vec<_>>();
+        if levels.is_empty() {
+            return Err(format!("{} doesn't list any levels", path.display()));
+        }
+        Ok(Campaign::new(levels, seed))
+    }
+
+    /// The index of the level being played and the number of levels in the campaign
+    pub fn position(&self) -> (usize, usize) {
+        (self.current, self.levels.len())
+    }
+
+    /// Check whether all levels have been completed
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.levels.len()
+    }
+
+    /// Create the world for the current level, with the lives carried over
+    pub fn start_level(&self) -> Result<World, String> {
+        let path = self
+            .levels
+            .get(self.current)
+            .ok_or("the campaign is finished")?;
+        // every level gets its own seed, so their grounds differ
+        let path = mods::level(&self.mods, path);
+        let mut level = level_file::load(path, self.seed.wrapping_add(self.current as u64))?;
+        level.lives = if self.hardcore { 1 } else { self.lives };
+        level.upgrades = self.upgrades;
+        Ok(World::from_level(level))
+    }
+
+    /// Book the completed level and move on to the next one. A level that doesn't
+    /// count, because something other than the players helped fly it, keeps the
+    /// campaign from earning unlocks.
+    pub fn complete_level(&mut self, world: &World, counts: bool) -> LevelSummary {
+        let summary = LevelSummary::new(world);
+        self.score += summary.score;
+        self.lives = world.lives();
+        self.flawless &= summary.damage_taken == 0.0 && world.deaths() == 0;
+        self.counts &= counts;
+        self.current += 1;
+        summary
+    }
+
+    /// Start over from the first level
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.score = 0;
+        self.lives = Self::LIVES;
+        self.flawless = true;
+        self.counts = true;
+    }
+
+    /// Check whether the jet pod comes with any upgrades
+    pub fn is_upgraded(&self) -> bool {
+        self.upgrades != Upgrades::default()
+    }
+
+    /// Check whether every level was completed without taking damage or losing a pod
+    pub fn is_flawless(&self) -> bool {
+        self.flawless
+    }
+
+    /// Check whether every level was flown by the players alone
+    pub fn counts(&self) -> bool {
+        self.counts
+    }
+
+    /// Draw the summary of a completed level along with the state of the campaign
+    pub fn draw_summary(&self, summary: &LevelSummary) {
+        clear_background(BLACK);
+        let x = screen_width() / 2.0 - 160.0;
+        let mut y = screen_height() / 2.0 - 140.0;
+        let (completed, total) = self.position();
+        let title = format!("{} complete! ({completed}/{total})", summary.level);
+        draw_text(&title, x, y, 32.0, GREEN);
+        y += 40.0;
+        let mut lines = vec![
+            format!("Time: {}", format_time(summary.time)),
+            format!("Fuel used: {:.0}", summary.fuel_used),
+            format!("Damage taken: {:.0}%", summary.damage_taken * 100.0),
+        ];
+        if summary.intact + summary.wrecked > 0 {
+            lines.push(format!(
+                "Fragile cargo: {} intact, {} broken",
+                summary.intact, summary.wrecked
+            ));
+        }
+        if summary.gems > 0 {
+            lines.push(format!("Gems: {}", summary.gems));
+        }
+        lines.extend([
+            format!("Score: {}", summary.score),
+            String::new(),
+            format!("Total score: {}", self.score),
+            format!("Lives left: {}", self.lives),
+        ]);
+        for line in lines {
+            draw_text(&line, x, y, 22.0, WHITE);
+            y += 26.0;
+        }
+        let next = if self.is_finished() {
+            "Campaign complete! Press Enter for the main menu"
+        } else {
+            "Press Enter for the next level"
+        };
+        draw_text(next, x, y + 20.0, 20.0, YELLOW);
+    }
+}
@@ -0,0 +1,221 @@
+use macroquad::prelude::*;
+
+use crate::enemy::Turret;
+use crate::physics::{Item, Teleporter};
+use crate::world::World;
+
+/// The most lines of output the console keeps
+const MAX_LINES: usize = 8;
+
+/// The commands the console understands, as listed by `help`
+const HELP: [&str; 6] = [
+    "gravity X Y - set the gravity",
+    "spawn item|livestock|bomb|goal|disposal|turret|launcher|drone X Y - put something into the world",
+    "tp X Y - move the pod",
+    "god - turn invulnerability on or off",
+    "seed N - drive the randomness from a new seed",
+    "help - list the commands",
+];
+
+/// An in-game console for changing the world while playing, to try things out quickly
+pub struct Console {
+    open: bool,
+    /// The command being typed
+    line: String,
+    /// The commands entered before, oldest first
+    history: Vec<String>,
+    /// How far back in the history the player has gone, if at all
+    recalled: Option<usize>,
+    /// The commands entered and their results, oldest first
+    output: Vec<(String, Color)>,
+}
+
+impl Console {
+    /// Create a closed console
+    pub fn new() -> Self {
+        Console {
+            open: false,
+            line: String::new(),
+            history: vec![],
+            recalled: None,
+            output: vec![],
+        }
+    }
+
+    /// Whether the console is open and takes the keyboard
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Open or close the console
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.line.clear();
+        self.recalled = None;
+    }
+
+    /// Take the keys typed into the open console, running the command once Enter is pressed.
+    /// Returns whether a command changed the world.
+    pub fn update(&mut self, world: &mut World) -> bool {
+        if !self.open {
+            return false;
+        }
+        while let Some(character) = get_char_pressed() {
+            // the key opening the console isn't part of any command
+            if character != '`' && !character.is_control() {
+                self.line.push(character);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.line.pop();
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            self.toggle();
+            return false;
+        }
+        if is_key_pressed(KeyCode::Up) && !self.history.is_empty() {
+            let index = self
+                .recalled
+                .map_or(self.history.len(), |i| i)
+                .saturating_sub(1);
+            self.recalled = Some(index);
+            self.line = self.history[index].clone();
+        }
+        if is_key_pressed(KeyCode::Down)
+            && let Some(index) = self.recalled
+        {
+            self.recalled = (index + 1 < self.history.len()).then_some(index + 1);
+            self.line = self
+                .recalled
+                .map_or(String::new(), |i| self.history[i].clone());
+        }
+        if !is_key_pressed(KeyCode::Enter) || self.line.trim().is_empty() {
+            return false;
+        }
+
+        let command = std::mem::take(&mut self.line);
+        self.recalled = None;
+        self.print(format!("> {command}"), GRAY);
+        let result = execute(world, &command);
+        self.history.push(command);
+        match result {
+            Ok(message) => {
+                self.print(message, WHITE);
+                true
+            }
+            Err(error) => {
+                self.print(error, RED);
+                false
+            }
+        }
+    }
+
+    /// Add a line to the output, dropping the oldest ones if there are too many
+    fn print(&mut self, line: String, color: Color) {
+        self.output
+            .extend(line.lines().map(|line| (line.to_string(), color)));
+        let excess = self.output.len().saturating_sub(MAX_LINES);
+        self.output.drain(..excess);
+    }
+
+    /// Draw the open console across the top of the screen
+    pub fn draw(&self) {
+        if !self.open {
+            return;
+        }
+        let height = 30.0 + 22.0 * MAX_LINES as f32;
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            height,
+            Color::new(0.0, 0.0, 0.0, 0.85),
+        );
+        draw_line(0.0, height, screen_width(), height, 2.0, YELLOW);
+        for (index, (line, color)) in self.output.iter().enumerate() {
+            draw_text(line, 10.0, 22.0 + 22.0 * index as f32, 20.0, *color);
+        }
+        // a blinking cursor after the command being typed
+        let cursor = if (get_time() * 2.0) as i64 % 2 == 0 {
+            "_"
+        } else {
+            ""
+        };
+        let prompt = format!("] {}{cursor}", self.line);
+        draw_text(&prompt, 10.0, height - 8.0, 20.0, YELLOW);
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Run a console command on the world, returning what it did or what's wrong with it
+pub fn execute(world: &mut World, command: &str) -> Result<String, String> {
+    let words = command.split_whitespace().collect::<Vec<_>>();
+    let number = |index: usize| -> Result<f32, String> {
+        let word = words.get(index).ok_or("not enough numbers")?;
+        word.parse()
+            .map_err(|_| format!("'{word}' is not a number"))
+    };
+    let point =
+        |index: usize| -> Result<Vec2, String> { Ok(vec2(number(index)?, number(index + 1)?)) };
+
+    match words.as_slice() {
+        ["gravity", ..] => {
+            world.gravity = point(1)?;
+            Ok(format!("gravity is now {}", world.gravity))
+        }
+        ["spawn", kind, ..] => {
+            let position = point(2)?;
+            match *kind {
+                "item" => world.entities.spawn_item(Item::new(position.x, position.y)),
+                "livestock" => world
+                    .entities
+                    .spawn_item(Item::livestock(position.x, position.y)),
+                "bomb" => {
+                    let fuse = number(4).unwrap_or(60.0);
+                    world
+                        .entities
+                        .spawn_item(Item::bomb(position.x, position.y, fuse))
+                }
+                "goal" => world.entities.spawn_teleporter(Teleporter::new(position)),
+                "disposal" => world
+                    .entities
+                    .spawn_teleporter(Teleporter::disposal(position)),
+                "turret" => world.entities.spawn_turret(Turret::new(position)),
+                "launcher" => world.entities.spawn_turret(Turret::launcher(position)),
+                "drone" => world.entities.spawn_drone(position),
+                other => return Err(format!("can't spawn '{other}'")),
+            };
+            Ok(format!("spawned a {kind} at {position}"))
+        }
+        ["tp", ..] => {
+            let position = point(1)?;
+            world.jetman.body.position = position;
+            world.jetman.body.velocity = Vec2::ZERO;
+            Ok(format!("moved the pod to {position}"))
+        }
+        ["god"] => {
+            world.jetman.invulnerable = !world.jetman.invulnerable;
+            let state = if world.jetman.invulnerable {
+                "on"
+            } else {
+                "off"
+            };
+            Ok(format!("god mode {state}"))
+        }
+        ["seed", seed] => {
+            let seed = seed
+                .parse()
+                .map_err(|_| format!("'{seed}' is not a seed"))?;
+            world.reseed(seed);
+            Ok(format!("seed is now {seed}"))
+        }
+        ["help"] => Ok(HELP.join("\n")),
+        [other, ..] => Err(format!("unknown command '{other}', try 'help'")),
+        [] => Err("no command".to_string()),
+    }
+}
@@ -0,0 +1,204 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use macroquad::prelude::*;
+
+use crate::ui::{Bindings, InputState};
+use crate::world::World;
+
+/// What a controller gets to see of the world: the pod and where to fly, but nothing
+/// it could change
+pub struct WorldView<'a> {
+    world: &'a World,
+}
+
+impl<'a> WorldView<'a> {
+    /// A view of the given world
+    pub fn new(world: &'a World) -> Self {
+        WorldView { world }
+    }
+
+    /// Where the pod is
+    pub fn position(&self) -> Vec2 {
+        self.world.jetman.body.position
+    }
+
+    /// How fast and where the pod is moving
+    pub fn velocity(&self) -> Vec2 {
+        self.world.jetman.body.velocity
+    }
+
+    /// The direction the pod points in, in radians
+    pub fn heading(&self) -> f32 {
+        self.world.jetman.heading
+    }
+
+    /// The length of the tractor beam
+    pub fn link_distance(&self) -> f32 {
+        self.world.jetman.link_distance
+    }
+
+    /// The heading from the pod towards the given point on the screen
+    pub fn heading_to(&self, pointer: Vec2) -> f32 {
+        (self.world.screen_to_world(pointer) - self.position()).to_angle()
+    }
+
+    /// The gravity pulling the pod down
+    pub fn gravity(&self) -> Vec2 {
+        self.world.gravity
+    }
+
+    /// The weight the thruster has to hold up, as a multiple of the pod's own:
+    /// 1.0 flying alone, more towing a chain
+    pub fn load(&self) -> f32 {
+        let jetman = &self.world.jetman;
+        1.0 + jetman.towed_mass / jetman.body.mass
+    }
+
+    /// Where the item hanging from the tractor beam is, if there is one
+    pub fn towed_item(&self) -> Option<Vec2> {
+        let entity = self.world.jetman.linked_item?;
+        self.world.entities.position(entity)
+    }
+
+    /// Where to head next: the nearest teleporter accepting the towed item,
+    /// or the nearest item if nothing is being towed
+    pub fn waypoint(&self) -> Option<Vec2> {
+        self.world.waypoint()
+    }
+
+    /// How far the terrain is from a point in the given direction, if it is within range
+    pub fn distance_to_terrain(&self, from: Vec2, direction: Vec2, range: f32) -> Option<f32> {
+        self.world.distance_to_terrain(from, direction, range)
+    }
+}
+
+/// Something flying the pod, deciding the input of every frame from what it sees
+pub trait Controller {
+    /// The input for the next frame
+    fn input(&mut self, view: &WorldView) -> InputState;
+
+    /// Whether a program flies the pod rather than a player, so the run doesn't count
+    fn is_bot(&self) -> bool {
+        false
+    }
+}
+
+/// The player at the keyboard, and maybe the mouse
+pub struct Keyboard {
+    bindings: Bindings,
+    /// Whether the pod points towards the mouse pointer and thrusts while the left
+    /// button is held, on top of the keys
+    mouse_aim: bool,
+}
+
+impl Keyboard {
+    /// The keyboard with the given key bindings
+    pub fn new(bindings: Bindings) -> Self {
+        Keyboard {
+            bindings,
+            mouse_aim: false,
+        }
+    }
+
+    /// Aim the pod with the mouse as well, or not
+    pub fn with_mouse_aim(mut self, mouse_aim: bool) -> Self {
+        self.mouse_aim = mouse_aim;
+        self
+    }
+}
+
+impl Controller for Keyboard {
+    fn input(&mut self, view: &WorldView) -> InputState {
+        let mut input = InputState::from_bindings(&self.bindings);
+        if self.mouse_aim {
+            input.aim = Some(view.heading_to(input.pointer));
+            input.thrust |= is_mouse_button_down(MouseButton::Left);
+        }
+        input
+    }
+}
+
+/// A simple bot flying to the nearest item, picking it up and dropping it off
+/// at the teleporter accepting it, one item after the other
+pub struct Autopilot;
+
+impl Autopilot {
+    /// The fastest the autopilot flies, and the fastest it flies towing an item,
+    /// so the item doesn't swing too wildly
+    const MAX_SPEED: (f32, f32) = (2.0, 1.0);
+    /// How quickly the autopilot wants to close the distance to its target
+    const APPROACH: f32 = 0.02;
+    /// How far ahead the autopilot looks out for terrain, in units of its speed
+    const LOOKAHEAD: f32 = 25.0;
+    /// The closest the autopilot lets itself get to the terrain below
+    const CLEARANCE: f32 = 40.0;
+    /// How far off the heading may be for the thruster to push the right way
+    const AIM: f32 = 0.5;
+
+    /// Create the autopilot
+    pub fn new() -> Self {
+        Autopilot
+    }
+
+    /// Where the pod has to be: above the next item to pick it up, or, towing an
+    /// item, where the item hangs right over its teleporter once it stops swinging
+    fn target(view: &WorldView) -> Option<Vec2> {
+        let waypoint = view.waypoint()?;
+        let height = match view.towed_item() {
+            Some(_) => view.link_distance(),
+            None => view.link_distance() * 0.6,
+        };
+        Some(waypoint - vec2(0.0, height))
+    }
+}
+
+impl Default for Autopilot {
+    fn default() -> Self {
+        Autopilot::new()
+    }
+}
+
+impl Controller for Autopilot {
+    fn is_bot(&self) -> bool {
+        true
+    }
+
+    fn input(&mut self, view: &WorldView) -> InputState {
+        let mut input = InputState::default();
+        let position = view.position();
+        let velocity = view.velocity();
+        let target = Self::target(view).unwrap_or(position);
+
+        // the velocity to fly at, slowing down as the target comes close
+        let max_speed = match view.towed_item() {
+            Some(_) => Self::MAX_SPEED.1,
+            None => Self::MAX_SPEED.0,
+        };
+        let mut desired = ((target - position) * Self::APPROACH).clamp_length_max(max_speed);
+        // steer clear of terrain coming up ahead and keep some room underneath
+        let speed = velocity.length();
+        if speed > 0.1
+            && let Some(distance) =
+                view.distance_to_terrain(position, velocity / speed, speed * Self::LOOKAHEAD)
+        {
+            desired -= velocity / speed * (1.0 - distance / (speed * Self::LOOKAHEAD)) * 2.0;
+        }
+        if view
+            .distance_to_terrain(position, Vec2::Y, Self::CLEARANCE)
+            .is_some()
+        {
+            desired.y = desired.y.min(-0.5);
+        }
+
+        // thrust against the difference, holding against gravity and the towed weight
+        let needed = (desired - velocity) * 0.1 - view.gravity() * view.load();
+        let aim = needed.y.atan2(needed.x);
+        let off = (aim - view.heading() + PI).rem_euclid(TAU) - PI;
+        input.turn_right = off > 0.05;
+        input.turn_left = off < -0.05;
+        // never thrust towards the ground, and only as much as needed
+        let pointing_down = (view.heading().rem_euclid(TAU) - FRAC_PI_2).abs() < FRAC_PI_2 * 0.6;
+        input.thrust = off.abs() < Self::AIM && needed.length() > 0.012 && !pointing_down;
+        input
+    }
+}
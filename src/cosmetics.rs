@@ -0,0 +1,144 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use macroquad::color::hsl_to_rgb;
+use macroquad::prelude::*;
+
+use crate::persistence;
+
+/// The trails the thruster can leave behind, purely for show
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Trail {
+    /// Just the flame, no trail
+    #[default]
+    Plain,
+    /// A trail of sparks cycling through the colors of the rainbow, earned by
+    /// completing the campaign without taking any damage
+    Chromatic,
+    /// A trail of embers, earned by completing a hardcore campaign
+    Ember,
+}
+
+impl Trail {
+    /// All trails, in the order they are cycled through
+    pub const ALL: [Trail; 3] = [Trail::Plain, Trail::Chromatic, Trail::Ember];
+
+    /// The name of the trail, in the unlocks file and on screen
+    pub fn name(&self) -> &'static str {
+        match self {
+            Trail::Plain => "plain",
+            Trail::Chromatic => "chromatic",
+            Trail::Ember => "ember",
+        }
+    }
+
+    /// The trail with the given name
+    pub fn from_name(name: &str) -> Option<Trail> {
+        Trail::ALL.into_iter().find(|trail| trail.name() == name)
+    }
+
+    /// The color of a spark of the trail emitted the given number of seconds into the level,
+    /// or `None` if the trail leaves no sparks
+    pub fn color(&self, time: f32) -> Option<Color> {
+        match self {
+            Trail::Plain => None,
+            Trail::Chromatic => Some(hsl_to_rgb((time * 0.5).fract(), 1.0, 0.6)),
+            // flickering between deep red and orange
+            Trail::Ember => Some(hsl_to_rgb(0.02 + 0.05 * (time * 9.0).sin().abs(), 1.0, 0.5)),
+        }
+    }
+}
+
+/// The cosmetics the player has unlocked and the one picked, kept in a local file
+pub struct Cosmetics {
+    unlocked: Vec<Trail>,
+    /// The trail the thruster leaves behind
+    selected: Trail,
+    /// The file the unlocks are kept in
+    path: PathBuf,
+}
+
+impl Cosmetics {
+    /// The file the unlocks are kept in by default
+    pub const PATH: &str = "unlocks.txt";
+
+    /// Load the unlocks from the default file
+    pub fn load() -> Self {
+        Cosmetics::load_from(Self::PATH)
+    }
+
+    /// Load the unlocks from the given file, one trail per line, with the picked one
+    /// marked by a `*` in front. A missing file unlocks nothing but the plain trail,
+    /// and lines that can't be read are skipped.
+    pub fn load_from(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let mut cosmetics = Cosmetics {
+            unlocked: vec![Trail::Plain],
+            selected: Trail::Plain,
+            path,
+        };
+        for line in persistence::read_to_string(&cosmetics.path)
+            .unwrap_or_default()
+            .lines()
+        {
+            let (selected, name) = match line.trim().strip_prefix('*') {
+                Some(name) => (true, name.trim()),
+                None => (false, line.trim()),
+            };
+            let Some(trail) = Trail::from_name(name) else {
+                continue;
+            };
+            cosmetics.unlock(trail);
+            if selected {
+                cosmetics.selected = trail;
+            }
+        }
+        cosmetics
+    }
+
+    /// Write the unlocks back to the file they were loaded from
+    pub fn save(&self) -> io::Result<()> {
+        let contents = self
+            .unlocked
+            .iter()
+            .map(|trail| {
+                let mark = if *trail == self.selected { "*" } else { "" };
+                format!("{mark}{}\n", trail.name())
+            })
+            .collect::<String>();
+        persistence::write(&self.path, contents)
+    }
+
+    /// Unlock a trail and pick it. Returns whether it was locked before.
+    pub fn unlock(&mut self, trail: Trail) -> bool {
+        if self.is_unlocked(trail) {
+            return false;
+        }
+        self.unlocked.push(trail);
+        self.selected = trail;
+        true
+    }
+
+    /// Check whether a trail has been unlocked
+    pub fn is_unlocked(&self, trail: Trail) -> bool {
+        self.unlocked.contains(&trail)
+    }
+
+    /// The trail the thruster leaves behind
+    pub fn trail(&self) -> Trail {
+        self.selected
+    }
+
+    /// Pick the next unlocked trail
+    pub fn cycle(&mut self) {
+        let unlocked = Trail::ALL
+            .into_iter()
+            .filter(|trail| self.is_unlocked(*trail))
+            .collect::<Vec<_>>();
+        let index = unlocked
+            .iter()
+            .position(|trail| *trail == self.selected)
+            .unwrap_or(0);
+        self.selected = unlocked[(index + 1) % unlocked.len()];
+    }
+}
@@ -0,0 +1,108 @@
+use macroquad::prelude::*;
+
+/// The credits manifest bundled with the game
+const MANIFEST: &str = include_str!("../assets/credits.txt");
+
+/// A line of the credits
+pub enum CreditLine {
+    /// The title of a section
+    Heading(String),
+    /// A contributor, or a crate or asset with its license
+    Entry(String),
+    /// An empty line between sections
+    Gap,
+}
+
+/// The scrolling credits screen listing contributors and third-party licenses
+pub struct Credits {
+    lines: Vec<CreditLine>,
+    /// How far the credits have scrolled up, in pixels
+    offset: f32,
+    /// The time in seconds until auto-scrolling resumes after scrolling by hand
+    manual_timer: f32,
+}
+
+impl Credits {
+    /// How fast the credits scroll by themselves, in pixels per second
+    const SPEED: f32 = 40.0;
+    /// The height of a line, in pixels
+    const LINE_HEIGHT: f32 = 28.0;
+
+    /// Create the credits from the bundled manifest
+    pub fn new() -> Self {
+        Credits::parse(MANIFEST)
+    }
+
+    /// Create credits from a manifest: lines starting with `[` begin a section,
+    /// lines starting with `#` are comments, and every other line is an entry
+    pub fn parse(manifest: &str) -> Self {
+        let mut lines = vec![];
+        for line in manifest.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(heading) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if !lines.is_empty() {
+                    lines.push(CreditLine::Gap);
+                }
+                lines.push(CreditLine::Heading(heading.to_string()));
+            } else {
+                lines.push(CreditLine::Entry(line.to_string()));
+            }
+        }
+        Credits {
+            lines,
+            offset: 0.0,
+            manual_timer: 0.0,
+        }
+    }
+
+    /// Scroll the credits, by themselves or by `scroll` lines the player asked for.
+    /// Once they have scrolled past the end they start over.
+    pub fn update(&mut self, seconds: f32, scroll: f32) {
+        if scroll != 0.0 {
+            self.offset += scroll * Self::LINE_HEIGHT;
+            self.manual_timer = 2.0;
+        } else if self.manual_timer > 0.0 {
+            self.manual_timer -= seconds;
+        } else {
+            self.offset += Self::SPEED * seconds;
+        }
+        let length = self.lines.len() as f32 * Self::LINE_HEIGHT + screen_height();
+        self.offset = self.offset.clamp(0.0, length);
+        if self.offset >= length && self.manual_timer <= 0.0 {
+            self.offset = 0.0;
+        }
+    }
+
+    /// Draw the credits rolling up from the bottom of the screen
+    pub fn draw(&self) {
+        clear_background(BLACK);
+        let mut y = screen_height() - self.offset;
+        for line in &self.lines {
+            let (text, size, color) = match line {
+                CreditLine::Heading(text) => (text.as_str(), 30, YELLOW),
+                CreditLine::Entry(text) => (text.as_str(), 22, WHITE),
+                CreditLine::Gap => ("", 22, WHITE),
+            };
+            if y > 0.0 && y < screen_height() + Self::LINE_HEIGHT {
+                let width = measure_text(text, None, size, 1.0).width;
+                draw_text(text, (screen_width() - width) / 2.0, y, size as f32, color);
+            }
+            y += Self::LINE_HEIGHT;
+        }
+        draw_text(
+            "Up/Down or mouse wheel to scroll, Escape to go back",
+            10.0,
+            screen_height() - 10.0,
+            18.0,
+            GRAY,
+        );
+    }
+}
+
+impl Default for Credits {
+    fn default() -> Self {
+        Credits::new()
+    }
+}
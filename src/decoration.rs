@@ -0,0 +1,89 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The kinds of scenery decorating a level
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum DecorationKind {
+    /// A vine hanging down from its anchor, swaying from side to side
+    Vine,
+    /// A cluster of crystals growing up from their base, shimmering
+    Crystal,
+    /// A bank of fog drifting back and forth around its center
+    FogBank,
+}
+
+/// Scenery drawn in front of the action, which nothing collides with
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Decoration {
+    pub kind: DecorationKind,
+    /// Where the vine hangs from, where the crystals grow from, or the center of the fog
+    pub position: Vec2,
+    /// The length of the vine, the height of the crystals, or the width of the fog
+    pub size: f32,
+    /// Where the decoration is in its oscillation at the start of the level, so
+    /// decorations next to each other don't sway in step
+    phase: f32,
+}
+
+impl Decoration {
+    /// The length of a segment of a vine
+    const SEGMENT: f32 = 12.0;
+
+    /// Create a decoration
+    pub fn new(kind: DecorationKind, position: Vec2, size: f32) -> Self {
+        Decoration {
+            kind,
+            position,
+            size,
+            phase: position.x * 0.037 + position.y * 0.019,
+        }
+    }
+
+    /// Draw the decoration as it is the given number of seconds into the level
+    pub fn draw(&self, time: f32) {
+        let t = time + self.phase;
+        match self.kind {
+            DecorationKind::Vine => {
+                // every segment bends a bit further than the one above it
+                let segments = (self.size / Self::SEGMENT).ceil().max(1.0) as usize;
+                let swing = 0.06 * (t * 1.3).sin();
+                let mut from = self.position;
+                for index in 0..segments {
+                    let angle = swing * (index + 1) as f32;
+                    let to = from + vec2(angle.sin(), angle.cos()) * Self::SEGMENT;
+                    draw_line(from.x, from.y, to.x, to.y, 3.0, DARKGREEN);
+                    if index % 2 == 1 {
+                        draw_circle(to.x, to.y, 4.0, GREEN);
+                    }
+                    from = to;
+                }
+            }
+            DecorationKind::Crystal => {
+                let shimmer = 0.6 + 0.3 * (t * 2.0).sin();
+                let color = Color::new(0.5, 0.8, 1.0, shimmer);
+                for (offset, scale) in [(-0.3, 0.6), (0.0, 1.0), (0.35, 0.7)] {
+                    let base = self.position + vec2(offset * self.size, 0.0);
+                    let height = self.size * scale;
+                    let width = height * 0.25;
+                    let tip = base + vec2(offset * width, -height);
+                    draw_triangle(base - vec2(width, 0.0), base + vec2(width, 0.0), tip, color);
+                }
+            }
+            DecorationKind::FogBank => {
+                let drift = 20.0 * (t * 0.3).sin();
+                let center = self.position + vec2(drift, 0.0);
+                for (offset, radius) in [(-0.3, 0.3), (0.0, 0.4), (0.3, 0.3)] {
+                    let puff = center + vec2(offset * self.size, 0.0);
+                    draw_ellipse(
+                        puff.x,
+                        puff.y,
+                        radius * self.size,
+                        radius * self.size * 0.35,
+                        0.0,
+                        Color::new(0.8, 0.8, 0.85, 0.25),
+                    );
+                }
+            }
+        }
+    }
+}
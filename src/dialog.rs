@@ -0,0 +1,140 @@
+use macroquad::prelude::*;
+
+use crate::ui::InputState;
+
+/// A modal dialog asking the player to pick one of a few buttons.
+/// The focus moves with any of the arrow keys, like the rest of the menus, so a
+/// gamepad whose d-pad works the menus works the dialog too, or follows the mouse.
+/// The focused button is picked with Enter or a click, and Escape backs out.
+pub struct Dialog {
+    title: String,
+    message: String,
+    buttons: Vec<String>,
+    /// The index of the focused button
+    focused: usize,
+    /// The button picked when the player backs out of the dialog
+    cancel: usize,
+    /// Where the mouse pointer was during the last update
+    pointer: Vec2,
+}
+
+impl Dialog {
+    /// The size of the dialog box
+    const SIZE: Vec2 = vec2(440.0, 130.0);
+    /// The size of a button
+    const BUTTON: Vec2 = vec2(110.0, 30.0);
+
+    /// Create a dialog with a single OK button
+    pub fn new(title: &str, message: &str) -> Self {
+        Dialog {
+            title: title.to_string(),
+            message: message.to_string(),
+            buttons: vec!["OK".to_string()],
+            focused: 0,
+            cancel: 0,
+            pointer: Vec2::ZERO,
+        }
+    }
+
+    /// Create a dialog asking a yes or no question, where yes is button 0
+    pub fn confirm(title: &str, message: &str) -> Self {
+        Dialog {
+            buttons: vec!["Yes".to_string(), "No".to_string()],
+            cancel: 1,
+            // don't let a stray Enter confirm anything drastic
+            focused: 1,
+            ..Dialog::new(title, message)
+        }
+    }
+
+    /// Create a dialog reporting an error to the player
+    pub fn error(message: &str) -> Self {
+        Dialog::new("Something went wrong", message)
+    }
+
+    /// Move the focus and pick buttons as the player says.
+    /// Returns the index of the picked button once the player has made a choice.
+    pub fn update(&mut self, input: &InputState) -> Option<usize> {
+        let count = self.buttons.len();
+        if input.menu_left || input.menu_up {
+            self.focused = (self.focused + count - 1) % count;
+        }
+        if input.menu_right || input.menu_down {
+            self.focused = (self.focused + 1) % count;
+        }
+        let hovered = self
+            .button_rects()
+            .iter()
+            .position(|rect| rect.contains(input.pointer));
+        // the focus follows the mouse, but only while it moves
+        if let Some(hovered) = hovered {
+            if input.pointer != self.pointer {
+                self.focused = hovered;
+            }
+            if input.click {
+                return Some(hovered);
+            }
+        }
+        self.pointer = input.pointer;
+        if input.back || (input.decline && count > 1) {
+            return Some(self.cancel);
+        }
+        if input.confirm && count > 1 {
+            return Some(0);
+        }
+        input.select.then_some(self.focused)
+    }
+
+    /// The area of the dialog box, centered on the screen
+    fn rect(&self) -> Rect {
+        let position = (vec2(screen_width(), screen_height()) - Self::SIZE) / 2.0;
+        Rect::new(position.x, position.y, Self::SIZE.x, Self::SIZE.y)
+    }
+
+    /// The areas of the buttons, lined up at the bottom right of the dialog box
+    fn button_rects(&self) -> Vec<Rect> {
+        let dialog = self.rect();
+        let count = self.buttons.len() as f32;
+        (0..self.buttons.len())
+            .map(|index| {
+                let x = dialog.right() - (count - index as f32) * (Self::BUTTON.x + 12.0);
+                let y = dialog.bottom() - Self::BUTTON.y - 12.0;
+                Rect::new(x, y, Self::BUTTON.x, Self::BUTTON.y)
+            })
+            .collect()
+    }
+
+    /// Draw the dialog over a dimmed screen
+    pub fn draw(&self) {
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            screen_height(),
+            Color::new(0.0, 0.0, 0.0, 0.5),
+        );
+        let rect = self.rect();
+        draw_rectangle(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            Color::new(0.0, 0.0, 0.0, 0.9),
+        );
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, YELLOW);
+        draw_text(&self.title, rect.x + 16.0, rect.y + 28.0, 22.0, YELLOW);
+        draw_text(&self.message, rect.x + 16.0, rect.y + 56.0, 20.0, WHITE);
+        for (index, (button, label)) in self.button_rects().iter().zip(&self.buttons).enumerate() {
+            let color = if index == self.focused { YELLOW } else { GRAY };
+            draw_rectangle_lines(button.x, button.y, button.w, button.h, 2.0, color);
+            let width = measure_text(label, None, 20, 1.0).width;
+            draw_text(
+                label,
+                button.x + (button.w - width) / 2.0,
+                button.y + 21.0,
+                20.0,
+                color,
+            );
+        }
+    }
+}
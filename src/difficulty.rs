@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+
+/// The difficulty chosen by the player, bounding how far adaptive tuning may go
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// The lowest and highest intensity adaptive tuning may scale the level to
+    fn bounds(&self) -> (f32, f32) {
+        match self {
+            Difficulty::Easy => (0.5, 1.0),
+            Difficulty::Normal => (0.7, 1.2),
+            Difficulty::Hard => (1.0, 1.5),
+        }
+    }
+
+    /// The intensity used while adaptive tuning is off
+    fn baseline(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.25,
+        }
+    }
+}
+
+/// Multipliers layered over a level's own parameters
+#[derive(Clone, Copy, Debug)]
+pub struct Modifiers {
+    /// Scales how often enemies fire
+    pub enemy_fire_rate: f32,
+    /// Scales the strength of the wind
+    pub wind_strength: f32,
+    /// The fraction of the level's fuel pads in service
+    pub fuel_pads: f32,
+}
+
+impl Default for Modifiers {
+    /// The level exactly as designed
+    fn default() -> Self {
+        Modifiers {
+            enemy_fire_rate: 1.0,
+            wind_strength: 1.0,
+            fuel_pads: 1.0,
+        }
+    }
+}
+
+/// Tunes the difficulty to the player's recent failure rate
+pub struct AdaptiveDifficulty {
+    /// Whether tuning adapts to the player at all
+    pub enabled: bool,
+    /// The chosen difficulty bounding the tuning
+    pub difficulty: Difficulty,
+    /// The outcomes of the most recent attempts, true for failures
+    recent: VecDeque<bool>,
+}
+
+impl AdaptiveDifficulty {
+    /// The number of attempts the failure rate is computed over
+    const WINDOW: usize = 10;
+
+    /// Create adaptive tuning for the given difficulty without any recorded attempts
+    pub fn new(difficulty: Difficulty) -> Self {
+        AdaptiveDifficulty {
+            enabled: true,
+            difficulty,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Record the outcome of an attempt
+    pub fn record(&mut self, failed: bool) {
+        self.recent.push_back(failed);
+        if self.recent.len() > Self::WINDOW {
+            self.recent.pop_front();
+        }
+    }
+
+    /// The fraction of recent attempts that failed
+    pub fn failure_rate(&self) -> f32 {
+        if self.recent.is_empty() {
+            return 0.0;
+        }
+        self.recent.iter().filter(|&&failed| failed).count() as f32 / self.recent.len() as f32
+    }
+
+    /// The number of recent attempts and how many of them failed
+    pub fn attempts(&self) -> (usize, usize) {
+        let failed = self.recent.iter().filter(|&&failed| failed).count();
+        (self.recent.len(), failed)
+    }
+
+    /// How intense the level should be: 1.0 plays it as designed, lower is easier.
+    /// Struggling players get an easier time, successful ones a harder one.
+    pub fn intensity(&self) -> f32 {
+        let (low, high) = self.difficulty.bounds();
+        if !self.enabled || self.recent.is_empty() {
+            return self.difficulty.baseline();
+        }
+        (1.0 + 0.5 - self.failure_rate()).clamp(low, high)
+    }
+
+    /// The modifiers to apply on top of a level's parameters
+    pub fn modifiers(&self) -> Modifiers {
+        let intensity = self.intensity();
+        Modifiers {
+            enemy_fire_rate: intensity,
+            wind_strength: intensity,
+            fuel_pads: (1.0 / intensity).min(1.0),
+        }
+    }
+
+    /// Explain the current tuning, one line each, so players can see exactly what changed
+    pub fn describe(&self) -> Vec<String> {
+        let modifiers = self.modifiers();
+        let (attempts, failed) = self.attempts();
+        let mut lines = vec![format!(
+            "Difficulty: {:?}, adaptive tuning {}",
+            self.difficulty,
+            if self.enabled { "on" } else { "off" }
+        )];
+        if self.enabled {
+            lines.push(format!(
+                "Based on {failed} failures in the last {attempts} attempts"
+            ));
+        }
+        lines.push(format!(
+            "Enemy fire rate: x{:.2}",
+            modifiers.enemy_fire_rate
+        ));
+        lines.push(format!("Wind strength: x{:.2}", modifiers.wind_strength));
+        lines.push(format!(
+            "Fuel pads in service: {:.0}%",
+            modifiers.fuel_pads * 100.0
+        ));
+        lines
+    }
+}
@@ -0,0 +1,120 @@
+use ::rand::Rng;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::enemy::{Enemy, EnemyKind};
+
+/// The level played in survival mode, where the director's waves keep coming until
+/// the last pod is lost
+pub const SURVIVAL: &str = "levels/survival.level";
+
+/// Sends in waves of enemies on a schedule, each bigger and faster than the one
+/// before. The waves come out of the level's nests, or from just beyond the edges
+/// of the view if the level has none.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Director {
+    /// The time in seconds after the start of the level at which the first wave comes
+    pub start: f32,
+    /// The time in seconds between two waves
+    pub interval: f32,
+    /// The number of enemies in the first wave
+    pub count: f32,
+    /// How many more enemies each wave brings than the one before
+    pub growth: f32,
+    /// How much faster each wave's enemies fly than the first wave's, as a share of their speed
+    pub speedup: f32,
+    /// The kinds of enemies the waves are made of, each with its weight in the mix.
+    /// Without any, the waves are all common drones.
+    pub mix: Vec<(EnemyKind, f32)>,
+    /// The places the waves come out of
+    pub nests: Vec<Vec2>,
+    /// The number of waves sent in so far
+    pub waves: u32,
+}
+
+impl Director {
+    /// Half the size of the area around the pod the view shows, beyond which
+    /// waves not coming from nests appear
+    const VIEW: Vec2 = vec2(440.0, 340.0);
+    /// How far from the center of its nest an enemy appears
+    const NEST_SPREAD: f32 = 20.0;
+
+    /// Create a director sending in its first wave of `count` enemies `start` seconds
+    /// into the level, and another wave `growth` enemies bigger every `interval` seconds
+    pub fn new(start: f32, interval: f32, count: f32, growth: f32) -> Self {
+        Director {
+            start,
+            interval,
+            count,
+            growth,
+            speedup: 0.05,
+            mix: vec![],
+            nests: vec![],
+            waves: 0,
+        }
+    }
+
+    /// The time in seconds after the start of the level at which the next wave comes
+    pub fn next_wave(&self) -> f32 {
+        self.start + self.interval * self.waves as f32
+    }
+
+    /// Make up the next wave of enemies, each with the position it appears at around
+    /// `center`, and count the wave as sent in
+    pub fn send(&mut self, center: Vec2, rng: &mut impl Rng) -> Vec<(Vec2, Enemy)> {
+        let count = (self.count + self.growth * self.waves as f32).max(0.0) as usize;
+        let speed = 1.0 + self.speedup * self.waves as f32;
+        self.waves += 1;
+        (0..count)
+            .map(|_| {
+                let mut enemy = Enemy::of_kind(self.pick(rng));
+                enemy.speed *= speed;
+                (self.spawn_point(center, rng), enemy)
+            })
+            .collect()
+    }
+
+    /// Pick the kind of an enemy by the weights of the mix
+    fn pick(&self, rng: &mut impl Rng) -> EnemyKind {
+        let total = self.mix.iter().map(|(_, weight)| weight).sum::<f32>();
+        if total <= 0.0 {
+            return EnemyKind::Drone;
+        }
+        let mut roll = rng.gen_range(0.0..total);
+        for (kind, weight) in &self.mix {
+            if roll < *weight {
+                return *kind;
+            }
+            roll -= weight;
+        }
+        EnemyKind::Drone
+    }
+
+    /// Where an enemy appears: in one of the nests, or somewhere along the edges
+    /// of the view around `center`
+    fn spawn_point(&self, center: Vec2, rng: &mut impl Rng) -> Vec2 {
+        if !self.nests.is_empty() {
+            let nest = self.nests[rng.gen_range(0..self.nests.len())];
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            return nest + Vec2::from_angle(angle) * rng.gen_range(0.0..Self::NEST_SPREAD);
+        }
+        let Vec2 { x: w, y: h } = Self::VIEW;
+        // walking the outline of the view, so every stretch of it is as likely
+        let offset = match rng.gen_range(0.0..4.0 * (w + h)) {
+            along if along < 2.0 * w => vec2(along - w, -h),
+            along if along < 2.0 * (w + h) => vec2(w, along - 2.0 * w - h),
+            along if along < 4.0 * w + 2.0 * h => vec2(3.0 * w + 2.0 * h - along, h),
+            along => vec2(-w, 4.0 * w + 3.0 * h - along),
+        };
+        center + offset
+    }
+
+    /// Draw the nests the waves come out of
+    pub fn draw(&self) {
+        for nest in &self.nests {
+            draw_circle(nest.x, nest.y, 14.0, Color::new(0.4, 0.0, 0.0, 0.8));
+            draw_circle_lines(nest.x, nest.y, 14.0, 2.0, RED);
+            draw_circle(nest.x, nest.y, 5.0, BLACK);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings;
+use crate::snapshot::RectDef;
+use crate::terrain::Terrain;
+
+/// A lever Jetman pulls by touching it, opening its door for good
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Lever {
+    pub position: Vec2,
+    pub pulled: bool,
+}
+
+/// A solid door blocking a passage, sliding up into the ceiling while something
+/// holds it open: an item weighing down one of its pressure plates, a pulled lever,
+/// its key delivered to it, the switches of its vault all lit, or a trigger firing.
+/// Every way of opening a passage in a level goes through a door.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Door {
+    /// The name plates, levers and keys refer to the door by in level files
+    pub name: String,
+    /// The area the door blocks while it is shut
+    #[serde(with = "RectDef")]
+    pub area: Rect,
+    /// How far the door has slid open, from 0.0 (shut) to 1.0 (open)
+    pub opened: f32,
+    /// Whether the door stays open for good, after pulling a lever, delivering its key,
+    /// lighting the switches of its vault or firing a trigger
+    pub unlocked: bool,
+    /// The pressure plates holding the door open while an item rests on one of them
+    pub plates: Vec<Vec2>,
+    pub levers: Vec<Lever>,
+    /// Whether an item rested on a plate during the last update
+    pub pressed: bool,
+}
+
+impl Door {
+    /// The distance at which an item weighs down a plate, Jetman pulls a lever,
+    /// or a key opens its door
+    pub const REACH: f32 = 20.0;
+    /// The part of the way the door slides per second
+    const SPEED: f32 = 0.8;
+
+    /// Create a shut door
+    pub fn new(name: &str, area: Rect) -> Self {
+        Door {
+            name: name.to_string(),
+            area,
+            opened: 0.0,
+            unlocked: false,
+            plates: vec![],
+            levers: vec![],
+            pressed: false,
+        }
+    }
+
+    /// Check whether something holds the door open
+    pub fn is_held_open(&self) -> bool {
+        self.unlocked || self.pressed
+    }
+
+    /// Check whether a point is within reach of the door
+    pub fn reaches(&self, point: Vec2) -> bool {
+        let closest = point.clamp(self.area.point(), self.area.point() + self.area.size());
+        (point - closest).length() < Self::REACH
+    }
+
+    /// Press the plates the items rest on and pull the levers the pod touches,
+    /// then slide the door open or shut
+    pub fn update(&mut self, seconds: f32, pod: Vec2, items: &[Vec2]) {
+        self.pressed = self.plates.iter().any(|plate| {
+            items
+                .iter()
+                .any(|item| (*item - *plate).length() < Self::REACH)
+        });
+        for lever in self.levers.iter_mut() {
+            if !lever.pulled && (pod - lever.position).length() < Self::REACH {
+                lever.pulled = true;
+                self.unlocked = true;
+            }
+        }
+        let target = if self.is_held_open() { 1.0 } else { 0.0 };
+        let step = Self::SPEED * seconds;
+        self.opened += (target - self.opened).clamp(-step, step);
+    }
+
+    /// The part of the door still blocking the passage, if any
+    pub fn terrain(&self) -> Option<Terrain> {
+        let height = self.area.h * (1.0 - self.opened);
+        (height > 1.0).then(|| Terrain::rectangle(self.area.x, self.area.y, self.area.w, height))
+    }
+
+    /// Draw the door with its plates and levers
+    pub fn draw(&self) {
+        let Rect { x, y, w, h } = self.area;
+        let height = h * (1.0 - self.opened);
+        draw_rectangle_lines(x, y, w, h, 1.0, Color::new(0.6, 0.6, 0.6, 0.4));
+        if height > 0.0 {
+            draw_rectangle(x, y, w, height, Color::from_hex(0x5A4A3A));
+            // bars across the door, sliding up with it
+            let mut bar = y + height - 6.0;
+            while bar > y {
+                draw_line(x, bar, x + w, bar, 2.0, Color::from_hex(0x8A7A6A));
+                bar -= 14.0;
+            }
+        }
+        let lit = if self.is_held_open() {
+            settings::good()
+        } else {
+            settings::bad()
+        };
+        for plate in &self.plates {
+            let sunk = if self.pressed { 2.0 } else { 0.0 };
+            draw_rectangle(plate.x - 14.0, plate.y - 4.0 + sunk, 28.0, 4.0, lit);
+        }
+        for lever in &self.levers {
+            let (x, y) = (lever.position.x, lever.position.y);
+            let tip = if lever.pulled { 8.0 } else { -8.0 };
+            draw_line(x, y, x + tip, y - 12.0, 3.0, LIGHTGRAY);
+            draw_circle(x + tip, y - 12.0, 3.0, lit);
+            draw_rectangle(x - 6.0, y - 2.0, 12.0, 4.0, DARKGRAY);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A handle to an entity: nothing but an identifier its components are stored under.
+/// Identifiers are never reused, so a stale handle simply finds no components.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub struct Entity(u32);
+
+/// Hands out fresh entity handles
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Allocator {
+    next: u32,
+}
+
+impl Allocator {
+    /// Create a handle no other entity has had before
+    pub fn allocate(&mut self) -> Entity {
+        let entity = Entity(self.next);
+        self.next += 1;
+        entity
+    }
+}
+
+/// The components of one type, keyed by the entity they belong to.
+/// Iteration goes by entity in order of creation, which keeps the systems deterministic.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Storage<T> {
+    components: BTreeMap<Entity, T>,
+}
+
+impl<T> Storage<T> {
+    /// Create an empty storage
+    pub fn new() -> Self {
+        Storage {
+            components: BTreeMap::new(),
+        }
+    }
+
+    /// Attach a component to an entity, replacing the one it may already have
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        self.components.insert(entity, component);
+    }
+
+    /// Detach the component from an entity, returning it
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        self.components.remove(&entity)
+    }
+
+    /// Get the component of an entity
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.components.get(&entity)
+    }
+
+    /// Get a mutable reference to the component of an entity
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.components.get_mut(&entity)
+    }
+
+    /// Check whether an entity has a component of this type
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.components.contains_key(&entity)
+    }
+
+    /// The number of entities with a component of this type
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Check whether no entity has a component of this type
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Iterate over the entities with a component of this type
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.components.keys().copied()
+    }
+
+    /// Iterate over the components along with their entities
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.components.iter().map(|(entity, c)| (*entity, c))
+    }
+
+    /// Iterate mutably over the components along with their entities
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.components.iter_mut().map(|(entity, c)| (*entity, c))
+    }
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Storage::new()
+    }
+}
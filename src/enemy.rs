@@ -0,0 +1,265 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::Animator;
+use crate::physics::Body;
+
+/// The entity an enemy is going after
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Target {
+    /// The player's jet pod
+    Jetman,
+    /// The friendly craft the player is escorting
+    Escort,
+}
+
+/// The kinds of hostile drones, mixed into the waves sent in
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum EnemyKind {
+    /// The common drone
+    Drone,
+    /// A fast and light drone that hits softly
+    Dart,
+    /// A slow and heavy drone that hits hard
+    Brute,
+}
+
+impl EnemyKind {
+    /// The kind of drone going by the given name in level files
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "drone" => Some(EnemyKind::Drone),
+            "dart" => Some(EnemyKind::Dart),
+            "brute" => Some(EnemyKind::Brute),
+            _ => None,
+        }
+    }
+
+    /// The top speed of the kind of drone
+    pub fn speed(&self) -> f32 {
+        match self {
+            EnemyKind::Drone => 1.2,
+            EnemyKind::Dart => 2.0,
+            EnemyKind::Brute => 0.8,
+        }
+    }
+
+    /// The damage the kind of drone deals ramming Jetman
+    pub fn damage(&self) -> f32 {
+        match self {
+            EnemyKind::Drone => 0.1,
+            EnemyKind::Dart => 0.05,
+            EnemyKind::Brute => 0.25,
+        }
+    }
+
+    /// The size of the kind of drone, relative to the common drone
+    fn size(&self) -> f32 {
+        match self {
+            EnemyKind::Drone => 1.0,
+            EnemyKind::Dart => 0.8,
+            EnemyKind::Brute => 1.5,
+        }
+    }
+
+    /// The color the kind of drone is drawn in
+    fn color(&self) -> Color {
+        match self {
+            EnemyKind::Drone => RED,
+            EnemyKind::Dart => ORANGE,
+            EnemyKind::Brute => MAROON,
+        }
+    }
+}
+
+/// The component of a hostile drone that rams into its target
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Enemy {
+    /// The kind of drone
+    pub kind: EnemyKind,
+    /// The entity the drone is currently going after
+    pub target: Target,
+    /// The top speed of the drone
+    pub speed: f32,
+}
+
+impl Enemy {
+    /// The distance at which the drone hits its target
+    pub const HIT_DISTANCE: f32 = 16.0;
+    /// The mass of a drone's body
+    pub const MASS: f32 = 1.0;
+
+    /// Create a new common drone
+    pub fn new() -> Self {
+        Enemy::of_kind(EnemyKind::Drone)
+    }
+
+    /// Create a new drone of the given kind
+    pub fn of_kind(kind: EnemyKind) -> Self {
+        Enemy {
+            kind,
+            target: Target::Jetman,
+            speed: kind.speed(),
+        }
+    }
+
+    /// Pick the entity to go after from the given position. Drones prefer
+    /// the escorted craft unless Jetman gets a lot closer to them.
+    pub fn choose_target(&mut self, position: Vec2, jetman: Vec2, escort: Option<Vec2>) {
+        self.target = match escort {
+            Some(escort) if (escort - position).length() < 2.0 * (jetman - position).length() => {
+                Target::Escort
+            }
+            _ => Target::Jetman,
+        };
+    }
+
+    /// Steer the drone's body towards the given target position
+    pub fn steer(&self, body: &mut Body, target: Vec2) {
+        let desired = (target - body.position).normalize_or_zero() * self.speed;
+        let steering = (desired - body.velocity) * 0.05;
+        body.apply_force(steering * body.mass);
+    }
+
+    /// Draw the drone pointing in its direction of flight, animated if it has an animator,
+    /// or as a triangle
+    pub fn draw(&self, body: &Body, animator: Option<&Animator>) {
+        let position = body.position;
+        let dir = body.velocity.normalize_or(Vec2::X);
+        let rotation = dir.to_angle() + std::f32::consts::FRAC_PI_2;
+        let size = self.kind.size();
+        if animator
+            .is_some_and(|animator| animator.draw(position, vec2(20.0, 20.0) * size, rotation))
+        {
+            return;
+        }
+        let (dir, right) = (dir * size, vec2(-dir.y, dir.x) * size);
+        draw_triangle(
+            position + dir * 10.0,
+            position - dir * 6.0 + right * 7.0,
+            position - dir * 6.0 - right * 7.0,
+            self.kind.color(),
+        );
+    }
+}
+
+impl Default for Enemy {
+    fn default() -> Self {
+        Enemy::new()
+    }
+}
+
+/// The component of an entity that damages Jetman's hull on contact
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Hazard {
+    /// The damage dealt on contact
+    pub damage: f32,
+    /// The distance at which the hazard touches Jetman
+    pub reach: f32,
+    /// Whether the hazard is destroyed by the contact, like a drone ramming Jetman
+    pub single_use: bool,
+}
+
+/// A group of drones that appears at a given time
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Wave {
+    /// The time in seconds after the start of the level at which the wave appears
+    pub time: f32,
+    /// The number of drones in the wave
+    pub count: usize,
+}
+
+/// A stationary gun emplacement that tracks Jetman and shoots at him, with laser
+/// bolts or, for a launcher, with homing missiles
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Turret {
+    /// The position of the turret's base
+    pub position: Vec2,
+    /// The direction the barrel points in
+    pub aim: f32,
+    /// The time in seconds until the turret can fire again
+    pub cooldown: f32,
+    /// The integrity of the turret, from 1.0 (intact) down to 0.0 (destroyed)
+    pub health: f32,
+    /// Whether the turret launches homing missiles rather than firing bolts
+    pub launcher: bool,
+}
+
+impl Turret {
+    /// The distance at which the turret spots Jetman
+    pub const RANGE: f32 = 400.0;
+    /// The distance at which bolts and falling items hit the turret
+    pub const HIT_DISTANCE: f32 = 14.0;
+    /// The time in seconds between two shots
+    const COOLDOWN: f32 = 1.2;
+    /// The time in seconds between two missiles of a launcher
+    const LAUNCHER_COOLDOWN: f32 = 5.0;
+    /// How fast the barrel turns, in radians per update
+    const TURN_RATE: f32 = 0.04;
+
+    /// Create a new turret pointing up
+    pub fn new(position: Vec2) -> Self {
+        Turret {
+            position,
+            aim: -std::f32::consts::FRAC_PI_2,
+            cooldown: Self::COOLDOWN,
+            health: 1.0,
+            launcher: false,
+        }
+    }
+
+    /// Create a new missile launcher pointing up
+    pub fn launcher(position: Vec2) -> Self {
+        Turret {
+            cooldown: Self::LAUNCHER_COOLDOWN,
+            launcher: true,
+            ..Turret::new(position)
+        }
+    }
+
+    /// Check whether the turret has been destroyed
+    pub fn is_destroyed(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    /// Reduce the turret's integrity by the given amount
+    pub fn damage(&mut self, amount: f32) {
+        self.health = (self.health - amount).max(0.0);
+    }
+
+    /// The tip of the barrel, where the bolts come out
+    pub fn muzzle(&self) -> Vec2 {
+        self.position + Vec2::from_angle(self.aim) * 14.0
+    }
+
+    /// Swing the barrel towards the target if the turret can see it,
+    /// reloading faster or slower depending on `fire_rate`.
+    /// Returns the velocity of a new bolt or missile if the turret fires.
+    pub fn update(&mut self, target: Option<Vec2>, seconds: f32, fire_rate: f32) -> Option<Vec2> {
+        self.cooldown = (self.cooldown - seconds * fire_rate).max(0.0);
+        let target = target.filter(|t| (*t - self.position).length() < Self::RANGE)?;
+        let desired = (target - self.position).to_angle();
+        let error = (desired - self.aim + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        self.aim += error.clamp(-Self::TURN_RATE, Self::TURN_RATE);
+        if error.abs() < 0.1 && self.cooldown <= 0.0 {
+            if self.launcher {
+                self.cooldown = Self::LAUNCHER_COOLDOWN;
+                return Some(Vec2::from_angle(self.aim) * 1.5);
+            }
+            self.cooldown = Self::COOLDOWN;
+            return Some(Vec2::from_angle(self.aim) * 5.0);
+        }
+        None
+    }
+
+    /// Draw the turret's dome and barrel, a launcher's barrel a wide tube
+    pub fn draw(&self) {
+        let (x, y) = (self.position.x, self.position.y);
+        let muzzle = self.muzzle();
+        let width = if self.launcher { 8.0 } else { 4.0 };
+        draw_line(x, y, muzzle.x, muzzle.y, width, GRAY);
+        draw_circle(x, y, 9.0, Color::from_hex(0x7A1F1F));
+        draw_circle_lines(x, y, 9.0, 1.0, RED);
+    }
+}
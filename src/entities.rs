@@ -0,0 +1,257 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{Animator, Clip};
+use crate::anomalies::Anomaly;
+use crate::ecs::{Allocator, Entity, Storage};
+use crate::enemy::{Enemy, Hazard, Turret};
+use crate::physics::{Body, Item, ItemKind, Link, Restless, Teleporter, TeleporterKind, Towable};
+use crate::pickups::Pickup;
+use crate::render::{DrawList, Layer};
+
+/// How an entity is drawn. Entities are drawn on their layer in the order of the
+/// variants, so later variants end up on top of earlier ones.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum Renderable {
+    /// Drawn from the entity's anomaly component
+    Anomaly,
+    /// Drawn from the entity's teleporter component
+    Teleporter,
+    /// Drawn from the entity's towable component at its body's position
+    Item,
+    /// Drawn from the entity's pickup component
+    Pickup,
+    /// Drawn from the entity's turret component
+    Turret,
+    /// Drawn from the entity's enemy component along its body's direction of flight
+    Drone,
+}
+
+impl Renderable {
+    /// The layer the entity is drawn on
+    pub fn layer(&self) -> Layer {
+        match self {
+            Renderable::Anomaly => Layer::Background,
+            Renderable::Teleporter | Renderable::Item | Renderable::Pickup => Layer::Items,
+            Renderable::Turret | Renderable::Drone => Layer::Actors,
+        }
+    }
+
+    /// The color the entity shows up in on the radar
+    pub fn radar_color(&self) -> Color {
+        match self {
+            Renderable::Anomaly => VIOLET,
+            Renderable::Teleporter => YELLOW,
+            Renderable::Item => LIGHTGRAY,
+            Renderable::Pickup => MAGENTA,
+            Renderable::Turret | Renderable::Drone => RED,
+        }
+    }
+}
+
+/// All entities of the game world, stored as one collection per component type.
+/// A new kind of entity is a new combination of components, plus a system in
+/// the world's schedule for whatever behavior is new about it.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Entities {
+    allocator: Allocator,
+    pub bodies: Storage<Body>,
+    pub renderables: Storage<Renderable>,
+    pub towables: Storage<Towable>,
+    pub pickups: Storage<Pickup>,
+    pub restless: Storage<Restless>,
+    pub links: Storage<Link>,
+    pub teleporters: Storage<Teleporter>,
+    pub hazards: Storage<Hazard>,
+    pub enemies: Storage<Enemy>,
+    pub turrets: Storage<Turret>,
+    pub anomalies: Storage<Anomaly>,
+    pub animators: Storage<Animator>,
+}
+
+impl Entities {
+    /// Create an empty set of entities
+    pub fn new() -> Self {
+        Entities::default()
+    }
+
+    /// Create a new entity without any components
+    pub fn spawn(&mut self) -> Entity {
+        self.allocator.allocate()
+    }
+
+    /// Remove an entity along with all its components
+    pub fn despawn(&mut self, entity: Entity) {
+        self.bodies.remove(entity);
+        self.renderables.remove(entity);
+        self.towables.remove(entity);
+        self.pickups.remove(entity);
+        self.restless.remove(entity);
+        self.links.remove(entity);
+        self.teleporters.remove(entity);
+        self.hazards.remove(entity);
+        self.enemies.remove(entity);
+        self.turrets.remove(entity);
+        self.anomalies.remove(entity);
+        self.animators.remove(entity);
+    }
+
+    /// Spawn an item Jetman can tow
+    pub fn spawn_item(&mut self, item: Item) -> Entity {
+        let entity = self.spawn();
+        let (body, towable) = item.into_parts();
+        if towable.kind == ItemKind::Livestock {
+            self.restless.insert(entity, Restless::new());
+        }
+        self.bodies.insert(entity, body);
+        self.towables.insert(entity, towable);
+        self.renderables.insert(entity, Renderable::Item);
+        entity
+    }
+
+    /// Spawn a gem or fuel cell Jetman collects on contact
+    pub fn spawn_pickup(&mut self, pickup: Pickup) -> Entity {
+        let entity = self.spawn();
+        self.pickups.insert(entity, pickup);
+        self.renderables.insert(entity, Renderable::Pickup);
+        entity
+    }
+
+    /// Spawn a teleporter
+    pub fn spawn_teleporter(&mut self, teleporter: Teleporter) -> Entity {
+        let entity = self.spawn();
+        let clip = match teleporter.kind {
+            TeleporterKind::Goal => Clip::GoalPulse,
+            TeleporterKind::Disposal => Clip::DisposalPulse,
+        };
+        self.teleporters.insert(entity, teleporter);
+        self.animators.insert(entity, Animator::new(clip));
+        self.renderables.insert(entity, Renderable::Teleporter);
+        entity
+    }
+
+    /// Spawn a common drone at the given position that rams whatever it goes after
+    pub fn spawn_drone(&mut self, position: Vec2) -> Entity {
+        self.spawn_enemy(position, Enemy::new())
+    }
+
+    /// Spawn a drone at the given position that rams whatever it goes after
+    pub fn spawn_enemy(&mut self, position: Vec2, enemy: Enemy) -> Entity {
+        let entity = self.spawn();
+        self.bodies.insert(entity, Body::new(position, Enemy::MASS));
+        self.hazards.insert(
+            entity,
+            Hazard {
+                damage: enemy.kind.damage(),
+                reach: Enemy::HIT_DISTANCE,
+                single_use: true,
+            },
+        );
+        self.enemies.insert(entity, enemy);
+        self.animators.insert(entity, Animator::new(Clip::Drone));
+        self.renderables.insert(entity, Renderable::Drone);
+        entity
+    }
+
+    /// Spawn a turret
+    pub fn spawn_turret(&mut self, turret: Turret) -> Entity {
+        let entity = self.spawn();
+        self.turrets.insert(entity, turret);
+        self.renderables.insert(entity, Renderable::Turret);
+        entity
+    }
+
+    /// Spawn a black hole or anti-grav bubble
+    pub fn spawn_anomaly(&mut self, anomaly: Anomaly) -> Entity {
+        let entity = self.spawn();
+        self.anomalies.insert(entity, anomaly);
+        self.renderables.insert(entity, Renderable::Anomaly);
+        entity
+    }
+
+    /// Where the link holding a towed item is attached, on the item's edge
+    pub fn attachment(&self, entity: Entity) -> Option<Vec2> {
+        let body = self.bodies.get(entity)?;
+        Some(self.towables.get(entity)?.attachment_point(body.position))
+    }
+
+    /// The position of an entity, if it has one
+    pub fn position(&self, entity: Entity) -> Option<Vec2> {
+        if let Some(body) = self.bodies.get(entity) {
+            return Some(body.position);
+        }
+        if let Some(teleporter) = self.teleporters.get(entity) {
+            return Some(teleporter.position);
+        }
+        if let Some(pickup) = self.pickups.get(entity) {
+            return Some(pickup.position);
+        }
+        if let Some(anomaly) = self.anomalies.get(entity) {
+            return Some(anomaly.position);
+        }
+        self.turrets.get(entity).map(|turret| turret.position)
+    }
+
+    /// The towable items along with their positions
+    pub fn items(&self) -> impl Iterator<Item = (Entity, &Towable, Vec2)> {
+        self.towables.iter().filter_map(|(entity, towable)| {
+            let body = self.bodies.get(entity)?;
+            Some((entity, towable, body.position))
+        })
+    }
+
+    /// Queue the renderable entities for which `visible` holds at their position
+    /// on their layers
+    pub fn queue_draws<'a>(&'a self, list: &mut DrawList<'a>, visible: impl Fn(Vec2) -> bool) {
+        let mut renderables = self.renderables.iter().collect::<Vec<_>>();
+        renderables.sort_by_key(|(_, renderable)| **renderable);
+        for (entity, renderable) in renderables {
+            if !self.position(entity).is_some_and(&visible) {
+                continue;
+            }
+            list.push(renderable.layer(), move || {
+                self.draw_entity(entity, *renderable)
+            });
+        }
+    }
+
+    /// Draw an entity the way it's rendered
+    fn draw_entity(&self, entity: Entity, renderable: Renderable) {
+        match renderable {
+            Renderable::Anomaly => {
+                if let Some(anomaly) = self.anomalies.get(entity) {
+                    anomaly.draw();
+                }
+            }
+            Renderable::Teleporter => {
+                if let Some(teleporter) = self.teleporters.get(entity) {
+                    teleporter.draw(self.animators.get(entity));
+                }
+            }
+            Renderable::Item => {
+                if let (Some(towable), Some(body)) =
+                    (self.towables.get(entity), self.bodies.get(entity))
+                {
+                    towable.draw(body.position);
+                }
+            }
+            Renderable::Pickup => {
+                if let Some(pickup) = self.pickups.get(entity) {
+                    pickup.draw();
+                }
+            }
+            Renderable::Turret => {
+                if let Some(turret) = self.turrets.get(entity) {
+                    turret.draw();
+                }
+            }
+            Renderable::Drone => {
+                if let (Some(enemy), Some(body)) =
+                    (self.enemies.get(entity), self.bodies.get(entity))
+                {
+                    enemy.draw(body, self.animators.get(entity));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::{Bodied, Body};
+
+/// A slow friendly craft following a scripted path, which Jetman has to protect
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Escort {
+    /// The craft's physics body
+    pub body: Body,
+    /// The waypoints the craft flies along, in order
+    pub path: Vec<Vec2>,
+    /// The index of the waypoint the craft is currently heading to
+    pub next_waypoint: usize,
+    /// The cruising speed of the craft
+    pub speed: f32,
+    /// The integrity of the craft's hull, from 1.0 (intact) down to 0.0 (destroyed)
+    pub health: f32,
+}
+
+impl Escort {
+    /// Create a new escort craft starting at the first waypoint of the path
+    pub fn new(path: Vec<Vec2>) -> Self {
+        let start = path.first().copied().unwrap_or_default();
+        Escort {
+            body: Body::new(start, 5.0),
+            path,
+            next_waypoint: 1,
+            speed: 0.4,
+            health: 1.0,
+        }
+    }
+
+    /// Check whether the craft has reached the end of its path
+    pub fn has_arrived(&self) -> bool {
+        self.next_waypoint >= self.path.len()
+    }
+
+    /// Check whether the craft has been destroyed
+    pub fn is_destroyed(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    /// Reduce the hull integrity by the given amount
+    pub fn damage(&mut self, amount: f32) {
+        self.health = (self.health - amount).max(0.0);
+    }
+
+    /// The fraction of the path the craft has covered so far, from 0.0 to 1.0
+    pub fn progress(&self) -> f32 {
+        let segments = self.path.windows(2).map(|w| (w[1] - w[0]).length());
+        let total: f32 = segments.clone().sum();
+        if total == 0.0 || self.has_arrived() {
+            return 1.0;
+        }
+        let covered: f32 = segments.take(self.next_waypoint - 1).sum();
+        let remaining = (self.path[self.next_waypoint] - self.body.position).length();
+        let current = (self.path[self.next_waypoint] - self.path[self.next_waypoint - 1]).length();
+        ((covered + (current - remaining).max(0.0)) / total).clamp(0.0, 1.0)
+    }
+
+    /// Fly along the path towards the next waypoint
+    pub fn update(&mut self, dt: f32) {
+        if self.has_arrived() || self.is_destroyed() {
+            self.body.velocity = Vec2::ZERO;
+            return;
+        }
+        let target = self.path[self.next_waypoint];
+        let delta = target - self.body.position;
+        if delta.length() < self.speed * dt + 1.0 {
+            self.next_waypoint += 1;
+        }
+        // the craft keeps a steady pace and is not affected by gravity
+        self.body.velocity = delta.normalize_or_zero() * self.speed;
+        self.body.update(dt);
+    }
+
+    /// Draw the craft with its health bar
+    pub fn draw(&self) {
+        let position = self.body.position;
+        let heading = self.body.velocity.y.atan2(self.body.velocity.x);
+        draw_ellipse(
+            position.x,
+            position.y,
+            24.0,
+            10.0,
+            heading.to_degrees(),
+            Color::from_hex(0x8A9BB0),
+        );
+        draw_ellipse_lines(
+            position.x,
+            position.y,
+            24.0,
+            10.0,
+            heading.to_degrees(),
+            1.0,
+            WHITE,
+        );
+        draw_rectangle(position.x - 20.0, position.y - 20.0, 40.0, 4.0, DARKGRAY);
+        draw_rectangle(
+            position.x - 20.0,
+            position.y - 20.0,
+            40.0 * self.health,
+            4.0,
+            GREEN,
+        );
+    }
+}
+
+impl Bodied for Escort {
+    /// Get a reference to the craft's physics body
+    fn body(&self) -> &Body {
+        &self.body
+    }
+
+    /// Get a mutable reference to the craft's physics body
+    fn body_mut(&mut self) -> &mut Body {
+        &mut self.body
+    }
+}
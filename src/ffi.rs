@@ -0,0 +1,113 @@
+use std::ffi::{CStr, c_char};
+use std::ptr;
+
+use crate::headless::{Controls, OBSERVATION_SIZE, Outcome, Rewards, Simulation};
+
+/// Start simulating the level in a level file, generating its random parts from `seed`.
+/// Returns null if the level can't be loaded.
+///
+/// # Safety
+///
+/// `path` must point to a null-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jetman_simulation_new(path: *const c_char, seed: u64) -> *mut Simulation {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    // SAFETY: the caller promises a null-terminated string
+    let path = unsafe { CStr::from_ptr(path) };
+    let Ok(path) = path.to_str() else {
+        return ptr::null_mut();
+    };
+    match Simulation::load(path, seed) {
+        Ok(simulation) => Box::into_raw(Box::new(simulation)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Stop a simulation and free it
+///
+/// # Safety
+///
+/// `simulation` must be null or come from `jetman_simulation_new`, and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jetman_simulation_free(simulation: *mut Simulation) {
+    if !simulation.is_null() {
+        // SAFETY: the caller hands back ownership of a simulation made by `jetman_simulation_new`
+        drop(unsafe { Box::from_raw(simulation) });
+    }
+}
+
+/// Start the level over, with the randomness while playing it driven by `seed`
+///
+/// # Safety
+///
+/// `simulation` must come from `jetman_simulation_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jetman_simulation_reset(simulation: *mut Simulation, seed: u64) {
+    // SAFETY: the caller promises a live simulation
+    if let Some(simulation) = unsafe { simulation.as_mut() } {
+        simulation.reset(seed);
+    }
+}
+
+/// Reward the agent as given from now on
+///
+/// # Safety
+///
+/// `simulation` must come from `jetman_simulation_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jetman_simulation_set_rewards(
+    simulation: *mut Simulation,
+    rewards: Rewards,
+) {
+    // SAFETY: the caller promises a live simulation
+    if let Some(simulation) = unsafe { simulation.as_mut() } {
+        simulation.set_rewards(rewards);
+    }
+}
+
+/// Advance the world by one frame with the given controls
+///
+/// # Safety
+///
+/// `simulation` must come from `jetman_simulation_new`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jetman_simulation_step(
+    simulation: *mut Simulation,
+    controls: Controls,
+) -> Outcome {
+    // SAFETY: the caller promises a live simulation
+    match unsafe { simulation.as_mut() } {
+        Some(simulation) => simulation.step(controls),
+        None => Outcome {
+            reward: 0.0,
+            done: true,
+        },
+    }
+}
+
+/// Write the state of the world into `observation`, as much of it as fits into `length`
+/// values. Returns the number of values in a full observation.
+///
+/// # Safety
+///
+/// `simulation` must come from `jetman_simulation_new`, and `observation` must point to
+/// room for `length` floats.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn jetman_simulation_observe(
+    simulation: *const Simulation,
+    observation: *mut f32,
+    length: usize,
+) -> usize {
+    // SAFETY: the caller promises a live simulation
+    if let Some(simulation) = unsafe { simulation.as_ref() }
+        && !observation.is_null()
+    {
+        let values = simulation.observe().to_array();
+        let count = length.min(values.len());
+        // SAFETY: the caller promises room for `length` floats, and no more than that are written
+        unsafe { ptr::copy_nonoverlapping(values.as_ptr(), observation, count) };
+    }
+    OBSERVATION_SIZE
+}
@@ -0,0 +1,77 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::Body;
+use crate::snapshot::RectDef;
+
+/// A body of water that lets things float and slows them down
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Water {
+    /// The area covered by the water, its top edge being the surface
+    #[serde(with = "RectDef")]
+    pub rect: Rect,
+    /// How strongly the water pushes submerged bodies up, relative to gravity.
+    /// Values above 1.0 make bodies float.
+    pub buoyancy: f32,
+    /// The quadratic drag coefficient slowing down bodies moving through the water
+    pub drag: f32,
+}
+
+impl Water {
+    /// The depth over which buoyancy builds up below the surface
+    const SURFACE_DEPTH: f32 = 10.0;
+
+    /// Create a new body of water
+    pub fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Water {
+            rect: Rect::new(x, y, w, h),
+            buoyancy: 1.4,
+            drag: 0.02,
+        }
+    }
+
+    /// Check whether a point is under water
+    pub fn contains(&self, point: Vec2) -> bool {
+        self.rect.contains(point)
+    }
+
+    /// Check whether a body has just dived in through the surface during the last step
+    pub fn entered(&self, body: &Body, dt: f32) -> bool {
+        let previous = body.position - body.velocity * dt;
+        self.contains(body.position) && previous.y < self.rect.y
+    }
+
+    /// Apply buoyancy and drag to a body in the water
+    pub fn apply(&self, body: &mut Body, gravity: Vec2) {
+        if !self.contains(body.position) {
+            return;
+        }
+        // bodies just below the surface are only partially submerged
+        let depth = body.position.y - self.rect.y;
+        let submerged = (depth / Self::SURFACE_DEPTH).min(1.0);
+        body.apply_force(-gravity * body.mass * self.buoyancy * submerged);
+
+        let speed = body.velocity.length();
+        body.apply_force(-body.velocity * speed * self.drag);
+    }
+
+    /// Draw the water on top of whatever is submerged in it
+    pub fn draw(&self) {
+        let rect = self.rect;
+        draw_rectangle(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            Color::new(0.1, 0.3, 0.8, 0.45),
+        );
+        draw_line(
+            rect.x,
+            rect.y,
+            rect.x + rect.w,
+            rect.y,
+            2.0,
+            Color::new(0.5, 0.7, 1.0, 0.9),
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::vector_from_angle;
+
+/// Where the jet pod was at a moment of a run
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Pose {
+    /// The time in seconds since the start of the level
+    pub time: f32,
+    pub position: Vec2,
+    pub heading: f32,
+}
+
+/// The path a previous run took, flown alongside the current attempt as a translucent pod
+#[derive(Clone, Debug)]
+pub struct Ghost {
+    /// The poses of the run, in the order of their times
+    poses: Vec<Pose>,
+}
+
+impl Ghost {
+    /// The directory the ghosts of the best runs are kept in
+    pub const DIR: &str = "ghosts";
+
+    /// Create a ghost following the given poses
+    pub fn new(poses: Vec<Pose>) -> Self {
+        Ghost { poses }
+    }
+
+    /// The file the ghost of the best run of the given level is kept in
+    pub fn path(level_name: &str) -> PathBuf {
+        let name = level_name
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        PathBuf::from(Self::DIR).join(format!("{name}.replay"))
+    }
+
+    /// The pose of the run at the given time since the start of the level, in between
+    /// the recorded poses, or `None` once the run has ended
+    pub fn pose_at(&self, time: f32) -> Option<Pose> {
+        let next = self.poses.partition_point(|pose| pose.time < time);
+        let after = self.poses.get(next)?;
+        let Some(before) = next.checked_sub(1).map(|index| self.poses[index]) else {
+            return Some(*after);
+        };
+        let span = after.time - before.time;
+        let t = if span > 0.0 {
+            (time - before.time) / span
+        } else {
+            1.0
+        };
+        // turn the short way round, in case the heading wrapped
+        let turn = (after.heading - before.heading + std::f32::consts::PI)
+            .rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        Some(Pose {
+            time,
+            position: before.position.lerp(after.position, t),
+            heading: before.heading + turn * t,
+        })
+    }
+
+    /// Draw the ghost pod where the run was at the given time
+    pub fn draw(&self, time: f32) {
+        let Some(pose) = self.pose_at(time) else {
+            return;
+        };
+        let angle = pose.heading.to_degrees() + 90.0;
+        let nose = pose.position + vector_from_angle(pose.heading) * 14.0;
+        let (x, y) = (pose.position.x, pose.position.y);
+        draw_ellipse(x, y, 10.0, 14.0, angle, Color::new(0.6, 0.8, 1.0, 0.25));
+        draw_ellipse_lines(x, y, 10.0, 14.0, angle, 1.5, Color::new(0.6, 0.8, 1.0, 0.6));
+        draw_line(x, y, nose.x, nose.y, 1.5, Color::new(0.6, 0.8, 1.0, 0.6));
+    }
+}
@@ -0,0 +1,597 @@
+use std::f32::consts::TAU;
+use std::path::Path;
+
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::Vec2;
+
+use crate::level::Level;
+use crate::level_file;
+use crate::physics::TeleporterKind;
+use crate::ui::InputState;
+use crate::world::World;
+
+/// The length of a frame a simulation steps by, as if running at 60 frames per second
+pub const FRAME_SECONDS: f32 = World::FIXED_STEP;
+
+/// The number of rays cast from the pod to find the terrain around it
+pub const RAYS: usize = 8;
+
+/// How far the rays reach
+pub const RAY_RANGE: f32 = 400.0;
+
+/// The number of values in an observation turned into plain numbers
+pub const OBSERVATION_SIZE: usize = 9 + RAYS + 8 + 5;
+
+/// What the jet pod is told to do during a frame
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Controls {
+    pub thrust: bool,
+    pub turn_left: bool,
+    pub turn_right: bool,
+    pub sever: bool,
+    pub reel_in: bool,
+    pub reel_out: bool,
+    pub fire: bool,
+}
+
+impl Controls {
+    /// The number of discrete actions: each way of turning, with and without thrust
+    pub const ACTIONS: usize = 6;
+
+    /// The controls of a discrete action, for agents picking one of `ACTIONS` numbers.
+    /// Actions 0 to 2 coast straight, to the left and to the right, and actions 3 to 5
+    /// do the same with thrust. Numbers past the last action coast straight.
+    pub fn from_action(action: usize) -> Self {
+        if action >= Self::ACTIONS {
+            return Controls::default();
+        }
+        Controls {
+            thrust: action >= 3,
+            turn_left: action % 3 == 1,
+            turn_right: action % 3 == 2,
+            ..Default::default()
+        }
+    }
+
+    /// The input carrying out the controls
+    pub fn input(&self) -> InputState {
+        InputState {
+            thrust: self.thrust,
+            turn_left: self.turn_left,
+            turn_right: self.turn_right,
+            sever_link: self.sever,
+            reel_in: self.reel_in,
+            reel_out: self.reel_out,
+            fire: self.fire,
+            ..Default::default()
+        }
+    }
+}
+
+/// What an agent gets to know about the world. Positions of other things are
+/// given relative to the pod, and are zero if there's no such thing.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Observation {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub heading: f32,
+    /// The fuel left, as a fraction of the tank's capacity
+    pub fuel: f32,
+    pub health: f32,
+    /// Whether the pod is towing an item
+    pub towing: bool,
+    /// The length of the tractor beam
+    pub link_distance: f32,
+    /// The distance to the terrain along rays spread evenly around the pod, starting
+    /// straight ahead and going clockwise, as a fraction of `RAY_RANGE`.
+    /// Rays not hitting any terrain are 1.0.
+    pub rays: [f32; RAYS],
+    /// The way to the nearest item not being towed
+    pub to_item: Vec2,
+    /// The way to the nearest goal teleporter
+    pub to_goal: Vec2,
+    /// The way to the nearest disposal teleporter
+    pub to_disposal: Vec2,
+    /// The way to where the pilot should head next
+    pub to_waypoint: Vec2,
+    /// The number of cargo items delivered
+    pub delivered: u32,
+    /// The number of bombs disposed of
+    pub defused: u32,
+    pub lives: u32,
+    /// Whether the objectives are complete
+    pub complete: bool,
+    /// The time in seconds since the start of the level
+    pub time: f32,
+}
+
+impl Observation {
+    /// Observe a world
+    pub fn new(world: &World) -> Self {
+        let jetman = &world.jetman;
+        let position = jetman.body.position;
+        let towed = world.chain();
+        let nearest = |positions: &mut dyn Iterator<Item = Vec2>| {
+            positions
+                .map(|p| p - position)
+                .min_by(|a, b| a.length().total_cmp(&b.length()))
+                .unwrap_or(Vec2::ZERO)
+        };
+        let teleporter = |kind: TeleporterKind| {
+            nearest(
+                &mut world
+                    .entities
+                    .teleporters
+                    .iter()
+                    .filter(|(_, teleporter)| teleporter.kind == kind)
+                    .map(|(_, teleporter)| teleporter.position),
+            )
+        };
+        let rays = std::array::from_fn(|index| {
+            let direction = Vec2::from_angle(jetman.heading + TAU * index as f32 / RAYS as f32);
+            world
+                .distance_to_terrain(position, direction, RAY_RANGE)
+                .map_or(1.0, |distance| distance / RAY_RANGE)
+        });
+        Observation {
+            position,
+            velocity: jetman.body.velocity,
+            heading: jetman.heading,
+            fuel: jetman.fuel / jetman.max_fuel,
+            health: jetman.health,
+            towing: jetman.linked_item.is_some(),
+            link_distance: jetman.link_distance,
+            rays,
+            to_item: nearest(
+                &mut world
+                    .entities
+                    .items()
+                    .filter(|(entity, _, _)| !towed.contains(entity))
+                    .map(|(_, _, position)| position),
+            ),
+            to_goal: teleporter(TeleporterKind::Goal),
+            to_disposal: teleporter(TeleporterKind::Disposal),
+            to_waypoint: world.waypoint().map_or(Vec2::ZERO, |w| w - position),
+            delivered: world.progress.delivered,
+            defused: world.progress.defused,
+            lives: world.lives(),
+            complete: world.objectives_complete(),
+            time: world.time(),
+        }
+    }
+
+    /// The observation as plain numbers, in the order of the fields
+    pub fn to_array(&self) -> [f32; OBSERVATION_SIZE] {
+        let mut values = [0.0; OBSERVATION_SIZE];
+        let head = [
+            self.position.x,
+            self.position.y,
+            self.velocity.x,
+            self.velocity.y,
+            self.heading,
+            self.fuel,
+            self.health,
+            self.towing as u8 as f32,
+            self.link_distance,
+        ];
+        let tail = [
+            self.to_item.x,
+            self.to_item.y,
+            self.to_goal.x,
+            self.to_goal.y,
+            self.to_disposal.x,
+            self.to_disposal.y,
+            self.to_waypoint.x,
+            self.to_waypoint.y,
+            self.delivered as f32,
+            self.defused as f32,
+            self.lives as f32,
+            self.complete as u8 as f32,
+            self.time,
+        ];
+        for (value, source) in values
+            .iter_mut()
+            .zip(head.iter().chain(&self.rays).chain(&tail))
+        {
+            *value = *source;
+        }
+        values
+    }
+}
+
+/// How much reward an agent gets for what happens during a frame.
+/// Penalties are negative rewards.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rewards {
+    /// For each cargo item delivered
+    pub delivery: f32,
+    /// For each bomb disposed of
+    pub defusal: f32,
+    /// For each bomb going off
+    pub detonation: f32,
+    /// For each unit of fuel burnt
+    pub fuel: f32,
+    /// For each pod lost
+    pub crash: f32,
+    /// For completing the objectives
+    pub completion: f32,
+    /// For each second passing
+    pub time: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Rewards {
+            delivery: 1.0,
+            defusal: 1.0,
+            detonation: -1.0,
+            fuel: -0.01,
+            crash: -1.0,
+            completion: 5.0,
+            time: 0.0,
+        }
+    }
+}
+
+/// What a step of a simulation led to
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Outcome {
+    /// The reward for what happened during the step
+    pub reward: f32,
+    /// Whether the run is over
+    pub done: bool,
+}
+
+/// A game world played without a window, by a program instead of a player,
+/// that can be started over as often as needed
+pub struct Simulation {
+    /// The level the world is created from on every reset
+    level: Level,
+    world: World,
+    rewards: Rewards,
+}
+
+impl Simulation {
+    /// Start simulating a level, rewarding the agent as by default
+    pub fn new(level: Level) -> Self {
+        let world = World::from_level(level.clone());
+        Simulation {
+            level,
+            world,
+            rewards: Rewards::default(),
+        }
+    }
+
+    /// Start simulating the level in a level file, generating its random parts from `seed`
+    pub fn load(path: impl AsRef<Path>, seed: u64) -> Result<Self, String> {
+        level_file::load(path, seed).map(Simulation::new)
+    }
+
+    /// Reward the agent as given from now on
+    pub fn set_rewards(&mut self, rewards: Rewards) {
+        self.rewards = rewards;
+    }
+
+    /// Start the level over, with the randomness while playing it driven by `seed`
+    pub fn reset(&mut self, seed: u64) {
+        self.level.seed = seed;
+        self.world = World::from_level(self.level.clone());
+    }
+
+    /// Advance the world by one frame with the given controls
+    pub fn step(&mut self, controls: Controls) -> Outcome {
+        if self.is_done() {
+            return Outcome {
+                reward: 0.0,
+                done: true,
+            };
+        }
+        let progress = self.world.progress;
+        let deaths = self.world.deaths();
+        let fuel_used = self.world.jetman.fuel_used;
+        let time = self.world.time();
+
+        self.world.step(&controls.input(), FRAME_SECONDS);
+
+        let rewards = &self.rewards;
+        let world = &self.world;
+        let mut reward = rewards.delivery * (world.progress.delivered - progress.delivered) as f32
+            + rewards.defusal * (world.progress.defused - progress.defused) as f32
+            + rewards.detonation * (world.progress.detonated - progress.detonated) as f32
+            + rewards.fuel * (world.jetman.fuel_used - fuel_used)
+            + rewards.crash * (world.deaths() - deaths) as f32
+            + rewards.time * (world.time() - time);
+        if world.objectives_complete() {
+            reward += rewards.completion;
+        }
+        Outcome {
+            reward,
+            done: self.is_done(),
+        }
+    }
+
+    /// Whether the run is over, because the objectives are complete or can't be
+    /// completed anymore, or because the last pod is gone
+    pub fn is_done(&self) -> bool {
+        self.world.objectives_complete()
+            || self.world.objectives_failed()
+            || self.world.is_game_over()
+    }
+
+    /// The world being simulated
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// What an agent gets to know about the world
+    pub fn observe(&self) -> Observation {
+        Observation::new(&self.world)
+    }
+}
+
+/// A simulation wrapped up for reinforcement learning, in the manner of a gym:
+/// `reset` starts an episode and `step` plays it a frame at a time. Every episode
+/// starts the level over with the next seed, and is cut off after a number of frames.
+pub struct Environment {
+    simulation: Simulation,
+    /// The seed of the next episode
+    seed: u64,
+    /// The number of frames played in the current episode
+    frames: usize,
+    /// The number of frames after which an episode is over, even if the run isn't
+    max_frames: usize,
+}
+
+impl Environment {
+    /// An environment playing a level, with the first episode driven by `seed`
+    pub fn new(level: Level, seed: u64) -> Self {
+        Environment {
+            simulation: Simulation::new(level),
+            seed,
+            frames: 0,
+            max_frames: Batch::MAX_FRAMES,
+        }
+    }
+
+    /// An environment playing the level in a level file, generating its random parts
+    /// from `seed`
+    pub fn load(path: impl AsRef<Path>, seed: u64) -> Result<Self, String> {
+        level_file::load(path, seed).map(|level| Environment::new(level, seed))
+    }
+
+    /// Reward the agent as given
+    pub fn with_rewards(mut self, rewards: Rewards) -> Self {
+        self.simulation.set_rewards(rewards);
+        self
+    }
+
+    /// Cut episodes off after the given number of frames
+    pub fn with_max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames.max(1);
+        self
+    }
+
+    /// Start a new episode, returning the first observation
+    pub fn reset(&mut self) -> Observation {
+        self.simulation.reset(self.seed);
+        self.seed = self.seed.wrapping_add(1);
+        self.frames = 0;
+        self.simulation.observe()
+    }
+
+    /// Play a frame of the episode with the given controls, returning what can be
+    /// observed afterwards, the reward for the frame and whether the episode is over
+    pub fn step(&mut self, controls: Controls) -> (Observation, f32, bool) {
+        let outcome = self.simulation.step(controls);
+        self.frames += 1;
+        let done = outcome.done || self.frames >= self.max_frames;
+        (self.simulation.observe(), outcome.reward, done)
+    }
+
+    /// The number of frames played in the current episode
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// The simulation the episodes are played in
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+}
+
+/// Something deciding how to fly a simulated pod, frame by frame
+pub trait Pilot: Send {
+    /// The controls for the next frame, given what can be observed of the world
+    fn controls(&mut self, observation: &Observation) -> Controls;
+}
+
+impl<F: FnMut(&Observation) -> Controls + Send> Pilot for F {
+    fn controls(&mut self, observation: &Observation) -> Controls {
+        self(observation)
+    }
+}
+
+/// A pilot flying at random, holding each choice of controls for a moment.
+/// Random rollouts show where a level is easy to crash in.
+pub struct RandomPilot {
+    rng: StdRng,
+    controls: Controls,
+    /// The number of frames the current controls are held for
+    hold: u32,
+}
+
+impl RandomPilot {
+    /// The longest the pilot holds the same controls, in frames
+    const MAX_HOLD: u32 = 30;
+
+    /// A random pilot making choices driven by `seed`
+    pub fn new(seed: u64) -> Self {
+        RandomPilot {
+            rng: StdRng::seed_from_u64(seed),
+            controls: Controls::default(),
+            hold: 0,
+        }
+    }
+}
+
+impl Pilot for RandomPilot {
+    fn controls(&mut self, _observation: &Observation) -> Controls {
+        if self.hold == 0 {
+            self.hold = self.rng.gen_range(1..=Self::MAX_HOLD);
+            let turn = self.rng.gen_range(-1..=1);
+            self.controls = Controls {
+                thrust: self.rng.gen_bool(0.5),
+                turn_left: turn < 0,
+                turn_right: turn > 0,
+                sever: self.rng.gen_bool(0.01),
+                reel_in: self.rng.gen_bool(0.1),
+                reel_out: self.rng.gen_bool(0.1),
+                fire: self.rng.gen_bool(0.1),
+            };
+        }
+        self.hold -= 1;
+        self.controls
+    }
+}
+
+/// One of the runs in a batch: the seed driving its randomness and who flies it
+pub struct Run {
+    pub seed: u64,
+    pub pilot: Box<dyn Pilot>,
+}
+
+impl Run {
+    /// A run with the given seed, flown by the given pilot
+    pub fn new(seed: u64, pilot: impl Pilot + 'static) -> Self {
+        Run {
+            seed,
+            pilot: Box::new(pilot),
+        }
+    }
+}
+
+/// How a run of a batch turned out
+#[derive(Clone, PartialEq, Debug)]
+pub struct Report {
+    pub seed: u64,
+    /// The number of frames simulated
+    pub frames: usize,
+    /// The reward collected over all frames
+    pub reward: f32,
+    /// Whether the objectives were completed
+    pub complete: bool,
+    /// The number of pods lost
+    pub deaths: u32,
+    /// Where the pods were lost
+    pub crashes: Vec<Vec2>,
+    /// The time in seconds the run took in the game
+    pub time: f32,
+}
+
+/// Many runs of a level, simulated side by side on several threads
+pub struct Batch {
+    level: Level,
+    rewards: Rewards,
+    /// The number of frames after which a run is stopped, even if it isn't over
+    max_frames: usize,
+    threads: usize,
+}
+
+impl Batch {
+    /// The longest a run lasts by default: five minutes of game time
+    pub const MAX_FRAMES: usize = 5 * 60 * 60;
+
+    /// Prepare to run a level, on as many threads as the machine runs at once
+    pub fn new(level: Level) -> Self {
+        Batch {
+            level,
+            rewards: Rewards::default(),
+            max_frames: Self::MAX_FRAMES,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// Reward the pilots as given
+    pub fn with_rewards(mut self, rewards: Rewards) -> Self {
+        self.rewards = rewards;
+        self
+    }
+
+    /// Stop runs after the given number of frames, even if they aren't over
+    pub fn with_max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames;
+        self
+    }
+
+    /// Spread the runs over the given number of threads
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Simulate each run to its end, returning the reports in the order of the runs
+    pub fn run(&self, runs: Vec<Run>) -> Vec<Report> {
+        let mut shares = (0..self.threads).map(|_| vec![]).collect::<Vec<_>>();
+        for (index, run) in runs.into_iter().enumerate() {
+            shares[index % self.threads].push((index, run));
+        }
+        let mut reports = std::thread::scope(|scope| {
+            let workers = shares
+                .into_iter()
+                .map(|share| {
+                    scope.spawn(move || {
+                        share
+                            .into_iter()
+                            .map(|(index, run)| (index, self.simulate(run)))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("a simulation panicked"))
+                .collect::<Vec<_>>()
+        });
+        reports.sort_by_key(|(index, _)| *index);
+        reports.into_iter().map(|(_, report)| report).collect()
+    }
+
+    /// Simulate a single run to its end
+    fn simulate(&self, mut run: Run) -> Report {
+        let mut level = self.level.clone();
+        level.seed = run.seed;
+        let mut simulation = Simulation::new(level);
+        simulation.set_rewards(self.rewards);
+        let mut report = Report {
+            seed: run.seed,
+            frames: 0,
+            reward: 0.0,
+            complete: false,
+            deaths: 0,
+            crashes: vec![],
+            time: 0.0,
+        };
+        while report.frames < self.max_frames {
+            let observation = simulation.observe();
+            let deaths = simulation.world().deaths();
+            let outcome = simulation.step(run.pilot.controls(&observation));
+            if simulation.world().deaths() > deaths {
+                report.crashes.push(observation.position);
+            }
+            report.frames += 1;
+            report.reward += outcome.reward;
+            if outcome.done {
+                break;
+            }
+        }
+        let world = simulation.world();
+        report.complete = world.objectives_complete();
+        report.deaths = world.deaths();
+        report.time = world.time();
+        report
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use image::ImageFormat;
+use image::io::Reader;
+use macroquad::prelude::*;
+
+use crate::terrain::{Terrain, point_in_polygon};
+
+/// The most pixels an image of terrain may have, as many as in 4096 by 4096
+pub const MAX_PIXELS: u64 = 4096 * 4096;
+
+/// Read the terrain painted in a PNG image, as a grayscale heightmap or a black and
+/// white collision mask: wherever the image is brighter than `threshold`, from 0.0
+/// to 1.0, is solid, and transparent pixels are empty.
+///
+/// The outlines of the solid parts are traced with marching squares, which places
+/// them between the pixels in proportion to their brightness, so soft gray edges make
+/// for smooth slopes. They are then simplified until they stray no further from the
+/// traced outline than `Terrain::TOLERANCE`. Caves inside the solid parts are kept
+/// open. Each pixel is `scale` units wide in the world. Images of more than
+/// `MAX_PIXELS` pixels are an error.
+pub fn parse(bytes: &[u8], scale: f32, threshold: f32) -> Result<Vec<Terrain>, String> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(format!("the threshold {threshold} isn't from 0 to 1"));
+    }
+    let (width, height) = Reader::with_format(Cursor::new(bytes), ImageFormat::Png)
+        .into_dimensions()
+        .map_err(|error| error.to_string())?;
+    if width == 0 || height == 0 {
+        return Err("the image is empty".to_string());
+    }
+    if width as u64 * height as u64 > MAX_PIXELS {
+        return Err(format!(
+            "the image is {width} by {height} pixels, more than the {MAX_PIXELS} it may have"
+        ));
+    }
+    let image = image::load_from_memory_with_format(bytes, ImageFormat::Png)
+        .map_err(|error| error.to_string())?
+        .to_luma_alpha8();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    // a border of empty pixels all around, so every outline closes
+    let columns = width + 2;
+    let rows = height + 2;
+    let mut field = vec![0.0; columns * rows];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [brightness, alpha] = pixel.0;
+        field[(y as usize + 1) * columns + x as usize + 1] =
+            brightness as f32 / 255.0 * alpha as f32 / 255.0;
+    }
+    // pixel centers sit in the middle of the pixels, the border ones just outside the image
+    let origin = vec2(-0.5, -0.5) * scale;
+    let outlines = trace(&field, columns, rows, threshold)
+        .into_iter()
+        .map(|outline| {
+            let outline = outline
+                .into_iter()
+                .map(|point| origin + point * scale)
+                .collect::<Vec<_>>();
+            simplify(&outline, Terrain::TOLERANCE)
+        })
+        .filter(|outline| outline.len() >= 3)
+        .collect::<Vec<_>>();
+    Ok(nest(outlines))
+}
+
+/// Trace the outlines between the parts of a field above and below a threshold with
+/// marching squares, in the field's own units of one per value
+fn trace(field: &[f32], columns: usize, rows: usize, threshold: f32) -> Vec<Vec<Vec2>> {
+    let value = |x: usize, y: usize| field[y * columns + x];
+    let solid = |x: usize, y: usize| value(x, y) > threshold;
+    // the edges between neighbouring values, as their first value and whether they
+    // run across to the right or down
+    let crossing = |(x, y, across): (usize, usize, bool)| {
+        let (a, b) = if across {
+            ((x, y), (x + 1, y))
+        } else {
+            ((x, y), (x, y + 1))
+        };
+        let (va, vb) = (value(a.0, a.1), value(b.0, b.1));
+        let share = ((threshold - va) / (vb - va)).clamp(0.0, 1.0);
+        let a = vec2(a.0 as f32, a.1 as f32);
+        let b = vec2(b.0 as f32, b.1 as f32);
+        a.lerp(b, share)
+    };
+
+    // the pieces of outline crossing each cell of four values, between two edges
+    let mut pieces = vec![];
+    for y in 0..rows - 1 {
+        for x in 0..columns - 1 {
+            let top = (x, y, true);
+            let bottom = (x, y + 1, true);
+            let left = (x, y, false);
+            let right = (x + 1, y, false);
+            let case = (solid(x, y) as u8) << 3
+                | (solid(x + 1, y) as u8) << 2
+                | (solid(x + 1, y + 1) as u8) << 1
+                | solid(x, y + 1) as u8;
+            // where two opposite corners are solid, the middle decides whether they join
+            let middle = || {
+                (value(x, y) + value(x + 1, y) + value(x + 1, y + 1) + value(x, y + 1)) / 4.0
+                    > threshold
+            };
+            match case {
+                1 | 14 => pieces.push((left, bottom)),
+                2 | 13 => pieces.push((bottom, right)),
+                3 | 12 => pieces.push((left, right)),
+                4 | 11 => pieces.push((top, right)),
+                6 | 9 => pieces.push((top, bottom)),
+                7 | 8 => pieces.push((left, top)),
+                5 if middle() => pieces.extend([(left, top), (bottom, right)]),
+                5 => pieces.extend([(top, right), (left, bottom)]),
+                10 if middle() => pieces.extend([(top, right), (left, bottom)]),
+                10 => pieces.extend([(left, top), (bottom, right)]),
+                _ => {}
+            }
+        }
+    }
+
+    // join the pieces into closed outlines through the edges they share
+    let mut at_edge = HashMap::<_, Vec<usize>>::new();
+    for (index, (a, b)) in pieces.iter().enumerate() {
+        at_edge.entry(*a).or_default().push(index);
+        at_edge.entry(*b).or_default().push(index);
+    }
+    let mut used = vec![false; pieces.len()];
+    let mut outlines = vec![];
+    for first in 0..pieces.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let (start, mut edge) = pieces[first];
+        let mut outline = vec![crossing(start)];
+        while edge != start {
+            outline.push(crossing(edge));
+            let next = at_edge[&edge].iter().copied().find(|index| !used[*index]);
+            let Some(next) = next else {
+                break;
+            };
+            used[next] = true;
+            let (a, b) = pieces[next];
+            edge = if a == edge { b } else { a };
+        }
+        outlines.push(outline);
+    }
+    outlines
+}
+
+/// Drop the points of a closed outline that lie within `tolerance` of the straight
+/// line between the points kept around them. The outline is split at the point straying
+/// furthest from the line between the ends of each stretch, until none strays further
+/// than `tolerance`.
+fn simplify(outline: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    let count = outline.len();
+    if count <= 3 {
+        return outline.to_vec();
+    }
+    let distance = |from: Vec2, to: Vec2, point: Vec2| {
+        let line = to - from;
+        let along =
+            ((point - from).dot(line) / line.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+        (point - (from + line * along)).length()
+    };
+    // a closed outline has no ends, so it's split at its first point and the point
+    // furthest from it to begin with
+    let first = outline[0];
+    let far = (1..count)
+        .max_by(|a, b| {
+            first
+                .distance(outline[*a])
+                .total_cmp(&first.distance(outline[*b]))
+        })
+        .unwrap_or(1);
+    let mut kept = vec![false; count];
+    kept[0] = true;
+    kept[far] = true;
+    // the stretches still to look at, by their ends; the end `count` is the first point
+    let mut stretches = vec![(0, far), (far, count)];
+    while let Some((from, to)) = stretches.pop() {
+        let (a, b) = (outline[from], outline[to % count]);
+        let furthest = (from + 1..to)
+            .map(|between| (between, distance(a, b, outline[between])))
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+        if let Some((between, strays)) = furthest
+            && strays > tolerance
+        {
+            kept[between] = true;
+            stretches.extend([(from, between), (between, to)]);
+        }
+    }
+    (0..count)
+        .filter(|index| kept[*index])
+        .map(|index| outline[index])
+        .collect()
+}
+
+/// Turn the traced outlines into terrain, the outlines inside an odd number of others
+/// being the caves in the solid part around them
+fn nest(outlines: Vec<Vec<Vec2>>) -> Vec<Terrain> {
+    // the outlines each outline lies inside of
+    let around = outlines
+        .iter()
+        .enumerate()
+        .map(|(index, outline)| {
+            (0..outlines.len())
+                .filter(|other| *other != index && point_in_polygon(outline[0], &outlines[*other]))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut terrain = vec![];
+    for (index, outline) in outlines.iter().enumerate() {
+        if around[index].len() % 2 == 1 {
+            continue;
+        }
+        // the caves right inside, not those in islands inside the caves
+        let holes = (0..outlines.len())
+            .filter(|hole| {
+                around[*hole].len() == around[index].len() + 1 && around[*hole].contains(&index)
+            })
+            .map(|hole| outlines[hole].clone())
+            .collect();
+        terrain.push(Terrain::with_holes(outline.clone(), holes));
+    }
+    terrain
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use crate::dialog::Dialog;
+use crate::ui::InputState;
+use crate::world::World;
+
+/// The help offered to players who keep failing a level, from gentle to generous
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hint {
+    /// Remind the player what the level's objectives are
+    ObjectiveReminder,
+    /// Show the hint line along the route to fly
+    RouteLine,
+    /// Slow the game down
+    SlowMotion,
+}
+
+impl Hint {
+    /// All hints with the number of failures after which they are offered
+    const ESCALATION: [(u32, Hint); 3] = [
+        (1, Hint::ObjectiveReminder),
+        (2, Hint::RouteLine),
+        (4, Hint::SlowMotion),
+    ];
+
+    /// The question asked when offering the hint
+    fn offer(&self) -> &'static str {
+        match self {
+            Hint::ObjectiveReminder => "Want a reminder of what to do here?",
+            Hint::RouteLine => "Want a line showing the way?",
+            Hint::SlowMotion => "Want to slow things down a bit?",
+        }
+    }
+
+    /// Give the hint to the player in the given world
+    fn apply(&self, world: &mut World) {
+        match self {
+            Hint::ObjectiveReminder => world.remind_objectives(),
+            Hint::RouteLine => world.practice = true,
+            Hint::SlowMotion => world.time_scale = 0.6,
+        }
+    }
+}
+
+/// Tracks failed attempts per level and offers escalating help
+pub struct HintSystem {
+    /// Whether hints are offered at all; purists can switch them off
+    pub enabled: bool,
+    /// The number of failed attempts per level name
+    failures: HashMap<String, u32>,
+    /// The deaths already counted in the current world
+    counted_deaths: u32,
+    /// The hints already offered per level name
+    offered: HashMap<String, Vec<Hint>>,
+    /// The hints the player accepted per level name
+    accepted: HashMap<String, Vec<Hint>>,
+    /// The hint currently offered in a prompt, waiting for an answer
+    prompt: Option<(Hint, Dialog)>,
+}
+
+impl HintSystem {
+    /// Create a hint system without any recorded failures
+    pub fn new() -> Self {
+        HintSystem {
+            enabled: true,
+            failures: HashMap::new(),
+            counted_deaths: 0,
+            offered: HashMap::new(),
+            accepted: HashMap::new(),
+            prompt: None,
+        }
+    }
+
+    /// Get ready for a fresh attempt at a level, reapplying the help the player
+    /// already accepted for it
+    pub fn start(&mut self, world: &mut World) {
+        self.counted_deaths = 0;
+        if let Some(accepted) = self.accepted.get(world.level_name()) {
+            for hint in accepted {
+                hint.apply(world);
+            }
+        }
+    }
+
+    /// The number of failed attempts at the given level
+    pub fn failures(&self, level: &str) -> u32 {
+        self.failures.get(level).copied().unwrap_or(0)
+    }
+
+    /// Check whether a prompt is waiting for the player's answer
+    pub fn is_prompting(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    /// Count new failures in the world and offer the next hint if the player is struggling
+    pub fn observe(&mut self, world: &World) {
+        let level = world.level_name().to_string();
+        let new_deaths = world.deaths().saturating_sub(self.counted_deaths);
+        if new_deaths == 0 {
+            return;
+        }
+        self.counted_deaths = world.deaths();
+        let failures = self.failures.entry(level.clone()).or_insert(0);
+        *failures += new_deaths;
+        let failures = *failures;
+
+        if !self.enabled || self.prompt.is_some() {
+            return;
+        }
+        let offered = self.offered.entry(level).or_default();
+        let hint = Hint::ESCALATION
+            .iter()
+            .find(|(threshold, hint)| failures >= *threshold && !offered.contains(hint))
+            .map(|&(_, hint)| hint);
+        offered.extend(hint);
+        self.prompt = hint.map(|hint| (hint, Dialog::confirm("Having trouble?", hint.offer())));
+    }
+
+    /// Let the player answer the open prompt, if any
+    pub fn respond(&mut self, input: &InputState, world: &mut World) {
+        if let Some((_, dialog)) = self.prompt.as_mut()
+            && let Some(choice) = dialog.update(input)
+        {
+            self.answer(choice == 0, world);
+        }
+    }
+
+    /// Answer the open prompt, applying the hint if the player accepted it
+    pub fn answer(&mut self, accept: bool, world: &mut World) {
+        let Some((hint, _)) = self.prompt.take() else {
+            return;
+        };
+        if accept {
+            hint.apply(world);
+            self.accepted
+                .entry(world.level_name().to_string())
+                .or_default()
+                .push(hint);
+        }
+    }
+
+    /// Draw the open prompt, if any
+    pub fn draw(&self) {
+        if let Some((_, dialog)) = &self.prompt {
+            dialog.draw();
+        }
+    }
+}
+
+impl Default for HintSystem {
+    fn default() -> Self {
+        HintSystem::new()
+    }
+}
@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Keeps an eye on files to pick up the changes made to them while the game runs,
+/// like a level being edited. The files are polled for the time they were last
+/// modified every so often, which works the same everywhere and costs next to nothing.
+pub struct Watcher {
+    /// The files watched, with the time each was last seen modified, if it exists
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    /// The seconds until the files are looked at again
+    countdown: f32,
+}
+
+impl Watcher {
+    /// The seconds between two looks at the files
+    const INTERVAL: f32 = 0.5;
+
+    /// Watch no files yet
+    pub fn new() -> Self {
+        Watcher {
+            files: vec![],
+            countdown: Self::INTERVAL,
+        }
+    }
+
+    /// Start watching a file, unless it's watched already
+    pub fn watch(&mut self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        if !self.files.iter().any(|(watched, _)| watched == path) {
+            self.files.push((path.to_path_buf(), modified(path)));
+        }
+    }
+
+    /// Stop watching a file
+    pub fn unwatch(&mut self, path: impl AsRef<Path>) {
+        self.files.retain(|(watched, _)| watched != path.as_ref());
+    }
+
+    /// Let the given seconds pass. Returns the files changed since the last look,
+    /// if it's time for another.
+    pub fn update(&mut self, seconds: f32) -> Vec<PathBuf> {
+        self.countdown -= seconds;
+        if self.countdown > 0.0 {
+            return vec![];
+        }
+        self.countdown = Self::INTERVAL;
+        let mut changed = vec![];
+        for (path, seen) in self.files.iter_mut() {
+            let modified = modified(path);
+            // a file being written may be missing for a moment, which isn't a change yet
+            if modified.is_some() && modified != *seen {
+                *seen = modified;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+impl Default for Watcher {
+    fn default() -> Self {
+        Watcher::new()
+    }
+}
+
+/// The time the file was last modified, None if there's no such file
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
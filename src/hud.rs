@@ -0,0 +1,568 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::objective::{Objective, Progress};
+use crate::physics::Jetman;
+use crate::settings;
+use crate::ui::InputState;
+use crate::warnings::LOW_OXYGEN;
+
+/// The widest aspect ratio the HUD spreads out over
+const MAX_HUD_ASPECT: f32 = 16.0 / 9.0;
+
+/// The centered area of the screen the HUD widgets stay within,
+/// so they don't end up in the far corners of ultrawide screens
+pub fn safe_zone() -> Rect {
+    let width = screen_width().min(screen_height() * MAX_HUD_ASPECT);
+    Rect::new((screen_width() - width) / 2.0, 0.0, width, screen_height())
+}
+
+/// The points of the safe zone HUD widgets are pinned to, so they keep their place
+/// as the window is resized or switched to fullscreen
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Center,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// The point on the screen at the given offset from an anchor
+pub fn anchor(anchor: Anchor, offset: Vec2) -> Vec2 {
+    let zone = safe_zone();
+    let (x, y) = match anchor {
+        Anchor::TopLeft => (0.0, 0.0),
+        Anchor::Top => (0.5, 0.0),
+        Anchor::TopRight => (1.0, 0.0),
+        Anchor::Center => (0.5, 0.5),
+        Anchor::BottomLeft => (0.0, 1.0),
+        Anchor::Bottom => (0.5, 1.0),
+        Anchor::BottomRight => (1.0, 1.0),
+    };
+    vec2(zone.x + zone.w * x, zone.y + zone.h * y) + offset
+}
+
+/// Where a HUD widget is drawn: at an offset from an anchor, if it's shown at all
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Placement {
+    pub anchor: Anchor,
+    /// The offset of the widget's top left corner from the anchor, in pixels
+    pub offset: Vec2,
+    pub shown: bool,
+}
+
+impl Placement {
+    /// Show a widget at an offset from an anchor
+    pub fn new(anchor: Anchor, x: f32, y: f32) -> Self {
+        Placement {
+            anchor,
+            offset: vec2(x, y),
+            shown: true,
+        }
+    }
+
+    /// The point on the screen the widget is drawn at
+    pub fn position(&self) -> Vec2 {
+        anchor(self.anchor, self.offset)
+    }
+}
+
+/// Where the widgets of the HUD are drawn, and which of them are shown. Players lay
+/// them out in the `[hud]` table of their settings, like
+/// `flight = { anchor = "BottomLeft", offset = [10.0, -150.0], shown = true }`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layout {
+    /// The controls, lit up as the player uses them
+    pub controls: Placement,
+    /// The speed, vertical speed, heading, altitude and link tension
+    pub flight: Placement,
+    /// The objectives and the state of the pod
+    pub objectives: Placement,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            controls: Placement::new(Anchor::TopLeft, 10.0, 10.0),
+            flight: Placement::new(Anchor::BottomLeft, 10.0, -140.0),
+            objectives: Placement::new(Anchor::TopRight, -260.0, 30.0),
+        }
+    }
+}
+
+/// What the flight instruments read
+pub struct FlightReadings {
+    pub speed: f32,
+    /// The speed downwards, negative when climbing
+    pub vertical_speed: f32,
+    /// The direction the pod's nose points in, in radians
+    pub heading: f32,
+    /// The height above the terrain below the pod, if there is any in range
+    pub altitude: Option<f32>,
+    /// The tension on the towed chain and the tension it breaks at, while towing
+    pub tension: Option<(f32, f32)>,
+}
+
+/// Draw the flight instruments with their top left corner at the given point:
+/// the speed, the vertical speed, a heading indicator, the altitude and, while
+/// towing, a bar filling up as the link comes under tension
+pub fn draw_flight_panel(at: Vec2, readings: &FlightReadings) {
+    let (w, h) = (190.0, 110.0);
+    draw_rectangle(at.x, at.y, w, h, Color::new(0.0, 0.0, 0.0, 0.5));
+    let x = at.x + 8.0;
+    let mut y = at.y + 20.0;
+    draw_text(&format!("SPD {:.1}", readings.speed), x, y, 18.0, WHITE);
+    y += 20.0;
+    // screen y points down, so a positive vertical speed means descending
+    let (arrow, color) = match readings.vertical_speed {
+        v if v > 0.05 => ("v", WHITE),
+        v if v < -0.05 => ("^", WHITE),
+        _ => ("-", GRAY),
+    };
+    let text = format!("V/S {arrow} {:.1}", readings.vertical_speed.abs());
+    draw_text(&text, x, y, 18.0, color);
+    y += 20.0;
+    let (text, color) = match readings.altitude {
+        Some(altitude) if altitude < 30.0 => (format!("ALT {altitude:.0}"), settings::bad()),
+        Some(altitude) => (format!("ALT {altitude:.0}"), WHITE),
+        None => ("ALT ---".to_string(), GRAY),
+    };
+    draw_text(&text, x, y, 18.0, color);
+    y += 20.0;
+    draw_text("TEN", x, y, 18.0, GRAY);
+    draw_rectangle(x + 40.0, y - 11.0, 80.0, 10.0, DARKGRAY);
+    if let Some((tension, strength)) = readings.tension {
+        let share = (tension / strength).clamp(0.0, 1.0);
+        let color = if share > 0.8 {
+            settings::bad()
+        } else {
+            SKYBLUE
+        };
+        draw_rectangle(x + 40.0, y - 11.0, 80.0 * share, 10.0, color);
+    }
+
+    // the heading indicator: a dial with the nose pointing the way the pod does
+    let center = vec2(at.x + w - 32.0, at.y + 32.0);
+    let radius = 24.0;
+    draw_circle_lines(center.x, center.y, radius, 1.0, GRAY);
+    let nose = Vec2::from_angle(readings.heading);
+    let tip = center + nose * (radius - 3.0);
+    let tail = center - nose * (radius / 3.0);
+    draw_line(tail.x, tail.y, tip.x, tip.y, 2.0, YELLOW);
+    draw_circle(tip.x, tip.y, 3.0, YELLOW);
+    let degrees = readings.heading.to_degrees().rem_euclid(360.0);
+    draw_text(
+        &format!("{degrees:03.0}"),
+        center.x - 12.0,
+        center.y + radius + 16.0,
+        16.0,
+        WHITE,
+    );
+}
+
+/// Format a duration in seconds as minutes, seconds and hundredths, like `1:23.45`
+pub fn format_time(seconds: f32) -> String {
+    // rounded before splitting, so 59.999 seconds make 1:00.00 rather than 0:60.00
+    let hundredths = (seconds.max(0.0) * 100.0).round() as u64;
+    format!(
+        "{}:{:02}.{:02}",
+        hundredths / 6000,
+        hundredths / 100 % 60,
+        hundredths % 100
+    )
+}
+
+/// A blip on the radar screen
+pub struct Contact {
+    /// The position in world coordinates
+    pub position: Vec2,
+    pub color: Color,
+}
+
+/// Draw a radar screen centered on the jet pod, showing the terrain outlines
+/// and contacts within `range` world units
+pub fn draw_radar(
+    center: Vec2,
+    radius: f32,
+    pod: Vec2,
+    range: f32,
+    terrain: &[(Vec2, Vec2)],
+    contacts: &[Contact],
+) {
+    draw_circle(center.x, center.y, radius, Color::new(0.0, 0.1, 0.0, 0.8));
+    draw_circle_lines(center.x, center.y, radius, 1.0, DARKGREEN);
+    draw_circle_lines(center.x, center.y, radius / 2.0, 1.0, DARKGREEN);
+
+    let scale = radius / range;
+    let to_screen = |p: Vec2| center + (p - pod) * scale;
+    let in_range = |p: Vec2| (p - pod).length() < range;
+
+    for &(a, b) in terrain {
+        // clip segments to the radar screen by subdividing them
+        let steps = (((b - a).length() / (range / 20.0)) as usize).max(1);
+        for i in 0..steps {
+            let p = a.lerp(b, i as f32 / steps as f32);
+            let q = a.lerp(b, (i + 1) as f32 / steps as f32);
+            if in_range(p) && in_range(q) {
+                let (p, q) = (to_screen(p), to_screen(q));
+                draw_line(p.x, p.y, q.x, q.y, 1.0, LIME);
+            }
+        }
+    }
+    for contact in contacts.iter().filter(|c| in_range(c.position)) {
+        let p = to_screen(contact.position);
+        draw_circle(p.x, p.y, 2.5, contact.color);
+    }
+    draw_circle(center.x, center.y, 2.0, WHITE);
+    draw_text(
+        "RADAR",
+        center.x - 20.0,
+        center.y + radius + 16.0,
+        16.0,
+        GRAY,
+    );
+}
+
+/// Draw an altimeter tape showing the height above the terrain below the pod,
+/// or "---" if there's no ground in sight
+pub fn draw_altimeter(position: Vec2, altitude: Option<f32>, vertical_speed: f32) {
+    let (w, h) = (70.0, 60.0);
+    draw_rectangle(position.x, position.y, w, h, Color::new(0.0, 0.0, 0.0, 0.7));
+    draw_rectangle_lines(position.x, position.y, w, h, 1.0, GRAY);
+    draw_text("ALT", position.x + 6.0, position.y + 16.0, 16.0, GRAY);
+    let (text, color) = match altitude {
+        Some(altitude) if altitude < 30.0 => (format!("{altitude:.0}"), RED),
+        Some(altitude) => (format!("{altitude:.0}"), WHITE),
+        None => ("---".to_string(), GRAY),
+    };
+    draw_text(&text, position.x + 6.0, position.y + 36.0, 22.0, color);
+    // screen y points down, so a positive vertical speed means descending
+    let arrow = if vertical_speed > 0.05 {
+        "v"
+    } else if vertical_speed < -0.05 {
+        "^"
+    } else {
+        "-"
+    };
+    draw_text(
+        &format!("{arrow} {:.1}", vertical_speed.abs()),
+        position.x + 6.0,
+        position.y + 54.0,
+        16.0,
+        GRAY,
+    );
+}
+
+/// Draw a compass whose needle points from the pod towards the current waypoint
+pub fn draw_waypoint_compass(center: Vec2, radius: f32, pod: Vec2, waypoint: Option<Vec2>) {
+    draw_circle(center.x, center.y, radius, Color::new(0.0, 0.0, 0.0, 0.7));
+    draw_circle_lines(center.x, center.y, radius, 1.0, GRAY);
+    draw_text("WPT", center.x - 14.0, center.y + radius + 16.0, 16.0, GRAY);
+    let Some(waypoint) = waypoint else {
+        return;
+    };
+    let delta = waypoint - pod;
+    let dir = delta.normalize_or_zero();
+    let tip = center + dir * (radius - 4.0);
+    let tail = center - dir * (radius / 3.0);
+    draw_line(tail.x, tail.y, tip.x, tip.y, 2.0, YELLOW);
+    draw_circle(tip.x, tip.y, 3.0, YELLOW);
+    draw_text(
+        &format!("{:.0}", delta.length()),
+        center.x - 14.0,
+        center.y - radius - 6.0,
+        16.0,
+        WHITE,
+    );
+}
+
+/// Draw an artificial horizon: the pod is fixed in the middle pointing up,
+/// the sky and ground halves rotate with the pod's attitude relative to gravity
+pub fn draw_attitude_indicator(center: Vec2, radius: f32, heading: f32, gravity: Vec2) {
+    draw_circle(center.x, center.y, radius, Color::from_hex(0x2B5DA8));
+    draw_text("ATT", center.x - 12.0, center.y + radius + 16.0, 16.0, GRAY);
+
+    // without gravity there's no up or down
+    let Some(down) = gravity.try_normalize() else {
+        draw_circle_lines(center.x, center.y, radius, 1.0, GRAY);
+        draw_text("0 G", center.x - 12.0, center.y + 5.0, 18.0, WHITE);
+        return;
+    };
+
+    // the angle between the pod's nose and "up" as defined by gravity
+    let up = (-down).to_angle();
+    let attitude = (heading - up + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+        - std::f32::consts::PI;
+
+    // in the instrument's frame the pod points up, so the world is rotated by -attitude
+    let ground = Vec2::from_angle(std::f32::consts::FRAC_PI_2 - attitude);
+    let segments = 24;
+    for i in 0..segments {
+        let a = ground.rotate(Vec2::from_angle(
+            -std::f32::consts::FRAC_PI_2 + std::f32::consts::PI * i as f32 / segments as f32,
+        ));
+        let b = ground.rotate(Vec2::from_angle(
+            -std::f32::consts::FRAC_PI_2 + std::f32::consts::PI * (i + 1) as f32 / segments as f32,
+        ));
+        draw_triangle(
+            center,
+            center + a * radius,
+            center + b * radius,
+            Color::from_hex(0x7A4B22),
+        );
+    }
+    let horizon = ground.perp() * radius;
+    draw_line(
+        center.x - horizon.x,
+        center.y - horizon.y,
+        center.x + horizon.x,
+        center.y + horizon.y,
+        2.0,
+        WHITE,
+    );
+
+    // the fixed pod symbol
+    draw_line(
+        center.x - 16.0,
+        center.y,
+        center.x - 5.0,
+        center.y,
+        3.0,
+        YELLOW,
+    );
+    draw_line(
+        center.x + 5.0,
+        center.y,
+        center.x + 16.0,
+        center.y,
+        3.0,
+        YELLOW,
+    );
+    draw_line(center.x, center.y, center.x, center.y - 10.0, 3.0, YELLOW);
+    draw_circle_lines(center.x, center.y, radius, 1.0, GRAY);
+
+    draw_text(
+        &format!("{:+.0}", attitude.to_degrees()),
+        center.x - 14.0,
+        center.y - radius - 6.0,
+        16.0,
+        WHITE,
+    );
+}
+
+/// Draw the pause menu overlay with the given lines of information
+pub fn draw_pause_menu(lines: &[String]) {
+    draw_rectangle(
+        0.0,
+        0.0,
+        screen_width(),
+        screen_height(),
+        Color::new(0.0, 0.0, 0.0, 0.6),
+    );
+    let x = screen_width() / 2.0 - 180.0;
+    let mut y = screen_height() / 2.0 - 80.0;
+    draw_text("PAUSED", x, y, 36.0, WHITE);
+    y += 36.0;
+    for line in lines {
+        draw_text(line, x, y, 20.0, LIGHTGRAY);
+        y += 22.0;
+    }
+    draw_text("Press T to toggle adaptive tuning", x, y + 16.0, 20.0, GRAY);
+    draw_text("Press C to change the trail", x, y + 38.0, 20.0, GRAY);
+    draw_text("Press Escape to resume", x, y + 60.0, 20.0, GRAY);
+    draw_text("Press M for the main menu", x, y + 82.0, 20.0, GRAY);
+}
+
+/// Draw the controls, lighting up the actions the player performs
+pub fn draw_controls(at: Vec2, input: &InputState, jetman: &Jetman) {
+    let Vec2 { x, mut y } = at;
+    let spacing = 20.0;
+    y += spacing;
+    draw_text("Press W for", x, y, 20.0, GRAY);
+    draw_text(
+        "THRUST",
+        x + 100.0,
+        y,
+        20.0,
+        if input.thrust { WHITE } else { GRAY },
+    );
+    y += spacing;
+    draw_text("Press A to turn     , D to turn", x, y, 20.0, GRAY);
+    draw_text(
+        "LEFT",
+        x + 140.0,
+        y,
+        20.0,
+        if input.turn_left { WHITE } else { GRAY },
+    );
+    draw_text(
+        "RIGHT",
+        x + 280.0,
+        y,
+        20.0,
+        if input.turn_right { WHITE } else { GRAY },
+    );
+
+    if input.aim.is_some() {
+        y += spacing;
+        draw_text(
+            "The pod points at the mouse, left click thrusts",
+            x,
+            y,
+            20.0,
+            GRAY,
+        );
+    }
+
+    y += spacing;
+    draw_text("Press Space to", x, y, 20.0, GRAY);
+    draw_text(
+        "FIRE",
+        x + 130.0,
+        y,
+        20.0,
+        if input.fire { WHITE } else { GRAY },
+    );
+
+    y += spacing;
+    draw_text("Press B to        , , and . to", x, y, 20.0, GRAY);
+    draw_text(
+        "BRAKE",
+        x + 100.0,
+        y,
+        20.0,
+        if input.brake { WHITE } else { GRAY },
+    );
+    draw_text(
+        "STRAFE",
+        x + 270.0,
+        y,
+        20.0,
+        if input.strafe_left || input.strafe_right {
+            WHITE
+        } else {
+            GRAY
+        },
+    );
+
+    y += spacing;
+    if jetman.grapple.is_some() {
+        draw_text("Press G to let go of the grappling hook", x, y, 20.0, WHITE);
+    } else {
+        draw_text("Press G to shoot the grappling hook", x, y, 20.0, GRAY);
+    }
+
+    y += spacing;
+    if jetman.linked_item.is_some() {
+        draw_text("Press S to sever the tractor beam", x, y, 20.0, WHITE);
+        y += spacing;
+        draw_text("Hold T to wind up a", x, y, 20.0, GRAY);
+        draw_text(
+            &format!("THROW {:.0}%", jetman.throw_charge * 100.0),
+            x + 175.0,
+            y,
+            20.0,
+            if input.throw { WHITE } else { GRAY },
+        );
+        y += spacing;
+    }
+    if jetman.linked_item.is_some() || jetman.grapple.is_some() {
+        draw_text("Press Q to reel      , E to reel", x, y, 20.0, GRAY);
+        draw_text(
+            "IN",
+            x + 125.0,
+            y,
+            20.0,
+            if input.reel_in { WHITE } else { GRAY },
+        );
+        draw_text(
+            "OUT",
+            x + 265.0,
+            y,
+            20.0,
+            if input.reel_out { WHITE } else { GRAY },
+        );
+        y += spacing;
+        let length = match jetman.grapple {
+            Some(grapple) => format!("Rope length: {:.0}", grapple.length),
+            None => format!("Beam length: {:.0}", jetman.link_distance),
+        };
+        draw_text(&length, x, y, 20.0, GRAY);
+    }
+}
+
+/// Draw the objectives and the state of the jet pod
+pub fn draw_objectives(at: Vec2, objectives: &[Objective], progress: &Progress, jetman: &Jetman) {
+    let Vec2 { x, mut y } = at;
+    let spacing = 20.0;
+    for objective in objectives {
+        let color = if objective.is_failed(progress) {
+            settings::bad()
+        } else if objective.is_complete(progress) {
+            settings::good()
+        } else {
+            WHITE
+        };
+        draw_text(&objective.describe(progress), x, y, 20.0, color);
+        y += spacing;
+        if let Objective::Escort = objective {
+            draw_rectangle(x, y - 12.0, 200.0, 8.0, DARKGRAY);
+            draw_rectangle(x, y - 12.0, 200.0 * progress.escorted, 8.0, color);
+            y += spacing;
+        }
+    }
+    if progress.detonated > 0 {
+        draw_text("A bomb went off!", x, y, 20.0, RED);
+        y += spacing;
+    }
+    draw_text(
+        &format!("Hull: {:.0}%", jetman.health * 100.0),
+        x,
+        y,
+        20.0,
+        if jetman.health < 0.3 { RED } else { GRAY },
+    );
+    y += spacing;
+    draw_text(
+        &format!("Fuel: {:.0}", jetman.fuel),
+        x,
+        y,
+        20.0,
+        if jetman.fuel < 20.0 { RED } else { GRAY },
+    );
+    y += spacing;
+    let low = jetman.beam_energy < Jetman::BEAM_ENGAGE;
+    draw_text("Beam:", x, y, 20.0, if low { RED } else { GRAY });
+    let share = jetman.beam_energy / jetman.max_beam_energy;
+    draw_rectangle(x + 52.0, y - 12.0, 120.0, 10.0, DARKGRAY);
+    draw_rectangle(
+        x + 52.0,
+        y - 12.0,
+        120.0 * share,
+        10.0,
+        if low { RED } else { SKYBLUE },
+    );
+    if let Some(oxygen) = jetman.oxygen {
+        y += spacing;
+        let low = oxygen.share() < LOW_OXYGEN;
+        draw_text("O2:", x, y, 20.0, if low { RED } else { GRAY });
+        draw_rectangle(x + 52.0, y - 12.0, 120.0, 10.0, DARKGRAY);
+        draw_rectangle(
+            x + 52.0,
+            y - 12.0,
+            120.0 * oxygen.share(),
+            10.0,
+            if low { RED } else { WHITE },
+        );
+        draw_text(&format!("{:.0}s", oxygen.left), x + 180.0, y, 20.0, GRAY);
+    }
+    for (modifier, left) in jetman.modifiers.iter() {
+        y += spacing;
+        let timer = format!("{}: {:.1}s", modifier.name(), left);
+        draw_text(&timer, x, y, 20.0, modifier.color());
+    }
+}
@@ -0,0 +1,175 @@
+use macroquad::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::level::{Level, Placement, Properties, Registry};
+use crate::level_file;
+use crate::physics::Material;
+use crate::terrain;
+
+/// The parts of an LDtk project the game reads
+#[derive(Deserialize)]
+struct Project {
+    defs: Definitions,
+    levels: Vec<ProjectLevel>,
+}
+
+#[derive(Deserialize)]
+struct Definitions {
+    layers: Vec<LayerDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerDefinition {
+    uid: i64,
+    #[serde(default)]
+    int_grid_values: Vec<IntGridValue>,
+}
+
+/// A value cells of an IntGrid layer can have, named in the editor
+#[derive(Deserialize)]
+struct IntGridValue {
+    value: i64,
+    identifier: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProjectLevel {
+    identifier: String,
+    #[serde(default)]
+    field_instances: Vec<Field>,
+    /// The layers, unless the project keeps each level in a file of its own
+    layer_instances: Option<Vec<Layer>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Layer {
+    #[serde(rename = "__cWid")]
+    columns: usize,
+    #[serde(rename = "__gridSize")]
+    grid_size: f32,
+    #[serde(rename = "__pxTotalOffsetX", default)]
+    offset_x: f32,
+    #[serde(rename = "__pxTotalOffsetY", default)]
+    offset_y: f32,
+    layer_def_uid: i64,
+    #[serde(default)]
+    int_grid_csv: Vec<i64>,
+    #[serde(default)]
+    entity_instances: Vec<Entity>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entity {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    /// Where the entity's pivot is, relative to the layer
+    px: [f32; 2],
+    /// The pivot as a fraction of the entity's size, from its top left corner
+    #[serde(rename = "__pivot", default)]
+    pivot: [f32; 2],
+    width: f32,
+    height: f32,
+    #[serde(default)]
+    field_instances: Vec<Field>,
+}
+
+/// A custom field of a level or an entity
+#[derive(Deserialize)]
+struct Field {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__value")]
+    value: Value,
+}
+
+/// Parse the first level of a project made with the LDtk editor, saved as a `.ldtk` file.
+///
+/// The cells of every IntGrid layer are solid terrain wherever they're set. Cell values
+/// named after a material, like `ice`, are made of it. Entities are placed by their
+/// identifier, as known to the level `Registry`. The level's fields set up the rest
+/// of the level, see `Level::set_properties`.
+pub fn parse(source: &str, seed: u64) -> Result<Level, String> {
+    let project: Project = serde_json::from_str(source).map_err(|error| error.to_string())?;
+    let source_level = project.levels.first().ok_or("the project has no levels")?;
+    let layers = source_level
+        .layer_instances
+        .as_ref()
+        .ok_or("the level is kept in a separate file, save the project with levels included")?;
+
+    let mut level = Level::new(&source_level.identifier);
+    level.seed = seed;
+    level.set_properties(&properties(&source_level.field_instances))?;
+
+    let registry = Registry::new();
+    for layer in layers {
+        let offset = vec2(layer.offset_x, layer.offset_y);
+        if !layer.int_grid_csv.is_empty() {
+            let definition = project
+                .defs
+                .layers
+                .iter()
+                .find(|definition| definition.uid == layer.layer_def_uid);
+            let cells = layer
+                .int_grid_csv
+                .iter()
+                .map(|&value| material(definition, value))
+                .collect::<Vec<_>>();
+            let cell_size = Vec2::splat(layer.grid_size);
+            level
+                .terrain
+                .extend(terrain::grid(&cells, layer.columns, cell_size, offset));
+        }
+        for entity in &layer.entity_instances {
+            let size = vec2(entity.width, entity.height);
+            let placement = Placement {
+                position: offset + Vec2::from(entity.px) - Vec2::from(entity.pivot) * size,
+                size,
+                properties: properties(&entity.field_instances),
+            };
+            let placed = registry
+                .place(&mut level, &entity.identifier, &placement)
+                .map_err(|error| format!("entity '{}': {error}", entity.identifier))?;
+            if !placed {
+                return Err(format!("unknown entity '{}'", entity.identifier));
+            }
+        }
+    }
+    Ok(level)
+}
+
+/// The material of an IntGrid cell with the given value, or `None` if the cell is empty
+fn material(definition: Option<&LayerDefinition>, value: i64) -> Option<Material> {
+    if value == 0 {
+        return None;
+    }
+    let name = definition
+        .and_then(|definition| {
+            definition
+                .int_grid_values
+                .iter()
+                .find(|named| named.value == value)
+        })
+        .and_then(|named| named.identifier.as_deref())
+        .unwrap_or_default();
+    Some(level_file::material(&name.to_lowercase()).unwrap_or(Material::DEFAULT))
+}
+
+/// The fields with a value, as properties
+fn properties(fields: &[Field]) -> Properties {
+    fields
+        .iter()
+        .filter_map(|field| {
+            let value = match &field.value {
+                Value::Null => return None,
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            Some((field.identifier.to_lowercase(), value))
+        })
+        .collect()
+}
@@ -0,0 +1,150 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use macroquad::prelude::*;
+
+use crate::hud::format_time;
+use crate::persistence;
+
+/// A completed run of a level
+#[derive(Clone, PartialEq, Debug)]
+pub struct Record {
+    /// The name of the level
+    pub level: String,
+    /// The seed the level was generated with
+    pub seed: u64,
+    /// The time it took to complete the level, in seconds
+    pub time: f32,
+}
+
+/// The name the times of a level are kept under, separately for hardcore campaigns,
+/// for pods upgraded in the shop and for runs flown in co-op, where a second pod shares
+/// the load
+pub fn category(level: &str, hardcore: bool, upgraded: bool, co_op: bool) -> String {
+    let mut category = level.to_string();
+    if hardcore {
+        category.push_str(" (hardcore)");
+    }
+    if upgraded {
+        category.push_str(" (upgraded)");
+    }
+    if co_op {
+        category.push_str(" (co-op)");
+    }
+    category
+}
+
+/// The best times per level and seed, kept in a local file
+pub struct Leaderboard {
+    records: Vec<Record>,
+    /// The file the times are kept in
+    path: PathBuf,
+}
+
+impl Leaderboard {
+    /// The file the best times are kept in by default
+    pub const PATH: &str = "best_times.txt";
+    /// The number of times kept per level and seed
+    const KEEP: usize = 5;
+
+    /// Load the best times from the default file
+    pub fn load() -> Self {
+        Leaderboard::load_from(Self::PATH)
+    }
+
+    /// Load the best times from the given file. A missing file makes for an empty
+    /// leaderboard, and lines that can't be read are skipped.
+    pub fn load_from(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let records = persistence::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                // seed, time and level name, separated by tabs
+                let mut fields = line.splitn(3, '\t');
+                let seed = fields.next()?.parse().ok()?;
+                let time = fields.next()?.parse().ok()?;
+                let level = fields.next()?.to_string();
+                Some(Record { level, seed, time })
+            })
+            .collect();
+        Leaderboard { records, path }
+    }
+
+    /// Write the best times back to the file they were loaded from
+    pub fn save(&self) -> io::Result<()> {
+        let contents = self
+            .records
+            .iter()
+            .map(|record| format!("{}\t{}\t{}\n", record.seed, record.time, record.level))
+            .collect::<String>();
+        persistence::write(&self.path, contents)
+    }
+
+    /// The best times for a level and seed, fastest first
+    pub fn best(&self, level: &str, seed: u64) -> Vec<&Record> {
+        let mut best = self
+            .records
+            .iter()
+            .filter(|record| record.level == level && record.seed == seed)
+            .collect::<Vec<_>>();
+        best.sort_by(|a, b| a.time.total_cmp(&b.time));
+        best
+    }
+
+    /// Enter a completed run. Returns its place among the best times
+    /// (0 being the fastest), or `None` if it wasn't fast enough to be kept.
+    pub fn submit(&mut self, record: Record) -> Option<usize> {
+        let mut best = self
+            .best(&record.level, record.seed)
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        let place = best
+            .iter()
+            .position(|other| record.time < other.time)
+            .unwrap_or(best.len());
+        if place >= Self::KEEP {
+            return None;
+        }
+        self.records
+            .retain(|other| other.level != record.level || other.seed != record.seed);
+        best.insert(place, record);
+        best.truncate(Self::KEEP);
+        self.records.extend(best);
+        Some(place)
+    }
+
+    /// Draw the best times for a level and seed, highlighting the given place
+    pub fn draw(&self, level: &str, seed: u64, time: f32, place: Option<usize>) {
+        clear_background(BLACK);
+        let x = screen_width() / 2.0 - 160.0;
+        let mut y = screen_height() / 2.0 - 140.0;
+        draw_text(&format!("{level} complete!"), x, y, 36.0, GREEN);
+        y += 40.0;
+        draw_text(
+            &format!("Your time: {}", format_time(time)),
+            x,
+            y,
+            24.0,
+            WHITE,
+        );
+        y += 28.0;
+        let verdict = match place {
+            Some(0) => "A new best time!",
+            Some(_) => "That's one of the best times",
+            None => "Not among the best times this time",
+        };
+        draw_text(verdict, x, y, 20.0, YELLOW);
+        y += 40.0;
+        draw_text(&format!("Best times for seed {seed}"), x, y, 22.0, GRAY);
+        y += 28.0;
+        for (index, record) in self.best(level, seed).iter().enumerate() {
+            let color = if Some(index) == place { YELLOW } else { WHITE };
+            let text = format!("{}. {}", index + 1, format_time(record.time));
+            draw_text(&text, x, y, 22.0, color);
+            y += 26.0;
+        }
+        draw_text("Press Enter to continue", x, y + 20.0, 20.0, GRAY);
+    }
+}
@@ -0,0 +1,765 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::*;
+
+use crate::anomalies::Anomaly;
+use crate::decoration::{Decoration, DecorationKind};
+use crate::director::Director;
+use crate::doors::{Door, Lever};
+use crate::enemy::{Turret, Wave};
+use crate::fluids::Water;
+use crate::objective::Objective;
+use crate::physics::{Checkpoint, FuelPad, Item, ItemKind, Material, Teleporter};
+use crate::pickups::Pickup;
+use crate::route::find_route;
+use crate::scripting::Script;
+use crate::shop::Upgrades;
+use crate::switches::{SwitchArray, SwitchColor};
+use crate::terrain::{Funnel, Terrain};
+use crate::triggers::{Trigger, TriggerAction, TriggerEvent};
+use crate::weather::{Fog, FogKeyframe};
+use crate::world::World;
+
+/// Generate a polygon of rolling ground spanning the given width
+pub(crate) fn generate_ground_poly(
+    width: i32,
+    height: i32,
+    segments: usize,
+    rng: &mut impl Rng,
+) -> Vec<Vec2> {
+    let base_y = 500.0;
+    let mut top = vec![];
+
+    for i in 0..=segments {
+        let x = i as f32 * (width as f32 / segments as f32);
+        let y = base_y - rng.gen_range(0.0..80.0);
+        top.push(Vec2::new(x, y));
+    }
+
+    let bottom = (0..=segments)
+        .rev()
+        .map(|i| {
+            let x = i as f32 * (width as f32 / segments as f32);
+            Vec2::new(x, height as f32)
+        })
+        .collect::<Vec<_>>();
+
+    top.extend(bottom);
+    top
+}
+
+/// The description of a level, from which a game world is created
+#[derive(Clone)]
+pub struct Level {
+    /// The name of the level
+    pub name: String,
+    /// The seed the level was generated with, which also drives the randomness while playing it
+    pub seed: u64,
+    /// The file the level was loaded from, to load again when it changes
+    pub path: Option<PathBuf>,
+    /// Where the jet pod starts out
+    pub spawn: Vec2,
+    /// The number of jet pods the player may lose before the game is over
+    pub lives: u32,
+    /// The upgrades the player's jet pod comes with
+    pub upgrades: Upgrades,
+    /// The time in seconds a good run of the level takes, if the level sets one
+    pub par_time: Option<f32>,
+    /// The gravity acting on all bodies
+    pub gravity: Vec2,
+    /// How many times per step the links and the collisions are solved together,
+    /// kept between 1 and `World::MAX_SOLVER_ITERATIONS`
+    pub solver_iterations: u32,
+    /// The wind pushing Jetman and the items around
+    pub wind: Vec2,
+    /// The seconds of air the pod's tanks hold, if the pilot has to breathe
+    pub oxygen: Option<f32>,
+    pub terrain: Vec<Terrain>,
+    pub items: Vec<Item>,
+    /// The gems and fuel cells collected on contact
+    pub pickups: Vec<Pickup>,
+    pub teleports: Vec<Teleporter>,
+    pub funnels: Vec<Funnel>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub fuel_pads: Vec<FuelPad>,
+    pub water: Vec<Water>,
+    /// The path of the friendly craft to escort, if any
+    pub escort_path: Option<Vec<Vec2>>,
+    /// The waves of enemies attacking during the level
+    pub waves: Vec<Wave>,
+    /// The director sending in ever bigger waves of enemies, if the level has one
+    pub director: Option<Director>,
+    pub turrets: Vec<Turret>,
+    /// The black holes and anti-grav bubbles
+    pub anomalies: Vec<Anomaly>,
+    pub fog: Fog,
+    pub objectives: Vec<Objective>,
+    /// Whether the world is hidden and the pilot has to fly by instruments only
+    pub instrument_only: bool,
+    /// Whether the level is played competitively, so wider screens must not see more of it
+    pub competitive: bool,
+    /// Whether the level is dark, so only what the lights reach can be seen
+    pub dark: bool,
+    /// The scenery drawn in front of the action
+    pub decorations: Vec<Decoration>,
+    /// The switches opening vaults, one array per color
+    pub switch_arrays: Vec<SwitchArray>,
+    /// The regions carrying out an action when Jetman or an item passes through
+    pub triggers: Vec<Trigger>,
+    /// The doors opened by pressure plates, levers and keys
+    pub doors: Vec<Door>,
+    /// The scripts running the level's custom logic
+    pub scripts: Vec<Script>,
+}
+
+impl Level {
+    /// The width and height of the demo level's ground, whatever the size of the window
+    const DEMO_SIZE: (i32, i32) = (800, 600);
+
+    /// Create an empty level with default gravity
+    pub fn new(name: &str) -> Self {
+        Level {
+            name: name.to_string(),
+            seed: 0,
+            path: None,
+            spawn: Vec2::new(200.0, 200.0),
+            lives: 3,
+            upgrades: Upgrades::default(),
+            par_time: None,
+            gravity: Vec2::new(0.0, 0.01),
+            solver_iterations: World::SOLVER_ITERATIONS,
+            wind: Vec2::ZERO,
+            oxygen: None,
+            terrain: vec![],
+            items: vec![],
+            pickups: vec![],
+            teleports: vec![],
+            funnels: vec![],
+            checkpoints: vec![],
+            fuel_pads: vec![],
+            water: vec![],
+            escort_path: None,
+            waves: vec![],
+            director: None,
+            turrets: vec![],
+            anomalies: vec![],
+            fog: Fog::clear(),
+            objectives: vec![],
+            instrument_only: false,
+            competitive: false,
+            dark: false,
+            decorations: vec![],
+            switch_arrays: vec![],
+            triggers: vec![],
+            doors: vec![],
+            scripts: vec![],
+        }
+    }
+
+    /// Add a funnel receiver along with its walls and the teleporter at its throat
+    pub fn add_funnel(&mut self, funnel: Funnel) {
+        self.terrain.extend(funnel.walls());
+        self.teleports.push(Teleporter::new(funnel.throat));
+        self.funnels.push(funnel);
+    }
+
+    /// The array of switches of the given color, added without a vault if there is none yet
+    pub fn switch_array(&mut self, color: SwitchColor) -> &mut SwitchArray {
+        let index = match self.switch_arrays.iter().position(|a| a.color == color) {
+            Some(index) => index,
+            None => {
+                let array = SwitchArray::new(color);
+                self.switch_arrays.push(array);
+                self.switch_arrays.len() - 1
+            }
+        };
+        &mut self.switch_arrays[index]
+    }
+
+    /// Add a vault with its door in the given area, unlocked by the switches of the given
+    /// color once they are all lit within `window` seconds of each other. The door is
+    /// named after the color, like `red vault`.
+    pub fn add_vault(&mut self, color: SwitchColor, area: Rect, window: f32) {
+        let door = self.door(&format!("{} vault", color.name()));
+        self.doors[door].area = area;
+        let array = self.switch_array(color);
+        array.door = Some(door);
+        array.window = window;
+    }
+
+    /// The index of the door with the given name, added without an area if there is none yet
+    pub fn door(&mut self, name: &str) -> usize {
+        match self.doors.iter().position(|door| door.name == name) {
+            Some(index) => index,
+            None => {
+                self.doors.push(Door::new(name, Rect::default()));
+                self.doors.len() - 1
+            }
+        }
+    }
+
+    /// Add a door of the given name blocking the given area
+    pub fn add_door(&mut self, name: &str, area: Rect) {
+        let index = self.door(name);
+        self.doors[index].area = area;
+    }
+
+    /// Add a pressure plate holding the named door open while an item rests on it
+    pub fn add_plate(&mut self, name: &str, position: Vec2) {
+        let index = self.door(name);
+        self.doors[index].plates.push(position);
+    }
+
+    /// Add a lever opening the named door for good once Jetman touches it
+    pub fn add_lever(&mut self, name: &str, position: Vec2) {
+        let index = self.door(name);
+        self.doors[index].levers.push(Lever {
+            position,
+            pulled: false,
+        });
+    }
+
+    /// Add a key opening the named door for good once it is carried there
+    pub fn add_key(&mut self, name: &str, position: Vec2) {
+        let door = self.door(name);
+        self.items.push(Item::key(position.x, position.y, door));
+    }
+
+    /// Set up the level from properties given by name, as level editors store them:
+    /// `name`, `gravity` and `wind`, `lives`, `par` time in seconds, `dark` and
+    /// `instruments`, and the numbers of items to `deliver` and bombs to `defuse`
+    pub fn set_properties(&mut self, properties: &Properties) -> Result<(), String> {
+        if let Some(name) = properties.get("name") {
+            self.name = name.clone();
+        }
+        if let Some(gravity) = number(properties, "gravity")? {
+            self.gravity = vec2(0.0, gravity);
+        }
+        if let Some(wind) = number(properties, "wind")? {
+            self.wind = vec2(wind, 0.0);
+        }
+        if let Some(lives) = number(properties, "lives")? {
+            self.lives = lives as u32;
+        }
+        self.par_time = number(properties, "par")?.or(self.par_time);
+        let flag = |name: &str| properties.get(name).is_some_and(|value| value == "true");
+        self.dark |= flag("dark");
+        self.instrument_only |= flag("instruments");
+        if let Some(count) = number(properties, "deliver")? {
+            self.objectives.push(Objective::Deliver(count as u32));
+        }
+        if let Some(count) = number(properties, "defuse")? {
+            self.objectives.push(Objective::Defuse(count as u32));
+        }
+        Ok(())
+    }
+
+    /// Check the level for problems that would make it impossible to beat,
+    /// returning a description of each problem found
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let cargo = self
+            .items
+            .iter()
+            .filter(|item| item.kind.is_cargo())
+            .count() as u32;
+        let bombs = self
+            .items
+            .iter()
+            .filter(|item| matches!(item.kind, ItemKind::Bomb { .. }))
+            .count() as u32;
+        for objective in &self.objectives {
+            match *objective {
+                Objective::Deliver(count) if count > cargo => problems.push(format!(
+                    "{count} cargo items must be delivered, but there are only {cargo}"
+                )),
+                Objective::Defuse(count) if count > bombs => problems.push(format!(
+                    "{count} bombs must be disposed of, but there are only {bombs}"
+                )),
+                Objective::Escort if self.escort_path.is_none() => {
+                    problems.push("there is no craft to escort".to_string())
+                }
+                _ => {}
+            }
+        }
+        for array in &self.switch_arrays {
+            let color = array.color.name();
+            if array.door.is_none() {
+                problems.push(format!("the {color} switches don't open any vault"));
+            }
+            if array.switches.is_empty() {
+                problems.push(format!("the {color} vault has no switches to open it"));
+            }
+        }
+        for (index, door) in self.doors.iter().enumerate() {
+            let name = &door.name;
+            if door.area.w <= 0.0 || door.area.h <= 0.0 {
+                problems.push(format!("there is no door '{name}' to open"));
+            }
+            let keyed = self
+                .items
+                .iter()
+                .any(|item| matches!(item.kind, ItemKind::Key { door } if self.doors[door].name == *name));
+            let vaulted = self
+                .switch_arrays
+                .iter()
+                .any(|array| array.door == Some(index));
+            let triggered = self
+                .triggers
+                .iter()
+                .any(|trigger| trigger.action == TriggerAction::OpenDoor(index));
+            let opened = keyed || vaulted || triggered;
+            if door.plates.is_empty() && door.levers.is_empty() && !opened {
+                problems.push(format!(
+                    "the door '{name}' has no plate, lever, key, switches or trigger to open it"
+                ));
+            }
+        }
+
+        // every item must be reachable from the spawn point and have a teleporter to go to
+        for (index, item) in self.items.iter().enumerate() {
+            // keys go to their doors instead
+            if let ItemKind::Key { door } = item.kind {
+                let door = self.doors[door].area.center();
+                if find_route(&self.terrain, &[self.spawn, item.body.position, door]).is_none() {
+                    problems.push(format!(
+                        "key {index} can't be carried from the spawn point to its door"
+                    ));
+                }
+                continue;
+            }
+            let target = self
+                .teleports
+                .iter()
+                .filter(|teleport| teleport.accepts(item.kind))
+                .map(|teleport| teleport.position)
+                .min_by(|a, b| {
+                    let distance = |p: &Vec2| (*p - item.body.position).length();
+                    distance(a).total_cmp(&distance(b))
+                });
+            let Some(target) = target else {
+                problems.push(format!("item {index} has no teleporter accepting it"));
+                continue;
+            };
+            if find_route(&self.terrain, &[self.spawn, item.body.position, target]).is_none() {
+                problems.push(format!(
+                    "item {index} can't be carried from the spawn point to its teleporter"
+                ));
+            }
+        }
+        problems
+    }
+
+    /// The demo level showing off all the features of the game,
+    /// its ground shaped by the given seed
+    pub fn demo(seed: u64) -> Self {
+        let mut level = Level::new("Demo");
+        level.seed = seed;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (width, height) = Self::DEMO_SIZE;
+        level.terrain.push(Terrain::polygon(generate_ground_poly(
+            width, height, 12, &mut rng,
+        )));
+        // a few surfaces to show off the different materials
+        level
+            .terrain
+            .push(Terrain::circle(520.0, 200.0, 14.0).with_material(Material::RUBBER));
+        level
+            .terrain
+            .push(Terrain::rectangle(-260.0, 380.0, 180.0, 12.0).with_material(Material::ICE));
+        level
+            .terrain
+            .push(Terrain::rectangle(-460.0, 420.0, 160.0, 12.0).with_material(Material::MUD));
+        level.teleports = vec![
+            Teleporter::new(Vec2::new(400.0, 300.0)),
+            Teleporter::disposal(Vec2::new(150.0, 320.0)),
+        ];
+        level.add_funnel(Funnel::new(Vec2::new(600.0, 360.0), 140.0, 30.0, 90.0));
+        level.checkpoints = vec![
+            Checkpoint::new(Vec2::new(-170.0, 360.0)),
+            Checkpoint::new(Vec2::new(780.0, 300.0)),
+        ];
+        level.wind = Vec2::new(0.002, 0.0);
+        level
+            .terrain
+            .push(Terrain::rectangle(250.0, 250.0, 80.0, 10.0));
+        level.fuel_pads = vec![
+            FuelPad::new(Vec2::new(290.0, 250.0)),
+            FuelPad::new(Vec2::new(-380.0, 420.0)),
+        ];
+        level.water.push(Water::new(820.0, 380.0, 300.0, 160.0));
+        level
+            .terrain
+            .push(Terrain::rectangle(820.0, 540.0, 300.0, 20.0));
+        level.items = vec![Item::new(100.0, 200.0), Item::bomb(300.0, 150.0, 45.0)];
+        level.escort_path = Some(vec![
+            Vec2::new(-200.0, 120.0),
+            Vec2::new(300.0, 80.0),
+            Vec2::new(700.0, 160.0),
+            Vec2::new(1100.0, 100.0),
+        ]);
+        level.waves = vec![
+            Wave {
+                time: 8.0,
+                count: 2,
+            },
+            Wave {
+                time: 25.0,
+                count: 4,
+            },
+            Wave {
+                time: 45.0,
+                count: 6,
+            },
+        ];
+        level.fog = Fog::scripted(vec![
+            FogKeyframe {
+                time: 30.0,
+                density: 0.8,
+            },
+            FogKeyframe {
+                time: 70.0,
+                density: 0.2,
+            },
+        ]);
+        level.objectives = vec![
+            Objective::Deliver(1),
+            Objective::Defuse(1),
+            Objective::Escort,
+        ];
+        level
+    }
+
+    /// A level crowded with the given number of items thrown at rough ground of the
+    /// given number of segments, to measure how fast the simulation runs. Items only
+    /// fall once let go of, so they're thrown to keep them moving.
+    pub fn stress(seed: u64, items: usize, segments: usize) -> Self {
+        let mut level = Level::new("Stress Test");
+        level.seed = seed;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let width = segments as f32 * 8.0;
+        level.terrain.push(Terrain::polygon(generate_ground_poly(
+            width as i32,
+            600,
+            segments,
+            &mut rng,
+        )));
+        level.spawn = Vec2::new(width / 2.0, 100.0);
+        level.items = (0..items)
+            .map(|index| {
+                let x = (index as f32 + 0.5) * width / items as f32;
+                let mut item = Item::new(x, rng.gen_range(50.0..350.0));
+                item.body.velocity = vec2(rng.gen_range(-2.0..2.0), rng.gen_range(1.0..4.0));
+                item
+            })
+            .collect();
+        level
+    }
+
+    /// A cave run flown by instruments only: the pod has to carry a crate
+    /// through a winding tunnel without ever seeing it
+    pub fn instrument_challenge() -> Self {
+        let mut level = Level::new("Blind Flight");
+        level.instrument_only = true;
+        level.spawn = Vec2::new(60.0, 100.0);
+        // the tunnel's ceiling and floor
+        let ceiling = [
+            (0.0, 0.0),
+            (300.0, 40.0),
+            (600.0, -40.0),
+            (900.0, 60.0),
+            (1200.0, 0.0),
+        ];
+        let floor = [
+            (0.0, 220.0),
+            (300.0, 260.0),
+            (600.0, 200.0),
+            (900.0, 300.0),
+            (1200.0, 240.0),
+        ];
+        for pair in ceiling.windows(2).chain(floor.windows(2)) {
+            let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+            level.terrain.push(Terrain::line(x1, y1, x2, y2));
+        }
+        level.terrain.push(Terrain::line(0.0, 0.0, 0.0, 220.0));
+        level
+            .terrain
+            .push(Terrain::line(1200.0, 0.0, 1200.0, 240.0));
+        level.items = vec![Item::new(140.0, 180.0)];
+        level.checkpoints = vec![Checkpoint::new(Vec2::new(600.0, 100.0))];
+        level.teleports = vec![Teleporter::new(Vec2::new(1120.0, 200.0))];
+        level.objectives = vec![Objective::Deliver(1)];
+        level
+    }
+}
+
+/// Custom properties of a level or of something placed in it, by name
+pub type Properties = HashMap<String, String>;
+
+/// The number in a property, if it is set
+pub fn number(properties: &Properties, name: &str) -> Result<Option<f32>, String> {
+    properties
+        .get(name)
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{name}' is not a number: '{value}'"))
+        })
+        .transpose()
+}
+
+/// Read the `color` property of a switch or a vault
+fn switch_color(properties: &Properties) -> Result<SwitchColor, String> {
+    let name = properties
+        .get("color")
+        .ok_or("switches and vaults need a 'color'")?;
+    SwitchColor::from_name(name.trim()).ok_or(format!("unknown switch color '{name}'"))
+}
+
+/// The name of the door a door, plate, lever or key belongs to, read from its `door` property
+fn door_name(properties: &Properties) -> Result<&str, String> {
+    properties
+        .get("door")
+        .map(|name| name.trim())
+        .ok_or("doors, plates, levers and keys need a 'door' name".to_string())
+}
+
+/// Something placed in a level with a level editor, like an entity in LDtk
+/// or an object in Tiled
+pub struct Placement {
+    /// The top left corner
+    pub position: Vec2,
+    pub size: Vec2,
+    pub properties: Properties,
+}
+
+impl Placement {
+    /// The center of what's placed
+    pub fn center(&self) -> Vec2 {
+        self.position + self.size / 2.0
+    }
+}
+
+/// Adds what a placement stands for to a level
+pub type Placer = fn(&mut Level, &Placement) -> Result<(), String>;
+
+/// The kinds of things that can be placed in levels made with level editors,
+/// each adding whatever it stands for to the level
+pub struct Registry {
+    placers: HashMap<String, Placer>,
+}
+
+impl Registry {
+    /// A registry knowing about the things of the game: `spawn`, `item` (with an optional
+    /// `length` property), `livestock`, `bomb` (with a
+    /// `fuse` property), `goal`, `disposal`, `checkpoint`, `fuel`, `turret`, `launcher`,
+    /// `blackhole` (with an optional `pull` property), `antigrav`, `water`, `wave` (with `time` and `count` properties), `vault` (with `color` and `window`
+    /// properties), `switch` (with a `color` property), `trigger` (with an `event` and a
+    /// `text`, `drones`, `door` (naming the door it unlocks) or `script` property), `door`, `plate`, `lever` and `key` (with a
+    /// `door` property naming the door they open), and the decorations `vine`,
+    /// `crystal` and `fogbank`
+    pub fn new() -> Self {
+        let mut registry = Registry {
+            placers: HashMap::new(),
+        };
+        registry.register("spawn", |level, placement| {
+            level.spawn = placement.center();
+            Ok(())
+        });
+        registry.register("item", |level, placement| {
+            let center = placement.center();
+            let item = Item::new(center.x, center.y);
+            level
+                .items
+                .push(match number(&placement.properties, "length")? {
+                    Some(length) if length <= 0.0 => {
+                        return Err("an item's length has to be more than 0".to_string());
+                    }
+                    Some(length) => item.with_length(length),
+                    None => item,
+                });
+            Ok(())
+        });
+        registry.register("livestock", |level, placement| {
+            let center = placement.center();
+            level.items.push(Item::livestock(center.x, center.y));
+            Ok(())
+        });
+        registry.register("vault", |level, placement| {
+            let color = switch_color(&placement.properties)?;
+            let window = number(&placement.properties, "window")?.unwrap_or(SwitchArray::WINDOW);
+            let Placement { position, size, .. } = placement;
+            let door = Rect::new(position.x, position.y, size.x, size.y);
+            level.add_vault(color, door, window);
+            Ok(())
+        });
+        registry.register("switch", |level, placement| {
+            let color = switch_color(&placement.properties)?;
+            level.switch_array(color).add_switch(placement.center());
+            Ok(())
+        });
+        registry.register("door", |level, placement| {
+            let name = door_name(&placement.properties)?;
+            let Placement { position, size, .. } = placement;
+            level.add_door(name, Rect::new(position.x, position.y, size.x, size.y));
+            Ok(())
+        });
+        registry.register("plate", |level, placement| {
+            let name = door_name(&placement.properties)?;
+            level.add_plate(name, placement.center());
+            Ok(())
+        });
+        registry.register("lever", |level, placement| {
+            let name = door_name(&placement.properties)?;
+            level.add_lever(name, placement.center());
+            Ok(())
+        });
+        registry.register("key", |level, placement| {
+            let name = door_name(&placement.properties)?;
+            level.add_key(name, placement.center());
+            Ok(())
+        });
+        registry.register("trigger", |level, placement| {
+            let properties = &placement.properties;
+            let event = properties
+                .get("event")
+                .map_or("enter", |event| event.trim());
+            let event =
+                TriggerEvent::from_name(event).ok_or(format!("unknown trigger event '{event}'"))?;
+            let action = if let Some(text) = properties.get("text") {
+                TriggerAction::ShowText(text.clone())
+            } else if let Some(count) = number(properties, "drones")? {
+                TriggerAction::SpawnDrones(count as usize)
+            } else if let Some(door) = properties.get("door") {
+                TriggerAction::OpenDoor(level.door(door.trim()))
+            } else if properties
+                .get("script")
+                .is_some_and(|value| value == "true")
+            {
+                TriggerAction::Script
+            } else {
+                return Err("triggers need a 'text', 'drones', 'door' or 'script'".to_string());
+            };
+            let Placement { position, size, .. } = placement;
+            let area = Rect::new(position.x, position.y, size.x, size.y);
+            level.triggers.push(Trigger::new(area, event, action));
+            Ok(())
+        });
+        registry.register("bomb", |level, placement| {
+            let center = placement.center();
+            let fuse = number(&placement.properties, "fuse")?.unwrap_or(60.0);
+            level.items.push(Item::bomb(center.x, center.y, fuse));
+            Ok(())
+        });
+        registry.register("goal", |level, placement| {
+            level.teleports.push(Teleporter::new(placement.center()));
+            Ok(())
+        });
+        registry.register("disposal", |level, placement| {
+            level
+                .teleports
+                .push(Teleporter::disposal(placement.center()));
+            Ok(())
+        });
+        registry.register("checkpoint", |level, placement| {
+            level.checkpoints.push(Checkpoint::new(placement.center()));
+            Ok(())
+        });
+        registry.register("fuel", |level, placement| {
+            level.fuel_pads.push(FuelPad::new(placement.center()));
+            Ok(())
+        });
+        registry.register("turret", |level, placement| {
+            level.turrets.push(Turret::new(placement.center()));
+            Ok(())
+        });
+        registry.register("launcher", |level, placement| {
+            level.turrets.push(Turret::launcher(placement.center()));
+            Ok(())
+        });
+        registry.register("water", |level, placement| {
+            let Placement { position, size, .. } = placement;
+            level
+                .water
+                .push(Water::new(position.x, position.y, size.x, size.y));
+            Ok(())
+        });
+        registry.register("vine", |level, placement| {
+            // vines hang from the top of the area down to its bottom
+            let anchor = placement.position + vec2(placement.size.x / 2.0, 0.0);
+            let vine = Decoration::new(DecorationKind::Vine, anchor, placement.size.y);
+            level.decorations.push(vine);
+            Ok(())
+        });
+        registry.register("crystal", |level, placement| {
+            // crystals grow from the bottom of the area up to its top
+            let base = placement.position + vec2(placement.size.x / 2.0, placement.size.y);
+            let crystal = Decoration::new(DecorationKind::Crystal, base, placement.size.y);
+            level.decorations.push(crystal);
+            Ok(())
+        });
+        registry.register("fogbank", |level, placement| {
+            let fog = Decoration::new(
+                DecorationKind::FogBank,
+                placement.center(),
+                placement.size.x,
+            );
+            level.decorations.push(fog);
+            Ok(())
+        });
+        registry.register("blackhole", |level, placement| {
+            let pull = number(&placement.properties, "pull")?.unwrap_or(Anomaly::PULL);
+            let radius = placement.size.min_element() / 2.0;
+            let black_hole = Anomaly::black_hole(placement.center(), radius, pull);
+            level.anomalies.push(black_hole);
+            Ok(())
+        });
+        registry.register("antigrav", |level, placement| {
+            let radius = placement.size.min_element() / 2.0;
+            let bubble = Anomaly::anti_gravity(placement.center(), radius);
+            level.anomalies.push(bubble);
+            Ok(())
+        });
+        registry.register("wave", |level, placement| {
+            let time = number(&placement.properties, "time")?.unwrap_or(0.0);
+            let count = number(&placement.properties, "count")?.unwrap_or(1.0);
+            level.waves.push(Wave {
+                time,
+                count: count as usize,
+            });
+            Ok(())
+        });
+        registry
+    }
+
+    /// Make a kind of thing placeable, replacing any kind of the same name.
+    /// Names are matched regardless of case.
+    pub fn register(&mut self, name: &str, placer: Placer) {
+        self.placers.insert(name.to_lowercase(), placer);
+    }
+
+    /// Add a thing of the named kind to the level.
+    /// Returns false if no such kind is registered.
+    pub fn place(
+        &self,
+        level: &mut Level,
+        name: &str,
+        placement: &Placement,
+    ) -> Result<bool, String> {
+        let Some(placer) = self.placers.get(&name.to_lowercase()) else {
+            return Ok(false);
+        };
+        placer(level, placement)?;
+        Ok(true)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,533 @@
+use std::path::Path;
+
+use ::rand::SeedableRng;
+use ::rand::rngs::StdRng;
+use macroquad::prelude::*;
+
+use crate::anomalies::Anomaly;
+use crate::decoration::{Decoration, DecorationKind};
+use crate::director::Director;
+use crate::enemy::{EnemyKind, Turret, Wave};
+use crate::fluids::Water;
+use crate::heightmap;
+use crate::ldtk;
+use crate::level::{Level, generate_ground_poly};
+use crate::objective::Objective;
+use crate::persistence;
+use crate::physics::{Checkpoint, FuelPad, Item, Material, Teleporter};
+use crate::pickups::{Pickup, PickupKind};
+use crate::scripting::Script;
+use crate::svg;
+use crate::switches::{SwitchArray, SwitchColor};
+use crate::terrain::{Funnel, Terrain, TerrainHazard};
+use crate::tiled;
+use crate::triggers::{Trigger, TriggerAction, TriggerEvent};
+use crate::weather::{Fog, FogKeyframe};
+
+/// Load a level from a level file, generating random parts like the ground from `seed`.
+/// Files ending in `.tmx` are read as Tiled maps, and files ending in `.ldtk`
+/// as LDtk projects.
+pub fn load(path: impl AsRef<Path>, seed: u64) -> Result<Level, String> {
+    let path = path.as_ref();
+    let source = persistence::read_to_string(path)
+        .map_err(|error| format!("can't read {}: {error}", path.display()))?;
+    let level = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("tmx") => tiled::parse(&source, seed),
+        Some("ldtk") => ldtk::parse(&source, seed),
+        _ => parse_in(&source, seed, path.parent().unwrap_or(Path::new(""))),
+    };
+    let mut level = level.map_err(|error| format!("{}: {error}", path.display()))?;
+    level.path = Some(path.to_path_buf());
+    Ok(level)
+}
+
+/// The terrain material of the given name
+pub(crate) fn material(name: &str) -> Result<Material, String> {
+    match name {
+        "default" => Ok(Material::DEFAULT),
+        "ice" => Ok(Material::ICE),
+        "rubber" => Ok(Material::RUBBER),
+        "mud" => Ok(Material::MUD),
+        "trampoline" => Ok(Material::TRAMPOLINE),
+        "conveyor" => Ok(Material::CONVEYOR),
+        other => Err(format!("unknown material '{other}'")),
+    }
+}
+
+/// Parse a level file. Each line holds a keyword followed by its numbers,
+/// for example `rect 0 500 800 40 ice`, `rect 300 480 100 20 lava`,
+/// `rect 0 400 200 20 conveyor -1.5`, `item 100 200` or `pickup gem 100 200`;
+/// empty lines and lines starting with `#` are skipped. Smooth terrain is given by
+/// the points of a `spline` running through them or of a chain of `bezier` curves,
+/// with an odd number left at the end setting how closely the straight pieces they
+/// are flattened into follow the curve, like `spline 0 600 0 500 400 450 800 500 800 600 0.5`.
+/// Terrain can also come from an `svg` image, or be painted as a PNG `image`, bright
+/// where it's solid, like `image caves.png 2 0.5 mud` for two units per pixel.
+/// Files the level refers to, like SVG images, are looked for in the current directory.
+pub fn parse(source: &str, seed: u64) -> Result<Level, String> {
+    parse_in(source, seed, Path::new(""))
+}
+
+/// Parse a level file, looking for the files it refers to in the given directory
+fn parse_in(source: &str, seed: u64, dir: &Path) -> Result<Level, String> {
+    let mut level = Level::new("Untitled");
+    level.seed = seed;
+    let mut rng = StdRng::seed_from_u64(seed);
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        parse_line(&mut level, line, &mut rng, dir)
+            .map_err(|error| format!("line {}: {error}", index + 1))?;
+    }
+    Ok(level)
+}
+
+/// Add whatever a single line describes to the level
+fn parse_line(level: &mut Level, line: &str, rng: &mut StdRng, dir: &Path) -> Result<(), String> {
+    let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let words = rest.split_whitespace().collect::<Vec<_>>();
+    // the numbers following the keyword, and for terrain the optional material after them
+    let numbers = words
+        .iter()
+        .map_while(|word| word.parse::<f32>().ok())
+        .collect::<Vec<_>>();
+    // the word after the numbers names the terrain's material or hazard, like `lava`
+    let surface = |terrain: Terrain| -> Result<Terrain, String> {
+        match words.get(numbers.len()) {
+            None => Ok(terrain),
+            Some(word) => match TerrainHazard::from_name(word) {
+                Some(hazard) => Ok(terrain.with_hazard(hazard)),
+                None => {
+                    let mut material = material(word)?;
+                    // a number after a conveyor sets its speed, negative to run the other way
+                    if material.conveyor != 0.0
+                        && let Some(speed) = words.get(numbers.len() + 1)
+                    {
+                        material.conveyor = speed
+                            .parse()
+                            .map_err(|_| format!("'{speed}' is not a speed"))?;
+                    }
+                    Ok(terrain.with_material(material))
+                }
+            },
+        }
+    };
+    let expect = |count: usize| {
+        if numbers.len() == count {
+            Ok(())
+        } else {
+            Err(format!("'{keyword}' takes {count} numbers"))
+        }
+    };
+    let point = |i: usize| Vec2::new(numbers[i], numbers[i + 1]);
+
+    match keyword {
+        "name" => level.name = rest.trim().to_string(),
+        "instruments" => level.instrument_only = true,
+        "competitive" => level.competitive = true,
+        "dark" => level.dark = true,
+        "spawn" => {
+            expect(2)?;
+            level.spawn = point(0);
+        }
+        "par" => {
+            expect(1)?;
+            level.par_time = Some(numbers[0]);
+        }
+        "lives" => {
+            expect(1)?;
+            level.lives = numbers[0] as u32;
+        }
+        "gravity" => {
+            expect(2)?;
+            level.gravity = point(0);
+        }
+        "iterations" => {
+            expect(1)?;
+            level.solver_iterations = numbers[0] as u32;
+        }
+        "oxygen" => {
+            expect(1)?;
+            if !numbers[0].is_finite() || numbers[0] <= 0.0 {
+                return Err("'oxygen' takes a number of seconds above 0".to_string());
+            }
+            level.oxygen = Some(numbers[0]);
+        }
+        "wind" => {
+            expect(2)?;
+            level.wind = point(0);
+        }
+        "ground" => {
+            expect(3)?;
+            let (width, height, segments) = (numbers[0], numbers[1], numbers[2]);
+            if width <= 0.0 || segments < 1.0 {
+                return Err(
+                    "'ground' takes a width above 0, a height and at least one segment".to_string(),
+                );
+            }
+            let outline = generate_ground_poly(width as i32, height as i32, segments as usize, rng);
+            level.terrain.push(surface(Terrain::polygon(outline))?);
+        }
+        "rect" => {
+            expect(4)?;
+            let [x, y, w, h] = [numbers[0], numbers[1], numbers[2], numbers[3]];
+            level.terrain.push(surface(Terrain::rectangle(x, y, w, h))?);
+        }
+        "line" => {
+            expect(4)?;
+            let [x1, y1, x2, y2] = [numbers[0], numbers[1], numbers[2], numbers[3]];
+            level.terrain.push(surface(Terrain::line(x1, y1, x2, y2))?);
+        }
+        "circle" => {
+            expect(3)?;
+            level.terrain.push(surface(Terrain::circle(
+                numbers[0], numbers[1], numbers[2],
+            ))?);
+        }
+        "poly" => {
+            if numbers.len() < 6 || numbers.len() % 2 != 0 {
+                return Err("'poly' takes at least three pairs of numbers".to_string());
+            }
+            let points = numbers.chunks(2).map(|p| Vec2::new(p[0], p[1])).collect();
+            level.terrain.push(surface(Terrain::polygon(points))?);
+        }
+        "spline" | "bezier" => {
+            // an odd number left after the pairs of coordinates is the tolerance
+            let tolerance = match numbers.len() % 2 {
+                1 => numbers[numbers.len() - 1],
+                _ => Terrain::TOLERANCE,
+            };
+            let points = numbers
+                .chunks_exact(2)
+                .map(|p| Vec2::new(p[0], p[1]))
+                .collect::<Vec<_>>();
+            if tolerance <= 0.0 {
+                return Err(format!("'{keyword}' needs a tolerance above 0"));
+            }
+            let terrain = if keyword == "spline" {
+                if points.len() < 3 {
+                    return Err("'spline' takes at least three pairs of numbers".to_string());
+                }
+                Terrain::spline(&points, tolerance)
+            } else {
+                if points.len() < 4 || (points.len() - 1) % 3 != 0 {
+                    return Err(
+                        "'bezier' takes a start and then two control points and an end per curve"
+                            .to_string(),
+                    );
+                }
+                Terrain::bezier(&points, tolerance)
+            };
+            level.terrain.push(surface(terrain)?);
+        }
+        "svg" => {
+            let [file, rest @ ..] = words.as_slice() else {
+                return Err("'svg' takes the path of an SVG image".to_string());
+            };
+            let material = rest
+                .first()
+                .map_or(Ok(Material::DEFAULT), |w| self::material(w))?;
+            let path = dir.join(file);
+            let source = persistence::read_to_string(&path)
+                .map_err(|error| format!("can't read {}: {error}", path.display()))?;
+            let terrain = svg::parse(&source).map_err(|error| format!("{file}: {error}"))?;
+            level
+                .terrain
+                .extend(terrain.into_iter().map(|t| t.with_material(material)));
+        }
+        "image" => {
+            // the scale and the threshold are optional, and so is the material after them
+            let [file, rest @ ..] = words.as_slice() else {
+                return Err("'image' takes the path of a PNG image".to_string());
+            };
+            let numbers = rest
+                .iter()
+                .map_while(|word| word.parse::<f32>().ok())
+                .collect::<Vec<_>>();
+            let scale = numbers.first().copied().unwrap_or(1.0);
+            let threshold = numbers.get(1).copied().unwrap_or(0.5);
+            if numbers.len() > 2 || scale <= 0.0 || !(0.0..=1.0).contains(&threshold) {
+                return Err("'image' takes a scale above 0 and a threshold from 0 to 1".to_string());
+            }
+            let material = rest
+                .get(numbers.len())
+                .map_or(Ok(Material::DEFAULT), |w| self::material(w))?;
+            let path = dir.join(file);
+            let bytes = persistence::read(&path)
+                .map_err(|error| format!("can't read {}: {error}", path.display()))?;
+            let terrain = heightmap::parse(&bytes, scale, threshold)
+                .map_err(|error| format!("{file}: {error}"))?;
+            level
+                .terrain
+                .extend(terrain.into_iter().map(|t| t.with_material(material)));
+        }
+        "script" => {
+            let [file] = words.as_slice() else {
+                return Err("'script' takes the path of a Rhai script".to_string());
+            };
+            let path = dir.join(file);
+            let source = persistence::read_to_string(&path)
+                .map_err(|error| format!("can't read {}: {error}", path.display()))?;
+            let script = Script::new(&source).map_err(|error| format!("{file}: {error}"))?;
+            level.scripts.push(script);
+        }
+        "item" => {
+            // an optional third number makes the item fragile
+            let item = Item::new(numbers[0], numbers[1]);
+            match numbers.len() {
+                2 => level.items.push(item),
+                3 => level.items.push(item.with_fragility(numbers[2])),
+                _ => return Err("'item' takes 2 numbers, or 3 with its fragility".to_string()),
+            }
+        }
+        "crate" => {
+            // a long crate, dangling from the edge the beam grabs it by
+            expect(3)?;
+            if numbers[2] <= 0.0 {
+                return Err("a crate's length has to be more than 0".to_string());
+            }
+            level
+                .items
+                .push(Item::new(numbers[0], numbers[1]).with_length(numbers[2]));
+        }
+        "pickup" => {
+            let [name, x, y] = words.as_slice() else {
+                return Err("'pickup' takes a kind and 2 numbers".to_string());
+            };
+            let kind = PickupKind::from_name(name).ok_or(format!("unknown pickup '{name}'"))?;
+            let [x, y] = [x, y].map(|word| {
+                word.parse::<f32>()
+                    .map_err(|_| format!("'{word}' is not a number"))
+            });
+            level.pickups.push(Pickup::new(kind, vec2(x?, y?)));
+        }
+        "vault" | "switch" => {
+            let [name, rest @ ..] = &words[..] else {
+                return Err(format!("'{keyword}' takes a color"));
+            };
+            let color =
+                SwitchColor::from_name(name).ok_or(format!("unknown switch color '{name}'"))?;
+            let numbers = rest
+                .iter()
+                .map(|word| {
+                    word.parse::<f32>()
+                        .map_err(|_| format!("'{word}' is not a number"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            match (keyword, numbers.as_slice()) {
+                ("vault", &[x, y, w, h]) => {
+                    level.add_vault(color, Rect::new(x, y, w, h), SwitchArray::WINDOW)
+                }
+                ("vault", &[x, y, w, h, window]) => {
+                    level.add_vault(color, Rect::new(x, y, w, h), window)
+                }
+                ("switch", &[x, y]) => level.switch_array(color).add_switch(vec2(x, y)),
+                ("vault", _) => {
+                    return Err("'vault' takes a color, 4 numbers and a time window".to_string());
+                }
+                _ => return Err("'switch' takes a color and 2 numbers".to_string()),
+            }
+        }
+        "trigger" => {
+            // trigger enter|exit x y w h, then what it does
+            let [event, x, y, w, h, action, args @ ..] = &words[..] else {
+                return Err("'trigger' takes an event, 4 numbers and an action".to_string());
+            };
+            let event =
+                TriggerEvent::from_name(event).ok_or(format!("unknown trigger event '{event}'"))?;
+            let number = |word: &str| {
+                word.parse::<f32>()
+                    .map_err(|_| format!("'{word}' is not a number"))
+            };
+            let area = Rect::new(number(x)?, number(y)?, number(w)?, number(h)?);
+            let action = match (*action, args) {
+                ("door", &[name]) => TriggerAction::OpenDoor(level.door(name)),
+                ("drones", &[count]) => TriggerAction::SpawnDrones(number(count)? as usize),
+                ("text", words) if !words.is_empty() => TriggerAction::ShowText(words.join(" ")),
+                ("script", &[]) => TriggerAction::Script,
+                _ => {
+                    return Err(format!(
+                        "unknown trigger action '{action}', try 'door name', 'drones count', 'text message' or 'script'"
+                    ));
+                }
+            };
+            level.triggers.push(Trigger::new(area, event, action));
+        }
+        "door" | "plate" | "lever" | "key" => {
+            // the name of the door comes first, then its area or the position
+            let [name, rest @ ..] = &words[..] else {
+                return Err(format!("'{keyword}' takes the name of a door"));
+            };
+            let numbers = rest
+                .iter()
+                .map(|word| {
+                    word.parse::<f32>()
+                        .map_err(|_| format!("'{word}' is not a number"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            match (keyword, numbers.as_slice()) {
+                ("door", &[x, y, w, h]) => level.add_door(name, Rect::new(x, y, w, h)),
+                ("plate", &[x, y]) => level.add_plate(name, vec2(x, y)),
+                ("lever", &[x, y]) => level.add_lever(name, vec2(x, y)),
+                ("key", &[x, y]) => level.add_key(name, vec2(x, y)),
+                ("door", _) => return Err("'door' takes a name and 4 numbers".to_string()),
+                _ => {
+                    return Err(format!(
+                        "'{keyword}' takes the name of a door and 2 numbers"
+                    ));
+                }
+            }
+        }
+        "livestock" => {
+            expect(2)?;
+            level.items.push(Item::livestock(numbers[0], numbers[1]));
+        }
+        "bomb" => {
+            expect(3)?;
+            level
+                .items
+                .push(Item::bomb(numbers[0], numbers[1], numbers[2]));
+        }
+        "goal" => {
+            expect(2)?;
+            level.teleports.push(Teleporter::new(point(0)));
+        }
+        "disposal" => {
+            expect(2)?;
+            level.teleports.push(Teleporter::disposal(point(0)));
+        }
+        "funnel" => {
+            expect(5)?;
+            if numbers[4] <= 0.0 {
+                return Err("'funnel' takes a depth above 0".to_string());
+            }
+            if numbers[3] < 0.0 || numbers[3] >= numbers[2] {
+                return Err("'funnel' takes a throat narrower than its mouth".to_string());
+            }
+            level.add_funnel(Funnel::new(point(0), numbers[2], numbers[3], numbers[4]));
+        }
+        "checkpoint" => {
+            expect(2)?;
+            level.checkpoints.push(Checkpoint::new(point(0)));
+        }
+        "fuel" => {
+            expect(2)?;
+            level.fuel_pads.push(FuelPad::new(point(0)));
+        }
+        "water" => {
+            expect(4)?;
+            let [x, y, w, h] = [numbers[0], numbers[1], numbers[2], numbers[3]];
+            level.water.push(Water::new(x, y, w, h));
+        }
+        "turret" => {
+            expect(2)?;
+            level.turrets.push(Turret::new(point(0)));
+        }
+        "launcher" => {
+            expect(2)?;
+            level.turrets.push(Turret::launcher(point(0)));
+        }
+        "blackhole" => {
+            // an optional fourth number sets how hard the black hole pulls
+            let pull = match numbers.len() {
+                3 => Anomaly::PULL,
+                4 => numbers[3],
+                _ => return Err("'blackhole' takes 3 numbers, or 4 with its pull".to_string()),
+            };
+            let black_hole = Anomaly::black_hole(point(0), numbers[2], pull);
+            level.anomalies.push(black_hole);
+        }
+        "antigrav" => {
+            expect(3)?;
+            let bubble = Anomaly::anti_gravity(point(0), numbers[2]);
+            level.anomalies.push(bubble);
+        }
+        "wave" => {
+            expect(2)?;
+            level.waves.push(Wave {
+                time: numbers[0],
+                count: numbers[1] as usize,
+            });
+        }
+        "director" => {
+            // an optional fifth number sets how much faster every wave gets
+            let mut director = match numbers.len() {
+                4 | 5 => Director::new(numbers[0], numbers[1], numbers[2], numbers[3]),
+                _ => return Err("'director' takes 4 numbers, or 5 with its speedup".to_string()),
+            };
+            if let Some(speedup) = numbers.get(4) {
+                director.speedup = *speedup;
+            }
+            level.director = Some(director);
+        }
+        "mix" => {
+            let [name, weight] = words.as_slice() else {
+                return Err("'mix' takes an enemy kind and its weight".to_string());
+            };
+            let kind = EnemyKind::from_name(name).ok_or(format!("unknown enemy '{name}'"))?;
+            let weight = weight
+                .parse::<f32>()
+                .map_err(|_| format!("'{weight}' is not a number"))?;
+            let director = level
+                .director
+                .as_mut()
+                .ok_or("'mix' needs a 'director' line before it")?;
+            director.mix.push((kind, weight));
+        }
+        "nest" => {
+            expect(2)?;
+            let director = level
+                .director
+                .as_mut()
+                .ok_or("'nest' needs a 'director' line before it")?;
+            director.nests.push(point(0));
+        }
+        "escort" => {
+            if numbers.len() < 4 || numbers.len() % 2 != 0 {
+                return Err("'escort' takes at least two pairs of numbers".to_string());
+            }
+            let path = numbers.chunks(2).map(|p| Vec2::new(p[0], p[1])).collect();
+            level.escort_path = Some(path);
+        }
+        "fog" => {
+            expect(2)?;
+            let keyframe = FogKeyframe {
+                time: numbers[0],
+                density: numbers[1],
+            };
+            level.fog.keyframes.push(keyframe);
+        }
+        "drifting-fog" => level.fog = Fog::drifting(),
+        "deliver" => {
+            expect(1)?;
+            level.objectives.push(Objective::Deliver(numbers[0] as u32));
+        }
+        "defuse" => {
+            expect(1)?;
+            level.objectives.push(Objective::Defuse(numbers[0] as u32));
+        }
+        "protect" => level.objectives.push(Objective::Escort),
+        "survive" => match numbers.len() {
+            0 => level.objectives.push(Objective::Survive(None)),
+            1 => level
+                .objectives
+                .push(Objective::Survive(Some(numbers[0] as u32))),
+            _ => return Err("'survive' takes the number of waves, if any".to_string()),
+        },
+        "vine" | "crystal" | "fogbank" => {
+            expect(3)?;
+            let kind = match keyword {
+                "vine" => DecorationKind::Vine,
+                "crystal" => DecorationKind::Crystal,
+                _ => DecorationKind::FogBank,
+            };
+            level
+                .decorations
+                .push(Decoration::new(kind, point(0), numbers[2]));
+        }
+        _ => return Err(format!("unknown keyword '{keyword}'")),
+    }
+    Ok(())
+}
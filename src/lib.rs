@@ -1,4 +1,69 @@
+pub mod analysis;
+pub mod animation;
+pub mod anomalies;
+pub mod assets;
+pub mod camera;
+pub mod campaign;
+pub mod console;
+pub mod controller;
+pub mod cosmetics;
+pub mod credits;
+pub mod decoration;
+pub mod dialog;
+pub mod difficulty;
+pub mod director;
+pub mod doors;
+pub mod ecs;
+pub mod enemy;
+pub mod entities;
+pub mod escort;
+pub mod ffi;
+pub mod fluids;
+pub mod ghost;
+pub mod headless;
+pub mod heightmap;
+pub mod hints;
+pub mod hot_reload;
+pub mod hud;
+pub mod ldtk;
+pub mod leaderboard;
+pub mod level;
+pub mod level_file;
+pub mod lighting;
+pub mod menu;
+pub mod mod_browser;
+pub mod modifiers;
+pub mod mods;
+pub mod net;
+pub mod objective;
+pub mod particles;
+pub mod persistence;
 pub mod physics;
+pub mod pickups;
+pub mod post;
+pub mod projectiles;
+#[cfg(feature = "python")]
+mod python;
+pub mod query;
+pub mod render;
+pub mod replay;
+pub mod route;
+pub mod scripting;
+pub mod settings;
+pub mod settings_menu;
+pub mod shop;
+pub mod snapshot;
+pub mod svg;
+pub mod switches;
+pub mod systems;
+pub mod tape;
+pub mod telemetry;
 pub mod terrain;
+pub mod tiled;
+pub mod time_control;
+pub mod triggers;
+pub mod tutorial;
 pub mod ui;
+pub mod warnings;
+pub mod weather;
 pub mod world;
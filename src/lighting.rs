@@ -0,0 +1,228 @@
+use std::cell::RefCell;
+use std::f32::consts::TAU;
+
+use macroquad::miniquad::{BlendFactor, BlendState, BlendValue, Equation};
+use macroquad::prelude::*;
+
+/// The light left in the darkness away from any light source
+pub const AMBIENT: Color = Color::new(0.05, 0.05, 0.08, 1.0);
+
+/// The number of layers a light is built up from, each reaching a bit further.
+/// Layers overlap near the source, so lights fade out towards their edge.
+const LAYERS: usize = 6;
+
+/// The number of segments making up the arc of a cone or the rim of a glow
+const SEGMENTS: usize = 16;
+
+/// Passes the positions, colors and texture coordinates through, as macroquad's own shader does
+pub(crate) const VERTEX: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}"#;
+
+const FRAGMENT: &str = r#"#version 100
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = color * texture2D(Texture, uv);
+}"#;
+
+/// A source of light in a dark level
+#[derive(Clone, Copy, Debug)]
+pub enum Light {
+    /// A beam of light shining from a point in a direction, like a headlamp
+    Cone {
+        origin: Vec2,
+        /// The direction the cone points in, in radians
+        angle: f32,
+        /// Half the angle the cone spreads over, in radians
+        spread: f32,
+        range: f32,
+        color: Color,
+    },
+    /// Light shining evenly all around a point
+    Glow {
+        center: Vec2,
+        radius: f32,
+        color: Color,
+    },
+}
+
+impl Light {
+    /// Draw the light with all its layers, fading out towards its edge
+    pub(crate) fn draw(&self) {
+        for layer in 1..=LAYERS {
+            self.draw_layer(layer as f32 / LAYERS as f32);
+        }
+    }
+
+    /// Draw one layer of the light, reaching the given fraction of its full size
+    fn draw_layer(&self, reach: f32) {
+        match *self {
+            Light::Cone {
+                origin,
+                angle,
+                spread,
+                range,
+                color,
+            } => {
+                let color = layer_color(color);
+                let point = |step: usize| {
+                    let angle = angle - spread + 2.0 * spread * step as f32 / SEGMENTS as f32;
+                    origin + Vec2::from_angle(angle) * range * reach
+                };
+                for step in 0..SEGMENTS {
+                    draw_triangle(origin, point(step), point(step + 1), color);
+                }
+            }
+            Light::Glow {
+                center,
+                radius,
+                color,
+            } => {
+                let color = layer_color(color);
+                let point = |step: usize| {
+                    center + Vec2::from_angle(TAU * step as f32 / SEGMENTS as f32) * radius * reach
+                };
+                for step in 0..SEGMENTS {
+                    draw_triangle(center, point(step), point(step + 1), color);
+                }
+            }
+        }
+    }
+}
+
+/// The color of a single layer, so all layers together add up to the full color
+fn layer_color(color: Color) -> Color {
+    Color::new(color.r, color.g, color.b, color.a / LAYERS as f32)
+}
+
+/// The off-screen light map and the materials for drawing into it and onto the screen
+struct LightMap {
+    target: RenderTarget,
+    /// Adds the lights up in the light map
+    additive: Material,
+    /// Multiplies the screen by the light map, darkening whatever isn't lit
+    multiply: Material,
+}
+
+impl LightMap {
+    fn new(width: u32, height: u32) -> Self {
+        let material = |color_blend| {
+            load_material(
+                ShaderSource::Glsl {
+                    vertex: VERTEX,
+                    fragment: FRAGMENT,
+                },
+                MaterialParams {
+                    pipeline_params: PipelineParams {
+                        color_blend: Some(color_blend),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .expect("the lighting shaders are valid")
+        };
+        LightMap {
+            target: render_target(width, height),
+            additive: material(BlendState::new(
+                Equation::Add,
+                BlendFactor::Value(BlendValue::SourceAlpha),
+                BlendFactor::One,
+            )),
+            multiply: material(BlendState::new(
+                Equation::Add,
+                BlendFactor::Value(BlendValue::DestinationColor),
+                BlendFactor::Zero,
+            )),
+        }
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (
+            self.target.texture.width() as u32,
+            self.target.texture.height() as u32,
+        )
+    }
+}
+
+thread_local! {
+    /// The light map, kept between frames and only recreated when the view changes size
+    static LIGHT_MAP: RefCell<Option<LightMap>> = const { RefCell::new(None) };
+}
+
+/// Darken the part of the screen shown through `camera` everywhere but around the lights.
+/// The scene must already be drawn; `view` is the part of the screen the camera draws to,
+/// unless the scene is drawn into a render target, `onto`, which the view fills instead.
+pub fn draw(camera: &Camera2D, view: Rect, lights: &[Light], onto: Option<&RenderTarget>) {
+    let size = (view.w.max(1.0) as u32, view.h.max(1.0) as u32);
+    LIGHT_MAP.with_borrow_mut(|light_map| {
+        let light_map = match light_map {
+            Some(light_map) if light_map.size() == size => light_map,
+            _ => light_map.insert(LightMap::new(size.0, size.1)),
+        };
+
+        // add the lights up in the light map, seen just like the scene
+        set_camera(&Camera2D {
+            zoom: camera.zoom,
+            target: camera.target,
+            rotation: camera.rotation,
+            offset: camera.offset,
+            render_target: Some(light_map.target.clone()),
+            ..Default::default()
+        });
+        clear_background(AMBIENT);
+        gl_use_material(&light_map.additive);
+        for layer in 1..=LAYERS {
+            let reach = layer as f32 / LAYERS as f32;
+            for light in lights {
+                light.draw_layer(reach);
+            }
+        }
+
+        // darken the scene by the light map
+        let at = match onto {
+            Some(target) => {
+                set_camera(&Camera2D {
+                    zoom: vec2(2.0 / view.w, 2.0 / view.h),
+                    target: view.size() / 2.0,
+                    render_target: Some(target.clone()),
+                    ..Default::default()
+                });
+                Vec2::ZERO
+            }
+            None => {
+                set_default_camera();
+                view.point()
+            }
+        };
+        gl_use_material(&light_map.multiply);
+        draw_texture_ex(
+            &light_map.target.texture,
+            at.x,
+            at.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(view.size()),
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+    });
+}
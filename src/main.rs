@@ -1,17 +1,1051 @@
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use macroquad::prelude::*;
 
-use jetman::ui::InputState;
-use jetman::world::World;
+use jetman::analysis::{self, Heatmap};
+use jetman::assets;
+use jetman::campaign::{Campaign, LevelSummary};
+use jetman::console::Console;
+use jetman::controller::{Autopilot, Controller, Keyboard, WorldView};
+use jetman::cosmetics::{Cosmetics, Trail};
+use jetman::credits::Credits;
+use jetman::dialog::Dialog;
+use jetman::difficulty::{AdaptiveDifficulty, Difficulty};
+use jetman::director;
+use jetman::ghost::Ghost;
+use jetman::headless::Batch;
+use jetman::hints::HintSystem;
+use jetman::hot_reload::Watcher;
+use jetman::hud;
+use jetman::leaderboard::{self, Leaderboard, Record};
+use jetman::level::Level;
+use jetman::level_file;
+use jetman::menu::{MainMenu, MenuChoice};
+use jetman::mod_browser::ModBrowser;
+use jetman::mods::{self, ModConfig};
+use jetman::net::{self, Client};
+use jetman::replay::{Replay, ReplayPlayer};
+use jetman::settings::{Palette, Settings};
+use jetman::settings_menu::SettingsMenu;
+use jetman::shop::{Profile, Shop};
+use jetman::snapshot::Compression;
+use jetman::tape::InputTape;
+use jetman::telemetry::Telemetry;
+use jetman::time_control::TimeControl;
+use jetman::tutorial::Tutorial;
+use jetman::ui::{Action, Bindings, InputState};
+use jetman::warnings::{self, Alerts};
+use jetman::world::{self, World};
+
+const USAGE: &str = "usage: jetman [LEVEL] [--seed N] [--replay FILE] [--fullscreen | --windowed] \
+[--paused] [--dev] [--crashes FILE] [--headless] [--telemetry FILE] [--bench-sim FRAMES]
+
+Plays Jetman. Given a level file or a seed, flies it straight away instead of showing
+the menu, starting paused with --paused. Given a replay file, watches the replay.
+With --dev come the tools for making levels: the level file is flown again as soon as
+it is saved, ` opens the console and [ ] \\ . slow, speed up, freeze and step time.
+With --crashes, the crash positions saved by the level analyzer are shown over every
+level as a heatmap. Runs the tools changed don't make the best times.
+With --headless there is no window: the autopilot flies the level file, or the replay
+plays out, as fast as it can, and how the run went is printed. With --telemetry, the
+physics of the pod after every step are logged to FILE as CSV, for plotting.
+With --bench-sim, the level file, or else a stress test crowded with items over long,
+rough ground, is stepped for FRAMES frames without a window, and the time a frame
+took is printed.";
+
+/// The items of the stress test `--bench-sim` steps unless given a level file
+const BENCH_ITEMS: usize = 300;
+/// The segments of the ground of the stress test
+const BENCH_SEGMENTS: usize = 3000;
+
+/// How the game was launched
+struct Options {
+    /// The level file to fly straight away, if any
+    level: Option<String>,
+    /// The seed of the terrain to fly straight away, if any
+    seed: Option<u64>,
+    /// The replay file to watch straight away, if any
+    replay: Option<String>,
+    fullscreen: bool,
+    /// Whether the level flown straight away starts out paused
+    paused: bool,
+    /// Whether the tools for making levels are on
+    dev: bool,
+    /// The crash positions saved by the level analyzer to show, if any
+    crashes: Option<String>,
+    /// Whether to run without a window
+    headless: bool,
+    /// The CSV file to log the physics of the pod to, if any
+    telemetry: Option<String>,
+    /// The number of frames to time the simulation over, if asked to
+    bench_frames: Option<usize>,
+}
+
+/// Read the options from the command line arguments
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        level: None,
+        seed: None,
+        replay: None,
+        fullscreen: false,
+        paused: false,
+        dev: false,
+        crashes: None,
+        headless: false,
+        telemetry: None,
+        bench_frames: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+        let number = |value: String| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{arg} needs a number, not '{value}'"))
+        };
+        match arg.as_str() {
+            "--seed" => options.seed = Some(number(value()?)?),
+            "--replay" => options.replay = Some(value()?),
+            "--fullscreen" => options.fullscreen = true,
+            "--windowed" => options.fullscreen = false,
+            "--paused" => options.paused = true,
+            "--dev" => options.dev = true,
+            "--crashes" => options.crashes = Some(value()?),
+            "--headless" => options.headless = true,
+            "--telemetry" => options.telemetry = Some(value()?),
+            "--bench-sim" => options.bench_frames = Some(number(value()?)? as usize),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+            _ => options.level = Some(arg),
+        }
+    }
+    if options.replay.is_some() && (options.level.is_some() || options.seed.is_some()) {
+        return Err("a replay brings its own level and seed".to_string());
+    }
+    if options.replay.is_some() && options.bench_frames.is_some() {
+        return Err("--bench-sim steps a level, not a replay".to_string());
+    }
+    if options.crashes.is_some() && !options.dev {
+        return Err("--crashes is one of the tools of --dev".to_string());
+    }
+    Ok(options)
+}
+
+/// The world to fly straight away: the level given on the command line, or else the
+/// terrain of the seed given. None if neither was given.
+fn launch(options: &Options) -> Option<Result<World, String>> {
+    match &options.level {
+        Some(path) => {
+            let seed = options.seed.unwrap_or_else(world::random_seed);
+            Some(level_file::load(path, seed).map(World::from_level))
+        }
+        None => options.seed.map(|seed| Ok(World::new_with_seed(seed))),
+    }
+}
+
+/// Log the physics of the pod, if asked to
+fn log(telemetry: &mut Option<Telemetry>, world: &World) -> Result<(), String> {
+    match telemetry {
+        Some(log) => log
+            .record(world)
+            .map_err(|error| format!("can't log the telemetry: {error}")),
+        None => Ok(()),
+    }
+}
+
+/// Play the replay, or else have the autopilot fly the level, without a window,
+/// and print how the run went
+fn simulate(
+    options: &Options,
+    replay: Option<Replay>,
+    mut telemetry: Option<Telemetry>,
+) -> Result<(), String> {
+    let world = match replay {
+        Some(replay) => {
+            let frames = replay.len();
+            let mut player = ReplayPlayer::new(replay);
+            while player.tick() < frames {
+                player.seek(player.tick() + 1);
+                log(&mut telemetry, player.world())?;
+            }
+            player.world().clone()
+        }
+        // the demo level fits the window, so without one there has to be a level file
+        None if options.level.is_none() => {
+            return Err("--headless needs a level file or a replay".to_string());
+        }
+        None => {
+            let mut world = launch(options).expect("a level file was given")?;
+            let mut autopilot = Autopilot::new();
+            for _ in 0..Batch::MAX_FRAMES {
+                if world.objectives_complete() || world.is_game_over() {
+                    break;
+                }
+                let input = autopilot.input(&WorldView::new(&world));
+                world.step(&input, World::FIXED_STEP);
+                log(&mut telemetry, &world)?;
+            }
+            world
+        }
+    };
+    if let Some(log) = telemetry.as_mut() {
+        log.flush()
+            .map_err(|error| format!("can't log the telemetry: {error}"))?;
+    }
+    let completed = if world.objectives_complete() {
+        "yes"
+    } else {
+        "no"
+    };
+    println!("{} (seed {})", world.level_name(), world.seed());
+    println!("completed: {completed}");
+    println!("time:      {}", hud::format_time(world.time()));
+    println!("deaths:    {}", world.deaths());
+    Ok(())
+}
+
+/// Step the level file, or else the stress test, for the given number of frames
+/// without a window, and print how long the frames took
+fn benchmark(options: &Options, frames: usize) -> Result<(), String> {
+    let seed = options.seed.unwrap_or(0);
+    let level = match &options.level {
+        Some(path) => level_file::load(path, seed)?,
+        None => Level::stress(seed, BENCH_ITEMS, BENCH_SEGMENTS),
+    };
+    let items = level.items.len();
+    let edges = level
+        .terrain
+        .iter()
+        .map(|terrain| terrain.outline().len())
+        .sum::<usize>();
+    let mut world = World::from_level(level);
+    let input = InputState::default();
+    let mut total = Duration::ZERO;
+    let mut slowest = Duration::ZERO;
+    for _ in 0..frames {
+        let start = Instant::now();
+        world.step(&input, World::FIXED_STEP);
+        let took = start.elapsed();
+        total += took;
+        slowest = slowest.max(took);
+    }
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    println!(
+        "{}: {items} items, {edges} edges of terrain outlines",
+        world.level_name()
+    );
+    println!("frames:  {frames}");
+    println!("average: {:.3} ms/frame", ms(total) / frames.max(1) as f64);
+    println!("slowest: {:.3} ms", ms(slowest));
+    Ok(())
+}
+
+/// The screens the game can show
+#[derive(Clone, Copy)]
+enum Screen {
+    Menu,
+    Playing,
+    Credits,
+    /// The best times shown after completing a level, with the place the run took
+    Results(Option<usize>),
+    /// The summary of a completed campaign level
+    Summary,
+    /// The shop between the levels of a campaign, where credits buy upgrades
+    Shop,
+    /// The replay of the last run
+    Replay,
+    /// The settings, to change
+    Settings,
+    /// The mods found, to enable and disable
+    Mods,
+    /// Playing on a server, together with other players over the network
+    Online,
+}
+
+/// What happens once the player confirms the open dialog
+enum Pending {
+    /// Leave the game
+    Quit,
+    /// Abandon the current run and go back to the main menu
+    LeaveRun,
+    /// Start a hardcore campaign
+    Hardcore,
+    /// Nothing, the dialog just informed the player
+    Nothing,
+}
+
+/// The seed of every time trial, so everybody races on the same ground
+const TIME_TRIAL_SEED: u64 = 0;
+
+/// The address of the server to join, from `JETMAN_SERVER` or else this machine
+fn server_address() -> String {
+    std::env::var("JETMAN_SERVER").unwrap_or_else(|_| format!("127.0.0.1:{}", net::PORT))
+}
+
+/// The player at the keyboard with the keys and the mouse aim the settings say
+fn keyboard(settings: &Settings) -> Keyboard {
+    Keyboard::new(settings.bindings()).with_mouse_aim(settings.mouse_aim)
+}
+
+/// Load the level of survival mode on fresh ground, with the pod upgraded as in the profile
+fn survival_level(profile: &Profile) -> Result<World, String> {
+    let mut level = level_file::load(director::SURVIVAL, world::random_seed())?;
+    level.upgrades = profile.upgrades;
+    Ok(World::from_level(level))
+}
+
+/// Get a fresh world ready to play, reapplying the accepted hints and the difficulty tuning
+fn prepare(mut world: World, hints: &mut HintSystem, difficulty: &AdaptiveDifficulty) -> World {
+    hints.start(&mut world);
+    world.apply_modifiers(&difficulty.modifiers());
+    world
+}
 
 /// Entry point of the jetman application
-#[macroquad::main("Jetman")]
-async fn main() {
+fn main() -> ExitCode {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            if !error.is_empty() {
+                eprintln!("{error}");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let telemetry = match options.telemetry.as_deref().map(Telemetry::create) {
+        Some(Ok(telemetry)) => Some(telemetry),
+        Some(Err(error)) => {
+            eprintln!("can't log the telemetry: {error}");
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+    let replay = match options.replay.as_deref().map(Replay::load) {
+        Some(Ok(replay)) => Some(replay),
+        Some(Err(error)) => {
+            eprintln!("can't load the replay: {error}");
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+    let crash_map = match options.crashes.as_deref().map(analysis::load_crashes) {
+        Some(Ok(crashes)) => Some(Arc::new(Heatmap::of(&crashes, Heatmap::CELL_SIZE))),
+        Some(Err(error)) => {
+            eprintln!("can't load the crashes: {error}");
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+    if options.headless || options.bench_frames.is_some() {
+        let outcome = match options.bench_frames {
+            Some(frames) => benchmark(&options, frames),
+            None => simulate(&options, replay, telemetry),
+        };
+        return match outcome {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("{error}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let conf = Conf {
+        window_title: "Jetman".to_string(),
+        fullscreen: options.fullscreen,
+        ..Default::default()
+    };
+    macroquad::Window::from_config(conf, play(options, replay, telemetry, crash_map));
+    ExitCode::SUCCESS
+}
+
+/// Run the game in its window
+async fn play(
+    options: Options,
+    watched: Option<Replay>,
+    mut telemetry: Option<Telemetry>,
+    crash_map: Option<Arc<Heatmap>>,
+) {
+    // the world or the replay given on the command line, to open once the game is ready
+    let mut launched = launch(&options);
+    let mut mod_browser = ModBrowser::new(
+        mods::discover(mods::MOD_DIR),
+        ModConfig::load(ModConfig::PROFILE),
+    );
+    // the mods played with, changed in the mod browser
+    let mut mods = mod_browser.active();
+    assets::load(&mods).await;
+    let mut screen = Screen::Menu;
+    let mut menu = MainMenu::new();
+    let mut credits = Credits::new();
     let mut world = World::new();
+    let mut hints = HintSystem::new();
+    let mut tutorial = Tutorial::new();
+    let mut console = Console::new();
+    let mut time_control = TimeControl::new();
+    let mut settings = Settings::load();
+    Palette::set_current(settings.palette());
+    let mut settings_menu = SettingsMenu::new(settings.clone());
+    let mut alerts = Alerts::load().await;
+    let mut bindings = settings.bindings();
+    let mut difficulty = AdaptiveDifficulty::new(Difficulty::Normal);
+    let mut leaderboard = Leaderboard::load();
+    let mut cosmetics = Cosmetics::load();
+    let mut profile = Profile::load(ModConfig::PROFILE);
+    let mut shop = Shop::new();
+    let mut campaign: Option<Campaign> = None;
+    // whether the campaign is raced as a time trial, against the ghosts of the best runs
+    let mut time_trial = false;
+    // whether a second player flies a partner pod
+    let mut co_op = false;
+    // whether the director's waves keep coming until the last pod is lost
+    let mut survival = false;
+    // whether the campaign is flown with the upgrades of the profile, earning credits.
+    // Time trials are raced on stock pods, so the best times compare.
+    let mut upgraded = false;
+    // the connection to the server while playing online
+    let mut online: Option<Client> = None;
+    // who flies the pod: the player, or the autopilot when watching it play
+    let mut controller: Box<dyn Controller> = Box::new(keyboard(&settings));
+    let mut summary: Option<LevelSummary> = None;
+    let mut deaths = 0;
+    let mut completed = false;
+    // whether the players flew the level by themselves. A run the autopilot or the
+    // tools for making levels helped along makes no best times, leaves no replay or
+    // ghost and unlocks nothing.
+    let mut counts = true;
+    let mut paused = false;
+    let mut modal: Option<(Dialog, Pending)> = None;
+    // mods the game can't use are left out, and the player is told why
+    let problems = mod_browser.problems();
+    if let Some(first) = problems.first() {
+        let message = match problems.len() {
+            1 => first.clone(),
+            count => format!("{first} (and {} more)", count - 1),
+        };
+        modal = Some((Dialog::new("Mods left out", &message), Pending::Nothing));
+    }
+    // the run being recorded, and the playback of a finished one
+    let mut replay: Option<Replay> = None;
+    // the actions of the run, to edit and play again without a window
+    let mut tape = InputTape::new();
+    let mut player: Option<ReplayPlayer> = None;
+    let mut after_replay = Screen::Menu;
+    // whether the player has just confirmed starting a hardcore campaign
+    let mut hardcore_confirmed = false;
+    // whether the world given on the command line is still to start out paused
+    let mut start_paused = options.paused && matches!(launched, Some(Ok(_)));
+    if let Some(watched) = watched {
+        player = Some(ReplayPlayer::new(watched));
+        screen = Screen::Replay;
+    }
+
+    let mut fullscreen = options.fullscreen;
+    // the files edited by hand while playing: the settings, and with --dev the level file
+    let mut watcher = Watcher::new();
+    watcher.watch(Settings::PATH);
 
     loop {
-        let input = InputState::from_raylib();
-        world.update(&input);
-        world.draw(&input);
+        // F11 switches between the window and fullscreen on every screen
+        if is_key_pressed(KeyCode::F11) {
+            fullscreen = !fullscreen;
+            set_fullscreen(fullscreen);
+        }
+        let mut input = InputState::from_bindings(&bindings);
+        // an open dialog takes all input until the player answers it
+        if let Some((dialog, pending)) = modal.as_mut() {
+            let choice = dialog.update(&input);
+            input = InputState::default();
+            if let Some(choice) = choice {
+                if choice == 0 {
+                    match pending {
+                        Pending::Quit => break,
+                        Pending::LeaveRun => screen = Screen::Menu,
+                        Pending::Hardcore => hardcore_confirmed = true,
+                        Pending::Nothing => {}
+                    }
+                }
+                modal = None;
+            }
+        }
+
+        // changes to the settings apply at once, and a changed level is rebuilt around the pod
+        for path in watcher.update(get_frame_time()) {
+            if path == Path::new(Settings::PATH) {
+                settings = Settings::load();
+                bindings = settings.bindings();
+                Palette::set_current(settings.palette());
+                world.set_camera_options(settings.camera_options());
+                world.set_hud_layout(settings.hud.clone());
+                world.assist = settings.assist;
+            } else if world.level_path() == Some(path.as_path()) {
+                match world.reload() {
+                    Ok(()) => {
+                        counts = false;
+                        world.apply_modifiers(&difficulty.modifiers());
+                        if let Some(replay) = replay.as_mut() {
+                            replay.keyframe(&world);
+                        }
+                    }
+                    Err(error) => modal = Some((Dialog::error(&error), Pending::Nothing)),
+                }
+            }
+        }
+
+        // a new level to play, from the menu, a restart or the campaign moving on
+        let mut next: Option<Result<World, String>> = launched.take();
+        let finished = matches!(screen, Screen::Results(_) | Screen::Summary);
+        if finished
+            && modal.is_none()
+            && is_key_pressed(KeyCode::R)
+            && let Some(recorded) = &replay
+        {
+            player = Some(ReplayPlayer::new(recorded.clone()));
+            after_replay = screen;
+            screen = Screen::Replay;
+        }
+        match screen {
+            Screen::Menu => {
+                // hardcore campaigns only start once the player has confirmed the risk
+                let choice = if std::mem::take(&mut hardcore_confirmed) {
+                    Some(MenuChoice::Hardcore)
+                } else {
+                    match menu.update(&input) {
+                        Some(MenuChoice::Hardcore) => {
+                            let dialog = Dialog::confirm(
+                                "Hardcore",
+                                "One pod. Lose it and the campaign is gone. Dare?",
+                            );
+                            modal = Some((dialog, Pending::Hardcore));
+                            None
+                        }
+                        choice => choice,
+                    }
+                };
+                match choice {
+                    Some(MenuChoice::Play) => {
+                        campaign = None;
+                        time_trial = false;
+                        co_op = false;
+                        survival = false;
+                        controller = Box::new(keyboard(&settings));
+                        next = Some(Ok(World::new()));
+                    }
+                    Some(
+                        choice @ (MenuChoice::Campaign
+                        | MenuChoice::TimeTrial
+                        | MenuChoice::Hardcore
+                        | MenuChoice::CoOp
+                        | MenuChoice::Autopilot),
+                    ) => {
+                        time_trial = choice == MenuChoice::TimeTrial;
+                        co_op = choice == MenuChoice::CoOp;
+                        survival = false;
+                        upgraded = !matches!(choice, MenuChoice::TimeTrial | MenuChoice::Autopilot);
+                        controller = match choice {
+                            MenuChoice::Autopilot => Box::new(Autopilot::new()),
+                            // the players split the keyboard between them
+                            MenuChoice::CoOp => Box::new(Keyboard::new(Bindings::arrows())),
+                            _ => Box::new(keyboard(&settings)),
+                        };
+                        let seed = if time_trial {
+                            TIME_TRIAL_SEED
+                        } else {
+                            world::random_seed()
+                        };
+                        match Campaign::load(Campaign::PATH, seed) {
+                            Ok(loaded) => {
+                                let upgrades = if upgraded {
+                                    profile.upgrades
+                                } else {
+                                    Default::default()
+                                };
+                                let loaded = loaded
+                                    .with_mods(mods.clone())
+                                    .with_hardcore(choice == MenuChoice::Hardcore)
+                                    .with_upgrades(upgrades);
+                                next = Some(loaded.start_level());
+                                campaign = Some(loaded);
+                            }
+                            Err(error) => next = Some(Err(error)),
+                        }
+                    }
+                    Some(MenuChoice::Survival) => {
+                        campaign = None;
+                        time_trial = false;
+                        co_op = false;
+                        survival = true;
+                        controller = Box::new(keyboard(&settings));
+                        next = Some(survival_level(&profile));
+                    }
+                    Some(MenuChoice::Online) => match Client::connect(server_address()) {
+                        Ok(client) => {
+                            online = Some(client);
+                            screen = Screen::Online;
+                        }
+                        Err(error) => {
+                            let message = format!("Could not reach the server: {error}");
+                            modal = Some((Dialog::error(&message), Pending::Nothing));
+                        }
+                    },
+                    Some(MenuChoice::Settings) => {
+                        settings_menu = SettingsMenu::new(settings.clone());
+                        screen = Screen::Settings;
+                    }
+                    Some(MenuChoice::Mods) => screen = Screen::Mods,
+                    Some(MenuChoice::Credits) => {
+                        credits = Credits::new();
+                        screen = Screen::Credits;
+                    }
+                    Some(MenuChoice::Quit) => {
+                        let dialog = Dialog::confirm("Quit", "Do you really want to leave?");
+                        modal = Some((dialog, Pending::Quit));
+                    }
+                    None => {}
+                }
+                set_default_camera();
+                menu.draw();
+            }
+            Screen::Credits => {
+                credits.update(get_frame_time(), input.scroll);
+                if input.back || input.select {
+                    screen = Screen::Menu;
+                }
+                set_default_camera();
+                credits.draw();
+            }
+            Screen::Settings => {
+                if settings_menu.update(&input) {
+                    settings = settings_menu.settings().clone();
+                    bindings = settings.bindings();
+                    Palette::set_current(settings.palette());
+                    if let Err(error) = settings_menu.save() {
+                        let message = format!("Could not save the settings: {error}");
+                        modal = Some((Dialog::error(&message), Pending::Nothing));
+                    }
+                    screen = Screen::Menu;
+                }
+                set_default_camera();
+                settings_menu.draw();
+            }
+            Screen::Mods => {
+                if mod_browser.update(&input) {
+                    mods = mod_browser.active();
+                    if let Err(error) = mod_browser.save() {
+                        let message = format!("Could not save the mod settings: {error}");
+                        modal = Some((Dialog::error(&message), Pending::Nothing));
+                    }
+                    screen = Screen::Menu;
+                }
+                set_default_camera();
+                mod_browser.draw();
+            }
+            Screen::Results(place) => {
+                if input.select {
+                    screen = Screen::Menu;
+                }
+                set_default_camera();
+                leaderboard.draw(world.level_name(), world.seed(), world.time(), place);
+            }
+            Screen::Summary => {
+                set_default_camera();
+                if let (Some(campaign), Some(summary)) = (&campaign, &summary) {
+                    campaign.draw_summary(summary);
+                }
+                let shopping = upgraded && campaign.as_ref().is_some_and(|c| !c.is_finished());
+                if shopping {
+                    let text = format!(
+                        "Press U to spend your {} credits in the shop",
+                        profile.credits
+                    );
+                    draw_text(&text, 10.0, screen_height() - 34.0, 20.0, GRAY);
+                }
+                if shopping && modal.is_none() && is_key_pressed(KeyCode::U) {
+                    shop = Shop::new();
+                    screen = Screen::Shop;
+                } else if input.select {
+                    match campaign.as_ref() {
+                        Some(running) if !running.is_finished() => {
+                            next = Some(running.start_level());
+                        }
+                        _ => {
+                            campaign = None;
+                            screen = Screen::Menu;
+                        }
+                    }
+                }
+            }
+            Screen::Shop => {
+                if shop.update(&input, &mut profile) {
+                    if let Err(error) = profile.save() {
+                        let message = format!("Could not save the profile: {error}");
+                        modal = Some((Dialog::error(&message), Pending::Nothing));
+                    }
+                    campaign = campaign.take().map(|c| c.with_upgrades(profile.upgrades));
+                    screen = Screen::Summary;
+                }
+                set_default_camera();
+                shop.draw(&profile);
+            }
+            Screen::Replay => {
+                if let Some(player) = player.as_mut() {
+                    player.update(&input, get_frame_time());
+                    player.draw();
+                }
+                if input.back {
+                    player = None;
+                    screen = after_replay;
+                }
+            }
+            Screen::Online => {
+                if let Some(client) = online.as_mut()
+                    && let Err(error) = client
+                        .send_input(&input)
+                        .and_then(|()| client.update(get_frame_time()))
+                {
+                    let message = format!("Lost the connection to the server: {error}");
+                    modal = Some((Dialog::error(&message), Pending::Nothing));
+                    online = None;
+                    screen = Screen::Menu;
+                }
+                if input.back {
+                    // the server lets go of players it stops hearing from anyway
+                    if let Some(client) = online.take() {
+                        let _ = client.leave();
+                    }
+                    screen = Screen::Menu;
+                }
+                match online.as_ref().and_then(Client::world) {
+                    Some(shown) => {
+                        shown.draw(&input);
+                        if let Some(won) = online.as_ref().and_then(Client::run_over) {
+                            let text = if won {
+                                "Level complete! Starting over..."
+                            } else {
+                                "The run is lost. Starting over..."
+                            };
+                            let width = measure_text(text, None, 36, 1.0).width;
+                            let y = screen_height() / 3.0;
+                            draw_text(text, (screen_width() - width) / 2.0, y, 36.0, WHITE);
+                        }
+                    }
+                    None => {
+                        let status = match &online {
+                            Some(client) if client.is_full() => "The server is full",
+                            _ => "Waiting for the server...",
+                        };
+                        set_default_camera();
+                        clear_background(BLACK);
+                        let x = screen_width() / 2.0 - 180.0;
+                        let y = screen_height() / 2.0;
+                        draw_text(status, x, y, 36.0, WHITE);
+                        draw_text("Press Escape for the main menu", x, y + 36.0, 20.0, GRAY);
+                    }
+                }
+            }
+            Screen::Playing => {
+                if options.dev && is_key_pressed(KeyCode::GraveAccent) {
+                    console.toggle();
+                }
+                // while the console is open, typing doesn't fly the pod
+                if console.is_open() {
+                    if console.update(&mut world) {
+                        counts = false;
+                        if let Some(replay) = replay.as_mut() {
+                            replay.keyframe(&world);
+                        }
+                    }
+                    input = InputState::default();
+                } else {
+                    if options.dev {
+                        time_control.update();
+                    }
+                    counts &= time_control.is_normal();
+                    // the controller flies the pod, the keyboard still works the menus
+                    let flown = controller.input(&WorldView::new(&world));
+                    counts &= !controller.is_bot();
+                    for action in Action::ALL {
+                        input.set(action, flown.performs(action));
+                    }
+                    input.aim = flown.aim;
+                    if co_op {
+                        let partner = InputState::from_bindings(&Bindings::wasd());
+                        input.partners = vec![partner];
+                    }
+                    // zooming is only for show, so it changes the settings rather than the run
+                    if input.zoom != 0.0 {
+                        settings.zoom_by(input.zoom);
+                        world.set_camera_options(settings.camera_options());
+                    }
+                }
+                if world.is_game_over() && input.select {
+                    next = match campaign.as_mut() {
+                        // losing the last pod ends the campaign, so it starts over
+                        Some(running) => {
+                            running.restart();
+                            Some(running.start_level())
+                        }
+                        None if survival => Some(survival_level(&profile)),
+                        // try again on the same terrain
+                        None => Some(Ok(World::new_with_seed(world.seed()))),
+                    };
+                }
+                // Escape on a hint prompt turns the hint down and leaves the pause alone
+                if input.back && !hints.is_prompting() {
+                    paused = !paused;
+                }
+                if paused && !console.is_open() && is_key_pressed(KeyCode::T) {
+                    difficulty.enabled = !difficulty.enabled;
+                    world.apply_modifiers(&difficulty.modifiers());
+                    if let Some(replay) = replay.as_mut() {
+                        replay.keyframe(&world);
+                    }
+                }
+                if paused && !console.is_open() && is_key_pressed(KeyCode::C) {
+                    cosmetics.cycle();
+                    world.set_trail(cosmetics.trail());
+                    if let Err(error) = cosmetics.save() {
+                        let message = format!("Could not save the unlocks: {error}");
+                        modal = Some((Dialog::error(&message), Pending::Nothing));
+                    }
+                }
+                if paused && modal.is_none() && !console.is_open() && is_key_pressed(KeyCode::M) {
+                    let dialog =
+                        Dialog::confirm("Main menu", "Abandon this run and go to the main menu?");
+                    modal = Some((dialog, Pending::LeaveRun));
+                }
+
+                // the game waits while paused or while the player considers a hint
+                if hints.is_prompting() {
+                    hints.respond(&input, &mut world);
+                    // an accepted hint changes the world, which the replay has to know about
+                    if !hints.is_prompting()
+                        && let Some(replay) = replay.as_mut()
+                    {
+                        replay.keyframe(&world);
+                    }
+                } else if !paused
+                    && let Some(frame_seconds) = time_control.seconds(get_frame_time())
+                {
+                    // stepping by fixed steps makes the run play out the same on replay
+                    let mut stepped = input.clone();
+                    for _ in 0..world.steps_due(frame_seconds) {
+                        world.step(&stepped, World::FIXED_STEP);
+                        if let Some(replay) = replay.as_mut() {
+                            replay.push(&stepped, World::FIXED_STEP, &world);
+                        }
+                        tape.record(&stepped);
+                        tutorial.observe(&world, &stepped, World::FIXED_STEP);
+                        stepped.release_presses();
+                        if let Some(log) = telemetry.as_mut()
+                            && let Err(error) = log.record(&world)
+                        {
+                            let message = format!("Could not log the telemetry: {error}");
+                            modal = Some((Dialog::error(&message), Pending::Nothing));
+                            telemetry = None;
+                        }
+                    }
+                    if let Some(log) = telemetry.as_mut()
+                        && let Err(error) = log.flush()
+                    {
+                        let message = format!("Could not log the telemetry: {error}");
+                        modal = Some((Dialog::error(&message), Pending::Nothing));
+                        telemetry = None;
+                    }
+                    let volume = settings.volume as f32;
+                    alerts.update(warnings::check(&world), frame_seconds, volume);
+                }
+                hints.observe(&world);
+
+                // every lost pod and every beaten level feeds into the difficulty tuning
+                // in a hardcore campaign, the first lost pod wipes out all progress
+                if world.deaths() > deaths
+                    && let Some(running) = &campaign
+                    && running.is_hardcore()
+                {
+                    let (completed, total) = running.position();
+                    let message = format!(
+                        "Your pod is gone, and the campaign with it ({completed}/{total})."
+                    );
+                    modal = Some((Dialog::new("Hardcore run over", &message), Pending::Nothing));
+                    campaign = None;
+                    screen = Screen::Menu;
+                }
+                // the last pod lost in survival mode may have held out longer than ever
+                if world.deaths() > deaths
+                    && survival
+                    && world.is_game_over()
+                    && world.progress().waves > profile.best_wave
+                {
+                    profile.best_wave = world.progress().waves;
+                    let message = format!("You held out until wave {}.", profile.best_wave);
+                    let dialog = match profile.save() {
+                        Ok(()) => Dialog::new("New best", &message),
+                        Err(error) => {
+                            Dialog::error(&format!("Could not save the profile: {error}"))
+                        }
+                    };
+                    modal = Some((dialog, Pending::Nothing));
+                }
+                if world.deaths() > deaths {
+                    deaths = world.deaths();
+                    difficulty.record(true);
+                    world.apply_modifiers(&difficulty.modifiers());
+                    if let Some(replay) = replay.as_mut() {
+                        replay.keyframe(&world);
+                    }
+                }
+                if !completed && world.objectives_complete() {
+                    completed = true;
+                    difficulty.record(false);
+                    let hardcore = campaign.as_ref().is_some_and(Campaign::is_hardcore);
+                    let fitted = campaign.as_ref().is_some_and(Campaign::is_upgraded);
+                    let place = counts
+                        .then(|| {
+                            let level = world.level_name();
+                            leaderboard.submit(Record {
+                                level: leaderboard::category(level, hardcore, fitted, co_op),
+                                seed: world.seed(),
+                                time: world.time(),
+                            })
+                        })
+                        .flatten();
+                    if counts && let Err(error) = leaderboard.save() {
+                        let message = format!("Could not save the best times: {error}");
+                        modal = Some((Dialog::error(&message), Pending::Nothing));
+                    }
+                    if counts
+                        && let Some(replay) = &replay
+                        && let Err(error) = replay.save(Replay::LAST_RUN, Compression::Fast)
+                    {
+                        let message = format!("Could not save the replay: {error}");
+                        modal = Some((Dialog::error(&message), Pending::Nothing));
+                    }
+                    if counts && let Err(error) = tape.save(InputTape::LAST_RUN) {
+                        let message = format!("Could not save the input tape: {error}");
+                        modal = Some((Dialog::error(&message), Pending::Nothing));
+                    }
+                    // the fastest run becomes the ghost to race against next time
+                    if time_trial
+                        && place == Some(0)
+                        && let Some(replay) = &replay
+                        && let Err(error) =
+                            replay.save(Ghost::path(world.level_name()), Compression::Fast)
+                    {
+                        let message = format!("Could not save the ghost: {error}");
+                        modal = Some((Dialog::error(&message), Pending::Nothing));
+                    }
+                    screen = match campaign.as_mut() {
+                        Some(running) => {
+                            let level = running.complete_level(&world, counts);
+                            // the score of every level flown on the profile's pod buys upgrades
+                            if upgraded {
+                                profile.credits += level.score;
+                                if let Err(error) = profile.save() {
+                                    let message = format!("Could not save the profile: {error}");
+                                    modal = Some((Dialog::error(&message), Pending::Nothing));
+                                }
+                            }
+                            summary = Some(level);
+                            // a whole campaign without a scratch earns the chromatic trail,
+                            // and surviving a hardcore one the ember trail
+                            let earned = [
+                                (running.is_flawless(), Trail::Chromatic, "Not a scratch!"),
+                                (running.is_hardcore(), Trail::Ember, "Hardcore survived!"),
+                            ];
+                            for (deserved, trail, praise) in earned {
+                                if !running.is_finished()
+                                    || !running.counts()
+                                    || !deserved
+                                    || !cosmetics.unlock(trail)
+                                {
+                                    continue;
+                                }
+                                let dialog = match cosmetics.save() {
+                                    Ok(()) => Dialog::new(
+                                        &format!("The {} trail is yours", trail.name()),
+                                        &format!("{praise} Press C when paused to switch trails."),
+                                    ),
+                                    Err(error) => Dialog::error(&format!(
+                                        "Could not save the unlocks: {error}"
+                                    )),
+                                };
+                                modal = Some((dialog, Pending::Nothing));
+                            }
+                            Screen::Summary
+                        }
+                        None => Screen::Results(place),
+                    };
+                }
+
+                world.draw(&input);
+                alerts.draw();
+                tutorial.draw(&bindings);
+                hints.draw();
+                if paused {
+                    let mut lines = difficulty.describe();
+                    lines.push(format!("Trail: {}", cosmetics.trail().name()));
+                    hud::draw_pause_menu(&lines);
+                }
+                time_control.draw();
+                console.draw();
+            }
+        }
+
+        match next {
+            Some(Ok(level)) => {
+                if let Some(path) = world.level_path() {
+                    watcher.unwatch(path);
+                }
+                world = prepare(level, &mut hints, &difficulty);
+                if options.dev
+                    && let Some(path) = world.level_path()
+                {
+                    watcher.watch(path);
+                }
+                world.set_trail(cosmetics.trail());
+                world.set_camera_options(settings.camera_options());
+                world.set_hud_layout(settings.hud.clone());
+                world.assist = settings.assist;
+                world.set_crash_map(crash_map.clone());
+                if co_op {
+                    world.add_partner();
+                }
+                if time_trial {
+                    let best = Replay::load(Ghost::path(world.level_name()));
+                    world.set_ghost(best.ok().map(|replay| replay.ghost()));
+                }
+                replay = Some(Replay::record(&world));
+                alerts.clear();
+                tape = InputTape::for_run(&world);
+                deaths = 0;
+                completed = false;
+                counts = true;
+                paused = std::mem::take(&mut start_paused);
+                screen = Screen::Playing;
+            }
+            Some(Err(error)) => {
+                campaign = None;
+                screen = Screen::Menu;
+                modal = Some((Dialog::error(&error), Pending::Nothing));
+            }
+            None => {}
+        }
+        if finished && replay.is_some() {
+            draw_text(
+                "Press R to watch the replay",
+                10.0,
+                screen_height() - 10.0,
+                20.0,
+                GRAY,
+            );
+        }
+        if let Some((dialog, _)) = &modal {
+            set_default_camera();
+            dialog.draw();
+        }
         next_frame().await;
     }
 }
@@ -0,0 +1,130 @@
+use macroquad::prelude::*;
+
+use crate::ui::InputState;
+
+/// The entries of the main menu
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuChoice {
+    /// Start playing the demo level
+    Play,
+    /// Play the levels of the campaign one after the other
+    Campaign,
+    /// Race through the levels of the campaign against the ghosts of the best runs
+    TimeTrial,
+    /// Play the campaign with a single pod, losing all progress when it is destroyed
+    Hardcore,
+    /// Play the campaign with two pods at the same keyboard
+    CoOp,
+    /// Hold out against ever bigger waves of drones for as long as the pods last
+    Survival,
+    /// Fly together with other players over the network
+    Online,
+    /// Watch the autopilot fly through the levels of the campaign
+    Autopilot,
+    /// Change the volume, the keys, the camera and the colors
+    Settings,
+    /// Enable and disable mods
+    Mods,
+    /// Show the credits and licenses
+    Credits,
+    /// Leave the game
+    Quit,
+}
+
+impl MenuChoice {
+    /// All entries in the order they are listed
+    const ALL: [MenuChoice; 12] = [
+        MenuChoice::Play,
+        MenuChoice::Campaign,
+        MenuChoice::TimeTrial,
+        MenuChoice::Hardcore,
+        MenuChoice::CoOp,
+        MenuChoice::Survival,
+        MenuChoice::Online,
+        MenuChoice::Autopilot,
+        MenuChoice::Settings,
+        MenuChoice::Mods,
+        MenuChoice::Credits,
+        MenuChoice::Quit,
+    ];
+
+    /// The label shown in the menu
+    fn label(&self) -> &'static str {
+        match self {
+            MenuChoice::Play => "Play",
+            MenuChoice::Campaign => "Campaign",
+            MenuChoice::TimeTrial => "Time Trial",
+            MenuChoice::Hardcore => "Hardcore",
+            MenuChoice::CoOp => "Co-op",
+            MenuChoice::Survival => "Survival",
+            MenuChoice::Online => "Join Server",
+            MenuChoice::Autopilot => "Autopilot",
+            MenuChoice::Settings => "Settings",
+            MenuChoice::Mods => "Mods",
+            MenuChoice::Credits => "Credits",
+            MenuChoice::Quit => "Quit",
+        }
+    }
+}
+
+/// The main menu shown when the game starts
+pub struct MainMenu {
+    /// The index of the highlighted entry
+    selected: usize,
+}
+
+impl MainMenu {
+    /// The room kept free above the title and below the last entry
+    const MARGIN: f32 = 40.0;
+    /// The room between the title and the first entry
+    const TITLE_GAP: f32 = 70.0;
+    /// The room between the entries when the window is big enough for it
+    const SPACING: f32 = 40.0;
+
+    /// Create the main menu with the first entry highlighted
+    pub fn new() -> Self {
+        MainMenu { selected: 0 }
+    }
+
+    /// Move the highlight as the player says.
+    /// Returns the highlighted entry once the player picks it.
+    pub fn update(&mut self, input: &InputState) -> Option<MenuChoice> {
+        let count = MenuChoice::ALL.len();
+        if input.menu_up {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if input.menu_down {
+            self.selected = (self.selected + 1) % count;
+        }
+        input.select.then_some(MenuChoice::ALL[self.selected])
+    }
+
+    /// Draw the title and the menu entries
+    pub fn draw(&self) {
+        clear_background(BLACK);
+        // the entries close up in small windows, so the last one still fits
+        let count = MenuChoice::ALL.len() as f32;
+        let room = screen_height() - 2.0 * Self::MARGIN - Self::TITLE_GAP;
+        let spacing = (room / count).min(Self::SPACING);
+        let height = Self::TITLE_GAP + count * spacing;
+        let title = "JETMAN";
+        let width = measure_text(title, None, 60, 1.0).width;
+        // the title's baseline sits below its top by most of its size
+        let mut y = (screen_height() - height) / 2.0 + 45.0;
+        draw_text(title, (screen_width() - width) / 2.0, y, 60.0, WHITE);
+        y += Self::TITLE_GAP;
+        for (index, choice) in MenuChoice::ALL.iter().enumerate() {
+            let color = if index == self.selected { YELLOW } else { GRAY };
+            let label = choice.label();
+            let width = measure_text(label, None, 30, 1.0).width;
+            draw_text(label, (screen_width() - width) / 2.0, y, 30.0, color);
+            y += spacing;
+        }
+    }
+}
+
+impl Default for MainMenu {
+    fn default() -> Self {
+        MainMenu::new()
+    }
+}
@@ -0,0 +1,170 @@
+use std::io;
+use std::path::PathBuf;
+
+use macroquad::prelude::*;
+
+use crate::mods::{self, Mod, ModConfig};
+use crate::ui::InputState;
+
+/// The screen listing the mods found, where the player enables and disables them
+/// and changes the order they're loaded in
+pub struct ModBrowser {
+    /// The mods that could be loaded
+    found: Vec<Mod>,
+    /// The errors of the mods that couldn't be loaded
+    broken: Vec<String>,
+    config: ModConfig,
+    /// The index of the highlighted mod in the load order
+    selected: usize,
+    /// The files replaced by more than one active mod, looked up when the browser opens
+    /// and whenever the mods change, since it takes listing their directories
+    conflicts: Vec<(PathBuf, Vec<String>)>,
+}
+
+impl ModBrowser {
+    /// Create the browser for the mods discovered, configured as the player profile says
+    pub fn new(discovered: Vec<Result<Mod, String>>, mut config: ModConfig) -> Self {
+        let (found, broken): (Vec<_>, Vec<_>) = discovered.into_iter().partition(Result::is_ok);
+        let found = found.into_iter().flatten().collect::<Vec<_>>();
+        let broken = broken.into_iter().filter_map(Result::err).collect();
+        config.sync(&found);
+        let conflicts = mods::conflicts(&config.active(&found));
+        ModBrowser {
+            found,
+            broken,
+            config,
+            selected: 0,
+            conflicts,
+        }
+    }
+
+    /// The mods to play with, enabled and usable, in load order
+    pub fn active(&self) -> Vec<Mod> {
+        self.config.active(&self.found)
+    }
+
+    /// Problems worth telling the player about at startup: enabled mods the game can't use
+    /// and mods that couldn't be loaded at all
+    pub fn problems(&self) -> Vec<String> {
+        let refused = self
+            .config
+            .entries()
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .filter_map(|(id, _)| self.find(id))
+            .filter(|found| !found.is_usable())
+            .map(Mod::report);
+        refused.chain(self.broken.iter().cloned()).collect()
+    }
+
+    /// Write the configuration to the player profile
+    pub fn save(&self) -> io::Result<()> {
+        self.config.save()
+    }
+
+    /// The mod with the given id
+    fn find(&self, id: &str) -> Option<&Mod> {
+        self.found.iter().find(|found| found.id() == id)
+    }
+
+    /// Move the highlight, toggle mods and move them in the load order as the player says.
+    /// Returns whether the player is done with the browser.
+    pub fn update(&mut self, input: &InputState) -> bool {
+        let count = self.config.entries().len();
+        let changed = count > 0 && (input.select || input.jump_back || input.jump_forward);
+        if count > 0 {
+            if input.menu_up {
+                self.selected = (self.selected + count - 1) % count;
+            }
+            if input.menu_down {
+                self.selected = (self.selected + 1) % count;
+            }
+            if input.select {
+                self.config.toggle(self.selected);
+            }
+            // page up loads the highlighted mod earlier, page down later
+            if input.jump_back && self.selected > 0 {
+                self.config.swap(self.selected, self.selected - 1);
+                self.selected -= 1;
+            }
+            if input.jump_forward && self.selected + 1 < count {
+                self.config.swap(self.selected, self.selected + 1);
+                self.selected += 1;
+            }
+        }
+        if changed {
+            self.conflicts = mods::conflicts(&self.active());
+        }
+        input.back
+    }
+
+    /// Draw the mods in load order, the files replaced by more than one enabled mod,
+    /// and the mods that couldn't be loaded
+    pub fn draw(&self) {
+        clear_background(BLACK);
+        let x = 60.0;
+        let mut y = 80.0;
+        draw_text("MODS", x, y, 40.0, WHITE);
+        y += 30.0;
+        draw_text(
+            "Enter enables or disables, Page Up and Page Down change the load order, \
+             Escape saves and goes back",
+            x,
+            y,
+            18.0,
+            GRAY,
+        );
+        y += 40.0;
+        if self.config.entries().is_empty() && self.broken.is_empty() {
+            draw_text(
+                &format!("No mods found in the {} directory", mods::MOD_DIR),
+                x,
+                y,
+                22.0,
+                LIGHTGRAY,
+            );
+        }
+        for (index, (id, enabled)) in self.config.entries().iter().enumerate() {
+            let Some(found) = self.find(id) else {
+                continue;
+            };
+            let color = match (index == self.selected, *enabled && found.is_usable()) {
+                (true, _) => YELLOW,
+                (false, true) => WHITE,
+                (false, false) => GRAY,
+            };
+            let check = if *enabled { "[x]" } else { "[ ]" };
+            draw_text(
+                &format!("{}. {check} {}", index + 1, found.report()),
+                x,
+                y,
+                22.0,
+                color,
+            );
+            y += 28.0;
+        }
+        for error in &self.broken {
+            draw_text(&format!("can't load: {error}"), x, y, 20.0, RED);
+            y += 26.0;
+        }
+
+        y += 20.0;
+        for (file, names) in &self.conflicts {
+            let winner = names.last().cloned().unwrap_or_default();
+            let message = format!(
+                "{} is replaced by {}, {winner} wins",
+                file.display(),
+                names.join(" and ")
+            );
+            draw_text(&message, x, y, 20.0, ORANGE);
+            y += 24.0;
+        }
+        draw_text(
+            "Changes apply to the next level loaded, and to sprites once the game restarts",
+            x,
+            screen_height() - 30.0,
+            18.0,
+            GRAY,
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The power-ups that change how the jet pod works for a while once collected
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Modifier {
+    /// Nothing damages the pod
+    Shield,
+    /// The tractor beam reaches further, and locks on through thin walls
+    Magnet,
+}
+
+impl Modifier {
+    /// The seconds a power-up lasts, added to whatever is left of the same one
+    pub fn duration(&self) -> f32 {
+        match self {
+            Modifier::Shield => 10.0,
+            Modifier::Magnet => 15.0,
+        }
+    }
+
+    /// The name of the modifier as shown in the HUD
+    pub fn name(&self) -> &'static str {
+        match self {
+            Modifier::Shield => "Shield",
+            Modifier::Magnet => "Magnet",
+        }
+    }
+
+    /// The color the modifier is shown in
+    pub fn color(&self) -> Color {
+        match self {
+            Modifier::Shield => SKYBLUE,
+            Modifier::Magnet => VIOLET,
+        }
+    }
+}
+
+/// The timed modifiers in effect on a jet pod, each with the seconds it has left
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Modifiers {
+    active: Vec<(Modifier, f32)>,
+}
+
+impl Modifiers {
+    /// How long before it wears off a modifier starts to flicker, in seconds
+    const FADING: f32 = 2.0;
+
+    /// Put a modifier into effect for the given seconds, or make it last that much longer
+    pub fn add(&mut self, modifier: Modifier, seconds: f32) {
+        match self
+            .active
+            .iter_mut()
+            .find(|(active, _)| *active == modifier)
+        {
+            Some((_, left)) => *left += seconds,
+            None => self.active.push((modifier, seconds)),
+        }
+    }
+
+    /// The seconds a modifier has left, 0.0 if it isn't in effect
+    pub fn remaining(&self, modifier: Modifier) -> f32 {
+        self.active
+            .iter()
+            .find(|(active, _)| *active == modifier)
+            .map_or(0.0, |(_, left)| *left)
+    }
+
+    /// Check whether a modifier is in effect
+    pub fn is_active(&self, modifier: Modifier) -> bool {
+        self.remaining(modifier) > 0.0
+    }
+
+    /// Check whether a modifier is about to wear off, for it to flicker
+    pub fn is_fading(&self, modifier: Modifier) -> bool {
+        let left = self.remaining(modifier);
+        left < Self::FADING && (left * 8.0) as i32 % 2 == 0
+    }
+
+    /// Count down the modifiers in effect, dropping the ones that wore off
+    pub fn update(&mut self, seconds: f32) {
+        for (_, left) in self.active.iter_mut() {
+            *left -= seconds;
+        }
+        self.active.retain(|(_, left)| *left > 0.0);
+    }
+
+    /// End all modifiers at once
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+
+    /// Iterate over the modifiers in effect with the seconds they have left
+    pub fn iter(&self) -> impl Iterator<Item = (Modifier, f32)> + '_ {
+        self.active.iter().copied()
+    }
+}
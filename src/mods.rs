@@ -0,0 +1,421 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::persistence;
+
+/// The version of everything mods build on: the level file formats and their keywords,
+/// the script API, and the names of the assets mods can replace. Changes that break
+/// existing mods bump it, while additions come with a new capability instead.
+pub const API_VERSION: u32 = 1;
+
+/// The directory mods are installed in, one directory per mod
+pub const MOD_DIR: &str = "mods";
+
+/// The file in a mod's directory describing the mod
+const MANIFEST: &str = "mod.txt";
+
+/// The directories in a mod holding files that replace the game's own
+const REPLACEMENT_DIRS: [(&str, Capability); 2] = [
+    ("levels", Capability::Levels),
+    ("sprites", Capability::Sprites),
+];
+
+/// Something a mod can make use of, which a version of the game may or may not provide
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Capability {
+    /// Level files replacing the game's own, or added to them, in the mod's `levels` directory
+    Levels,
+    /// Levels made with the Tiled editor
+    TiledMaps,
+    /// Levels made with the LDtk editor
+    LdtkProjects,
+    /// Terrain imported from SVG images
+    SvgTerrain,
+    /// Dark levels lit by the headlamp
+    DarkLevels,
+    /// Sprites replacing the game's own, in the mod's `sprites` directory
+    Sprites,
+    /// Rhai scripts attached to levels
+    Scripts,
+}
+
+impl Capability {
+    /// Every capability the game knows of
+    pub const ALL: [Capability; 7] = [
+        Capability::Levels,
+        Capability::TiledMaps,
+        Capability::LdtkProjects,
+        Capability::SvgTerrain,
+        Capability::DarkLevels,
+        Capability::Sprites,
+        Capability::Scripts,
+    ];
+
+    /// The name mods declare the capability by
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::Levels => "levels",
+            Capability::TiledMaps => "tiled",
+            Capability::LdtkProjects => "ldtk",
+            Capability::SvgTerrain => "svg",
+            Capability::DarkLevels => "dark",
+            Capability::Sprites => "sprites",
+            Capability::Scripts => "scripts",
+        }
+    }
+
+    /// The capability of the given name, if the game knows of it
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|capability| capability.name() == name)
+    }
+
+    /// Whether this version of the game provides the capability. All of them are
+    /// provided now, but newer mods may ask for ones this version doesn't know of.
+    pub fn is_supported(&self) -> bool {
+        Capability::ALL.contains(self)
+    }
+}
+
+/// Whether the given capability name is known and provided by this version of the game
+fn is_supported(name: &str) -> bool {
+    Capability::from_name(name).is_some_and(|capability| capability.is_supported())
+}
+
+/// The description of a mod, read from the `mod.txt` file in its directory. Each line
+/// holds a keyword and its value, for example:
+///
+/// ```text
+/// name Cave Pack
+/// version 1.2
+/// api 1
+/// requires levels svg
+/// optional sprites
+/// ```
+///
+/// Empty lines and lines starting with `#` are skipped.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Manifest {
+    pub name: String,
+    /// The version of the mod itself
+    pub version: String,
+    /// The version of the mod API the mod was made for
+    pub api: u32,
+    /// The names of the capabilities the mod can't do without
+    pub requires: Vec<String>,
+    /// The names of the capabilities the mod makes use of when they're there
+    pub optional: Vec<String>,
+}
+
+impl Manifest {
+    /// Parse the contents of a `mod.txt` file
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut manifest = Manifest {
+            name: String::new(),
+            version: String::new(),
+            api: 0,
+            requires: vec![],
+            optional: vec![],
+        };
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let rest = rest.trim();
+            let names = || rest.split_whitespace().map(str::to_lowercase);
+            match keyword {
+                "name" => manifest.name = rest.to_string(),
+                "version" => manifest.version = rest.to_string(),
+                "api" => {
+                    manifest.api = rest
+                        .parse()
+                        .map_err(|_| format!("line {}: '{rest}' is not a version", index + 1))?
+                }
+                "requires" => manifest.requires.extend(names()),
+                "optional" => manifest.optional.extend(names()),
+                other => return Err(format!("line {}: unknown keyword '{other}'", index + 1)),
+            }
+        }
+        if manifest.name.is_empty() {
+            return Err("the mod has no name".to_string());
+        }
+        if manifest.api == 0 {
+            return Err("the mod doesn't say which api version it was made for".to_string());
+        }
+        Ok(manifest)
+    }
+
+    /// Check how well this version of the game supports the mod
+    pub fn check(&self) -> Support {
+        if self.api > API_VERSION {
+            return Support::Refused(format!(
+                "made for a newer game (api {}, this game has {API_VERSION})",
+                self.api
+            ));
+        }
+        let missing = self
+            .requires
+            .iter()
+            .filter(|name| !is_supported(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Support::Refused(format!("needs {}", missing.join(", ")));
+        }
+        let missing = self
+            .optional
+            .iter()
+            .filter(|name| !is_supported(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            Support::Full
+        } else {
+            Support::Degraded(missing)
+        }
+    }
+
+    /// Whether the mod declares the capability, required or optional
+    pub fn declares(&self, capability: Capability) -> bool {
+        self.requires
+            .iter()
+            .chain(&self.optional)
+            .any(|name| name == capability.name())
+    }
+}
+
+/// How well the game supports a mod
+#[derive(Clone, PartialEq, Debug)]
+pub enum Support {
+    /// Everything the mod declares is there
+    Full,
+    /// The mod works, but without the optional capabilities named
+    Degraded(Vec<String>),
+    /// The mod can't be used, for the reason given
+    Refused(String),
+}
+
+/// A mod found in the mod directory
+#[derive(Clone, Debug)]
+pub struct Mod {
+    /// The directory the mod's files are in
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+    pub support: Support,
+}
+
+impl Mod {
+    /// Load the mod in the given directory and check whether the game supports it
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        let path = dir.join(MANIFEST);
+        let source = persistence::read_to_string(&path)
+            .map_err(|error| format!("can't read {}: {error}", path.display()))?;
+        let manifest =
+            Manifest::parse(&source).map_err(|error| format!("{}: {error}", path.display()))?;
+        let support = manifest.check();
+        Ok(Mod {
+            dir: dir.to_path_buf(),
+            manifest,
+            support,
+        })
+    }
+
+    /// Whether the game can use the mod at all
+    pub fn is_usable(&self) -> bool {
+        !matches!(self.support, Support::Refused(_))
+    }
+
+    /// Whether the mod makes use of the capability in this game
+    pub fn provides(&self, capability: Capability) -> bool {
+        self.is_usable() && capability.is_supported() && self.manifest.declares(capability)
+    }
+
+    /// The name the mod is known by in the configuration: the name of its directory
+    pub fn id(&self) -> String {
+        self.dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// The game's files the mod replaces, like `sprites/jetman.png`
+    pub fn replacements(&self) -> Vec<PathBuf> {
+        let mut files = vec![];
+        for (dir, capability) in REPLACEMENT_DIRS {
+            if !self.provides(capability) {
+                continue;
+            }
+            let Ok(entries) = persistence::list(self.dir.join(dir)) else {
+                continue;
+            };
+            files.extend(
+                entries
+                    .into_iter()
+                    .filter(|path| persistence::is_file(path))
+                    .filter_map(|path| Some(Path::new(dir).join(path.file_name()?))),
+            );
+        }
+        files.sort();
+        files
+    }
+
+    /// A line telling the player how the mod was loaded
+    pub fn report(&self) -> String {
+        let name = format!("{} {}", self.manifest.name, self.manifest.version);
+        match &self.support {
+            Support::Full => format!("{}: ready", name.trim()),
+            Support::Degraded(missing) => {
+                format!("{}: works without {}", name.trim(), missing.join(", "))
+            }
+            Support::Refused(reason) => format!("{}: can't be used, {reason}", name.trim()),
+        }
+    }
+
+    /// The mod's sprite replacing the game's sprite of the given name, if it has one
+    pub fn sprite(&self, name: &str) -> Option<PathBuf> {
+        let path = self.dir.join("sprites").join(format!("{name}.png"));
+        (self.provides(Capability::Sprites) && persistence::is_file(&path)).then_some(path)
+    }
+
+    /// The mod's level file replacing the game's level file at the given path,
+    /// relative to the game's `levels` directory, if it has one
+    pub fn level(&self, path: &Path) -> Option<PathBuf> {
+        let relative = path.strip_prefix("levels").unwrap_or(path);
+        let path = self.dir.join("levels").join(relative);
+        (self.provides(Capability::Levels) && persistence::is_file(&path)).then_some(path)
+    }
+}
+
+/// Find the mods in the given directory, in the order of their directory names,
+/// with an error for each mod that couldn't be loaded. A missing directory has no mods.
+pub fn discover(dir: impl AsRef<Path>) -> Vec<Result<Mod, String>> {
+    let Ok(entries) = persistence::list(dir) else {
+        return vec![];
+    };
+    let mut dirs = entries
+        .into_iter()
+        .filter(|path| persistence::is_dir(path))
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs.into_iter().map(Mod::load).collect()
+}
+
+/// The level file to load in place of the game's level file at the given path:
+/// the replacement from the last of the mods that has one, or the path itself
+pub fn level(mods: &[Mod], path: &Path) -> PathBuf {
+    mods.iter()
+        .rev()
+        .find_map(|found| found.level(path))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Files replaced by more than one of the given mods, each with the names of the mods
+/// replacing it in load order, so the last one wins
+pub fn conflicts(mods: &[Mod]) -> Vec<(PathBuf, Vec<String>)> {
+    let mut replaced: Vec<(PathBuf, Vec<String>)> = vec![];
+    for found in mods {
+        for file in found.replacements() {
+            match replaced.iter_mut().find(|(path, _)| *path == file) {
+                Some((_, names)) => names.push(found.manifest.name.clone()),
+                None => replaced.push((file, vec![found.manifest.name.clone()])),
+            }
+        }
+    }
+    replaced.retain(|(_, names)| names.len() > 1);
+    replaced
+}
+
+/// Which mods are enabled and the order they're loaded in, kept per player profile
+pub struct ModConfig {
+    /// The ids of the mods in load order, each with whether it's enabled
+    entries: Vec<(String, bool)>,
+    /// The file the configuration is kept in
+    path: PathBuf,
+}
+
+impl ModConfig {
+    /// The profile played when nobody picked another
+    pub const PROFILE: &str = "default";
+
+    /// Load the configuration of the given player profile
+    pub fn load(profile: &str) -> Self {
+        ModConfig::load_from(Path::new("profiles").join(profile).join("mods.txt"))
+    }
+
+    /// Load the configuration from the given file, one mod id per line in load order,
+    /// preceded by `+` if the mod is enabled or `-` if it isn't.
+    /// A missing file makes for an empty configuration, and lines that can't be read are skipped.
+    pub fn load_from(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let entries = persistence::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (state, id) = line.split_once(' ')?;
+                let enabled = match state {
+                    "+" => true,
+                    "-" => false,
+                    _ => return None,
+                };
+                Some((id.to_string(), enabled))
+            })
+            .collect();
+        ModConfig { entries, path }
+    }
+
+    /// Write the configuration back to the file it was loaded from
+    pub fn save(&self) -> io::Result<()> {
+        let contents = self
+            .entries
+            .iter()
+            .map(|(id, enabled)| format!("{} {id}\n", if *enabled { '+' } else { '-' }))
+            .collect::<String>();
+        persistence::write(&self.path, contents)
+    }
+
+    /// Bring the configuration in line with the mods found: mods that are gone are
+    /// forgotten, and new ones are enabled and loaded last
+    pub fn sync(&mut self, found: &[Mod]) {
+        let ids = found.iter().map(Mod::id).collect::<Vec<_>>();
+        self.entries.retain(|(id, _)| ids.contains(id));
+        for id in ids {
+            if !self.entries.iter().any(|(known, _)| *known == id) {
+                self.entries.push((id, true));
+            }
+        }
+    }
+
+    /// The ids of the mods in load order, each with whether it's enabled
+    pub fn entries(&self) -> &[(String, bool)] {
+        &self.entries
+    }
+
+    /// Enable the mod at the given position in the load order if it's disabled, and the other way round
+    pub fn toggle(&mut self, index: usize) {
+        if let Some((_, enabled)) = self.entries.get_mut(index) {
+            *enabled = !*enabled;
+        }
+    }
+
+    /// Swap the mods at the given positions in the load order
+    pub fn swap(&mut self, a: usize, b: usize) {
+        if a < self.entries.len() && b < self.entries.len() {
+            self.entries.swap(a, b);
+        }
+    }
+
+    /// The mods among those found that are enabled and usable, in load order
+    pub fn active(&self, found: &[Mod]) -> Vec<Mod> {
+        self.entries
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .filter_map(|(id, _)| found.iter().find(|candidate| candidate.id() == *id))
+            .filter(|candidate| candidate.is_usable())
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,543 @@
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use serde::{Deserialize, Serialize};
+
+use crate::level::Level;
+use crate::replay::Frame;
+use crate::snapshot::{self, Compression};
+use crate::ui::InputState;
+use crate::world::World;
+
+/// The port servers listen on, unless told otherwise
+pub const PORT: u16 = 7777;
+
+/// The most players a server lets in: one flying Jetman, the others the partner pods
+pub const MAX_PLAYERS: usize = 4;
+
+/// The largest message that fits into a single UDP datagram
+const MAX_MESSAGE: usize = 65_507;
+
+/// The most bytes of a snapshot sent in one message, leaving room for the rest of it
+const SNAPSHOT_PART: usize = MAX_MESSAGE - 64;
+
+/// The most parts a snapshot is split into, which caps its size at about 4 MB
+const MAX_PARTS: usize = 64;
+
+/// What clients and servers tell each other, one message per datagram
+#[derive(Serialize, Deserialize)]
+enum Message {
+    /// A client asking to play, or to leave again
+    Join,
+    Leave,
+    /// The actions a client's player performs, one bit per action in the order of `Action::ALL`
+    Input(u16),
+    /// The server letting a client in as the player with the given number,
+    /// where player 0 flies Jetman and the others the partner pods in order
+    Welcome(usize),
+    /// The server turning a client away, as all players are taken
+    Full,
+    /// The server's run of the level coming to an end, won or lost, before the
+    /// server starts the level over
+    Over {
+        won: bool,
+    },
+    /// A part of the world as it is on the server, encoded like a snapshot and split
+    /// into parts small enough for a datagram each, for the frame the server stepped
+    /// in the run it's of, counting from 0
+    Snapshot {
+        run: u64,
+        frame: u64,
+        part: usize,
+        parts: usize,
+        bytes: Vec<u8>,
+    },
+}
+
+impl Message {
+    /// Send the message to the given address
+    fn send(&self, socket: &UdpSocket, to: SocketAddr) -> io::Result<()> {
+        let bytes = bincode::serialize(self).map_err(io::Error::other)?;
+        if bytes.len() > MAX_MESSAGE {
+            return Err(io::Error::other("the message doesn't fit into a datagram"));
+        }
+        socket.send_to(&bytes, to).map(drop)
+    }
+
+    /// The next message waiting on the socket and where it's from, if there is one.
+    /// Datagrams that can't be read are dropped, and so are the reports of datagrams
+    /// sent earlier not arriving, which some systems hand to the next receive.
+    fn receive(socket: &UdpSocket) -> io::Result<Option<(Message, SocketAddr)>> {
+        let mut buffer = vec![0; MAX_MESSAGE];
+        loop {
+            match socket.recv_from(&mut buffer) {
+                Ok((length, from)) => {
+                    if let Ok(message) = bincode::deserialize(&buffer[..length]) {
+                        return Ok(Some((message, from)));
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(error) if error.kind() == io::ErrorKind::ConnectionReset => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+/// A client playing on a server
+struct Player {
+    address: SocketAddr,
+    /// The actions the player performs, as last heard
+    actions: u16,
+    /// The frames since the player was last heard from
+    silence: u32,
+}
+
+/// A server running the world everybody plays in. Clients send the actions of their
+/// players, and the server steps the world with them and sends snapshots of it back.
+/// Once a run is won or lost, the server starts the level over.
+pub struct Server {
+    socket: UdpSocket,
+    /// The level every run starts from
+    level: Level,
+    world: World,
+    /// The runs of the level finished so far
+    run: u64,
+    /// The players by number, with the places of those who left empty
+    players: [Option<Player>; MAX_PLAYERS],
+    /// The frames stepped so far
+    frame: u64,
+}
+
+impl Server {
+    /// The frames between two snapshots sent to the clients
+    pub const SNAPSHOT_FRAMES: u64 = 3;
+    /// The frames after which a player not heard from is dropped
+    const TIMEOUT: u32 = 600;
+
+    /// Listen for clients at the given address, to play the given level
+    pub fn bind(address: impl ToSocketAddrs, level: Level) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Server {
+            socket,
+            world: World::from_level(level.clone()),
+            level,
+            run: 0,
+            players: Default::default(),
+            frame: 0,
+        })
+    }
+
+    /// The address the server listens at
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// The world being played in
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// The number of players playing
+    pub fn players(&self) -> usize {
+        self.players.iter().flatten().count()
+    }
+
+    /// The runs of the level finished so far
+    pub fn runs(&self) -> u64 {
+        self.run
+    }
+
+    /// Hear what the clients have to say, step the world by a frame of the given
+    /// length with the actions of their players, and send them the world now and then.
+    /// A run won or lost is over: the clients are told, and the level starts over.
+    /// Clients that can't be reached are dropped; returns what went wrong along the way.
+    pub fn update(&mut self, frame_seconds: f32) -> Vec<String> {
+        let mut problems = vec![];
+        loop {
+            match Message::receive(&self.socket) {
+                Ok(Some((message, from))) => {
+                    if let Err(error) = self.handle(message, from) {
+                        problems.push(self.drop_client(from, error));
+                    }
+                }
+                Ok(None) => break,
+                // the socket is worth another try on the next frame
+                Err(error) => {
+                    problems.push(format!("can't receive: {error}"));
+                    break;
+                }
+            }
+        }
+        for place in self.players.iter_mut() {
+            if let Some(player) = place {
+                player.silence += 1;
+                if player.silence > Self::TIMEOUT {
+                    *place = None;
+                }
+            }
+        }
+
+        // players who left leave their pods hanging in the air
+        let input = |number: usize| {
+            let actions = self.players[number].as_ref().map_or(0, |p| p.actions);
+            Frame {
+                seconds: frame_seconds,
+                actions,
+                ..Default::default()
+            }
+            .input()
+        };
+        let mut controls = input(0);
+        controls.partners = (1..=self.world.partners.len()).map(input).collect();
+        self.world.step(&controls, frame_seconds);
+        let won = self.world.objectives_complete();
+        if won || self.world.objectives_failed() || self.world.is_game_over() {
+            problems.extend(self.start_over(won));
+        }
+
+        self.frame += 1;
+        if self.frame.is_multiple_of(Self::SNAPSHOT_FRAMES) {
+            match self.snapshot() {
+                Ok(messages) => {
+                    for address in self.addresses() {
+                        let sent = messages
+                            .iter()
+                            .try_for_each(|message| message.send(&self.socket, address));
+                        if let Err(error) = sent {
+                            problems.push(self.drop_client(address, error));
+                        }
+                    }
+                }
+                Err(error) => problems.push(format!("can't send the world: {error}")),
+            }
+        }
+        problems
+    }
+
+    /// Tell the clients how the run went and start the level over, the pods of the
+    /// players still there waiting in it. Returns what went wrong along the way.
+    fn start_over(&mut self, won: bool) -> Vec<String> {
+        let mut problems = vec![];
+        for address in self.addresses() {
+            if let Err(error) = (Message::Over { won }).send(&self.socket, address) {
+                problems.push(self.drop_client(address, error));
+            }
+        }
+        self.world = World::from_level(self.level.clone());
+        let last = self.players.iter().rposition(Option::is_some).unwrap_or(0);
+        while self.world.partners.len() < last {
+            self.world.add_partner();
+        }
+        self.run += 1;
+        problems
+    }
+
+    /// The addresses of the players playing
+    fn addresses(&self) -> Vec<SocketAddr> {
+        self.players
+            .iter()
+            .flatten()
+            .map(|player| player.address)
+            .collect()
+    }
+
+    /// The world as it is now, in as many messages as it takes
+    fn snapshot(&self) -> io::Result<Vec<Message>> {
+        let world = snapshot::encode(&self.world, Compression::Fast)?;
+        let parts = world.len().div_ceil(SNAPSHOT_PART).max(1);
+        if parts > MAX_PARTS {
+            return Err(io::Error::other(format!(
+                "the world takes {} bytes, more than fit into {MAX_PARTS} messages",
+                world.len()
+            )));
+        }
+        Ok(world
+            .chunks(SNAPSHOT_PART)
+            .enumerate()
+            .map(|(part, bytes)| Message::Snapshot {
+                run: self.run,
+                frame: self.frame,
+                part,
+                parts,
+                bytes: bytes.to_vec(),
+            })
+            .collect())
+    }
+
+    /// Let go of the client at the given address after a problem with it, telling
+    /// what happened
+    fn drop_client(&mut self, address: SocketAddr, error: io::Error) -> String {
+        for place in self.players.iter_mut() {
+            if place
+                .as_ref()
+                .is_some_and(|player| player.address == address)
+            {
+                *place = None;
+            }
+        }
+        format!("dropped {address}: {error}")
+    }
+
+    /// Let a client in, hear its player's actions, or let it go
+    fn handle(&mut self, message: Message, from: SocketAddr) -> io::Result<()> {
+        let known = self
+            .players
+            .iter()
+            .position(|p| p.as_ref().is_some_and(|p| p.address == from));
+        match (message, known) {
+            // the welcome may have been lost on the way
+            (Message::Join, Some(number)) => Message::Welcome(number).send(&self.socket, from),
+            (Message::Join, None) => {
+                let Some(number) = self.players.iter().position(Option::is_none) else {
+                    return Message::Full.send(&self.socket, from);
+                };
+                self.players[number] = Some(Player {
+                    address: from,
+                    actions: 0,
+                    silence: 0,
+                });
+                while self.world.partners.len() < number {
+                    self.world.add_partner();
+                }
+                Message::Welcome(number).send(&self.socket, from)
+            }
+            (Message::Input(actions), Some(number)) => {
+                if let Some(player) = self.players[number].as_mut() {
+                    player.actions = actions;
+                    player.silence = 0;
+                }
+                Ok(())
+            }
+            (Message::Leave, Some(number)) => {
+                self.players[number] = None;
+                Ok(())
+            }
+            // whatever else comes in is ignored
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A client playing on a server. It sends the actions of its player and shows the
+/// world as the server sends it, smoothing the way between snapshots.
+pub struct Client {
+    socket: UdpSocket,
+    server: SocketAddr,
+    /// The number of the client's player, once the server has let it in
+    player: Option<usize>,
+    /// Whether the server turned the client away
+    full: bool,
+    /// The run on the server the world shown is of
+    run: u64,
+    /// Whether the last run on the server was won, and the seconds left to show it
+    over: Option<(bool, f32)>,
+    /// The two latest snapshots of the world, the earlier one first
+    previous: Option<World>,
+    latest: Option<World>,
+    /// The parts of the snapshot arriving, by the frame it's of
+    arriving: Option<(u64, Vec<Option<Vec<u8>>>)>,
+    /// The time on the world's clock being shown, a little behind the latest snapshot
+    time: f32,
+    /// The time in seconds until asking to join again, while the server hasn't answered
+    retry: f32,
+}
+
+impl Client {
+    /// How far behind the latest snapshot the world is shown, in seconds, so there
+    /// is usually a later snapshot to move towards
+    const DELAY: f32 = 0.1;
+    /// The time in seconds between asking to join
+    const RETRY: f32 = 1.0;
+    /// The time in seconds how the last run went is shown
+    const OVER_SECONDS: f32 = 3.0;
+
+    /// Ask the server at the given address to play
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let server = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::other("no address to connect to"))?;
+        let local: SocketAddr = if server.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        Message::Join.send(&socket, server)?;
+        Ok(Client {
+            socket,
+            server,
+            player: None,
+            full: false,
+            run: 0,
+            over: None,
+            previous: None,
+            latest: None,
+            arriving: None,
+            time: 0.0,
+            retry: Self::RETRY,
+        })
+    }
+
+    /// The number of the client's player, once the server has let it in
+    pub fn player(&self) -> Option<usize> {
+        self.player
+    }
+
+    /// Whether the server turned the client away, as all players are taken
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    /// Whether the last run on the server was won, for a few seconds after it ended,
+    /// while the next run starts
+    pub fn run_over(&self) -> Option<bool> {
+        self.over.map(|(won, _)| won)
+    }
+
+    /// Tell the server what the player does
+    pub fn send_input(&self, input: &InputState) -> io::Result<()> {
+        if self.player.is_none() {
+            return Ok(());
+        }
+        let actions = Frame::capture(input, 0.0).actions;
+        Message::Input(actions).send(&self.socket, self.server)
+    }
+
+    /// Tell the server the player is leaving
+    pub fn leave(&self) -> io::Result<()> {
+        Message::Leave.send(&self.socket, self.server)
+    }
+
+    /// Hear what the server has to say and move the world shown along by a frame
+    /// of the given length
+    pub fn update(&mut self, frame_seconds: f32) -> io::Result<()> {
+        if self.player.is_none() && !self.full {
+            self.retry -= frame_seconds;
+            if self.retry <= 0.0 {
+                self.retry = Self::RETRY;
+                Message::Join.send(&self.socket, self.server)?;
+            }
+        }
+        if let Some((_, seconds)) = self.over.as_mut() {
+            *seconds -= frame_seconds;
+            if *seconds <= 0.0 {
+                self.over = None;
+            }
+        }
+        while let Some((message, from)) = Message::receive(&self.socket)? {
+            if from != self.server {
+                continue;
+            }
+            match message {
+                Message::Welcome(number) => self.player = Some(number),
+                Message::Full => self.full = true,
+                Message::Over { won } => self.over = Some((won, Self::OVER_SECONDS)),
+                Message::Snapshot {
+                    run,
+                    frame,
+                    part,
+                    parts,
+                    bytes,
+                } => {
+                    // snapshots of runs already over are of no use anymore
+                    if run < self.run {
+                        continue;
+                    }
+                    let Some(bytes) = self.assemble(frame, part, parts, bytes) else {
+                        continue;
+                    };
+                    let Ok(world) = snapshot::decode_within::<World>(&bytes, snapshot::MAX_WORLD)
+                    else {
+                        continue;
+                    };
+                    // the world of a new run doesn't move on from the last one's
+                    if run > self.run {
+                        self.run = run;
+                        self.previous = None;
+                        self.latest = None;
+                    }
+                    // snapshots arriving late are of no use anymore
+                    if self
+                        .latest
+                        .as_ref()
+                        .is_some_and(|w| w.clock() >= world.clock())
+                    {
+                        continue;
+                    }
+                    self.previous = self.latest.replace(world);
+                }
+                _ => {}
+            }
+        }
+        let Some(latest) = &self.latest else {
+            return Ok(());
+        };
+        // keep the delay, catching up at once if the world shown fell far behind
+        self.time += frame_seconds;
+        let target = latest.clock() - Self::DELAY;
+        if (self.time - target).abs() > Self::DELAY * 2.0 {
+            self.time = target;
+        }
+        Ok(())
+    }
+
+    /// Put a part of a snapshot in its place, returning the whole snapshot once all
+    /// its parts have arrived. Parts of older snapshots than the one arriving are dropped.
+    fn assemble(
+        &mut self,
+        frame: u64,
+        part: usize,
+        parts: usize,
+        bytes: Vec<u8>,
+    ) -> Option<Vec<u8>> {
+        if part >= parts || parts > MAX_PARTS {
+            return None;
+        }
+        match &self.arriving {
+            Some((arriving, pieces)) if *arriving == frame && pieces.len() == parts => {}
+            Some((arriving, _)) if *arriving > frame => return None,
+            _ => self.arriving = Some((frame, vec![None; parts])),
+        }
+        let (_, pieces) = self.arriving.as_mut()?;
+        pieces[part] = Some(bytes);
+        if pieces.iter().any(Option::is_none) {
+            return None;
+        }
+        let (_, pieces) = self.arriving.take()?;
+        Some(pieces.into_iter().flatten().flatten().collect())
+    }
+
+    /// The world to show, in between the two latest snapshots, or `None` if none
+    /// has arrived yet
+    pub fn world(&self) -> Option<World> {
+        let latest = self.latest.as_ref()?;
+        let Some(previous) = &self.previous else {
+            return Some(latest.clone());
+        };
+        let span = latest.clock() - previous.clock();
+        let t = if span > 0.0 {
+            ((self.time - previous.clock()) / span).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let mut world = latest.clone();
+        world.jetman.body.position = previous
+            .jetman
+            .body
+            .position
+            .lerp(latest.jetman.body.position, t);
+        for (partner, before) in world.partners.iter_mut().zip(&previous.partners) {
+            partner.body.position = before.body.position.lerp(partner.body.position, t);
+        }
+        for (entity, body) in world.entities.bodies.iter_mut() {
+            if let Some(before) = previous.entities.bodies.get(entity) {
+                body.position = before.position.lerp(body.position, t);
+            }
+        }
+        Some(world)
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+/// A goal the player has to achieve in the game world
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Objective {
+    /// Deliver the given number of cargo items to a goal teleporter
+    Deliver(u32),
+    /// Dispose of the given number of bombs before any of them detonates
+    Defuse(u32),
+    /// Keep the friendly craft alive until it reaches the end of its path
+    Escort,
+    /// Hold out against the given number of the director's waves, clearing the last
+    /// one, or against all of them if there's no number
+    Survive(Option<u32>),
+}
+
+/// The player's progress towards the world's objectives
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// The number of cargo items delivered to a goal teleporter
+    pub delivered: u32,
+    /// The number of fragile items delivered without any damage
+    pub intact: u32,
+    /// The number of fragile items destroyed by hard impacts
+    pub wrecked: u32,
+    /// The number of cargo items lost before they were delivered: destroyed, wrecked
+    /// or fallen out of the level
+    pub lost: u32,
+    /// The number of cargo items about, waiting to be delivered
+    pub cargo_left: u32,
+    /// The number of bombs dropped into a disposal teleporter
+    pub defused: u32,
+    /// The number of bombs that went off
+    pub detonated: u32,
+    /// The fraction of its path the escorted craft has covered, from 0.0 to 1.0
+    pub escorted: f32,
+    /// Whether the escorted craft has been destroyed
+    pub escort_lost: bool,
+    /// The number of waves the director has sent in
+    pub waves: u32,
+    /// The number of enemies about
+    pub hostiles: u32,
+}
+
+impl Objective {
+    /// Check whether the objective has been achieved
+    pub fn is_complete(&self, progress: &Progress) -> bool {
+        match *self {
+            Objective::Deliver(count) => progress.delivered >= count,
+            Objective::Defuse(count) => progress.defused >= count && progress.detonated == 0,
+            Objective::Escort => progress.escorted >= 1.0 && !progress.escort_lost,
+            Objective::Survive(waves) => {
+                waves.is_some_and(|waves| progress.waves >= waves && progress.hostiles == 0)
+            }
+        }
+    }
+
+    /// Check whether the objective can no longer be achieved
+    pub fn is_failed(&self, progress: &Progress) -> bool {
+        match *self {
+            // levels may bring in more cargo later on, so only losing some can fail them
+            Objective::Deliver(count) => {
+                progress.lost > 0 && progress.delivered + progress.cargo_left < count
+            }
+            Objective::Defuse(_) => progress.detonated > 0,
+            Objective::Escort => progress.escort_lost,
+            Objective::Survive(_) => false,
+        }
+    }
+
+    /// A short description of the objective and the progress made towards it
+    pub fn describe(&self, progress: &Progress) -> String {
+        match *self {
+            Objective::Deliver(count) => {
+                format!("Deliver cargo: {}/{}", progress.delivered.min(count), count)
+            }
+            Objective::Defuse(count) => {
+                format!(
+                    "Dispose of bombs: {}/{}",
+                    progress.defused.min(count),
+                    count
+                )
+            }
+            Objective::Escort => format!("Escort the freighter: {:.0}%", progress.escorted * 100.0),
+            Objective::Survive(Some(waves)) => {
+                format!("Survive the waves: {}/{}", progress.waves.min(waves), waves)
+            }
+            Objective::Survive(None) => format!("Survive: wave {}", progress.waves),
+        }
+    }
+}
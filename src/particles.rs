@@ -0,0 +1,92 @@
+use ::rand::rngs::StdRng;
+use ::rand::{Rng, SeedableRng};
+use macroquad::prelude::*;
+
+use crate::render::ShapeBatch;
+
+/// A short-lived visual particle without any influence on the physics
+#[derive(Clone)]
+pub struct Particle {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub color: Color,
+    /// The remaining lifetime in seconds
+    pub life: f32,
+    /// The lifetime the particle started out with, in seconds
+    pub max_life: f32,
+}
+
+/// A collection of particles, e.g. sparks and debris
+#[derive(Clone)]
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    /// The random generator scattering the particles
+    rng: StdRng,
+}
+
+impl ParticleSystem {
+    /// Create an empty particle system scattering its particles with the given seed
+    pub fn new(seed: u64) -> Self {
+        ParticleSystem {
+            particles: vec![],
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Emit a burst of particles flying off in all directions from a point
+    pub fn burst(&mut self, position: Vec2, count: usize, speed: f32, color: Color) {
+        for _ in 0..count {
+            let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+            let velocity = Vec2::from_angle(angle) * self.rng.gen_range(0.2..1.0) * speed;
+            let life = self.rng.gen_range(0.3..0.8);
+            self.particles.push(Particle {
+                position,
+                velocity,
+                color,
+                life,
+                max_life: life,
+            });
+        }
+    }
+
+    /// Emit a single particle drifting off from a point in roughly the given direction
+    pub fn spray(&mut self, position: Vec2, velocity: Vec2, color: Color) {
+        let spread = self.rng.gen_range(-0.4..0.4);
+        let life = self.rng.gen_range(0.4..0.9);
+        self.particles.push(Particle {
+            position,
+            velocity: Vec2::from_angle(spread).rotate(velocity),
+            color,
+            life,
+            max_life: life,
+        });
+    }
+
+    /// Move the particles and remove the ones that have faded out
+    pub fn update(&mut self, dt: f32, seconds: f32) {
+        for particle in self.particles.iter_mut() {
+            particle.position += particle.velocity * dt;
+            particle.life -= seconds;
+        }
+        self.particles.retain(|particle| particle.life > 0.0);
+    }
+
+    /// Draw the particles, fading them out towards the end of their lifetime
+    pub fn draw(&self) {
+        let mut batch = ShapeBatch::new();
+        for particle in &self.particles {
+            let alpha = particle.life / particle.max_life;
+            let color = Color::new(particle.color.r, particle.color.g, particle.color.b, alpha);
+            // too small to tell more sides apart
+            batch.circle(particle.position, 1.5, 6, color);
+        }
+        batch.draw();
+    }
+}
+
+impl Default for ParticleSystem {
+    /// Create an empty particle system with a fixed seed
+    fn default() -> Self {
+        ParticleSystem::new(0)
+    }
+}
@@ -0,0 +1,195 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the game keeps what it saves between runs: the settings, the profiles,
+/// the unlocks, the best times, the replays and the input tapes, and where it reads
+/// the levels, the campaigns and the mods from. Natively that's files next to the
+/// game; in the browser, where there are no files, it's the page's local storage.
+pub trait Storage: Sync {
+    /// Read everything saved under the given path
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Save the contents under the given path, replacing whatever was saved there
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// The paths of the files and the directories right inside the given directory
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Check whether a file is saved under the given path
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Check whether anything is saved inside the given directory
+    fn is_dir(&self, path: &Path) -> bool;
+}
+
+/// The files on disk, with the paths relative to where the game runs
+pub struct FileSystem;
+
+impl Storage for FileSystem {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    /// Write the file, creating the directories it goes into first
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, contents)
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        std::fs::read_dir(dir)?
+            .map(|entry| Ok(entry?.path()))
+            .collect()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+}
+
+/// The browser's local storage, keeping each file under its path. The functions
+/// reaching into it come with the page, from `web/storage.js`.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage;
+
+#[cfg(target_arch = "wasm32")]
+unsafe extern "C" {
+    /// The length of what's saved under a key, or -1 if nothing is
+    fn jetman_storage_len(key: *const u8, key_len: usize) -> i32;
+    /// Copy what's saved under a key to `out`, which has room for all of it
+    fn jetman_storage_get(key: *const u8, key_len: usize, out: *mut u8);
+    /// Save a value under a key, returning 0 if the browser refused, like when
+    /// the storage is full
+    fn jetman_storage_set(key: *const u8, key_len: usize, value: *const u8, len: usize) -> i32;
+    /// The length of the keys starting with a prefix, one per line
+    fn jetman_storage_keys_len(prefix: *const u8, prefix_len: usize) -> usize;
+    /// Copy the keys starting with a prefix, one per line, to `out`, which has room
+    /// for all of them
+    fn jetman_storage_keys(prefix: *const u8, prefix_len: usize, out: *mut u8);
+}
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    /// The key a path is kept under, the same whichever way its separators lean
+    fn key(path: &Path) -> String {
+        path.to_string_lossy().replace('\\', "/")
+    }
+
+    /// The keys of everything saved inside the given directory, relative to it
+    fn inside(dir: &Path) -> Vec<String> {
+        let prefix = match Self::key(dir).trim_end_matches('/') {
+            "" => String::new(),
+            dir => format!("{dir}/"),
+        };
+        // SAFETY: the prefix outlives the calls, and the buffer has room for the keys' length
+        let keys = unsafe {
+            let len = jetman_storage_keys_len(prefix.as_ptr(), prefix.len());
+            let mut keys = vec![0; len];
+            jetman_storage_keys(prefix.as_ptr(), prefix.len(), keys.as_mut_ptr());
+            keys
+        };
+        String::from_utf8_lossy(&keys)
+            .lines()
+            .filter_map(|key| key.strip_prefix(&prefix))
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let key = Self::key(path);
+        // SAFETY: the key outlives the calls, and the buffer has room for the value's length
+        unsafe {
+            let len = jetman_storage_len(key.as_ptr(), key.len());
+            if len < 0 {
+                let message = format!("nothing saved under {key}");
+                return Err(io::Error::new(io::ErrorKind::NotFound, message));
+            }
+            let mut contents = vec![0; len as usize];
+            jetman_storage_get(key.as_ptr(), key.len(), contents.as_mut_ptr());
+            Ok(contents)
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let key = Self::key(path);
+        // SAFETY: the key and the contents outlive the call
+        let saved = unsafe {
+            jetman_storage_set(key.as_ptr(), key.len(), contents.as_ptr(), contents.len())
+        };
+        if saved == 0 {
+            return Err(io::Error::other(format!("the browser didn't save {key}")));
+        }
+        Ok(())
+    }
+
+    /// The keys have no directories, so those are the first parts of the keys inside
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut paths = Self::inside(dir)
+            .iter()
+            .filter_map(|key| key.split('/').next())
+            .map(|name| dir.join(name))
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        let key = Self::key(path);
+        // SAFETY: the key outlives the call
+        unsafe { jetman_storage_len(key.as_ptr(), key.len()) >= 0 }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        !Self::inside(path).is_empty()
+    }
+}
+
+/// The storage of the platform the game runs on
+pub fn storage() -> &'static dyn Storage {
+    #[cfg(target_arch = "wasm32")]
+    return &LocalStorage;
+    #[cfg(not(target_arch = "wasm32"))]
+    return &FileSystem;
+}
+
+/// Read everything saved under the given path
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    storage().read(path.as_ref())
+}
+
+/// Read what's saved under the given path as text
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+    String::from_utf8(read(path)?)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Save the contents under the given path, replacing whatever was saved there
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    storage().write(path.as_ref(), contents.as_ref())
+}
+
+/// The paths of the files and the directories right inside the given directory
+pub fn list(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    storage().list(dir.as_ref())
+}
+
+/// Check whether a file is saved under the given path
+pub fn is_file(path: impl AsRef<Path>) -> bool {
+    storage().is_file(path.as_ref())
+}
+
+/// Check whether anything is saved inside the given directory
+pub fn is_dir(path: impl AsRef<Path>) -> bool {
+    storage().is_dir(path.as_ref())
+}
@@ -1,17 +1,104 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{Animator, Clip};
+use crate::assets;
+use crate::ecs::Entity;
+use crate::modifiers::{Modifier, Modifiers};
 
 /// Create a vector of length 1 from an angle
-fn vector_from_angle(angle: f32) -> Vec2 {
+pub(crate) fn vector_from_angle(angle: f32) -> Vec2 {
     Vec2::new(angle.cos(), angle.sin())
 }
 
+/// Surface properties deciding how bodies and terrain respond to a collision
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Material {
+    /// The fraction of the tangential velocity lost on impact, from 0.0 (frictionless) to 1.0
+    pub friction: f32,
+    /// The fraction of the normal velocity kept on impact, from 0.0 (no bounce) to 1.0,
+    /// or more for trampolines, which throw bodies back faster than they came
+    pub restitution: f32,
+    /// The speed the surface runs at, carrying resting bodies along like a conveyor belt.
+    /// Positive speeds run clockwise around the terrain, so to the right on top of it.
+    pub conveyor: f32,
+}
+
+impl Material {
+    /// The material used unless stated otherwise
+    pub const DEFAULT: Material = Material {
+        friction: 0.5,
+        restitution: 0.5,
+        conveyor: 0.0,
+    };
+    /// Slippery ice bodies slide across
+    pub const ICE: Material = Material {
+        friction: 0.02,
+        restitution: 0.1,
+        conveyor: 0.0,
+    };
+    /// Bouncy rubber for bumpers
+    pub const RUBBER: Material = Material {
+        friction: 0.3,
+        restitution: 0.95,
+        conveyor: 0.0,
+    };
+    /// Sticky mud that swallows any bounce
+    pub const MUD: Material = Material {
+        friction: 0.95,
+        restitution: 0.0,
+        conveyor: 0.0,
+    };
+    /// A trampoline throwing bodies back harder than they hit it
+    pub const TRAMPOLINE: Material = Material {
+        friction: 0.3,
+        restitution: 1.4,
+        conveyor: 0.0,
+    };
+    /// A conveyor belt running to the right, gripping whatever rests on it
+    pub const CONVEYOR: Material = Material {
+        friction: 0.6,
+        restitution: 0.2,
+        conveyor: 1.0,
+    };
+
+    /// Check whether the material throws bodies back faster than they hit it
+    pub fn is_springy(&self) -> bool {
+        self.restitution > 1.0
+    }
+
+    /// Combine the materials of two surfaces in contact. A trampoline throws anything
+    /// back as hard as it would throw itself, and conveyors keep their speed.
+    pub fn combine(self, other: Material) -> Material {
+        let restitution = if self.is_springy() || other.is_springy() {
+            self.restitution.max(other.restitution)
+        } else {
+            (self.restitution * other.restitution).sqrt()
+        };
+        Material {
+            friction: (self.friction * other.friction).sqrt(),
+            restitution,
+            conveyor: self.conveyor + other.conveyor,
+        }
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::DEFAULT
+    }
+}
+
 /// A physics body
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct Body {
     pub position: Vec2,
     pub velocity: Vec2,
     pub acceleration: Vec2,
     pub mass: f32,
+    pub material: Material,
 }
 
 impl Body {
@@ -22,6 +109,7 @@ impl Body {
             velocity: Vec2::new(0.0, 0.0),
             acceleration: Vec2::new(0.0, 0.0),
             mass,
+            material: Material::DEFAULT,
         }
     }
 
@@ -42,6 +130,100 @@ impl Body {
         self.position += self.velocity * dt;
         self.acceleration = Vec2::ZERO;
     }
+
+    /// The fastest a trampoline throws a body back
+    pub const MAX_BOUNCE: f32 = 4.0;
+
+    /// Respond to hitting a surface with the given normal and material:
+    /// bounce off along the normal and lose speed along the surface, or pick up
+    /// the speed of a conveyor
+    pub fn bounce(&mut self, normal: Vec2, surface: Material) {
+        let material = self.material.combine(surface);
+        let normal_speed = self.velocity.dot(normal);
+        let normal_velocity = normal * normal_speed;
+        let tangent_velocity = self.velocity - normal_velocity;
+        let bounced = if normal_speed >= 0.0 {
+            normal_velocity
+        } else if material.is_springy() {
+            normal * (-normal_speed * material.restitution).min(Self::MAX_BOUNCE)
+        } else {
+            -normal_velocity * material.restitution
+        };
+        // friction drags the body towards the speed of the surface
+        let belt = normal.perp() * material.conveyor;
+        self.velocity = bounced + belt + (tangent_velocity - belt) * (1.0 - material.friction);
+    }
+}
+
+/// Enforce a rigid link of the given length between two bodies,
+/// correcting their positions and velocities in proportion to their masses.
+/// Returns the impulse the link had to apply to keep the bodies from drifting apart.
+pub fn resolve_link(a: &mut Body, b: &mut Body, rest_length: f32) -> f32 {
+    let delta = b.position - a.position;
+    let distance = delta.length();
+    if distance == 0.0 {
+        return 0.0;
+    }
+    let direction = delta / distance;
+    let correction = direction * (distance - rest_length);
+
+    // Calculate correction ratio based on masses
+    let total_mass = a.mass + b.mass;
+    let a_ratio = b.mass / total_mass;
+    let b_ratio = a.mass / total_mass;
+
+    // Correct positions
+    a.position += correction * a_ratio;
+    b.position -= correction * b_ratio;
+
+    // Also correct velocity along the axis to enforce rigid link
+    let relative_velocity = b.velocity - a.velocity;
+    let projected_velocity = relative_velocity.dot(direction);
+    let velocity_correction = direction * projected_velocity;
+
+    a.velocity += velocity_correction * a_ratio;
+    b.velocity -= velocity_correction * b_ratio;
+
+    // only pulling apart puts the link under tension
+    let reduced_mass = a.mass * b.mass / total_mass;
+    projected_velocity.max(0.0) * reduced_mass
+}
+
+/// Enforce a rigid link of the given length between a body and the point on the edge
+/// of a towed item the link is attached to. The correction is shared between moving
+/// the item and turning it about its center, weighed by its mass and its moment of
+/// inertia, so an item hanging off center swings round until it hangs under the link.
+/// Returns the impulse the link had to apply to keep the bodies from drifting apart.
+pub fn resolve_link_at(a: &mut Body, b: &mut Body, towable: &mut Towable, rest_length: f32) -> f32 {
+    let offset = Vec2::from_angle(towable.angle).rotate(towable.attachment);
+    let delta = b.position + offset - a.position;
+    let distance = delta.length();
+    if distance == 0.0 {
+        return 0.0;
+    }
+    let direction = delta / distance;
+    let inertia = towable.inertia(b.mass);
+    // how far the point turns along the link for every unit of spin
+    let lever = offset.perp_dot(direction);
+    let (a_weight, b_weight) = (1.0 / a.mass, 1.0 / b.mass);
+    let total_weight = a_weight + b_weight + lever * lever / inertia;
+
+    // correct the positions and the angle
+    let correction = (distance - rest_length) / total_weight;
+    a.position += direction * correction * a_weight;
+    b.position -= direction * correction * b_weight;
+    towable.angle -= correction * lever / inertia;
+
+    // also correct the velocities and the spin along the link to keep it rigid
+    let point_velocity = b.velocity + offset.perp() * towable.spin;
+    let projected_velocity = (point_velocity - a.velocity).dot(direction);
+    let impulse = projected_velocity / total_weight;
+    a.velocity += direction * impulse * a_weight;
+    b.velocity -= direction * impulse * b_weight;
+    towable.spin -= impulse * lever / inertia;
+
+    // only pulling apart puts the link under tension
+    impulse.max(0.0)
 }
 
 /// Convenience methods for all structs
@@ -84,11 +266,59 @@ pub trait Bodied {
     }
 }
 
-/// Identifier for game items
-#[derive(Clone, Copy)]
-pub struct ItemId(pub usize);
+/// The air in the jet pod's tanks, running out as the pilot breathes
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Oxygen {
+    /// The seconds of air left
+    pub left: f32,
+    /// The seconds of air the full tanks hold
+    pub supply: f32,
+}
+
+impl Oxygen {
+    /// The seconds of air a landing pad pumps into the tanks per second
+    pub const FLOW: f32 = 10.0;
+
+    /// Create full tanks holding the given seconds of air
+    pub fn new(supply: f32) -> Self {
+        Oxygen {
+            left: supply,
+            supply,
+        }
+    }
+
+    /// Use up the air breathed during the given seconds
+    pub fn breathe(&mut self, seconds: f32) {
+        self.left = (self.left - seconds).max(0.0);
+    }
+
+    /// Pump the given seconds of air into the tanks, up to their capacity
+    pub fn refill(&mut self, seconds: f32) {
+        self.left = (self.left + seconds).min(self.supply);
+    }
+
+    /// The share of the air left, from 0.0 to 1.0
+    pub fn share(&self) -> f32 {
+        self.left / self.supply
+    }
+
+    /// Check whether the air has run out
+    pub fn is_empty(&self) -> bool {
+        self.left <= 0.0
+    }
+}
+
+/// A grappling hook stuck in the terrain, holding the jet pod on a rope it swings from
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Grapple {
+    /// Where the hook is stuck
+    pub anchor: Vec2,
+    /// The length of the rope, the furthest the pod gets from the hook
+    pub length: f32,
+}
 
 /// The Jetman is the object manipulated by the player
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Jetman {
     /// The Jetman's physics body
     pub body: Body,
@@ -96,45 +326,367 @@ pub struct Jetman {
     pub heading: f32,
     /// The length of the tractor beam
     pub link_distance: f32,
+    /// The speed at which the winch reels the tractor beam in and out
+    pub winch_speed: f32,
+    /// The longest length the winch can reel the tractor beam out to
+    pub max_link_distance: f32,
     /// The item attached to the jet pod by the tractor beam, if any
-    pub linked_item: Option<ItemId>,
+    pub linked_item: Option<Entity>,
+    /// The highest tension on any link of the towed chain during the last update
+    pub link_tension: f32,
+    /// The number of terrain surfaces the pod bumped into during the last update
+    pub contacts: u32,
+    /// The tension above which the links of the towed chain are overloaded
+    pub link_strength: f32,
+    /// The number of consecutive updates the towed chain has been overloaded
+    pub overload_frames: u32,
+    /// The mass hanging from the tractor beam, which the thruster has to lift as well
+    pub towed_mass: f32,
+    /// The grappling hook the jet pod hangs from, if it's stuck in the terrain
+    pub grapple: Option<Grapple>,
+    /// How far a throw of the towed chain has been wound up, from 0.0 to 1.0
+    pub throw_charge: f32,
+    /// The air the pilot breathes, if the level makes them breathe
+    pub oxygen: Option<Oxygen>,
     /// This value keeps track of whether the jet pod should apply thrust during update
     pub thrusting: i32,
+    /// The energy left to power the tractor beam
+    pub beam_energy: f32,
+    /// The time in seconds until a partner pod's beam locks onto an item again after
+    /// letting go of one
+    pub relink_cooldown: f32,
+    /// The most energy the tractor beam can store
+    pub max_beam_energy: f32,
+    /// The fuel left in the tank
+    pub fuel: f32,
+    /// The capacity of the fuel tank
+    pub max_fuel: f32,
+    /// The time in seconds until the laser can fire again
+    pub weapon_cooldown: f32,
+    /// Whether the jet pod is under water, which weakens its thruster
+    pub submerged: bool,
+    /// The integrity of the jet pod's hull, from 1.0 (intact) down to 0.0 (destroyed)
+    pub health: f32,
+    /// The share of the damage the armor absorbs before it reaches the hull
+    pub armor: f32,
+    /// The force of the thruster out of the water
+    pub thrust: f32,
+    /// The fuel burnt since the start of the level, across all pods
+    pub fuel_used: f32,
+    /// The hull damage taken since the start of the level, across all pods
+    pub damage_taken: f32,
+    /// The flickering of the thruster's flame
+    pub flame: Animator,
+    /// Whether the jet pod shrugs off all damage, as in god mode
+    pub invulnerable: bool,
+    /// The power-ups in effect on the pod
+    pub modifiers: Modifiers,
 }
 
 impl Jetman {
-    /// Create a new Jetman
+    /// The beam energy drained per unit of time for every unit of mass being towed
+    pub const BEAM_DRAIN: f32 = 0.08;
+    /// The beam energy recharged per unit of time while nothing is being towed
+    pub const BEAM_RECHARGE: f32 = 0.2;
+    /// The beam energy recharged per unit of time while the pod rests on the ground
+    pub const LANDED_RECHARGE: f32 = 0.8;
+    /// The beam energy needed to pick up an item
+    pub const BEAM_ENGAGE: f32 = 20.0;
+    /// The time in seconds a partner pod's beam stays off after letting go of an item,
+    /// so it doesn't lock straight back onto the item still hanging in reach
+    pub const RELINK_DELAY: f32 = 1.0;
+
+    /// Create a new Jetman with a stock jet pod
     pub fn new() -> Self {
         Jetman {
             body: Body::new(Vec2::new(200.0, 200.0), 1.0),
             heading: 0.0,
             link_distance: 50.0,
+            winch_speed: 1.0,
+            max_link_distance: 150.0,
             linked_item: None,
+            link_tension: 0.0,
+            contacts: 0,
+            link_strength: 0.08,
+            overload_frames: 0,
+            towed_mass: 0.0,
+            grapple: None,
+            throw_charge: 0.0,
+            oxygen: None,
             thrusting: 0,
+            beam_energy: 100.0,
+            relink_cooldown: 0.0,
+            max_beam_energy: 100.0,
+            fuel: 100.0,
+            max_fuel: 100.0,
+            weapon_cooldown: 0.0,
+            submerged: false,
+            health: 1.0,
+            armor: 0.0,
+            thrust: Self::THRUST,
+            fuel_used: 0.0,
+            damage_taken: 0.0,
+            flame: Animator::new(Clip::Thrust),
+            invulnerable: false,
+            modifiers: Modifiers::default(),
         }
     }
 
-    /// Apply thrust, i.e. a force in the direction of the jet pod's heading
+    /// Apply thrust, i.e. a force in the direction of the jet pod's heading.
+    /// While towing, the rigid link shares the push with the chain, so heavy loads
+    /// pick up speed slowly.
     pub fn apply_thrust(&mut self) {
-        let thrust = vector_from_angle(self.heading) * 0.1;
+        if self.fuel <= 0.0 {
+            return;
+        }
+        let power = if self.submerged { 0.04 } else { self.thrust };
+        let thrust = vector_from_angle(self.heading) * power;
         self.body.apply_force(thrust);
         self.thrusting = 2;
     }
 
+    /// The force of the stock thruster out of the water
+    pub const THRUST: f32 = 0.1;
+    /// The fuel burnt by the thruster per unit of time
+    pub const FUEL_BURN: f32 = 0.3;
+
+    /// The force of the retro and side thrusters
+    pub const SIDE_THRUST: f32 = 0.05;
+    /// How much more fuel the retro and side thrusters burn than the main thruster
+    pub const SIDE_FUEL: f32 = 1.5;
+
+    /// Fire the retro thruster, pushing against the jet pod's heading
+    pub fn brake(&mut self, dt: f32) {
+        self.fire_side_thruster(-vector_from_angle(self.heading), dt);
+    }
+
+    /// Fire a side thruster, pushing the jet pod across its heading: to its right
+    /// for a positive `side`, to its left for a negative one
+    pub fn strafe(&mut self, side: f32, dt: f32) {
+        let forward = vector_from_angle(self.heading);
+        let right = vec2(-forward.y, forward.x);
+        self.fire_side_thruster(right * side.signum(), dt);
+    }
+
+    /// Push the jet pod in a direction with one of the smaller thrusters, which burn
+    /// more fuel than the main thruster
+    fn fire_side_thruster(&mut self, direction: Vec2, dt: f32) {
+        if self.fuel <= 0.0 {
+            return;
+        }
+        self.body.apply_force(direction * Self::SIDE_THRUST);
+        let burnt = (Self::FUEL_BURN * Self::SIDE_FUEL * dt).min(self.fuel);
+        self.fuel -= burnt;
+        self.fuel_used += burnt;
+    }
+
+    /// The most force the stability assist puts behind its counter-thrust
+    pub const ASSIST_THRUST: f32 = 0.05;
+    /// The fastest the stability assist lets the pod sink
+    pub const ASSIST_DESCENT: f32 = 0.8;
+
+    /// Counter-thrust automatically, as the stability assist does: cancel the drift to
+    /// the sides and hold the descent below `ASSIST_DESCENT`. The counter-thrust burns
+    /// fuel like the thruster would for the same force.
+    pub fn stabilize(&mut self, gravity: Vec2, dt: f32) {
+        if self.fuel <= 0.0 {
+            return;
+        }
+        let velocity = self.body.velocity;
+        let mut force = vec2(-velocity.x * 0.1, 0.0);
+        if velocity.y > Self::ASSIST_DESCENT {
+            force.y = -gravity.y - (velocity.y - Self::ASSIST_DESCENT) * 0.1;
+        }
+        let force = force.clamp_length_max(Self::ASSIST_THRUST);
+        self.body.apply_force(force);
+        let burnt = (Self::FUEL_BURN * dt * force.length() / self.thrust).min(self.fuel);
+        self.fuel -= burnt;
+        self.fuel_used += burnt;
+    }
+
+    /// Pump fuel into the tank, up to its capacity
+    pub fn refuel(&mut self, amount: f32) {
+        self.fuel = (self.fuel + amount).min(self.max_fuel);
+    }
+
+    /// The time in seconds between two laser shots
+    pub const WEAPON_COOLDOWN: f32 = 0.25;
+
+    /// Try to fire the forward laser. Returns the position and velocity of the
+    /// new bolt, or `None` if the laser is still cooling down.
+    pub fn fire(&mut self) -> Option<(Vec2, Vec2)> {
+        if self.weapon_cooldown > 0.0 {
+            return None;
+        }
+        self.weapon_cooldown = Self::WEAPON_COOLDOWN;
+        let dir = vector_from_angle(self.heading);
+        Some((
+            self.body.position + dir * 16.0,
+            self.body.velocity + dir * 8.0,
+        ))
+    }
+
     /// Rotate the jet pod to the left by a fixed amount
     pub fn turn_left(&mut self) {
-        self.heading -= 0.1;
+        self.heading -= self.turn_rate();
     }
 
     /// Rotate the jet pod to the right by a fixed amount
     pub fn turn_right(&mut self) {
-        self.heading += 0.1;
+        self.heading += self.turn_rate();
+    }
+
+    /// How far the jet pod rotates per turn, less the more mass swings from the beam
+    pub fn turn_rate(&self) -> f32 {
+        0.1 / (1.0 + Self::TURN_DRAG * self.towed_mass / self.body.mass)
+    }
+
+    /// How much the towed mass slows down turning, relative to the pod's own mass
+    pub const TURN_DRAG: f32 = 0.5;
+
+    /// Turn the jet pod back towards pointing straight up, as fast as it turns
+    pub fn level_out(&mut self) {
+        self.turn_towards(-FRAC_PI_2);
+    }
+
+    /// Turn the jet pod the shorter way round towards the given heading, as fast as it turns
+    pub fn turn_towards(&mut self, heading: f32) {
+        let off = (heading - self.heading + PI).rem_euclid(TAU) - PI;
+        let rate = self.turn_rate();
+        self.heading += off.clamp(-rate, rate);
+    }
+
+    /// The shortest length the winch can reel the tractor beam in to
+    pub const MIN_LINK_DISTANCE: f32 = 20.0;
+
+    /// The number of consecutive overloaded updates after which a link snaps
+    pub const OVERLOAD_FRAMES_TO_SNAP: u32 = 10;
+
+    /// The time in seconds it takes to wind a throw up all the way
+    pub const THROW_WINDUP: f32 = 1.0;
+    /// The force per unit of mass whirling the towed chain round when a throw is wound up all the way
+    pub const THROW_FORCE: f32 = 0.5;
+
+    /// The furthest the grappling hook flies
+    pub const GRAPPLE_RANGE: f32 = 250.0;
+    /// How close to where the hook is stuck there has to be something solid for it to hold
+    pub const GRAPPLE_GRIP: f32 = 1.0;
+
+    /// Shorten the grappling rope using the winch
+    pub fn reel_in_rope(&mut self, dt: f32) {
+        if let Some(grapple) = self.grapple.as_mut() {
+            grapple.length = (grapple.length - self.winch_speed * dt).max(Self::MIN_LINK_DISTANCE);
+        }
+    }
+
+    /// Lengthen the grappling rope using the winch
+    pub fn reel_out_rope(&mut self, dt: f32) {
+        if let Some(grapple) = self.grapple.as_mut() {
+            grapple.length = (grapple.length + self.winch_speed * dt).min(Self::GRAPPLE_RANGE);
+        }
+    }
+
+    /// Keep the jet pod within the rope's length of the grappling hook. The rope only
+    /// pulls, so the pod swings from it like a pendulum and keeps its speed along the arc.
+    pub fn hang(&mut self) {
+        let Some(grapple) = self.grapple else {
+            return;
+        };
+        let delta = self.body.position - grapple.anchor;
+        let distance = delta.length();
+        if distance <= grapple.length {
+            return;
+        }
+        let direction = delta / distance;
+        self.body.position = grapple.anchor + direction * grapple.length;
+        let outward = self.body.velocity.dot(direction);
+        if outward > 0.0 {
+            self.body.velocity -= direction * outward;
+        }
+    }
+
+    /// Shorten the tractor beam using the winch
+    pub fn reel_in(&mut self, dt: f32) {
+        self.link_distance =
+            (self.link_distance - self.winch_speed * dt).max(Self::MIN_LINK_DISTANCE);
+    }
+
+    /// Lengthen the tractor beam using the winch
+    pub fn reel_out(&mut self, dt: f32) {
+        self.link_distance =
+            (self.link_distance + self.winch_speed * dt).min(self.max_link_distance);
+    }
+
+    /// How much further the tractor beam reaches out to lock onto an item with a magnet
+    pub const MAGNET_REACH: f32 = 1.5;
+    /// The thickest wall the tractor beam locks on through with a magnet
+    pub const MAGNET_WALL: f32 = 24.0;
+
+    /// The distance at which the tractor beam locks onto an item
+    pub fn reach(&self) -> f32 {
+        if self.modifiers.is_active(Modifier::Magnet) {
+            self.link_distance * Self::MAGNET_REACH
+        } else {
+            self.link_distance
+        }
+    }
+
+    /// The thickest wall the tractor beam locks onto an item through while the magnet
+    /// widens it. Without a magnet the beam doesn't look for walls at all.
+    pub fn wall_reach(&self) -> Option<f32> {
+        self.modifiers
+            .is_active(Modifier::Magnet)
+            .then_some(Self::MAGNET_WALL)
+    }
+
+    /// Reduce the hull integrity by the given amount, less what the armor absorbs,
+    /// unless the pod is shielded
+    pub fn damage(&mut self, amount: f32) {
+        if self.invulnerable || self.modifiers.is_active(Modifier::Shield) {
+            return;
+        }
+        let dealt = (amount * (1.0 - self.armor)).min(self.health);
+        self.health -= dealt;
+        self.damage_taken += dealt;
+    }
+
+    /// Check whether the jet pod has been destroyed, or its pilot has run out of air
+    pub fn is_destroyed(&self) -> bool {
+        self.health <= 0.0 || self.oxygen.is_some_and(|oxygen| oxygen.is_empty())
+    }
+
+    /// Put a fresh jet pod at the given position
+    pub fn respawn(&mut self, position: Vec2) {
+        self.body = Body::new(position, self.body.mass);
+        self.heading = 0.0;
+        self.linked_item = None;
+        self.grapple = None;
+        self.throw_charge = 0.0;
+        self.link_tension = 0.0;
+        self.overload_frames = 0;
+        self.thrusting = 0;
+        self.fuel = self.max_fuel;
+        self.beam_energy = self.max_beam_energy;
+        self.health = 1.0;
+        self.modifiers.clear();
+        if let Some(oxygen) = self.oxygen.as_mut() {
+            oxygen.left = oxygen.supply;
+        }
     }
 
     /// Update the jet pod's state in the game world
     pub fn update(&mut self, dt: f32) {
         self.body.update(dt);
+        // the thruster has been fired during this update
+        if self.thrusting == 2 {
+            let burnt = (Self::FUEL_BURN * dt).min(self.fuel);
+            self.fuel -= burnt;
+            self.fuel_used += burnt;
+        }
         self.thrusting -= 1;
+        // dt runs at 20 steps per second
+        self.weapon_cooldown = (self.weapon_cooldown - dt / 20.0).max(0.0);
+        self.modifiers.update(dt / 20.0);
     }
 
     /// Draw the jet pod
@@ -144,8 +696,39 @@ impl Jetman {
         let right = vec2(-dir.y, dir.x);
         let angle = self.heading.to_degrees() + 90.0;
 
+        // --- Grappling rope ---
+        if let Some(grapple) = self.grapple {
+            let (x, y) = (grapple.anchor.x, grapple.anchor.y);
+            draw_line(position.x, position.y, x, y, 1.5, BEIGE);
+            draw_circle(x, y, 3.0, GRAY);
+        }
+
+        // --- Power-ups, flickering as they wear off ---
+        let shows =
+            |modifier| self.modifiers.is_active(modifier) && !self.modifiers.is_fading(modifier);
+        if shows(Modifier::Magnet) && self.linked_item.is_none() {
+            let reach = self.reach();
+            let color = Color::new(0.56, 0.24, 0.75, 0.35);
+            draw_circle_lines(position.x, position.y, reach, 1.0, color);
+        }
+        if shows(Modifier::Shield) {
+            draw_circle(position.x, position.y, 22.0, Color::new(0.4, 0.8, 1.0, 0.2));
+            draw_circle_lines(position.x, position.y, 22.0, 2.0, SKYBLUE);
+            draw_circle_lines(
+                position.x,
+                position.y,
+                25.0,
+                1.0,
+                Color::new(0.4, 0.8, 1.0, 0.4),
+            );
+        }
+
         // --- Thruster ---
-        if self.thrusting != 0 {
+        let sprite = assets::texture("jetman");
+        // the sprites point up, so they're turned a quarter further than the heading
+        let rotation = self.heading + FRAC_PI_2;
+        let flame = position - dir * 20.0;
+        if self.thrusting != 0 && !self.flame.draw(flame, vec2(10.0, 18.0), rotation) {
             draw_ellipse(
                 position.x - dir.x * 4.0,
                 position.y - dir.y * 4.0,
@@ -156,6 +739,11 @@ impl Jetman {
             );
         }
 
+        if let Some(sprite) = sprite {
+            assets::draw_sprite(sprite, position, vec2(20.0, 28.0), rotation);
+            return;
+        }
+
         // --- Main Pod (capsule-like body) ---
         draw_ellipse(
             position.x,
@@ -205,10 +793,51 @@ impl Default for Jetman {
     }
 }
 
-/// An item in the game world that the Jetman can interact with
+/// The different kinds of items
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ItemKind {
+    /// Regular cargo to be delivered to a goal teleporter
+    Cargo,
+    /// A ticking bomb that has to be dropped into a disposal teleporter
+    /// before the fuse (in seconds) runs out
+    Bomb { fuse: f32 },
+    /// A critter to be delivered to a goal teleporter, which wanders about on its own
+    Livestock,
+    /// A key unlocking the door with the given index when carried to it
+    Key { door: usize },
+}
+
+impl ItemKind {
+    /// Check whether the item is delivered to a goal teleporter
+    pub fn is_cargo(&self) -> bool {
+        matches!(self, ItemKind::Cargo | ItemKind::Livestock)
+    }
+
+    /// The width and the height of an item of this kind
+    pub fn size(&self) -> Vec2 {
+        match self {
+            ItemKind::Cargo => vec2(30.0, 20.0),
+            ItemKind::Bomb { .. } => vec2(24.0, 24.0),
+            ItemKind::Livestock => vec2(26.0, 20.0),
+            ItemKind::Key { .. } => vec2(24.0, 12.0),
+        }
+    }
+}
+
+/// An item in the game world that the Jetman can interact with,
+/// as placed in a level before it is spawned into the world
+#[derive(Clone)]
 pub struct Item {
     /// The item's physics body
     pub body: Body,
+    /// What kind of item this is
+    pub kind: ItemKind,
+    /// The length of the link to the next item in the chain
+    pub link_distance: f32,
+    /// How easily hard impacts damage the item, 0.0 for not at all
+    pub fragility: f32,
+    /// The width and the height of the item
+    pub size: Vec2,
 }
 
 impl Item {
@@ -216,18 +845,70 @@ impl Item {
     pub fn new(x: f32, y: f32) -> Self {
         Item {
             body: Body::new(Vec2::new(x, y), 1.0),
+            kind: ItemKind::Cargo,
+            link_distance: 40.0,
+            fragility: 0.0,
+            size: ItemKind::Cargo.size(),
         }
     }
 
-    /// Draw the item
-    pub fn draw(&self) {
-        draw_rectangle(
-            self.body.position.x - 15.0,
-            self.body.position.y - 10.0,
-            30.0,
-            20.0,
-            LIGHTGRAY,
-        );
+    /// Stretch the item to the given length, as heavy as the crates it would take
+    pub fn with_length(self, length: f32) -> Self {
+        let mut body = self.body;
+        body.mass *= length / self.size.x;
+        let size = vec2(length, self.size.y);
+        Item { body, size, ..self }
+    }
+
+    /// Make the item as fragile as given, from 0.0 for sturdy to 1.0 for breaking
+    /// on the first hard impact
+    pub fn with_fragility(self, fragility: f32) -> Self {
+        Item { fragility, ..self }
+    }
+
+    /// Create a new bomb that detonates after `fuse` seconds
+    pub fn bomb(x: f32, y: f32, fuse: f32) -> Self {
+        Item {
+            kind: ItemKind::Bomb { fuse },
+            size: ItemKind::Bomb { fuse }.size(),
+            ..Item::new(x, y)
+        }
+    }
+
+    /// Create a new key unlocking the door with the given index
+    pub fn key(x: f32, y: f32, door: usize) -> Self {
+        Item {
+            body: Body::new(Vec2::new(x, y), 0.5),
+            kind: ItemKind::Key { door },
+            size: ItemKind::Key { door }.size(),
+            ..Item::new(x, y)
+        }
+    }
+
+    /// Create a new critter that wanders about on its own
+    pub fn livestock(x: f32, y: f32) -> Self {
+        Item {
+            body: Body::new(Vec2::new(x, y), 0.8),
+            kind: ItemKind::Livestock,
+            size: ItemKind::Livestock.size(),
+            ..Item::new(x, y)
+        }
+    }
+
+    /// Split the item into its physics body and the component that makes it towable
+    pub fn into_parts(self) -> (Body, Towable) {
+        let towable = Towable {
+            kind: self.kind,
+            link_distance: self.link_distance,
+            fragility: self.fragility,
+            damage: 0.0,
+            size: self.size,
+            angle: 0.0,
+            spin: 0.0,
+            attachment: Vec2::ZERO,
+            loose: false,
+        };
+        (self.body, towable)
     }
 }
 
@@ -243,20 +924,407 @@ impl Bodied for Item {
     }
 }
 
+/// The component of an entity that Jetman can pick up and tow
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Towable {
+    /// What kind of item this is
+    pub kind: ItemKind,
+    /// The length of the link to the next item in the chain
+    pub link_distance: f32,
+    /// How easily hard impacts damage the item, 0.0 for not at all
+    pub fragility: f32,
+    /// How badly the item is damaged, from 0.0 for intact to 1.0 for destroyed
+    pub damage: f32,
+    /// The width and the height of the item
+    pub size: Vec2,
+    /// The angle the item is turned by, in radians
+    pub angle: f32,
+    /// How fast the item turns, in radians per time step
+    pub spin: f32,
+    /// Where on the item the link holding it is attached, relative to its center
+    /// before turning it
+    pub attachment: Vec2,
+    /// Whether the item was let go of in flight, and falls until it comes to rest
+    pub loose: bool,
+}
+
+impl Towable {
+    /// The speed of impact the sturdiest packing takes without a scratch
+    pub const SAFE_IMPACT: f32 = 1.0;
+    /// The share of its spin a turning item loses per time step to the air
+    const SPIN_DRAG: f32 = 0.02;
+    /// The share of its speed a loose item loses per time step to the air
+    pub const AIR_DRAG: f32 = 0.01;
+    /// The speed below which a loose item on the ground comes to rest
+    pub const REST_SPEED: f32 = 0.1;
+
+    /// How hard the item of the given mass is to turn, as a solid box of its size
+    pub fn inertia(&self, mass: f32) -> f32 {
+        mass * self.size.length_squared() / 12.0
+    }
+
+    /// Attach the link to the point on the edge of the item at the given position
+    /// that faces the given anchor
+    pub fn attach_towards(&mut self, position: Vec2, anchor: Vec2) {
+        let toward = Vec2::from_angle(-self.angle).rotate(anchor - position);
+        let half = self.size / 2.0;
+        // as far along the direction as the box reaches
+        let scale = (half.x / toward.x.abs()).min(half.y / toward.y.abs());
+        self.attachment = if scale.is_finite() {
+            toward * scale
+        } else {
+            Vec2::ZERO
+        };
+    }
+
+    /// The middles of the ends of the item's length, as far along it as is beyond the
+    /// reach of its middle, relative to its center and turned with it
+    pub fn ends(&self) -> [Vec2; 2] {
+        let reach = ((self.size.x - self.size.y) / 2.0).max(0.0);
+        let end = Vec2::from_angle(self.angle) * reach;
+        [end, -end]
+    }
+
+    /// Where the link holding the item at the given position is attached
+    pub fn attachment_point(&self, position: Vec2) -> Vec2 {
+        position + Vec2::from_angle(self.angle).rotate(self.attachment)
+    }
+
+    /// Turn the item by its spin, which the air slowly takes out
+    pub fn turn(&mut self, dt: f32) {
+        self.angle += self.spin * dt;
+        self.spin *= 1.0 - Self::SPIN_DRAG * dt;
+    }
+
+    /// Damage the item by an impact at the given speed, the more fragile it is the worse.
+    /// Returns true if the impact destroyed the item.
+    pub fn crack(&mut self, impact: f32) -> bool {
+        if impact > Self::SAFE_IMPACT {
+            self.damage += (impact - Self::SAFE_IMPACT) * self.fragility;
+        }
+        self.damage >= 1.0
+    }
+
+    /// Check whether the item is fragile and made it without any damage so far
+    pub fn is_intact(&self) -> bool {
+        self.fragility > 0.0 && self.damage == 0.0
+    }
+
+    /// Advance the item's timers by `seconds`.
+    /// Returns true if the item is a bomb whose fuse just ran out.
+    pub fn tick(&mut self, seconds: f32) -> bool {
+        match self.kind {
+            ItemKind::Bomb { ref mut fuse } if *fuse > 0.0 => {
+                *fuse -= seconds;
+                *fuse <= 0.0
+            }
+            _ => false,
+        }
+    }
+
+    /// Draw the item at the given position, turned by its angle
+    pub fn draw(&self, position: Vec2) {
+        // a point given relative to the item's center, turned along with it
+        let rotation = Vec2::from_angle(self.angle);
+        let at = |x: f32, y: f32| position + rotation.rotate(vec2(x, y));
+        match self.kind {
+            ItemKind::Cargo => {
+                if let Some(sprite) = assets::texture("cargo") {
+                    assets::draw_sprite(sprite, position, self.size, self.angle);
+                } else {
+                    let params = DrawRectangleParams {
+                        offset: vec2(0.5, 0.5),
+                        rotation: self.angle,
+                        color: LIGHTGRAY,
+                    };
+                    draw_rectangle_ex(position.x, position.y, self.size.x, self.size.y, params);
+                }
+            }
+            ItemKind::Bomb { fuse } => {
+                if let Some(sprite) = assets::texture("bomb") {
+                    assets::draw_sprite(sprite, position, self.size, self.angle);
+                } else {
+                    draw_circle(position.x, position.y, 11.0, MAROON);
+                    draw_circle_lines(position.x, position.y, 11.0, 1.0, RED);
+                }
+                // show the remaining time above the bomb
+                let text = format!("{:.1}", fuse.max(0.0));
+                let color = if fuse < 10.0 { RED } else { WHITE };
+                draw_text(&text, position.x - 12.0, position.y - 16.0, 18.0, color);
+            }
+            ItemKind::Livestock => {
+                if let Some(sprite) = assets::texture("livestock") {
+                    assets::draw_sprite(sprite, position, self.size, self.angle);
+                } else {
+                    let degrees = self.angle.to_degrees();
+                    draw_ellipse(position.x, position.y, 13.0, 9.0, degrees, BEIGE);
+                    let head = at(10.0, -6.0);
+                    draw_circle(head.x, head.y, 5.0, BEIGE);
+                    let eye = at(12.0, -7.0);
+                    draw_circle(eye.x, eye.y, 1.5, BLACK);
+                }
+            }
+            ItemKind::Key { .. } => {
+                if let Some(sprite) = assets::texture("key") {
+                    assets::draw_sprite(sprite, position, self.size, self.angle);
+                } else {
+                    let bow = at(-7.0, 0.0);
+                    draw_circle_lines(bow.x, bow.y, 5.0, 2.0, GOLD);
+                    let lines = [
+                        ((-2.0, 0.0), (12.0, 0.0)),
+                        ((8.0, 0.0), (8.0, 5.0)),
+                        ((12.0, 0.0), (12.0, 5.0)),
+                    ];
+                    for ((x1, y1), (x2, y2)) in lines {
+                        let (from, to) = (at(x1, y1), at(x2, y2));
+                        draw_line(from.x, from.y, to.x, to.y, 2.0, GOLD);
+                    }
+                }
+            }
+        }
+        self.draw_damage(position);
+    }
+
+    /// Draw a fragile item's damage as cracks across it, more of them the worse it is
+    fn draw_damage(&self, position: Vec2) {
+        if self.fragility <= 0.0 {
+            return;
+        }
+        if self.damage == 0.0 {
+            // mark intact fragile items, so the pilot knows to go easy on them
+            draw_circle_lines(position.x, position.y, 16.0, 1.0, SKYBLUE);
+            return;
+        }
+        let cracks = (self.damage * 4.0).ceil().min(4.0) as usize;
+        for crack in 0..cracks {
+            let angle = crack as f32 * 1.9 + 0.4;
+            let start = position + vector_from_angle(angle) * 10.0;
+            let bend = position + vector_from_angle(angle + 0.6) * 3.0;
+            let end = position - vector_from_angle(angle - 0.3) * 8.0;
+            draw_line(start.x, start.y, bend.x, bend.y, 1.5, BLACK);
+            draw_line(bend.x, bend.y, end.x, end.y, 1.5, BLACK);
+        }
+    }
+}
+
+/// The component of an item that moves on its own, every so often giving itself
+/// a push in a random direction
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Restless {
+    /// The time in seconds until the next push
+    pub timer: f32,
+}
+
+impl Restless {
+    /// The shortest and the longest time between two pushes, in seconds
+    pub const INTERVAL: (f32, f32) = (1.5, 4.0);
+    /// The speed a push gives, at most
+    pub const STRENGTH: f32 = 1.2;
+    /// The part of its speed the item loses per unit of time when nothing holds it
+    pub const DRAG: f32 = 0.05;
+
+    /// Start out waiting for the first push
+    pub fn new() -> Self {
+        Restless {
+            timer: Self::INTERVAL.0,
+        }
+    }
+}
+
+impl Default for Restless {
+    fn default() -> Self {
+        Restless::new()
+    }
+}
+
+/// The component of an entity that has the next item of a towed chain hanging from it
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Link {
+    /// The entity hanging from this one
+    pub next: Entity,
+}
+
+/// The different kinds of teleporters
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TeleporterKind {
+    /// Accepts cargo items
+    Goal,
+    /// Accepts bombs and gets rid of them safely
+    Disposal,
+}
+
 /// A teleporter that allows Jetman to drop items.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Teleporter {
     /// The teleporter's position
     pub position: Vec2,
+    /// What kind of items the teleporter accepts
+    pub kind: TeleporterKind,
 }
 
 impl Teleporter {
     /// Create a new teleporter
     pub fn new(position: Vec2) -> Self {
-        Teleporter { position }
+        Teleporter {
+            position,
+            kind: TeleporterKind::Goal,
+        }
+    }
+
+    /// Create a new teleporter for disposing of bombs
+    pub fn disposal(position: Vec2) -> Self {
+        Teleporter {
+            position,
+            kind: TeleporterKind::Disposal,
+        }
+    }
+
+    /// Check whether the teleporter accepts the given kind of item
+    pub fn accepts(&self, kind: ItemKind) -> bool {
+        match self.kind {
+            TeleporterKind::Goal => kind.is_cargo(),
+            TeleporterKind::Disposal => matches!(kind, ItemKind::Bomb { .. }),
+        }
+    }
+
+    /// Draw the teleporter, animated if it has an animator
+    pub fn draw(&self, animator: Option<&Animator>) {
+        let (name, color) = match self.kind {
+            TeleporterKind::Goal => ("goal", YELLOW),
+            TeleporterKind::Disposal => ("disposal", ORANGE),
+        };
+        let size = vec2(24.0, 24.0);
+        if animator.is_some_and(|animator| animator.draw(self.position, size, 0.0)) {
+            return;
+        }
+        if let Some(sprite) = assets::texture(name) {
+            assets::draw_sprite(sprite, self.position, size, 0.0);
+        } else {
+            draw_circle(self.position.x, self.position.y, 10.0, color);
+        }
+    }
+}
+
+/// A checkpoint that becomes Jetman's respawn point once he touches it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The checkpoint's position
+    pub position: Vec2,
+    /// Whether Jetman has reached the checkpoint
+    pub reached: bool,
+}
+
+impl Checkpoint {
+    /// The distance at which Jetman touches the checkpoint
+    pub const RADIUS: f32 = 20.0;
+
+    /// Create a new checkpoint
+    pub fn new(position: Vec2) -> Self {
+        Checkpoint {
+            position,
+            reached: false,
+        }
+    }
+
+    /// Draw the checkpoint as a flag, lit once it has been reached
+    pub fn draw(&self) {
+        let (x, y) = (self.position.x, self.position.y);
+        let color = if self.reached { GREEN } else { DARKGRAY };
+        draw_line(x, y + 12.0, x, y - 12.0, 2.0, LIGHTGRAY);
+        draw_triangle(
+            vec2(x, y - 12.0),
+            vec2(x + 14.0, y - 7.0),
+            vec2(x, y - 2.0),
+            color,
+        );
+    }
+}
+
+/// A landing pad where Jetman can fill up his fuel tank
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FuelPad {
+    /// The center of the pad's surface
+    pub position: Vec2,
+    /// Whether the pad is in service
+    pub active: bool,
+}
+
+impl FuelPad {
+    /// The distance above the pad at which the pod gets refuelled
+    pub const REACH: f32 = 24.0;
+    /// The fuel pumped per second
+    pub const FLOW: f32 = 25.0;
+
+    /// Create a new fuel pad
+    pub fn new(position: Vec2) -> Self {
+        FuelPad {
+            position,
+            active: true,
+        }
     }
 
-    /// Draw the teleporter
+    /// Check whether a body hovers on the pad slowly enough to be refuelled
+    pub fn serves(&self, body: &Body) -> bool {
+        self.active
+            && (body.position - self.position).length() < Self::REACH
+            && body.velocity.length() < 0.5
+    }
+
+    /// Draw the pad, dimmed if it is out of service
     pub fn draw(&self) {
-        draw_circle(self.position.x, self.position.y, 10.0, YELLOW);
+        let (x, y) = (self.position.x, self.position.y);
+        let color = if self.active { SKYBLUE } else { DARKGRAY };
+        draw_rectangle(x - 20.0, y, 40.0, 4.0, color);
+        draw_text("F", x - 5.0, y + 18.0, 18.0, color);
+    }
+}
+
+/// The expanding blast of a detonated bomb
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Explosion {
+    /// The center of the blast
+    pub position: Vec2,
+    /// The radius the blast expands to
+    pub radius: f32,
+    /// The time in seconds since the detonation
+    pub age: f32,
+}
+
+impl Explosion {
+    /// How long the blast stays visible, in seconds
+    pub const DURATION: f32 = 0.6;
+
+    /// Create a new explosion
+    pub fn new(position: Vec2, radius: f32) -> Self {
+        Explosion {
+            position,
+            radius,
+            age: 0.0,
+        }
+    }
+
+    /// Check whether the blast has faded out
+    pub fn is_finished(&self) -> bool {
+        self.age >= Self::DURATION
+    }
+
+    /// Draw the blast
+    pub fn draw(&self) {
+        // the sprite sheet shows the blast out to the reach of its push, twice its radius
+        let size = Vec2::splat(self.radius * 4.0);
+        if Clip::Explosion
+            .animation()
+            .draw(self.age, self.position, size, 0.0)
+        {
+            return;
+        }
+        let t = (self.age / Self::DURATION).min(1.0);
+        let radius = self.radius * (0.3 + 0.7 * t);
+        draw_circle(
+            self.position.x,
+            self.position.y,
+            radius,
+            Color::new(1.0, 0.6, 0.1, 1.0 - t),
+        );
     }
 }
@@ -0,0 +1,183 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::modifiers::Modifier;
+use crate::physics::Oxygen;
+
+/// The kinds of small things Jetman picks up just by flying through them,
+/// without the tractor beam
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum PickupKind {
+    /// Worth points at the end of the level
+    Gem,
+    /// A spare load of fuel, tipped into the tank once there's room for it
+    FuelCell,
+    /// A spare load of air, let into the pod's tanks once there's room for it
+    OxygenCanister,
+    /// A power-up shielding the pod from all damage for a while
+    Shield,
+    /// A power-up letting the tractor beam reach further for a while
+    Magnet,
+}
+
+impl PickupKind {
+    /// The kind of pickup going by the given name in level files
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gem" => Some(PickupKind::Gem),
+            "fuel-cell" => Some(PickupKind::FuelCell),
+            "oxygen" => Some(PickupKind::OxygenCanister),
+            "shield" => Some(PickupKind::Shield),
+            "magnet" => Some(PickupKind::Magnet),
+            _ => None,
+        }
+    }
+
+    /// The color the pickup is drawn in
+    pub fn color(&self) -> Color {
+        match self {
+            PickupKind::Gem => MAGENTA,
+            PickupKind::FuelCell => ORANGE,
+            PickupKind::OxygenCanister => SKYBLUE,
+            PickupKind::Shield => Color::from_hex(0x7FDBFF),
+            PickupKind::Magnet => VIOLET,
+        }
+    }
+
+    /// The modifier a power-up puts into effect as soon as it's collected,
+    /// `None` for pickups kept in the inventory
+    pub fn modifier(&self) -> Option<Modifier> {
+        match self {
+            PickupKind::Shield => Some(Modifier::Shield),
+            PickupKind::Magnet => Some(Modifier::Magnet),
+            _ => None,
+        }
+    }
+}
+
+/// A small thing lying around the level, collected on contact
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Pickup {
+    pub kind: PickupKind,
+    pub position: Vec2,
+}
+
+impl Pickup {
+    /// How close the jet pod has to come to collect the pickup
+    pub const REACH: f32 = 16.0;
+
+    /// Create a pickup of the given kind
+    pub fn new(kind: PickupKind, position: Vec2) -> Self {
+        Pickup { kind, position }
+    }
+
+    /// Draw the pickup
+    pub fn draw(&self) {
+        let Vec2 { x, y } = self.position;
+        let color = self.kind.color();
+        match self.kind {
+            PickupKind::Gem => {
+                let (top, bottom) = (vec2(x, y - 7.0), vec2(x, y + 7.0));
+                let (left, right) = (vec2(x - 5.0, y), vec2(x + 5.0, y));
+                draw_triangle(top, left, right, color);
+                draw_triangle(bottom, left, right, color);
+                draw_line(left.x, left.y, right.x, right.y, 1.0, WHITE);
+            }
+            PickupKind::FuelCell => {
+                draw_rectangle(x - 4.0, y - 6.0, 8.0, 12.0, color);
+                draw_rectangle(x - 2.0, y - 8.0, 4.0, 2.0, GRAY);
+                draw_rectangle_lines(x - 4.0, y - 6.0, 8.0, 12.0, 1.0, WHITE);
+            }
+            PickupKind::OxygenCanister => {
+                draw_circle(x, y + 2.0, 5.0, color);
+                draw_rectangle(x - 5.0, y - 4.0, 10.0, 6.0, color);
+                draw_circle(x, y - 4.0, 5.0, color);
+                draw_text("O2", x - 5.0, y + 3.0, 12.0, DARKBLUE);
+            }
+            PickupKind::Shield => {
+                draw_circle_lines(x, y, 7.0, 2.0, color);
+                draw_circle(x, y, 3.0, WHITE);
+            }
+            PickupKind::Magnet => {
+                draw_line(x - 5.0, y - 6.0, x - 5.0, y + 2.0, 3.0, color);
+                draw_line(x + 5.0, y - 6.0, x + 5.0, y + 2.0, 3.0, color);
+                draw_line(x - 6.5, y + 2.0, x + 6.5, y + 2.0, 3.0, color);
+                draw_rectangle(x - 6.5, y - 8.0, 3.0, 3.0, LIGHTGRAY);
+                draw_rectangle(x + 3.5, y - 8.0, 3.0, 3.0, LIGHTGRAY);
+            }
+        }
+    }
+}
+
+/// The pickups Jetman has collected
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    pub gems: u32,
+    /// The fuel cells not tipped into the tank yet
+    pub fuel_cells: u32,
+    /// The oxygen canisters not let into the pod's tanks yet
+    pub oxygen_canisters: u32,
+}
+
+impl Inventory {
+    /// The points a gem is worth at the end of the level
+    pub const GEM_SCORE: u32 = 50;
+    /// The fuel a fuel cell holds
+    pub const FUEL_CELL: f32 = 25.0;
+    /// The seconds of air an oxygen canister holds
+    pub const OXYGEN_CANISTER: f32 = 30.0;
+    /// Put a collected pickup into the inventory. Power-ups aren't kept
+    /// but take effect as soon as they're collected.
+    pub fn add(&mut self, kind: PickupKind) {
+        match kind {
+            PickupKind::Gem => self.gems += 1,
+            PickupKind::FuelCell => self.fuel_cells += 1,
+            PickupKind::OxygenCanister => self.oxygen_canisters += 1,
+            PickupKind::Shield | PickupKind::Magnet => {}
+        }
+    }
+
+    /// The number of pickups in the inventory
+    pub fn count(&self) -> u32 {
+        self.gems + self.fuel_cells + self.oxygen_canisters
+    }
+
+    /// Take a fuel cell out of the inventory if there's one and the tank has room
+    /// for all of it, or is empty if it holds less than a cell. Returns the fuel
+    /// that fits into the tank.
+    pub fn use_fuel_cell(&mut self, fuel: f32, max_fuel: f32) -> Option<f32> {
+        let room = max_fuel - fuel;
+        if self.fuel_cells == 0 || room < Self::FUEL_CELL.min(max_fuel) {
+            return None;
+        }
+        self.fuel_cells -= 1;
+        Some(Self::FUEL_CELL.min(room))
+    }
+
+    /// Take an oxygen canister out of the inventory if there's one and the pod's
+    /// tanks have room for all of it, or are empty if they hold less than a canister.
+    /// Returns the seconds of air that fit into the tanks.
+    pub fn use_oxygen_canister(&mut self, oxygen: &Oxygen) -> Option<f32> {
+        let room = oxygen.supply - oxygen.left;
+        if self.oxygen_canisters == 0 || room < Self::OXYGEN_CANISTER.min(oxygen.supply) {
+            return None;
+        }
+        self.oxygen_canisters -= 1;
+        Some(Self::OXYGEN_CANISTER.min(room))
+    }
+
+    /// Draw the counters of the collected pickups
+    pub fn draw(&self, at: Vec2) {
+        let counters = [
+            (PickupKind::Gem, self.gems),
+            (PickupKind::FuelCell, self.fuel_cells),
+            (PickupKind::OxygenCanister, self.oxygen_canisters),
+        ];
+        let mut x = at.x;
+        for (kind, count) in counters {
+            Pickup::new(kind, vec2(x + 5.0, at.y - 6.0)).draw();
+            draw_text(&format!("x{count}"), x + 14.0, at.y, 20.0, WHITE);
+            x += 60.0;
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::cell::RefCell;
+
+use macroquad::miniquad::{UniformDesc, UniformType};
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::lighting::{self, Light};
+
+/// The size of the glow map relative to the view. Drawn small and stretched back out,
+/// the glows come out blurred for free.
+const GLOW_SCALE: f32 = 0.25;
+
+const FRAGMENT: &str = r#"#version 100
+precision mediump float;
+
+varying lowp vec4 color;
+// precise enough to bend the picture smoothly on large screens
+varying mediump vec2 uv;
+
+uniform sampler2D Texture;
+uniform sampler2D Glow;
+uniform vec2 Size;
+uniform float Crt;
+uniform float Bloom;
+uniform float Flash;
+
+void main() {
+    vec2 at = uv;
+    if (Crt > 0.5) {
+        // bulge the picture out like the glass of an old tube, black beyond its rim
+        vec2 centered = at * 2.0 - 1.0;
+        centered *= 1.0 + centered.yx * centered.yx * vec2(0.04, 0.05);
+        at = centered * 0.5 + 0.5;
+        if (at.x < 0.0 || at.x > 1.0 || at.y < 0.0 || at.y > 1.0) {
+            gl_FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+            return;
+        }
+    }
+    vec3 scene = texture2D(Texture, at).rgb;
+    if (Bloom > 0.5) {
+        // spread the glows out further still, the nearer samples weighing more
+        vec2 spacing = 6.0 / Size;
+        vec3 glow = vec3(0.0);
+        float total = 0.0;
+        for (int x = -2; x <= 2; x++) {
+            for (int y = -2; y <= 2; y++) {
+                float weight = 1.0 / (1.0 + float(x * x + y * y));
+                glow += texture2D(Glow, at + vec2(float(x), float(y)) * spacing).rgb * weight;
+                total += weight;
+            }
+        }
+        scene += glow / total * 1.5;
+    }
+    if (Crt > 0.5) {
+        // dark gaps between the lines, a tint of the phosphor stripes and darker corners
+        scene *= 0.75 + 0.25 * sin(at.y * Size.y * 3.14159);
+        float stripe = mod(gl_FragCoord.x, 3.0);
+        scene *= vec3(stripe < 1.0 ? 1.1 : 0.95, stripe >= 1.0 && stripe < 2.0 ? 1.1 : 0.95,
+            stripe >= 2.0 ? 1.1 : 0.95);
+        vec2 edge = at * (1.0 - at);
+        scene *= clamp(pow(edge.x * edge.y * 16.0, 0.2), 0.0, 1.0);
+    }
+    gl_FragColor = vec4(mix(scene, vec3(1.0), Flash), 1.0) * color;
+}"#;
+
+/// What the post-processing pass does to the picture of the world
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Effects {
+    /// Whether the picture looks like it's shown on an old CRT, with scanlines and
+    /// the curve of the glass
+    pub crt: bool,
+    /// Whether bright things like thruster flames and teleporters glow
+    pub bloom: bool,
+    /// How far the picture is washed out to white, from 0 to 1
+    pub flash: f32,
+}
+
+impl Effects {
+    /// Check whether the pass changes the picture at all, or the world can just as
+    /// well be drawn straight to the screen
+    pub fn any(&self) -> bool {
+        self.crt || self.bloom || self.flash > 0.0
+    }
+}
+
+/// Flashes the screen white for a moment after explosions. The flash fades out fast,
+/// and bigger blasts flash brighter.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ScreenFlash {
+    /// How bright the flash is, from 0 to 1
+    strength: f32,
+}
+
+impl ScreenFlash {
+    /// The brightness faded per second
+    const DECAY: f32 = 4.0;
+    /// The brightest a flash gets, so the screen never goes all white
+    const MAX: f32 = 0.6;
+
+    /// Flash the screen, up to the brightest a flash gets
+    pub fn add(&mut self, amount: f32) {
+        self.strength = (self.strength + amount).min(Self::MAX);
+    }
+
+    /// How far the picture is washed out to white, from 0 to 1
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    /// Fade the flash out
+    pub fn update(&mut self, seconds: f32) {
+        self.strength = (self.strength - Self::DECAY * seconds).max(0.0);
+    }
+}
+
+/// The off-screen targets the world and its glows are drawn into, and the material
+/// bringing them together on the screen
+struct Pass {
+    scene: RenderTarget,
+    glow: RenderTarget,
+    material: Material,
+}
+
+impl Pass {
+    fn new(width: u32, height: u32) -> Self {
+        let material = load_material(
+            ShaderSource::Glsl {
+                vertex: lighting::VERTEX,
+                fragment: FRAGMENT,
+            },
+            MaterialParams {
+                uniforms: vec![
+                    UniformDesc::new("Size", UniformType::Float2),
+                    UniformDesc::new("Crt", UniformType::Float1),
+                    UniformDesc::new("Bloom", UniformType::Float1),
+                    UniformDesc::new("Flash", UniformType::Float1),
+                ],
+                textures: vec!["Glow".to_string()],
+                ..Default::default()
+            },
+        )
+        .expect("the post-processing shaders are valid");
+        let glow_size = |size: u32| ((size as f32 * GLOW_SCALE) as u32).max(1);
+        let glow = render_target(glow_size(width), glow_size(height));
+        glow.texture.set_filter(FilterMode::Linear);
+        Pass {
+            scene: render_target(width, height),
+            glow,
+            material,
+        }
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (
+            self.scene.texture.width() as u32,
+            self.scene.texture.height() as u32,
+        )
+    }
+}
+
+thread_local! {
+    /// The targets and the material, kept between frames and only recreated when the
+    /// view changes size
+    static PASS: RefCell<Option<Pass>> = const { RefCell::new(None) };
+}
+
+/// Draw the world through `camera` into `view` on the screen with the given effects.
+/// `scene` draws the world, given the camera to draw through and the render target to
+/// draw into; `glows` are the bright things that bloom.
+pub fn draw(
+    camera: &Camera2D,
+    view: Rect,
+    effects: Effects,
+    glows: &[Light],
+    scene: impl FnOnce(&Camera2D, &RenderTarget),
+) {
+    let size = (view.w.max(1.0) as u32, view.h.max(1.0) as u32);
+    PASS.with_borrow_mut(|pass| {
+        let pass = match pass {
+            Some(pass) if pass.size() == size => pass,
+            _ => pass.insert(Pass::new(size.0, size.1)),
+        };
+        // the view fills the whole of the targets, wherever it is on the screen
+        let through = |target: &RenderTarget| Camera2D {
+            zoom: camera.zoom,
+            target: camera.target,
+            rotation: camera.rotation,
+            offset: camera.offset,
+            render_target: Some(target.clone()),
+            ..Default::default()
+        };
+
+        let scene_camera = through(&pass.scene);
+        set_camera(&scene_camera);
+        clear_background(BLACK);
+        scene(&scene_camera, &pass.scene);
+
+        if effects.bloom {
+            set_camera(&through(&pass.glow));
+            clear_background(BLACK);
+            for glow in glows {
+                glow.draw();
+            }
+        }
+
+        set_default_camera();
+        let material = &pass.material;
+        material.set_uniform("Size", view.size());
+        material.set_uniform("Crt", if effects.crt { 1.0f32 } else { 0.0 });
+        material.set_uniform("Bloom", if effects.bloom { 1.0f32 } else { 0.0 });
+        material.set_uniform("Flash", effects.flash);
+        material.set_texture("Glow", pass.glow.texture.clone());
+        gl_use_material(material);
+        draw_texture_ex(
+            &pass.scene.texture,
+            view.x,
+            view.y,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(view.size()),
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+    });
+}
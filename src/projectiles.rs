@@ -0,0 +1,171 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::physics::Body;
+
+/// Who fired a projectile, deciding what it can hit
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Owner {
+    /// Fired by Jetman, hits enemies
+    Player,
+    /// Fired by hostiles, hits Jetman
+    Enemy,
+}
+
+/// A laser bolt flying in a straight line, or a homing missile chasing its target
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Projectile {
+    /// The bolt's physics body
+    pub body: Body,
+    /// Who fired the bolt
+    pub owner: Owner,
+    /// The remaining lifetime in seconds
+    pub life: f32,
+    /// The seconds of fuel a missile has left to steer after its target with,
+    /// `None` for bolts
+    pub fuel: Option<f32>,
+    /// Whether the slot in the pool is in use
+    pub active: bool,
+}
+
+impl Projectile {
+    /// How far a missile can turn its course, in radians per update
+    const TURN_RATE: f32 = 0.05;
+    /// The speed a missile's motor drives it up to
+    const MISSILE_SPEED: f32 = 4.0;
+    /// How quickly a missile's motor gets it up to speed
+    const MISSILE_THRUST: f32 = 0.15;
+
+    /// Turn a missile towards the target as far as it can turn, and speed it up,
+    /// while it has fuel. A spent missile drops under `gravity` instead.
+    pub fn home(&mut self, target: Vec2, gravity: Vec2, dt: f32, seconds: f32) {
+        let Some(fuel) = &mut self.fuel else {
+            return;
+        };
+        if *fuel <= 0.0 {
+            self.body.velocity += gravity * dt;
+            return;
+        }
+        *fuel -= seconds;
+        let heading = self.body.velocity.to_angle();
+        let desired = (target - self.body.position).to_angle();
+        let error = (desired - heading + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        let turn = Self::TURN_RATE * dt;
+        let heading = heading + error.clamp(-turn, turn);
+        let speed =
+            (self.body.velocity.length() + Self::MISSILE_THRUST * dt).min(Self::MISSILE_SPEED);
+        self.body.velocity = Vec2::from_angle(heading) * speed;
+    }
+}
+
+/// A pool of projectiles whose slots are reused once a bolt is gone,
+/// so firing doesn't allocate every time
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ProjectilePool {
+    projectiles: Vec<Projectile>,
+}
+
+impl ProjectilePool {
+    /// The distance at which a bolt hits its target
+    pub const HIT_DISTANCE: f32 = 12.0;
+    /// How long a bolt flies before it fizzles out, in seconds
+    const LIFETIME: f32 = 1.5;
+    /// How long a missile flies before it blows up on its own, in seconds
+    const MISSILE_LIFETIME: f32 = 8.0;
+    /// The seconds a missile's motor burns for
+    pub const MISSILE_FUEL: f32 = 4.0;
+    /// The radius of the blast of a missile
+    pub const MISSILE_BLAST: f32 = 24.0;
+
+    /// Create an empty pool
+    pub fn new() -> Self {
+        ProjectilePool {
+            projectiles: vec![],
+        }
+    }
+
+    /// Fire a new bolt, reusing a free slot if there is one
+    pub fn fire(&mut self, position: Vec2, velocity: Vec2, owner: Owner) {
+        self.add(position, velocity, owner, Self::LIFETIME, None);
+    }
+
+    /// Launch a homing missile at Jetman, reusing a free slot if there is one
+    pub fn launch(&mut self, position: Vec2, velocity: Vec2) {
+        let fuel = Some(Self::MISSILE_FUEL);
+        self.add(
+            position,
+            velocity,
+            Owner::Enemy,
+            Self::MISSILE_LIFETIME,
+            fuel,
+        );
+    }
+
+    /// Put a new projectile into a free slot, or a new one if all are in use
+    fn add(&mut self, position: Vec2, velocity: Vec2, owner: Owner, life: f32, fuel: Option<f32>) {
+        let mut body = Body::new(position, 0.1);
+        body.velocity = velocity;
+        let projectile = Projectile {
+            body,
+            owner,
+            life,
+            fuel,
+            active: true,
+        };
+        match self.projectiles.iter_mut().find(|p| !p.active) {
+            Some(slot) => *slot = projectile,
+            None => self.projectiles.push(projectile),
+        }
+    }
+
+    /// Move the bolts and retire the ones that have fizzled out.
+    /// Returns where missiles ran out of time, to blow up there.
+    pub fn update(&mut self, dt: f32, seconds: f32) -> Vec<Vec2> {
+        let mut expired = vec![];
+        for projectile in self.iter_mut() {
+            projectile.body.update(dt);
+            projectile.life -= seconds;
+            if projectile.life <= 0.0 {
+                projectile.active = false;
+                if projectile.fuel.is_some() {
+                    expired.push(projectile.body.position);
+                }
+            }
+        }
+        expired
+    }
+
+    /// Iterate over the bolts in flight
+    pub fn iter(&self) -> impl Iterator<Item = &Projectile> {
+        self.projectiles.iter().filter(|p| p.active)
+    }
+
+    /// Iterate mutably over the bolts in flight
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Projectile> {
+        self.projectiles.iter_mut().filter(|p| p.active)
+    }
+
+    /// Draw the bolts as short streaks along their direction of flight,
+    /// and the missiles with a flame behind them while their motor burns
+    pub fn draw(&self) {
+        for projectile in self.iter() {
+            let position = projectile.body.position;
+            let tail = position - projectile.body.velocity.normalize_or_zero() * 8.0;
+            if let Some(fuel) = projectile.fuel {
+                if fuel > 0.0 {
+                    let flame = position - projectile.body.velocity.normalize_or_zero() * 14.0;
+                    draw_line(tail.x, tail.y, flame.x, flame.y, 3.0, ORANGE);
+                }
+                draw_line(tail.x, tail.y, position.x, position.y, 4.0, LIGHTGRAY);
+                draw_circle(position.x, position.y, 2.0, RED);
+                continue;
+            }
+            let color = match projectile.owner {
+                Owner::Player => SKYBLUE,
+                Owner::Enemy => PINK,
+            };
+            draw_line(tail.x, tail.y, position.x, position.y, 2.0, color);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::headless::{self, Controls, Rewards};
+
+/// A headless simulation of a level, driven from Python
+#[pyclass(name = "Simulation", unsendable)]
+struct PySimulation(headless::Simulation);
+
+#[pymethods]
+impl PySimulation {
+    /// Start simulating the level in a level file, generating its random parts from `seed`
+    #[new]
+    #[pyo3(signature = (path, seed = 0))]
+    fn new(path: &str, seed: u64) -> PyResult<Self> {
+        headless::Simulation::load(path, seed)
+            .map(PySimulation)
+            .map_err(PyValueError::new_err)
+    }
+
+    /// Start the level over with a new seed, returning the first observation
+    #[pyo3(signature = (seed = 0))]
+    fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.0.reset(seed);
+        self.observe()
+    }
+
+    /// Reward the agent as given from now on, as by default for anything not given
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (delivery = None, defusal = None, detonation = None, fuel = None, crash = None, completion = None, time = None))]
+    fn set_rewards(
+        &mut self,
+        delivery: Option<f32>,
+        defusal: Option<f32>,
+        detonation: Option<f32>,
+        fuel: Option<f32>,
+        crash: Option<f32>,
+        completion: Option<f32>,
+        time: Option<f32>,
+    ) {
+        let default = Rewards::default();
+        self.0.set_rewards(Rewards {
+            delivery: delivery.unwrap_or(default.delivery),
+            defusal: defusal.unwrap_or(default.defusal),
+            detonation: detonation.unwrap_or(default.detonation),
+            fuel: fuel.unwrap_or(default.fuel),
+            crash: crash.unwrap_or(default.crash),
+            completion: completion.unwrap_or(default.completion),
+            time: time.unwrap_or(default.time),
+        });
+    }
+
+    /// Advance by one frame, returning the observation, the reward and whether the run is over
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (thrust = false, turn_left = false, turn_right = false, sever = false, reel_in = false, reel_out = false, fire = false))]
+    fn step(
+        &mut self,
+        thrust: bool,
+        turn_left: bool,
+        turn_right: bool,
+        sever: bool,
+        reel_in: bool,
+        reel_out: bool,
+        fire: bool,
+    ) -> (Vec<f32>, f32, bool) {
+        let outcome = self.0.step(Controls {
+            thrust,
+            turn_left,
+            turn_right,
+            sever,
+            reel_in,
+            reel_out,
+            fire,
+        });
+        (self.observe(), outcome.reward, outcome.done)
+    }
+
+    /// The state of the world as a list of numbers
+    fn observe(&self) -> Vec<f32> {
+        self.0.observe().to_array().to_vec()
+    }
+}
+
+/// A level to learn flying in, one episode after the other, picking one of
+/// `ACTIONS` discrete actions every frame
+#[pyclass(name = "Environment", unsendable)]
+struct PyEnvironment(headless::Environment);
+
+#[pymethods]
+impl PyEnvironment {
+    /// Play the level in a level file, the first episode driven by `seed`,
+    /// cutting episodes off after `max_frames` frames
+    #[new]
+    #[pyo3(signature = (path, seed = 0, max_frames = headless::Batch::MAX_FRAMES))]
+    fn new(path: &str, seed: u64, max_frames: usize) -> PyResult<Self> {
+        headless::Environment::load(path, seed)
+            .map(|environment| PyEnvironment(environment.with_max_frames(max_frames)))
+            .map_err(PyValueError::new_err)
+    }
+
+    /// Start a new episode, returning the first observation
+    fn reset(&mut self) -> Vec<f32> {
+        self.0.reset().to_array().to_vec()
+    }
+
+    /// Play a frame with a discrete action, returning the observation, the reward
+    /// and whether the episode is over
+    fn step(&mut self, action: usize) -> (Vec<f32>, f32, bool) {
+        let (observation, reward, done) = self.0.step(Controls::from_action(action));
+        (observation.to_array().to_vec(), reward, done)
+    }
+}
+
+/// Headless Jetman simulations for training agents
+#[pymodule]
+fn jetman(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PySimulation>()?;
+    module.add_class::<PyEnvironment>()?;
+    module.add("OBSERVATION_SIZE", headless::OBSERVATION_SIZE)?;
+    module.add("ACTIONS", Controls::ACTIONS)?;
+    Ok(())
+}
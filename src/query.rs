@@ -0,0 +1,33 @@
+use macroquad::prelude::*;
+
+use crate::ecs::Entity;
+
+/// What overlaps a point or an area of the world
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Overlaps {
+    /// The indices of the terrain elements
+    pub terrain: Vec<usize>,
+    pub items: Vec<Entity>,
+    pub teleporters: Vec<Entity>,
+    /// The indices of the bodies of water
+    pub water: Vec<usize>,
+}
+
+impl Overlaps {
+    /// How far from its position an item or a teleporter counts as overlapping
+    pub const ENTITY_RADIUS: f32 = 12.0;
+
+    /// Check whether nothing overlaps
+    pub fn is_empty(&self) -> bool {
+        self.terrain.is_empty()
+            && self.items.is_empty()
+            && self.teleporters.is_empty()
+            && self.water.is_empty()
+    }
+}
+
+/// Check whether a circle overlaps an axis-aligned rectangle
+pub fn circle_overlaps_rect(center: Vec2, radius: f32, rect: Rect) -> bool {
+    let closest = center.clamp(rect.point(), rect.point() + rect.size());
+    (center - closest).length() <= radius
+}
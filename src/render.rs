@@ -0,0 +1,170 @@
+use std::f32::consts::TAU;
+use std::ops::RangeBounds;
+
+use macroquad::models::{Mesh, Vertex, draw_mesh};
+use macroquad::prelude::*;
+
+use crate::world::World;
+
+/// The layers the world is drawn in, from back to front
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum Layer {
+    /// Scenery far behind everything else
+    Background,
+    Terrain,
+    /// Things lying around: cargo, bombs, teleporters, checkpoints and fuel pads
+    Items,
+    /// Things flying around: the jet pod, the escorted craft and the hostiles
+    Actors,
+    /// Particles, projectiles and explosions
+    Particles,
+    /// Things in front of the action, like water, decoration and fog
+    Foreground,
+    /// The heads-up display, drawn on the screen instead of in the world
+    Hud,
+}
+
+/// Something to draw, queued on a draw list
+type Command<'a> = Box<dyn FnOnce() + 'a>;
+
+/// Drawing commands queued by layer, drawn back to front once everything is queued.
+/// Within a layer, commands are drawn by their depth, and commands of equal depth
+/// in the order they were queued.
+pub struct DrawList<'a> {
+    commands: Vec<(Layer, i32, Command<'a>)>,
+}
+
+impl<'a> DrawList<'a> {
+    /// Create an empty draw list
+    pub fn new() -> Self {
+        DrawList { commands: vec![] }
+    }
+
+    /// Queue a command on a layer, at depth 0
+    pub fn push(&mut self, layer: Layer, draw: impl FnOnce() + 'a) {
+        self.push_at(layer, 0, draw);
+    }
+
+    /// Queue a command on a layer at the given depth. Deeper commands are drawn
+    /// on top of shallower ones of the same layer.
+    pub fn push_at(&mut self, layer: Layer, depth: i32, draw: impl FnOnce() + 'a) {
+        self.commands.push((layer, depth, Box::new(draw)));
+    }
+
+    /// Draw the commands queued on the given layers, back to front,
+    /// and take them off the list
+    pub fn draw(&mut self, layers: impl RangeBounds<Layer>) {
+        let (mut drawn, kept) = std::mem::take(&mut self.commands)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(layer, _, _)| layers.contains(layer));
+        self.commands = kept;
+        // sorting is stable, so commands of equal depth keep their order
+        drawn.sort_by_key(|(layer, depth, _)| (*layer, *depth));
+        for (_, _, draw) in drawn {
+            draw();
+        }
+    }
+}
+
+impl Default for DrawList<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lines, triangles and circles gathered into meshes, to be drawn with a few
+/// submissions instead of one for every shape. Shapes are drawn in the order they
+/// were added, like they would be one by one.
+pub struct ShapeBatch {
+    meshes: Vec<Mesh>,
+}
+
+impl ShapeBatch {
+    /// The most vertices a mesh takes, well below what macroquad draws in one call
+    const MAX_VERTICES: usize = 4000;
+    /// The most indices a mesh takes, well below what macroquad draws in one call
+    const MAX_INDICES: usize = 4800;
+
+    /// Create an empty batch
+    pub fn new() -> Self {
+        ShapeBatch { meshes: vec![] }
+    }
+
+    /// Add a shape of the given vertices, its triangles given by the indices of their
+    /// corners among them
+    fn add(&mut self, vertices: impl IntoIterator<Item = Vec2>, indices: &[u16], color: Color) {
+        let vertices = vertices
+            .into_iter()
+            .map(|point| Vertex::new(point.x, point.y, 0.0, 0.0, 0.0, color))
+            .collect::<Vec<_>>();
+        let fits = |mesh: &Mesh| {
+            mesh.vertices.len() + vertices.len() <= Self::MAX_VERTICES
+                && mesh.indices.len() + indices.len() <= Self::MAX_INDICES
+        };
+        if !self.meshes.last().is_some_and(fits) {
+            self.meshes.push(Mesh {
+                vertices: vec![],
+                indices: vec![],
+                texture: None,
+            });
+        }
+        let Some(mesh) = self.meshes.last_mut() else {
+            return;
+        };
+        let first = mesh.vertices.len() as u16;
+        mesh.indices
+            .extend(indices.iter().map(|index| first + index));
+        mesh.vertices.extend(vertices);
+    }
+
+    /// Add a filled triangle
+    pub fn triangle(&mut self, a: Vec2, b: Vec2, c: Vec2, color: Color) {
+        self.add([a, b, c], &[0, 1, 2], color);
+    }
+
+    /// Add a filled axis-aligned rectangle
+    pub fn rectangle(&mut self, rect: Rect, color: Color) {
+        let corners = [
+            rect.point(),
+            rect.point() + vec2(rect.w, 0.0),
+            rect.point() + rect.size(),
+            rect.point() + vec2(0.0, rect.h),
+        ];
+        self.add(corners, &[0, 1, 2, 0, 2, 3], color);
+    }
+
+    /// Add a straight line of the given thickness
+    pub fn line(&mut self, from: Vec2, to: Vec2, thickness: f32, color: Color) {
+        let across = (to - from).perp().normalize_or_zero() * thickness / 2.0;
+        let corners = [from + across, to + across, to - across, from - across];
+        self.add(corners, &[0, 1, 2, 0, 2, 3], color);
+    }
+
+    /// Add a filled circle, as a polygon of the given number of sides
+    pub fn circle(&mut self, center: Vec2, radius: f32, sides: u16, color: Color) {
+        let sides = sides.max(3);
+        let rim = (0..sides)
+            .map(|side| center + Vec2::from_angle(side as f32 / sides as f32 * TAU) * radius);
+        let indices = (1..=sides)
+            .flat_map(|side| [0, side, side % sides + 1])
+            .collect::<Vec<_>>();
+        self.add(std::iter::once(center).chain(rim), &indices, color);
+    }
+
+    /// Draw everything added to the batch
+    pub fn draw(&self) {
+        for mesh in &self.meshes {
+            draw_mesh(mesh);
+        }
+    }
+}
+
+impl Default for ShapeBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A drawer queues extra things to draw for the world, on any layer, after the
+/// built-in ones. World coordinates apply to all layers but the HUD.
+pub type Drawer = for<'a> fn(&'a World, &mut DrawList<'a>);
@@ -0,0 +1,492 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ghost::{Ghost, Pose};
+use crate::hud;
+use crate::persistence;
+use crate::settings;
+use crate::snapshot::{self, Compression};
+use crate::ui::{Action, InputState};
+use crate::world::World;
+
+/// The input of one frame of a run, which is all it takes to play the frame again
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Frame {
+    /// The length of the frame in real time, in seconds
+    pub seconds: f32,
+    /// The actions the player performed, one bit per action in the order of `Action::ALL`
+    pub actions: u16,
+    /// The heading the player aimed the pod at with the mouse, if they did
+    pub aim: Option<f32>,
+    /// The actions the players flying the partner pods performed, in the same bits
+    pub partners: Vec<u16>,
+}
+
+impl Frame {
+    /// Capture the actions the players perform in a frame of the given length
+    pub fn capture(input: &InputState, seconds: f32) -> Self {
+        Frame {
+            seconds,
+            actions: actions(input),
+            aim: input.aim,
+            partners: input.partners.iter().map(actions).collect(),
+        }
+    }
+
+    /// The input to play the frame again with
+    pub fn input(&self) -> InputState {
+        let mut input = performing(self.actions);
+        input.aim = self.aim;
+        input.partners = self.partners.iter().copied().map(performing).collect();
+        input
+    }
+}
+
+/// The actions performed in the input, one bit per action in the order of `Action::ALL`
+fn actions(input: &InputState) -> u16 {
+    Action::ALL
+        .iter()
+        .enumerate()
+        .filter(|(_, action)| input.performs(**action))
+        .fold(0, |bits, (index, _)| bits | 1 << index)
+}
+
+/// The input performing the actions of the given bits
+fn performing(actions: u16) -> InputState {
+    let mut input = InputState::default();
+    for (index, action) in Action::ALL.iter().enumerate() {
+        input.set(*action, actions & 1 << index != 0);
+    }
+    input
+}
+
+/// The things worth jumping to in a replay
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum EventKind {
+    /// A cargo item reached a goal
+    Delivery,
+    /// A bomb got disposed of
+    Defusal,
+    /// A bomb went off
+    Explosion,
+    /// Jetman lost a pod
+    Crash,
+}
+
+impl EventKind {
+    /// All kinds of events
+    const ALL: [EventKind; 4] = [
+        EventKind::Delivery,
+        EventKind::Defusal,
+        EventKind::Explosion,
+        EventKind::Crash,
+    ];
+
+    /// How many events of this kind happened in the world so far
+    fn count(&self, world: &World) -> u32 {
+        match self {
+            EventKind::Delivery => world.progress.delivered,
+            EventKind::Defusal => world.progress.defused,
+            EventKind::Explosion => world.progress.detonated,
+            EventKind::Crash => world.deaths(),
+        }
+    }
+
+    /// The color of the event's marker on the timeline
+    fn color(&self) -> Color {
+        match self {
+            EventKind::Delivery => settings::good(),
+            EventKind::Defusal => SKYBLUE,
+            EventKind::Explosion => ORANGE,
+            EventKind::Crash => settings::bad(),
+        }
+    }
+}
+
+/// Something that happened during a run
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    /// The number of frames played when the event had happened
+    pub tick: usize,
+    pub kind: EventKind,
+}
+
+/// A recorded run: the input of every frame, along with snapshots of the world
+/// taken every so often, so playback can jump anywhere without starting over
+#[derive(Clone)]
+pub struct Replay {
+    frames: Vec<Frame>,
+    /// Snapshots of the world by the number of frames played before they were taken
+    keyframes: BTreeMap<usize, World>,
+    events: Vec<ReplayEvent>,
+    /// The number of frames between two snapshots
+    keyframe_interval: usize,
+    /// The number of events of each kind counted so far, in the order of `EventKind::ALL`
+    counts: [u32; 4],
+    /// Where the jet pod was at the start and after each frame, for racing against a ghost
+    poses: Vec<Pose>,
+}
+
+impl Replay {
+    /// The number of frames between two snapshots by default, about two seconds
+    pub const KEYFRAME_INTERVAL: usize = 120;
+    /// The file the last completed run is kept in
+    pub const LAST_RUN: &str = "last_run.replay";
+    /// The first bytes of every replay file, ending in the version of the format.
+    /// The version goes up whenever the frames or the world snapshots change shape;
+    /// version 5 brought in the mouse aim, the partners' input, the solver iterations
+    /// and items that turn.
+    const MAGIC: &[u8] = b"JETMAN-REPLAY-5";
+    /// The most bytes a replay file inflates to. Replays are shared, so a file
+    /// claiming more is taken for a broken one rather than read.
+    const MAX_FILE: usize = 256 << 20;
+
+    /// Start recording a run of the given world
+    pub fn record(world: &World) -> Self {
+        Replay {
+            frames: vec![],
+            keyframes: BTreeMap::from([(0, world.clone())]),
+            events: vec![],
+            keyframe_interval: Self::KEYFRAME_INTERVAL,
+            counts: EventKind::ALL.map(|kind| kind.count(world)),
+            poses: vec![pose(world)],
+        }
+    }
+
+    /// Take snapshots every given number of frames. Shorter intervals make seeking faster
+    /// at the cost of memory.
+    pub fn with_keyframe_interval(mut self, frames: usize) -> Self {
+        self.keyframe_interval = frames.max(1);
+        self
+    }
+
+    /// Record a frame the world has just been stepped by, noting what happened during it
+    pub fn push(&mut self, input: &InputState, frame_seconds: f32, world: &World) {
+        self.frames.push(Frame::capture(input, frame_seconds));
+        self.poses.push(pose(world));
+        let tick = self.frames.len();
+        for (kind, count) in EventKind::ALL.iter().zip(self.counts.iter_mut()) {
+            let now = kind.count(world);
+            if now > *count {
+                self.events.push(ReplayEvent { tick, kind: *kind });
+            }
+            *count = now;
+        }
+        if tick.is_multiple_of(self.keyframe_interval) {
+            self.keyframe(world);
+        }
+    }
+
+    /// Take a snapshot of the world right away. This is needed whenever something
+    /// other than the player's input changes the world, like accepting a hint,
+    /// since playing back the input alone wouldn't bring that change about.
+    pub fn keyframe(&mut self, world: &World) {
+        self.keyframes.insert(self.frames.len(), world.clone());
+    }
+
+    /// The number of frames recorded
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Check whether no frames have been recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The events of the run, in the order they happened
+    pub fn events(&self) -> &[ReplayEvent] {
+        &self.events
+    }
+
+    /// The ghost of the recorded run, to race against
+    pub fn ghost(&self) -> Ghost {
+        Ghost::new(self.poses.clone())
+    }
+
+    /// Write the replay to a file, snapshots and all, compressed as asked
+    pub fn save(&self, path: impl AsRef<Path>, compression: Compression) -> io::Result<()> {
+        // the snapshots are stored as differences, and compressed along with the rest
+        let mut keyframes = vec![];
+        let mut previous = None;
+        for (tick, world) in &self.keyframes {
+            let bytes = match previous {
+                None => snapshot::encode(world, Compression::None)?,
+                Some(base) => snapshot::encode_delta(base, world, Compression::None)?,
+            };
+            keyframes.push((*tick, bytes));
+            previous = Some(world);
+        }
+        let file = ReplayFile {
+            frames: self.frames.clone(),
+            events: self.events.clone(),
+            keyframe_interval: self.keyframe_interval,
+            keyframes,
+            poses: self.poses.clone(),
+        };
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.extend(snapshot::encode(&file, compression)?);
+        persistence::write(path, bytes)
+    }
+
+    /// Read a replay from a file written by `save`, which may come from anywhere
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = persistence::read(path)?;
+        let Some(encoded) = bytes.strip_prefix(Self::MAGIC) else {
+            let message = "not a replay, or one from another version of the game";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        };
+        let file: ReplayFile = snapshot::decode_within(encoded, Self::MAX_FILE)?;
+        let mut keyframes = BTreeMap::new();
+        let mut previous: Option<World> = None;
+        for (tick, bytes) in file.keyframes {
+            let world = match &previous {
+                None => snapshot::decode_within(&bytes, snapshot::MAX_WORLD)?,
+                Some(base) => snapshot::decode_delta_within(base, &bytes, snapshot::MAX_WORLD)?,
+            };
+            keyframes.insert(tick, world.clone());
+            previous = Some(world);
+        }
+        let Some(last) = previous.filter(|_| keyframes.contains_key(&0)) else {
+            let message = "the replay doesn't start with a snapshot";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        };
+        Ok(Replay {
+            frames: file.frames,
+            keyframes,
+            events: file.events,
+            keyframe_interval: file.keyframe_interval,
+            counts: EventKind::ALL.map(|kind| kind.count(&last)),
+            poses: file.poses,
+        })
+    }
+
+    /// The latest snapshot taken at or before the given tick. Every replay has one
+    /// at tick 0, from `record` or checked by `load`.
+    fn keyframe_before(&self, tick: usize) -> (usize, &World) {
+        self.keyframes
+            .range(..=tick)
+            .next_back()
+            .map(|(tick, world)| (*tick, world))
+            .expect("replays start with a snapshot, checked when they're loaded")
+    }
+}
+
+/// A replay as it's written to a file, with every snapshot but the first
+/// stored as the difference to the one before
+#[derive(Serialize, Deserialize)]
+struct ReplayFile {
+    frames: Vec<Frame>,
+    events: Vec<ReplayEvent>,
+    keyframe_interval: usize,
+    /// The encoded snapshots by the number of frames played before they were taken
+    keyframes: Vec<(usize, Vec<u8>)>,
+    poses: Vec<Pose>,
+}
+
+/// Where the jet pod of the world is right now
+fn pose(world: &World) -> Pose {
+    Pose {
+        time: world.clock(),
+        position: world.jetman.body.position,
+        heading: world.jetman.heading,
+    }
+}
+
+/// Plays a replay back with a timeline to scrub through it
+pub struct ReplayPlayer {
+    replay: Replay,
+    /// The world as it was after `tick` frames
+    world: World,
+    tick: usize,
+    playing: bool,
+    /// How much faster than real time the replay plays
+    speed: f32,
+    /// The real time in seconds played back but not yet made up for by frames
+    backlog: f32,
+}
+
+impl ReplayPlayer {
+    /// The slowest and the fastest playback speeds
+    const SPEEDS: (f32, f32) = (0.125, 8.0);
+
+    /// Start playing a replay from the beginning
+    pub fn new(replay: Replay) -> Self {
+        let world = replay.keyframe_before(0).1.clone();
+        ReplayPlayer {
+            replay,
+            world,
+            tick: 0,
+            playing: true,
+            speed: 1.0,
+            backlog: 0.0,
+        }
+    }
+
+    /// The number of frames played so far
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+
+    /// The world as it is at the current point of the replay
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Jump to the given tick, starting from the latest snapshot before it
+    pub fn seek(&mut self, tick: usize) {
+        let tick = tick.min(self.replay.len());
+        // going forward from where we are beats starting from an older snapshot
+        let (start, snapshot) = self.replay.keyframe_before(tick);
+        if tick < self.tick || start > self.tick {
+            self.world = snapshot.clone();
+            self.tick = start;
+        }
+        while self.tick < tick {
+            self.advance();
+        }
+        self.backlog = 0.0;
+    }
+
+    /// Play the next frame, taking over any snapshot taken right after it
+    fn advance(&mut self) {
+        let Some(frame) = self.replay.frames.get(self.tick) else {
+            return;
+        };
+        self.world.step(&frame.input(), frame.seconds);
+        self.tick += 1;
+        if let Some(snapshot) = self.replay.keyframes.get(&self.tick) {
+            self.world = snapshot.clone();
+        }
+    }
+
+    /// Handle the playback controls and play as many frames as the time passed calls for
+    pub fn update(&mut self, input: &InputState, seconds: f32) {
+        if input.select {
+            // playing again from the start once the end was reached
+            if !self.playing && self.tick == self.replay.len() {
+                self.seek(0);
+            }
+            self.playing = !self.playing;
+        }
+        if input.menu_up {
+            self.speed = (self.speed * 2.0).min(Self::SPEEDS.1);
+        }
+        if input.menu_down {
+            self.speed = (self.speed / 2.0).max(Self::SPEEDS.0);
+        }
+        // stepping frame by frame pauses the playback
+        if input.menu_right {
+            self.playing = false;
+            self.seek(self.tick + 1);
+        }
+        if input.menu_left {
+            self.playing = false;
+            self.seek(self.tick.saturating_sub(1));
+        }
+        if input.jump_forward
+            && let Some(event) = self.replay.events.iter().find(|e| e.tick > self.tick)
+        {
+            self.seek(event.tick);
+        }
+        if input.jump_back
+            && let Some(event) = self.replay.events.iter().rev().find(|e| e.tick < self.tick)
+        {
+            self.seek(event.tick);
+        }
+        if input.click && Self::timeline().contains(input.pointer) {
+            self.seek(self.tick_at(input.pointer.x));
+        }
+
+        if self.playing {
+            self.backlog += seconds * self.speed;
+            while let Some(frame) = self.replay.frames.get(self.tick)
+                && self.backlog >= frame.seconds
+            {
+                self.backlog -= frame.seconds;
+                self.advance();
+            }
+            if self.tick == self.replay.len() {
+                self.playing = false;
+            }
+        }
+    }
+
+    /// The area of the screen the timeline takes up, including some room around
+    /// the bar to click on
+    fn timeline() -> Rect {
+        let zone = hud::safe_zone();
+        Rect::new(zone.x + 20.0, screen_height() - 60.0, zone.w - 40.0, 24.0)
+    }
+
+    /// The x-coordinate on the timeline of the given tick
+    fn x_at(&self, tick: usize) -> f32 {
+        let timeline = Self::timeline();
+        timeline.x + timeline.w * tick as f32 / self.replay.len().max(1) as f32
+    }
+
+    /// The tick at the given x-coordinate on the timeline, snapping to events close by
+    fn tick_at(&self, x: f32) -> usize {
+        let event = self
+            .replay
+            .events
+            .iter()
+            .find(|event| (self.x_at(event.tick) - x).abs() < 6.0);
+        if let Some(event) = event {
+            return event.tick;
+        }
+        let timeline = Self::timeline();
+        let fraction = ((x - timeline.x) / timeline.w).clamp(0.0, 1.0);
+        (fraction * self.replay.len() as f32).round() as usize
+    }
+
+    /// Draw the world at the current point of the replay, with the timeline on top
+    pub fn draw(&self) {
+        let input = self
+            .tick
+            .checked_sub(1)
+            .and_then(|tick| self.replay.frames.get(tick))
+            .map(Frame::input)
+            .unwrap_or_default();
+        self.world.draw(&input);
+
+        set_default_camera();
+        let timeline = Self::timeline();
+        let y = timeline.center().y;
+        draw_rectangle(
+            timeline.x,
+            y - 3.0,
+            timeline.w,
+            6.0,
+            Color::new(1.0, 1.0, 1.0, 0.3),
+        );
+        let played = self.x_at(self.tick);
+        draw_rectangle(timeline.x, y - 3.0, played - timeline.x, 6.0, WHITE);
+        for event in &self.replay.events {
+            let x = self.x_at(event.tick);
+            draw_line(x, y - 10.0, x, y + 10.0, 3.0, event.kind.color());
+        }
+        draw_circle(played, y, 7.0, YELLOW);
+
+        let state = if self.playing { "PLAYING" } else { "PAUSED" };
+        let text = format!(
+            "REPLAY  {state}  {}  x{}  frame {}/{}",
+            hud::format_time(self.world.time()),
+            self.speed,
+            self.tick,
+            self.replay.len()
+        );
+        draw_text(&text, timeline.x, timeline.y - 6.0, 20.0, WHITE);
+        draw_text(
+            "Enter play/pause, Left/Right step, Up/Down speed, PgUp/PgDn events, Escape back",
+            timeline.x,
+            timeline.bottom() + 18.0,
+            18.0,
+            GRAY,
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use macroquad::prelude::*;
+
+use crate::terrain::Terrain;
+
+/// The size of a grid cell used for route finding
+const CELL: f32 = 20.0;
+/// The free space required around the pod for a cell to be passable
+const CLEARANCE: f32 = 12.0;
+/// How far the search area reaches beyond the terrain and the stops
+const MARGIN: f32 = 200.0;
+
+/// A coarse grid over the free space of a level
+struct Grid {
+    origin: Vec2,
+    width: i32,
+    height: i32,
+    blocked: Vec<bool>,
+}
+
+impl Grid {
+    /// Rasterize the terrain into a grid covering the terrain and the given points
+    fn new(terrain: &[Terrain], points: &[Vec2]) -> Self {
+        let corners = terrain
+            .iter()
+            .flat_map(|t| t.outline())
+            .flat_map(|(a, b)| [a, b])
+            .chain(points.iter().copied());
+        let (min, max) = corners.fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), p| (min.min(p), max.max(p)),
+        );
+        let origin = min - Vec2::splat(MARGIN);
+        let size = max - min + Vec2::splat(2.0 * MARGIN);
+        let width = (size.x / CELL).ceil() as i32;
+        let height = (size.y / CELL).ceil() as i32;
+
+        let probes = [
+            Vec2::ZERO,
+            Vec2::new(CLEARANCE, 0.0),
+            Vec2::new(-CLEARANCE, 0.0),
+            Vec2::new(0.0, CLEARANCE),
+            Vec2::new(0.0, -CLEARANCE),
+        ];
+        let mut blocked = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let center = origin + Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * CELL;
+                blocked.push(
+                    probes
+                        .iter()
+                        .any(|&offset| terrain.iter().any(|t| t.contains(center + offset))),
+                );
+            }
+        }
+        Grid {
+            origin,
+            width,
+            height,
+            blocked,
+        }
+    }
+
+    /// The cell containing a point
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        let p = (point - self.origin) / CELL;
+        (p.x.floor() as i32, p.y.floor() as i32)
+    }
+
+    /// The center of a cell
+    fn center(&self, (x, y): (i32, i32)) -> Vec2 {
+        self.origin + Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * CELL
+    }
+
+    /// Check whether the pod can pass through a cell
+    fn is_free(&self, (x, y): (i32, i32)) -> bool {
+        x >= 0
+            && y >= 0
+            && x < self.width
+            && y < self.height
+            && !self.blocked[(y * self.width + x) as usize]
+    }
+
+    /// Find the shortest path of cells between two points with A*
+    fn search(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.cell(from);
+        let goal = self.cell(to);
+        let heuristic = |(x, y): (i32, i32)| {
+            let (dx, dy) = ((x - goal.0).abs(), (y - goal.1).abs());
+            10 * dx.max(dy) + 4 * dx.min(dy)
+        };
+
+        let mut open = BinaryHeap::new();
+        let mut came_from = HashMap::new();
+        let mut cost = HashMap::new();
+        open.push(Reverse((heuristic(start), start)));
+        cost.insert(start, 0);
+
+        while let Some(Reverse((_, current))) = open.pop() {
+            if current == goal {
+                let mut path = vec![to];
+                let mut cell = current;
+                while let Some(&previous) = came_from.get(&cell) {
+                    path.push(self.center(cell));
+                    cell = previous;
+                }
+                path.push(from);
+                path.reverse();
+                return Some(path);
+            }
+            for (dx, dy) in [
+                (1, 0),
+                (-1, 0),
+                (0, 1),
+                (0, -1),
+                (1, 1),
+                (1, -1),
+                (-1, 1),
+                (-1, -1),
+            ] {
+                let next = (current.0 + dx, current.1 + dy);
+                // stops right next to terrain, like teleporters on the ground, count as reachable
+                if next != goal && !self.is_free(next) {
+                    continue;
+                }
+                // don't cut corners diagonally past blocked cells
+                if dx != 0
+                    && dy != 0
+                    && !(self.is_free((current.0 + dx, current.1))
+                        && self.is_free((current.0, current.1 + dy)))
+                {
+                    continue;
+                }
+                let step = if dx != 0 && dy != 0 { 14 } else { 10 };
+                let new_cost = cost[&current] + step;
+                if cost.get(&next).is_none_or(|&c| new_cost < c) {
+                    cost.insert(next, new_cost);
+                    came_from.insert(next, current);
+                    open.push(Reverse((new_cost + heuristic(next), next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Find a coarse route through the free space of the terrain that visits
+/// all the stops in order. Returns `None` if any stop can't be reached.
+pub fn find_route(terrain: &[Terrain], stops: &[Vec2]) -> Option<Vec<Vec2>> {
+    let grid = Grid::new(terrain, stops);
+    let mut route: Vec<Vec2> = vec![];
+    for leg in stops.windows(2) {
+        let path = grid.search(leg[0], leg[1])?;
+        route.extend(path.into_iter().skip(if route.is_empty() { 0 } else { 1 }));
+    }
+    Some(route)
+}
+
+/// Draw a route as a dashed hint line
+pub fn draw_route(route: &[Vec2]) {
+    let color = Color::new(1.0, 1.0, 0.4, 0.5);
+    for (i, leg) in route.windows(2).enumerate() {
+        if i % 2 == 0 {
+            draw_line(leg[0].x, leg[0].y, leg[1].x, leg[1].y, 2.0, color);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::sync::{Arc, Mutex};
+
+use macroquad::prelude::*;
+use rhai::{AST, Dynamic, Engine, EvalAltResult, FuncArgs, Scope};
+use serde::{Deserialize, Serialize};
+
+use crate::triggers::TriggerEvent;
+
+/// What a script asks of the world. Scripts can't touch the world themselves,
+/// so what they ask for is carried out once they return.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    /// Put a cargo item at the given position
+    SpawnItem(Vec2),
+    /// Put a bomb with the given fuse in seconds at the given position
+    SpawnBomb(Vec2, f32),
+    /// Move the terrain element with the given index, counting in the order of the
+    /// level file whatever has been blasted away since, by the given offset
+    MoveTerrain(usize, Vec2),
+    /// Pull everything down with the given strength
+    SetGravity(f32),
+    /// Show a line of text in the middle of the screen for a while
+    ShowText(String),
+    /// Call the script function with the given name every so many seconds. Scripts
+    /// keep their timers to themselves, so this never reaches the world.
+    Every(f32, String),
+}
+
+/// What a script gets to know about the world while it runs
+#[derive(Clone, Copy, Default)]
+pub struct Facts {
+    /// The time in seconds since the start of the level
+    pub time: f32,
+    /// The number of cargo items delivered
+    pub delivered: u32,
+    pub pod: Vec2,
+}
+
+/// A function of a script called over and over
+#[derive(Clone, Serialize, Deserialize)]
+struct Timer {
+    function: String,
+    interval: f32,
+    /// The time in seconds until the next call
+    left: f32,
+}
+
+/// A script compiled and ready to run, along with the engine running it
+struct Compiled {
+    engine: Engine,
+    ast: AST,
+    commands: Arc<Mutex<Vec<Command>>>,
+    facts: Arc<Mutex<Facts>>,
+}
+
+/// A Rhai script attached to a level, running its top level once at the start and
+/// then its event functions as things happen: `on_enter(trigger)` and
+/// `on_exit(trigger)` when Jetman or an item passes through a trigger, given by its
+/// index, and `on_deliver(delivered)` whenever an item is delivered. Calling
+/// `every(seconds, "name")` has the function of that name called every so many seconds.
+///
+/// Scripts change the world with `spawn_item(x, y)`, `spawn_bomb(x, y, fuse)`,
+/// `move_terrain(index, dx, dy)`, `set_gravity(strength)` and `message(text)`, and
+/// find out about it with `time()`, `delivered()`, `pod_x()` and `pod_y()`.
+#[derive(Serialize, Deserialize)]
+pub struct Script {
+    source: String,
+    timers: Vec<Timer>,
+    /// Whether the top level has run
+    started: bool,
+    /// The number of items delivered the last time the script heard of it
+    delivered: u32,
+    /// The compiled script, which snapshots leave out, so it's compiled again
+    /// from the source after restoring one
+    #[serde(skip)]
+    compiled: Option<Arc<Compiled>>,
+}
+
+impl Script {
+    /// The most operations a single run of a script may take, so a script stuck in
+    /// a loop can't hang the game
+    const MAX_OPERATIONS: u64 = 100_000;
+
+    /// Compile a script from its source
+    pub fn new(source: &str) -> Result<Self, String> {
+        let mut script = Script {
+            source: source.to_string(),
+            timers: vec![],
+            started: false,
+            delivered: 0,
+            compiled: None,
+        };
+        script.compiled()?;
+        Ok(script)
+    }
+
+    /// The compiled script, compiling it if it hasn't been yet
+    fn compiled(&mut self) -> Result<Arc<Compiled>, String> {
+        if let Some(compiled) = &self.compiled {
+            return Ok(compiled.clone());
+        }
+        let commands = Arc::new(Mutex::new(vec![]));
+        let facts = Arc::new(Mutex::new(Facts::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(Self::MAX_OPERATIONS);
+        register_api(&mut engine, &commands, &facts);
+        let ast = engine
+            .compile(&self.source)
+            .map_err(|error| format!("script error: {error}"))?;
+        let compiled = Arc::new(Compiled {
+            engine,
+            ast,
+            commands,
+            facts,
+        });
+        self.compiled = Some(compiled.clone());
+        Ok(compiled)
+    }
+
+    /// Run the top level of the script, unless it has run already
+    pub fn start(&mut self, facts: Facts) -> Result<Vec<Command>, String> {
+        if self.started {
+            return Ok(vec![]);
+        }
+        self.started = true;
+        let compiled = self.compiled()?;
+        *compiled.facts.lock().unwrap() = facts;
+        let result = compiled.engine.run_ast(&compiled.ast);
+        let commands = self.take_commands(&compiled);
+        result.map_err(|error| format!("script error: {error}"))?;
+        Ok(commands)
+    }
+
+    /// Call a function of the script, if it has one of that name taking that many arguments
+    pub fn call(
+        &mut self,
+        function: &str,
+        args: impl FuncArgs,
+        facts: Facts,
+    ) -> Result<Vec<Command>, String> {
+        let compiled = self.compiled()?;
+        let mut values = vec![];
+        args.parse(&mut values);
+        let defined = compiled
+            .ast
+            .iter_functions()
+            .any(|f| f.name == function && f.params.len() == values.len());
+        if !defined {
+            return Ok(vec![]);
+        }
+        *compiled.facts.lock().unwrap() = facts;
+        let result = compiled
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &compiled.ast, function, values)
+            // whatever the function returns is of no interest
+            .map(drop);
+        let commands = self.take_commands(&compiled);
+        result.map_err(|error| format!("script error in {function}: {error}"))?;
+        Ok(commands)
+    }
+
+    /// Tell the script that Jetman or an item passed through the trigger with the given index
+    pub fn on_trigger(
+        &mut self,
+        trigger: usize,
+        event: TriggerEvent,
+        facts: Facts,
+    ) -> Result<Vec<Command>, String> {
+        let function = match event {
+            TriggerEvent::Enter => "on_enter",
+            TriggerEvent::Exit => "on_exit",
+        };
+        self.call(function, (trigger as rhai::INT,), facts)
+    }
+
+    /// Tell the script about deliveries, count down the timers and call the functions
+    /// whose time has come
+    pub fn update(&mut self, seconds: f32, facts: Facts) -> Result<Vec<Command>, String> {
+        let mut commands = vec![];
+        if facts.delivered > self.delivered {
+            self.delivered = facts.delivered;
+            let delivered = facts.delivered as rhai::INT;
+            commands.extend(self.call("on_deliver", (delivered,), facts)?);
+        }
+        let mut due = vec![];
+        for timer in self.timers.iter_mut() {
+            timer.left -= seconds;
+            if timer.left <= 0.0 {
+                timer.left += timer.interval;
+                due.push(timer.function.clone());
+            }
+        }
+        for function in due {
+            commands.extend(self.call(&function, (), facts)?);
+        }
+        Ok(commands)
+    }
+
+    /// Take what the script asked for during its last run, setting up the timers
+    /// it asked for and handing on the rest
+    fn take_commands(&mut self, compiled: &Compiled) -> Vec<Command> {
+        let commands = std::mem::take(&mut *compiled.commands.lock().unwrap());
+        commands
+            .into_iter()
+            .filter(|command| {
+                let Command::Every(interval, function) = command else {
+                    return true;
+                };
+                self.timers.push(Timer {
+                    function: function.clone(),
+                    interval: *interval,
+                    left: *interval,
+                });
+                false
+            })
+            .collect()
+    }
+}
+
+impl Clone for Script {
+    /// Copy the script along with its timers. The copy gets an engine of its own,
+    /// so copies can run side by side without their commands getting mixed up.
+    fn clone(&self) -> Self {
+        Script {
+            source: self.source.clone(),
+            timers: self.timers.clone(),
+            started: self.started,
+            delivered: self.delivered,
+            compiled: None,
+        }
+    }
+}
+
+/// Read a number given to an API function, whether the script wrote it with a
+/// decimal point or not
+fn number(value: Dynamic) -> Result<f32, Box<EvalAltResult>> {
+    let float = value.as_float().ok();
+    let int = value.as_int().ok().map(|int| int as rhai::FLOAT);
+    float
+        .or(int)
+        .map(|number| number as f32)
+        .ok_or_else(|| format!("expected a number, got a {}", value.type_name()).into())
+}
+
+/// Make the functions scripts call available to them, queueing commands and
+/// reading the facts about the world
+fn register_api(
+    engine: &mut Engine,
+    commands: &Arc<Mutex<Vec<Command>>>,
+    facts: &Arc<Mutex<Facts>>,
+) {
+    type Result = std::result::Result<(), Box<EvalAltResult>>;
+
+    let queue = commands.clone();
+    engine.register_fn("spawn_item", move |x: Dynamic, y: Dynamic| -> Result {
+        let position = vec2(number(x)?, number(y)?);
+        queue.lock().unwrap().push(Command::SpawnItem(position));
+        Ok(())
+    });
+    let queue = commands.clone();
+    engine.register_fn(
+        "spawn_bomb",
+        move |x: Dynamic, y: Dynamic, fuse: Dynamic| -> Result {
+            let position = vec2(number(x)?, number(y)?);
+            let command = Command::SpawnBomb(position, number(fuse)?);
+            queue.lock().unwrap().push(command);
+            Ok(())
+        },
+    );
+    let queue = commands.clone();
+    engine.register_fn(
+        "move_terrain",
+        move |index: rhai::INT, dx: Dynamic, dy: Dynamic| -> Result {
+            let offset = vec2(number(dx)?, number(dy)?);
+            let command = Command::MoveTerrain(index.max(0) as usize, offset);
+            queue.lock().unwrap().push(command);
+            Ok(())
+        },
+    );
+    let queue = commands.clone();
+    engine.register_fn("set_gravity", move |strength: Dynamic| -> Result {
+        let command = Command::SetGravity(number(strength)?);
+        queue.lock().unwrap().push(command);
+        Ok(())
+    });
+    let queue = commands.clone();
+    engine.register_fn("message", move |text: &str| {
+        let command = Command::ShowText(text.to_string());
+        queue.lock().unwrap().push(command);
+    });
+    let queue = commands.clone();
+    engine.register_fn("every", move |seconds: Dynamic, function: &str| -> Result {
+        let seconds = number(seconds)?;
+        if seconds <= 0.0 {
+            return Err("timers need a time above 0".into());
+        }
+        let command = Command::Every(seconds, function.to_string());
+        queue.lock().unwrap().push(command);
+        Ok(())
+    });
+
+    let known = facts.clone();
+    engine.register_fn("time", move || known.lock().unwrap().time as rhai::FLOAT);
+    let known = facts.clone();
+    engine.register_fn("delivered", move || {
+        known.lock().unwrap().delivered as rhai::INT
+    });
+    let known = facts.clone();
+    engine.register_fn("pod_x", move || known.lock().unwrap().pod.x as rhai::FLOAT);
+    let known = facts.clone();
+    engine.register_fn("pod_y", move || known.lock().unwrap().pod.y as rhai::FLOAT);
+}
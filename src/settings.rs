@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::CameraOptions;
+use crate::hud;
+use crate::persistence;
+use crate::ui::{self, Action, Bindings};
+
+/// Whether the colorblind palette is in use, as the settings say
+static COLORBLIND: AtomicBool = AtomicBool::new(false);
+
+/// The colors telling good news from bad, like an open door from a shut one
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Palette {
+    /// Green for good and red for bad
+    Standard,
+    /// Bluish green and vermillion, told apart with any kind of color blindness
+    Colorblind,
+}
+
+impl Palette {
+    /// The palette in use
+    pub fn current() -> Self {
+        if COLORBLIND.load(Ordering::Relaxed) {
+            Palette::Colorblind
+        } else {
+            Palette::Standard
+        }
+    }
+
+    /// Use the given palette from now on
+    pub fn set_current(palette: Palette) {
+        COLORBLIND.store(palette == Palette::Colorblind, Ordering::Relaxed);
+    }
+
+    /// The color of things going well, like a completed objective
+    pub fn good(&self) -> Color {
+        match self {
+            Palette::Standard => GREEN,
+            Palette::Colorblind => Color::from_hex(0x009E73),
+        }
+    }
+
+    /// The color of things going wrong, like a failed objective
+    pub fn bad(&self) -> Color {
+        match self {
+            Palette::Standard => RED,
+            Palette::Colorblind => Color::from_hex(0xD55E00),
+        }
+    }
+}
+
+/// The color of things going well in the palette in use
+pub fn good() -> Color {
+    Palette::current().good()
+}
+
+/// The color of things going wrong in the palette in use
+pub fn bad() -> Color {
+    Palette::current().bad()
+}
+
+/// The player's settings, kept in a TOML file. Settings missing from the file keep
+/// their defaults. The numbers are `f64`, so they read back in the file as they were set.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The loudness of the game's sounds, from 0.0 (silent) to 1.0
+    pub volume: f64,
+    /// How far the camera zooms in, 1.0 showing the world at its natural size
+    pub zoom: f64,
+    /// How much the zoom changes with every turn of the mouse wheel or press of a zoom key
+    pub zoom_sensitivity: f64,
+    /// Whether impacts shake the camera
+    pub screen_shake: bool,
+    /// Whether the camera pulls back while towing, to keep the cargo in view
+    pub auto_zoom: bool,
+    /// Whether the world looks like it's shown on an old CRT, with scanlines
+    pub crt: bool,
+    /// Whether bright things like thruster flames and teleporters glow
+    pub bloom: bool,
+    /// Whether explosions flash the screen
+    pub screen_flash: bool,
+    /// Whether to draw with the colorblind palette
+    pub colorblind: bool,
+    /// Whether the stability assist is on when a level starts
+    pub assist: bool,
+    /// Whether the pod points towards the mouse pointer and the left button thrusts
+    pub mouse_aim: bool,
+    /// The keys bound to each action, by the names of the actions and the keys,
+    /// like `turn-left = ["Left", "A"]`. Actions missing keep their default keys.
+    pub bindings: BTreeMap<String, Vec<String>>,
+    /// Where the widgets of the HUD are drawn
+    pub hud: hud::Layout,
+    /// The file the settings are kept in
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Settings {
+    /// The file the settings are kept in by default
+    pub const PATH: &str = "settings.toml";
+    /// The range the volume is kept in
+    pub const VOLUME: (f64, f64) = (0.0, 1.0);
+    /// The range the zoom is kept in
+    pub const ZOOM: (f64, f64) = (0.5, 2.0);
+    /// The range the zoom sensitivity is kept in
+    pub const ZOOM_SENSITIVITY: (f64, f64) = (0.05, 0.5);
+
+    /// Load the settings from the default file
+    pub fn load() -> Self {
+        Settings::load_from(Self::PATH)
+    }
+
+    /// Load the settings from the given file. A missing or broken file leaves
+    /// every setting at its default, and numbers out of range are brought back into it.
+    pub fn load_from(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let mut settings = persistence::read_to_string(&path)
+            .ok()
+            .and_then(|contents| toml::from_str::<Settings>(&contents).ok())
+            .unwrap_or_default();
+        settings.volume = settings.volume.clamp(Self::VOLUME.0, Self::VOLUME.1);
+        settings.zoom = settings.zoom.clamp(Self::ZOOM.0, Self::ZOOM.1);
+        settings.zoom_sensitivity = settings
+            .zoom_sensitivity
+            .clamp(Self::ZOOM_SENSITIVITY.0, Self::ZOOM_SENSITIVITY.1);
+        settings.path = path;
+        settings
+    }
+
+    /// Write the settings back to the file they were loaded from
+    pub fn save(&self) -> io::Result<()> {
+        let contents = toml::to_string(self).map_err(io::Error::other)?;
+        persistence::write(&self.path, contents)
+    }
+
+    /// The keys the player flies with: the default ones, with the actions the
+    /// settings bind replaced. Keys that can't be bound are skipped.
+    pub fn bindings(&self) -> Bindings {
+        let mut bindings = Bindings::new();
+        for (name, keys) in &self.bindings {
+            let Some(action) = Action::from_name(name) else {
+                continue;
+            };
+            let keys = keys.iter().filter_map(|key| ui::key_from_name(key));
+            bindings.bind(action, keys.collect());
+        }
+        bindings
+    }
+
+    /// Zoom the camera in by the given number of steps, negative for out, each step
+    /// as large as the zoom sensitivity says
+    pub fn zoom_by(&mut self, steps: f32) {
+        let factor = (1.0 + self.zoom_sensitivity).powf(steps as f64);
+        self.zoom = (self.zoom * factor).clamp(Self::ZOOM.0, Self::ZOOM.1);
+    }
+
+    /// Bind an action to the given keys, replacing its previous keys
+    pub fn bind(&mut self, action: Action, keys: &[KeyCode]) {
+        let names = keys.iter().map(|key| format!("{key:?}")).collect();
+        self.bindings.insert(action.name().to_string(), names);
+    }
+
+    /// How the camera shows the world, as the settings say
+    pub fn camera_options(&self) -> CameraOptions {
+        CameraOptions {
+            zoom: self.zoom as f32,
+            shake: self.screen_shake,
+            auto_zoom: self.auto_zoom,
+            crt: self.crt,
+            bloom: self.bloom,
+            flash: self.screen_flash,
+        }
+    }
+
+    /// The palette to draw with, as the settings say
+    pub fn palette(&self) -> Palette {
+        if self.colorblind {
+            Palette::Colorblind
+        } else {
+            Palette::Standard
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let defaults = Bindings::new();
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = defaults.keys(action).iter().map(|key| format!("{key:?}"));
+                (action.name().to_string(), keys.collect())
+            })
+            .collect();
+        Settings {
+            volume: 0.8,
+            zoom: 1.0,
+            zoom_sensitivity: 0.1,
+            screen_shake: true,
+            auto_zoom: true,
+            crt: false,
+            bloom: true,
+            screen_flash: true,
+            colorblind: false,
+            assist: false,
+            mouse_aim: false,
+            bindings,
+            hud: hud::Layout::default(),
+            path: PathBuf::from(Self::PATH),
+        }
+    }
+}
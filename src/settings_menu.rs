@@ -0,0 +1,205 @@
+use std::io;
+
+use macroquad::prelude::*;
+
+use crate::settings::Settings;
+use crate::ui::{self, Action, InputState};
+
+/// The entries of the settings menu
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Entry {
+    Volume,
+    Zoom,
+    ZoomSensitivity,
+    ScreenShake,
+    AutoZoom,
+    Crt,
+    Bloom,
+    ScreenFlash,
+    Colorblind,
+    Assist,
+    MouseAim,
+    /// The keys bound to an action
+    Binding(Action),
+}
+
+impl Entry {
+    /// All entries in the order they are listed
+    fn all() -> Vec<Entry> {
+        let options = [
+            Entry::Volume,
+            Entry::Zoom,
+            Entry::ZoomSensitivity,
+            Entry::ScreenShake,
+            Entry::AutoZoom,
+            Entry::Crt,
+            Entry::Bloom,
+            Entry::ScreenFlash,
+            Entry::Colorblind,
+            Entry::Assist,
+            Entry::MouseAim,
+        ];
+        let bindings = Action::ALL.into_iter().map(Entry::Binding);
+        options.into_iter().chain(bindings).collect()
+    }
+}
+
+/// The screen where the player changes the settings: Left and Right change numbers,
+/// Enter switches options on and off or waits for a key to bind an action to
+pub struct SettingsMenu {
+    settings: Settings,
+    entries: Vec<Entry>,
+    /// The index of the highlighted entry
+    selected: usize,
+    /// Whether the highlighted action waits for the key to bind it to
+    rebinding: bool,
+}
+
+impl SettingsMenu {
+    /// Create the menu for changing the given settings
+    pub fn new(settings: Settings) -> Self {
+        SettingsMenu {
+            settings,
+            entries: Entry::all(),
+            selected: 0,
+            rebinding: false,
+        }
+    }
+
+    /// The settings as the player changed them so far
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Write the settings to their file
+    pub fn save(&self) -> io::Result<()> {
+        self.settings.save()
+    }
+
+    /// Move the highlight and change the highlighted setting as the player says.
+    /// Returns whether the player is done with the menu.
+    pub fn update(&mut self, input: &InputState) -> bool {
+        let entry = self.entries[self.selected];
+        if self.rebinding {
+            // going back leaves the keys as they were
+            if input.back {
+                self.rebinding = false;
+            } else if let Entry::Binding(action) = entry
+                && let Some(key) = get_last_key_pressed()
+                    .filter(|key| ui::key_from_name(&format!("{key:?}")).is_some())
+            {
+                self.settings.bind(action, &[key]);
+                self.rebinding = false;
+            }
+            return false;
+        }
+
+        let count = self.entries.len();
+        if input.menu_up {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if input.menu_down {
+            self.selected = (self.selected + 1) % count;
+        }
+        let direction = match (input.menu_left, input.menu_right) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        };
+        let settings = &mut self.settings;
+        let adjust = |value: &mut f64, step: f64, (low, high): (f64, f64)| {
+            if direction == 0.0 {
+                return;
+            }
+            // counting in whole steps keeps the value on the grid, so it reads back as
+            // 0.3 rather than 0.30000000000000004
+            let steps = (*value / step).round() + direction;
+            *value = (steps / step.recip()).clamp(low, high);
+        };
+        match entry {
+            Entry::Volume => adjust(&mut settings.volume, 0.1, Settings::VOLUME),
+            Entry::Zoom => adjust(&mut settings.zoom, 0.1, Settings::ZOOM),
+            Entry::ZoomSensitivity => adjust(
+                &mut settings.zoom_sensitivity,
+                0.05,
+                Settings::ZOOM_SENSITIVITY,
+            ),
+            Entry::ScreenShake if input.select => settings.screen_shake = !settings.screen_shake,
+            Entry::AutoZoom if input.select => settings.auto_zoom = !settings.auto_zoom,
+            Entry::Crt if input.select => settings.crt = !settings.crt,
+            Entry::Bloom if input.select => settings.bloom = !settings.bloom,
+            Entry::ScreenFlash if input.select => settings.screen_flash = !settings.screen_flash,
+            Entry::Colorblind if input.select => settings.colorblind = !settings.colorblind,
+            Entry::Assist if input.select => settings.assist = !settings.assist,
+            Entry::MouseAim if input.select => settings.mouse_aim = !settings.mouse_aim,
+            Entry::Binding(_) if input.select => self.rebinding = true,
+            _ => {}
+        }
+        input.back
+    }
+
+    /// The name of an entry and its value as shown in the menu
+    fn describe(&self, entry: Entry) -> (String, String) {
+        let settings = &self.settings;
+        let switch = |on: bool| if on { "On" } else { "Off" }.to_string();
+        match entry {
+            Entry::Volume => (
+                "Volume".to_string(),
+                format!("{:.0}%", settings.volume * 100.0),
+            ),
+            Entry::Zoom => ("Zoom".to_string(), format!("x{:.1}", settings.zoom)),
+            Entry::ZoomSensitivity => (
+                "Zoom sensitivity".to_string(),
+                format!("{:.2}", settings.zoom_sensitivity),
+            ),
+            Entry::ScreenShake => ("Screen shake".to_string(), switch(settings.screen_shake)),
+            Entry::AutoZoom => ("Auto zoom".to_string(), switch(settings.auto_zoom)),
+            Entry::Crt => ("CRT screen".to_string(), switch(settings.crt)),
+            Entry::Bloom => ("Bloom".to_string(), switch(settings.bloom)),
+            Entry::ScreenFlash => ("Screen flash".to_string(), switch(settings.screen_flash)),
+            Entry::Colorblind => (
+                "Colorblind palette".to_string(),
+                switch(settings.colorblind),
+            ),
+            Entry::Assist => ("Stability assist".to_string(), switch(settings.assist)),
+            Entry::MouseAim => ("Mouse aim".to_string(), switch(settings.mouse_aim)),
+            Entry::Binding(action) => {
+                let keys = if self.rebinding && entry == self.entries[self.selected] {
+                    "press a key...".to_string()
+                } else {
+                    settings.bindings().label(action)
+                };
+                (action.name().replace('-', " "), keys)
+            }
+        }
+    }
+
+    /// Draw the settings with their values, the highlighted one in yellow
+    pub fn draw(&self) {
+        clear_background(BLACK);
+        let x = 60.0;
+        let mut y = 80.0;
+        draw_text("SETTINGS", x, y, 40.0, WHITE);
+        y += 30.0;
+        draw_text(
+            "Left and Right change numbers, Enter switches options or rebinds keys, \
+             Escape saves and goes back",
+            x,
+            y,
+            18.0,
+            GRAY,
+        );
+        y += 40.0;
+        for (index, entry) in self.entries.iter().enumerate() {
+            let color = if index == self.selected {
+                YELLOW
+            } else {
+                WHITE
+            };
+            let (name, value) = self.describe(*entry);
+            draw_text(&name, x, y, 22.0, color);
+            draw_text(&value, x + 260.0, y, 22.0, color);
+            y += 28.0;
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::persistence;
+use crate::physics::Jetman;
+use crate::settings;
+use crate::ui::InputState;
+
+/// The parts of the jet pod that can be upgraded in the shop
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Upgrade {
+    Thrust,
+    FuelTank,
+    LinkLength,
+    Armor,
+    Winch,
+}
+
+impl Upgrade {
+    /// All upgrades in the order the shop lists them
+    pub const ALL: [Upgrade; 5] = [
+        Upgrade::Thrust,
+        Upgrade::FuelTank,
+        Upgrade::LinkLength,
+        Upgrade::Armor,
+        Upgrade::Winch,
+    ];
+    /// The number of times each upgrade can be bought
+    pub const MAX_LEVEL: u32 = 5;
+
+    /// The name of the upgrade as shown in the shop
+    pub fn name(&self) -> &'static str {
+        match self {
+            Upgrade::Thrust => "Thrust power",
+            Upgrade::FuelTank => "Fuel tank",
+            Upgrade::LinkLength => "Beam length",
+            Upgrade::Armor => "Armor",
+            Upgrade::Winch => "Winch speed",
+        }
+    }
+
+    /// The credits the next level of the upgrade costs, going up with every level bought
+    pub fn price(&self, level: u32) -> u32 {
+        500 * (level + 1)
+    }
+}
+
+/// How far each part of the jet pod has been upgraded, from 0 for the stock part
+/// up to `Upgrade::MAX_LEVEL`
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Upgrades {
+    pub thrust: u32,
+    pub fuel_tank: u32,
+    pub link_length: u32,
+    pub armor: u32,
+    pub winch: u32,
+}
+
+impl Upgrades {
+    /// The level an upgrade has been bought to
+    pub fn level(&self, upgrade: Upgrade) -> u32 {
+        match upgrade {
+            Upgrade::Thrust => self.thrust,
+            Upgrade::FuelTank => self.fuel_tank,
+            Upgrade::LinkLength => self.link_length,
+            Upgrade::Armor => self.armor,
+            Upgrade::Winch => self.winch,
+        }
+    }
+
+    /// The level of an upgrade, to change
+    fn level_mut(&mut self, upgrade: Upgrade) -> &mut u32 {
+        match upgrade {
+            Upgrade::Thrust => &mut self.thrust,
+            Upgrade::FuelTank => &mut self.fuel_tank,
+            Upgrade::LinkLength => &mut self.link_length,
+            Upgrade::Armor => &mut self.armor,
+            Upgrade::Winch => &mut self.winch,
+        }
+    }
+
+    /// Fit a jet pod with the upgrades
+    pub fn fit(&self, jetman: &mut Jetman) {
+        jetman.thrust = self.thrust();
+        jetman.max_fuel = self.max_fuel();
+        jetman.fuel = jetman.max_fuel;
+        jetman.max_link_distance = self.max_link_distance();
+        jetman.armor = self.armor();
+        jetman.winch_speed = self.winch_speed();
+    }
+
+    /// The force of the thruster out of the water
+    pub fn thrust(&self) -> f32 {
+        Jetman::THRUST * (1.0 + 0.1 * self.thrust as f32)
+    }
+
+    /// The fuel the tank holds
+    pub fn max_fuel(&self) -> f32 {
+        100.0 + 20.0 * self.fuel_tank as f32
+    }
+
+    /// The longest length the winch can reel the tractor beam out to
+    pub fn max_link_distance(&self) -> f32 {
+        150.0 + 25.0 * self.link_length as f32
+    }
+
+    /// The share of the damage the armor absorbs
+    pub fn armor(&self) -> f32 {
+        0.1 * self.armor as f32
+    }
+
+    /// The speed at which the winch reels the tractor beam in and out
+    pub fn winch_speed(&self) -> f32 {
+        1.0 + 0.25 * self.winch as f32
+    }
+
+    /// The effect of the level an upgrade has been bought to, as shown in the shop
+    fn describe(&self, upgrade: Upgrade) -> String {
+        match upgrade {
+            Upgrade::Thrust => format!("x{:.1}", self.thrust() / Jetman::THRUST),
+            Upgrade::FuelTank => format!("{:.0}", self.max_fuel()),
+            Upgrade::LinkLength => format!("{:.0}", self.max_link_distance()),
+            Upgrade::Armor => format!("{:.0}%", self.armor() * 100.0),
+            Upgrade::Winch => format!("x{:.2}", self.winch_speed()),
+        }
+    }
+}
+
+/// What the player has earned and bought over all campaigns, kept per player profile
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// The score earned in campaigns and not spent in the shop yet
+    pub credits: u32,
+    /// The upgrades bought for the jet pod
+    pub upgrades: Upgrades,
+    /// The most waves held out against in survival mode
+    pub best_wave: u32,
+    /// The file the profile is kept in
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Profile {
+    /// Load the given player profile
+    pub fn load(profile: &str) -> Self {
+        Profile::load_from(Path::new("profiles").join(profile).join("profile.toml"))
+    }
+
+    /// Load the profile from the given file. A missing or broken file makes for
+    /// a fresh profile without credits or upgrades.
+    pub fn load_from(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let mut profile = persistence::read_to_string(&path)
+            .ok()
+            .and_then(|contents| toml::from_str::<Profile>(&contents).ok())
+            .unwrap_or_default();
+        for upgrade in Upgrade::ALL {
+            let level = profile.upgrades.level_mut(upgrade);
+            *level = (*level).min(Upgrade::MAX_LEVEL);
+        }
+        profile.path = path;
+        profile
+    }
+
+    /// Write the profile back to the file it was loaded from
+    pub fn save(&self) -> io::Result<()> {
+        let contents = toml::to_string(self).map_err(io::Error::other)?;
+        persistence::write(&self.path, contents)
+    }
+
+    /// Buy the next level of an upgrade
+    pub fn buy(&mut self, upgrade: Upgrade) -> Result<(), String> {
+        let level = self.upgrades.level(upgrade);
+        if level >= Upgrade::MAX_LEVEL {
+            return Err(format!("{} is fully upgraded", upgrade.name()));
+        }
+        let price = upgrade.price(level);
+        if price > self.credits {
+            return Err(format!("{} costs {price} credits", upgrade.name()));
+        }
+        self.credits -= price;
+        *self.upgrades.level_mut(upgrade) += 1;
+        Ok(())
+    }
+}
+
+/// The screen between the levels of a campaign where the player spends credits
+/// on upgrades: Enter buys the highlighted upgrade, Escape leaves the shop
+pub struct Shop {
+    /// The index of the highlighted upgrade
+    selected: usize,
+    /// Why the last purchase didn't go through, if it didn't
+    refusal: Option<String>,
+}
+
+impl Shop {
+    /// Open the shop
+    pub fn new() -> Self {
+        Shop {
+            selected: 0,
+            refusal: None,
+        }
+    }
+
+    /// Move the highlight and buy upgrades as the player says.
+    /// Returns whether the player is done shopping.
+    pub fn update(&mut self, input: &InputState, profile: &mut Profile) -> bool {
+        let count = Upgrade::ALL.len();
+        if input.menu_up {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if input.menu_down {
+            self.selected = (self.selected + 1) % count;
+        }
+        if input.select {
+            self.refusal = profile.buy(Upgrade::ALL[self.selected]).err();
+        }
+        input.back
+    }
+
+    /// Draw the upgrades with their levels and prices, the highlighted one in yellow
+    pub fn draw(&self, profile: &Profile) {
+        clear_background(BLACK);
+        let x = 60.0;
+        let mut y = 80.0;
+        draw_text("SHOP", x, y, 40.0, WHITE);
+        y += 30.0;
+        draw_text(
+            "Enter buys the highlighted upgrade, Escape goes back",
+            x,
+            y,
+            18.0,
+            GRAY,
+        );
+        y += 30.0;
+        let credits = format!("Credits: {}", profile.credits);
+        draw_text(&credits, x, y, 24.0, settings::good());
+        y += 40.0;
+        let upgrades = &profile.upgrades;
+        for (index, upgrade) in Upgrade::ALL.iter().enumerate() {
+            let color = if index == self.selected {
+                YELLOW
+            } else {
+                WHITE
+            };
+            let level = upgrades.level(*upgrade);
+            let price = if level >= Upgrade::MAX_LEVEL {
+                "sold out".to_string()
+            } else {
+                format!("{} credits", upgrade.price(level))
+            };
+            draw_text(upgrade.name(), x, y, 22.0, color);
+            draw_text(&upgrades.describe(*upgrade), x + 200.0, y, 22.0, color);
+            for pip in 0..Upgrade::MAX_LEVEL {
+                let filled = pip < level;
+                let pip_x = x + 300.0 + pip as f32 * 16.0;
+                if filled {
+                    draw_rectangle(pip_x, y - 14.0, 12.0, 14.0, color);
+                } else {
+                    draw_rectangle_lines(pip_x, y - 14.0, 12.0, 14.0, 1.0, GRAY);
+                }
+            }
+            draw_text(&price, x + 400.0, y, 22.0, color);
+            y += 28.0;
+        }
+        if let Some(refusal) = &self.refusal {
+            draw_text(refusal, x, y + 20.0, 20.0, settings::bad());
+        }
+    }
+}
+
+impl Default for Shop {
+    fn default() -> Self {
+        Shop::new()
+    }
+}
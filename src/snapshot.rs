@@ -0,0 +1,189 @@
+use std::error::Error;
+use std::io::{self, Read, Write};
+
+use bincode::Options;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use macroquad::prelude::Rect;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// The most bytes a world decodes from where it can't be trusted, like the network
+/// or a shared replay, far more than any level takes
+pub const MAX_WORLD: usize = 64 << 20;
+
+/// Stands in for macroquad's `Rect`, which serde doesn't know about
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Rect")]
+pub(crate) struct RectDef {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+/// How hard to squeeze encoded snapshots
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Compression {
+    /// Store the bytes as they are
+    None,
+    /// Compress quickly, saving most of the space
+    #[default]
+    Fast,
+    /// Compress as small as possible, taking longer
+    Best,
+    /// Compress with LZ4, which is even faster but saves less space
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+impl Compression {
+    /// The marker written ahead of the encoded bytes. `Best` came after LZ4 took 2.
+    fn marker(&self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Fast => 1,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => 2,
+            Compression::Best => 3,
+        }
+    }
+
+    /// Compress the bytes, putting the marker in front
+    fn compress(&self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut compressed = vec![self.marker()];
+        match self {
+            Compression::None => compressed.extend(bytes),
+            Compression::Fast | Compression::Best => {
+                let level = if *self == Compression::Best {
+                    flate2::Compression::best()
+                } else {
+                    flate2::Compression::fast()
+                };
+                let mut encoder = DeflateEncoder::new(compressed, level);
+                encoder.write_all(bytes)?;
+                compressed = encoder.finish()?;
+            }
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => compressed.extend(lz4_flex::compress_prepend_size(bytes)),
+        }
+        Ok(compressed)
+    }
+
+    /// Decompress bytes made by `compress`, however they were compressed, as long as
+    /// they don't inflate to more than `limit` bytes
+    fn decompress(compressed: &[u8], limit: usize) -> io::Result<Vec<u8>> {
+        let Some((&marker, bytes)) = compressed.split_first() else {
+            return Err(invalid("no data"));
+        };
+        let too_large = || invalid(format!("the data inflates to more than {limit} bytes"));
+        match marker {
+            0 if bytes.len() > limit => Err(too_large()),
+            0 => Ok(bytes.to_vec()),
+            1 | 3 => {
+                let mut inflated = vec![];
+                DeflateDecoder::new(bytes)
+                    .take((limit as u64).saturating_add(1))
+                    .read_to_end(&mut inflated)?;
+                if inflated.len() > limit {
+                    return Err(too_large());
+                }
+                Ok(inflated)
+            }
+            #[cfg(feature = "lz4")]
+            2 => {
+                // the size comes first, so there's no need to inflate to know it
+                let size = bytes
+                    .first_chunk::<4>()
+                    .map_or(0, |size| u32::from_le_bytes(*size) as usize);
+                if size > limit {
+                    return Err(too_large());
+                }
+                lz4_flex::decompress_size_prepended(bytes).map_err(invalid)
+            }
+            #[cfg(not(feature = "lz4"))]
+            2 => Err(invalid(
+                "compressed with LZ4, which this build doesn't support",
+            )),
+            _ => Err(invalid(format!("unknown compression {marker}"))),
+        }
+    }
+}
+
+/// An error about data that can't be decoded
+fn invalid(error: impl Into<Box<dyn Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// The way `bincode::serialize` lays out values, reading no more than `limit` bytes
+/// so a length read from untrusted bytes can't make it allocate more. Bincode only
+/// minds the limit when reading from a reader, not from a slice.
+fn options(limit: usize) -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(limit as u64)
+}
+
+/// Encode a value, like a snapshot of the world, into bytes, compressed as asked
+pub fn encode<T: Serialize>(value: &T, compression: Compression) -> io::Result<Vec<u8>> {
+    let bytes = bincode::serialize(value).map_err(io::Error::other)?;
+    compression.compress(&bytes)
+}
+
+/// Decode a value from bytes made by `encode`, however they were compressed
+pub fn decode<T: DeserializeOwned>(encoded: &[u8]) -> io::Result<T> {
+    decode_within(encoded, usize::MAX)
+}
+
+/// Decode a value from bytes made by `encode`, unless they inflate to more than `limit`
+/// bytes or the value would take more than that, for bytes from where they can't be trusted
+pub fn decode_within<T: DeserializeOwned>(encoded: &[u8], limit: usize) -> io::Result<T> {
+    let bytes = Compression::decompress(encoded, limit)?;
+    options(limit)
+        .deserialize_from(bytes.as_slice())
+        .map_err(invalid)
+}
+
+/// Encode a value as the difference to an earlier one, compressed as asked.
+/// Consecutive snapshots of the world mostly agree, so their difference is
+/// mostly zeros, which compress far better than the snapshot itself.
+pub fn encode_delta<T: Serialize>(
+    base: &T,
+    value: &T,
+    compression: Compression,
+) -> io::Result<Vec<u8>> {
+    let base = bincode::serialize(base).map_err(io::Error::other)?;
+    let bytes = bincode::serialize(value).map_err(io::Error::other)?;
+    compression.compress(&xor(&base, &bytes))
+}
+
+/// Decode a value from bytes made by `encode_delta` against the same base
+pub fn decode_delta<T: Serialize + DeserializeOwned>(base: &T, encoded: &[u8]) -> io::Result<T> {
+    decode_delta_within(base, encoded, usize::MAX)
+}
+
+/// Decode a value from bytes made by `encode_delta` against the same base, unless
+/// they inflate to more than `limit` bytes or the value would take more than that,
+/// for bytes from where they can't be trusted
+pub fn decode_delta_within<T: Serialize + DeserializeOwned>(
+    base: &T,
+    encoded: &[u8],
+    limit: usize,
+) -> io::Result<T> {
+    let base = bincode::serialize(base).map_err(io::Error::other)?;
+    let delta = Compression::decompress(encoded, limit)?;
+    options(limit)
+        .deserialize_from(xor(&base, &delta).as_slice())
+        .map_err(invalid)
+}
+
+/// Combine two byte strings byte by byte, as long as the second one.
+/// Applying the result to the first one again gives back the second.
+fn xor(base: &[u8], bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| byte ^ base.get(index).copied().unwrap_or(0))
+        .collect()
+}
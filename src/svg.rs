@@ -0,0 +1,461 @@
+use std::f32::consts::TAU;
+
+use macroquad::math::Affine2;
+use macroquad::prelude::*;
+use roxmltree::{Document, Node};
+
+use crate::terrain::Terrain;
+
+/// The longest a straight piece of a flattened curve gets, in world units
+const FLATNESS: f32 = 8.0;
+
+/// Read the terrain drawn in an SVG image, as made with Inkscape for example.
+///
+/// Every path, polygon, polyline, rectangle, circle and ellipse becomes terrain, with
+/// curves flattened into straight pieces and transforms applied. Closed shapes become
+/// polygons, open ones a line for each of their pieces. One unit in the image is one
+/// unit in the world.
+pub fn parse(source: &str) -> Result<Vec<Terrain>, String> {
+    let document = Document::parse(source).map_err(|error| error.to_string())?;
+    let mut terrain = vec![];
+    for node in document.descendants().filter(Node::is_element) {
+        let outlines = match node.tag_name().name() {
+            "path" => parse_path(node.attribute("d").unwrap_or_default())?,
+            "polygon" => vec![(numbers(node.attribute("points").unwrap_or_default())?, true)],
+            "polyline" => vec![(
+                numbers(node.attribute("points").unwrap_or_default())?,
+                false,
+            )],
+            "rect" => vec![(rect(node)?, true)],
+            "circle" => {
+                let radius = length(node, "r")?;
+                vec![(ellipse(node, vec2(radius, radius))?, true)]
+            }
+            "ellipse" => {
+                let radii = vec2(length(node, "rx")?, length(node, "ry")?);
+                vec![(ellipse(node, radii)?, true)]
+            }
+            _ => continue,
+        };
+        let transform = transform(node)?;
+        for (points, closed) in outlines {
+            let points = points
+                .iter()
+                .map(|point| transform.transform_point2(*point))
+                .collect::<Vec<_>>();
+            if closed && points.len() >= 3 {
+                terrain.push(Terrain::polygon(points));
+            } else {
+                terrain.extend(
+                    points
+                        .windows(2)
+                        .map(|line| Terrain::line(line[0].x, line[0].y, line[1].x, line[1].y)),
+                );
+            }
+        }
+    }
+    Ok(terrain)
+}
+
+/// The transform from an element's coordinates to the image's,
+/// taking the transforms of all the groups it is in into account
+fn transform(node: Node) -> Result<Affine2, String> {
+    let mut transform = Affine2::IDENTITY;
+    for ancestor in node.ancestors().filter(Node::is_element) {
+        if let Some(attribute) = ancestor.attribute("transform") {
+            transform = parse_transform(attribute)? * transform;
+        }
+    }
+    Ok(transform)
+}
+
+/// Parse a transform attribute like `translate(10,20) scale(2)`
+fn parse_transform(attribute: &str) -> Result<Affine2, String> {
+    let mut transform = Affine2::IDENTITY;
+    for part in attribute.split_terminator(')') {
+        let Some((name, arguments)) = part.split_once('(') else {
+            continue;
+        };
+        let values = Tokens::new(arguments).numbers()?;
+        let value = |index: usize, default: f32| values.get(index).copied().unwrap_or(default);
+        let step = match name.trim().trim_start_matches(',').trim() {
+            "translate" => Affine2::from_translation(vec2(value(0, 0.0), value(1, 0.0))),
+            "scale" => Affine2::from_scale(vec2(value(0, 1.0), value(1, value(0, 1.0)))),
+            "rotate" => {
+                let center = vec2(value(1, 0.0), value(2, 0.0));
+                Affine2::from_translation(center)
+                    * Affine2::from_angle(value(0, 0.0).to_radians())
+                    * Affine2::from_translation(-center)
+            }
+            "matrix" if values.len() == 6 => Affine2::from_cols_array(&[
+                values[0], values[1], values[2], values[3], values[4], values[5],
+            ]),
+            other => return Err(format!("unsupported transform '{other}'")),
+        };
+        transform *= step;
+    }
+    Ok(transform)
+}
+
+/// Read pairs of numbers, separated by commas or whitespace, as points.
+/// A trailing odd number is dropped.
+fn numbers(text: &str) -> Result<Vec<Vec2>, String> {
+    let values = Tokens::new(text).numbers()?;
+    Ok(values
+        .chunks_exact(2)
+        .map(|pair| vec2(pair[0], pair[1]))
+        .collect())
+}
+
+/// Read a length attribute of an element, 0 when it is missing
+fn length(node: Node, name: &str) -> Result<f32, String> {
+    node.attribute(name)
+        .map_or(Ok(0.0), |value| Tokens::new(value).number())
+        .map_err(|error| format!("{name}: {error}"))
+}
+
+/// The outline of a rectangle, its corners rounded by the radii of the `rx` and `ry`
+/// attributes if it has them
+fn rect(node: Node) -> Result<Vec<Vec2>, String> {
+    let corner = vec2(length(node, "x")?, length(node, "y")?);
+    let size = vec2(length(node, "width")?, length(node, "height")?);
+    if size.x <= 0.0 || size.y <= 0.0 {
+        return Err("a rectangle must be wider and taller than 0".to_string());
+    }
+    // a radius given alone goes for both, and neither reaches past the middle
+    let rx = node
+        .attribute("rx")
+        .map(|_| length(node, "rx"))
+        .transpose()?;
+    let ry = node
+        .attribute("ry")
+        .map(|_| length(node, "ry"))
+        .transpose()?;
+    let radii =
+        vec2(rx.or(ry).unwrap_or(0.0), ry.or(rx).unwrap_or(0.0)).clamp(Vec2::ZERO, size / 2.0);
+    let (left, top) = (corner.x, corner.y);
+    let (right, bottom) = (corner.x + size.x, corner.y + size.y);
+    if radii.x == 0.0 || radii.y == 0.0 {
+        return Ok(vec![
+            vec2(left, top),
+            vec2(right, top),
+            vec2(right, bottom),
+            vec2(left, bottom),
+        ]);
+    }
+    // along each side and then around the corner that follows it
+    let sides = [
+        (
+            vec2(left + radii.x, top),
+            vec2(right - radii.x, top),
+            vec2(right, top + radii.y),
+        ),
+        (
+            vec2(right, top + radii.y),
+            vec2(right, bottom - radii.y),
+            vec2(right - radii.x, bottom),
+        ),
+        (
+            vec2(right - radii.x, bottom),
+            vec2(left + radii.x, bottom),
+            vec2(left, bottom - radii.y),
+        ),
+        (
+            vec2(left, bottom - radii.y),
+            vec2(left, top + radii.y),
+            vec2(left + radii.x, top),
+        ),
+    ];
+    let mut points = vec![];
+    for (start, end, around) in sides {
+        points.extend([start, end]);
+        arc(&mut points, end, around, radii, 0.0, false, true);
+        // the next side starts where the corner ends
+        points.pop();
+    }
+    // corners as big as the sides leave nothing of the sides in between
+    points.dedup();
+    Ok(points)
+}
+
+/// The outline of a circle or an ellipse with the given radii, centered on the `cx`
+/// and `cy` attributes
+fn ellipse(node: Node, radii: Vec2) -> Result<Vec<Vec2>, String> {
+    if radii.x <= 0.0 || radii.y <= 0.0 {
+        return Err("a circle or an ellipse must have a radius above 0".to_string());
+    }
+    let center = vec2(length(node, "cx")?, length(node, "cy")?);
+    let mut points = vec![center + vec2(radii.x, 0.0)];
+    flatten(&mut points, |t| {
+        let angle = t * TAU;
+        center + vec2(angle.cos(), angle.sin()) * radii
+    });
+    // the last point comes back round to the first
+    points.pop();
+    Ok(points)
+}
+
+/// Parse the `d` attribute of a path into its subpaths, each a list of points
+/// along with whether it is closed
+fn parse_path(data: &str) -> Result<Vec<(Vec<Vec2>, bool)>, String> {
+    let mut tokens = Tokens::new(data);
+    let mut subpaths = vec![];
+    let mut points: Vec<Vec2> = vec![];
+    let mut current = Vec2::ZERO;
+    let mut start = Vec2::ZERO;
+    // the control point of the last curve, for the smooth curves following it
+    let mut last_control: Option<(char, Vec2)> = None;
+    let mut command = None;
+
+    while let Some(next) = tokens.command_or_number() {
+        let letter = match next {
+            Token::Command(letter) => letter,
+            // numbers without a command repeat the last one, lines following a move or
+            // a close, which starts the next line where the closed one started
+            Token::Number => match command {
+                Some('M' | 'Z') => 'L',
+                Some('m' | 'z') => 'l',
+                Some(letter) => letter,
+                None => return Err("path data must start with a command".to_string()),
+            },
+        };
+        command = Some(letter);
+        let relative = letter.is_ascii_lowercase();
+        let origin = if relative { current } else { Vec2::ZERO };
+        let point = |tokens: &mut Tokens| -> Result<Vec2, String> {
+            Ok(origin + vec2(tokens.number()?, tokens.number()?))
+        };
+        let mut control = None;
+        match letter.to_ascii_uppercase() {
+            'M' => {
+                if points.len() > 1 {
+                    subpaths.push((std::mem::take(&mut points), false));
+                }
+                current = point(&mut tokens)?;
+                start = current;
+                points = vec![current];
+            }
+            'L' => {
+                current = point(&mut tokens)?;
+                points.push(current);
+            }
+            'H' => {
+                current.x = origin.x + tokens.number()?;
+                points.push(current);
+            }
+            'V' => {
+                current.y = origin.y + tokens.number()?;
+                points.push(current);
+            }
+            'C' | 'S' => {
+                let first = if letter.eq_ignore_ascii_case(&'C') {
+                    point(&mut tokens)?
+                } else {
+                    reflect(last_control, 'C', current)
+                };
+                let second = point(&mut tokens)?;
+                let end = point(&mut tokens)?;
+                flatten(&mut points, |t| {
+                    let u = 1.0 - t;
+                    current * u * u * u
+                        + first * 3.0 * u * u * t
+                        + second * 3.0 * u * t * t
+                        + end * t * t * t
+                });
+                control = Some(('C', second));
+                current = end;
+            }
+            'Q' | 'T' => {
+                let middle = if letter.eq_ignore_ascii_case(&'Q') {
+                    point(&mut tokens)?
+                } else {
+                    reflect(last_control, 'Q', current)
+                };
+                let end = point(&mut tokens)?;
+                flatten(&mut points, |t| {
+                    let u = 1.0 - t;
+                    current * u * u + middle * 2.0 * u * t + end * t * t
+                });
+                control = Some(('Q', middle));
+                current = end;
+            }
+            'A' => {
+                let radii = vec2(tokens.number()?.abs(), tokens.number()?.abs());
+                let rotation = tokens.number()?.to_radians();
+                let large_arc = tokens.flag()?;
+                let sweep = tokens.flag()?;
+                let end = point(&mut tokens)?;
+                arc(&mut points, current, end, radii, rotation, large_arc, sweep);
+                current = end;
+            }
+            'Z' => {
+                if points.len() > 1 {
+                    subpaths.push((std::mem::take(&mut points), true));
+                }
+                current = start;
+                points = vec![current];
+            }
+            _ => return Err(format!("unknown path command '{letter}'")),
+        }
+        last_control = control;
+    }
+    if points.len() > 1 {
+        subpaths.push((points, false));
+    }
+    Ok(subpaths)
+}
+
+/// The first control point of a smooth curve: the last curve's control point mirrored
+/// at the current point if that curve was of the same kind, or else the current point
+fn reflect(last_control: Option<(char, Vec2)>, kind: char, current: Vec2) -> Vec2 {
+    match last_control {
+        Some((last_kind, control)) if last_kind == kind => current * 2.0 - control,
+        _ => current,
+    }
+}
+
+/// Add the points along a curve, given as a function from 0.0 at its start to
+/// 1.0 at its end, in pieces short enough to look smooth
+fn flatten(points: &mut Vec<Vec2>, curve: impl Fn(f32) -> Vec2) {
+    // the curve is no longer than the path through a few points along it
+    let length = (1..=8)
+        .map(|i| (curve(i as f32 / 8.0) - curve((i - 1) as f32 / 8.0)).length())
+        .sum::<f32>();
+    let pieces = (length / FLATNESS).ceil().clamp(1.0, 64.0) as usize;
+    points.extend((1..=pieces).map(|i| curve(i as f32 / pieces as f32)));
+}
+
+/// Add the points along an elliptical arc, following the SVG specification's
+/// conversion from the end points to the center of the ellipse
+fn arc(
+    points: &mut Vec<Vec2>,
+    from: Vec2,
+    to: Vec2,
+    radii: Vec2,
+    rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+) {
+    if radii.x == 0.0 || radii.y == 0.0 || from == to {
+        points.push(to);
+        return;
+    }
+    let unrotate = Vec2::from_angle(-rotation);
+    let half = unrotate.rotate((from - to) / 2.0);
+    // radii too small to reach from one end to the other are scaled up until they do
+    let scale = (half.x * half.x / (radii.x * radii.x) + half.y * half.y / (radii.y * radii.y))
+        .sqrt()
+        .max(1.0);
+    let radii = radii * scale;
+    let (rx2, ry2) = (radii.x * radii.x, radii.y * radii.y);
+    let numerator = rx2 * ry2 - rx2 * half.y * half.y - ry2 * half.x * half.x;
+    let denominator = rx2 * half.y * half.y + ry2 * half.x * half.x;
+    let mut factor = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        factor = -factor;
+    }
+    let center_rotated = vec2(radii.x * half.y / radii.y, -radii.y * half.x / radii.x) * factor;
+    let center = Vec2::from_angle(rotation).rotate(center_rotated) + (from + to) / 2.0;
+
+    let angle = |v: Vec2| v.y.atan2(v.x);
+    let start = angle((half - center_rotated) / radii);
+    let mut delta = angle((-half - center_rotated) / radii) - start;
+    if sweep && delta < 0.0 {
+        delta += TAU;
+    } else if !sweep && delta > 0.0 {
+        delta -= TAU;
+    }
+    flatten(points, |t| {
+        let theta = start + delta * t;
+        center + Vec2::from_angle(rotation).rotate(vec2(theta.cos(), theta.sin()) * radii)
+    });
+}
+
+/// What comes next in path data
+enum Token {
+    Command(char),
+    Number,
+}
+
+/// Reads commands and numbers from path data and similar lists of numbers,
+/// where numbers may be separated by commas, whitespace or nothing at all, as in `1-2.5.5`
+struct Tokens<'a> {
+    text: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(text: &'a str) -> Self {
+        Tokens { text }
+    }
+
+    fn skip_separators(&mut self) {
+        self.text = self
+            .text
+            .trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+
+    /// Take the next command letter, or tell that a number comes next
+    fn command_or_number(&mut self) -> Option<Token> {
+        self.skip_separators();
+        let next = self.text.chars().next()?;
+        if next.is_ascii_alphabetic() && next != 'e' && next != 'E' {
+            self.text = &self.text[1..];
+            Some(Token::Command(next))
+        } else {
+            Some(Token::Number)
+        }
+    }
+
+    /// Take the next number
+    fn number(&mut self) -> Result<f32, String> {
+        self.skip_separators();
+        let bytes = self.text.as_bytes();
+        let mut end = 0;
+        let mut seen_dot = false;
+        let mut seen_exponent = false;
+        while end < bytes.len() {
+            match bytes[end] {
+                b'+' | b'-' if end == 0 || matches!(bytes[end - 1], b'e' | b'E') => {}
+                b'0'..=b'9' => {}
+                b'.' if !seen_dot && !seen_exponent => seen_dot = true,
+                b'e' | b'E' if !seen_exponent && end > 0 => seen_exponent = true,
+                _ => break,
+            }
+            end += 1;
+        }
+        let (number, rest) = self.text.split_at(end);
+        self.text = rest;
+        number
+            .parse()
+            .map_err(|_| format!("expected a number at '{}'", truncate(number, rest)))
+    }
+
+    /// Take the next flag of an arc, a single 0 or 1 that needn't be separated
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        let flag = match self.text.as_bytes().first() {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            _ => return Err(format!("expected a flag at '{}'", truncate("", self.text))),
+        };
+        self.text = &self.text[1..];
+        Ok(flag)
+    }
+
+    /// Take all the numbers
+    fn numbers(&mut self) -> Result<Vec<f32>, String> {
+        let mut numbers = vec![];
+        loop {
+            self.skip_separators();
+            if self.text.is_empty() {
+                return Ok(numbers);
+            }
+            numbers.push(self.number()?);
+        }
+    }
+}
+
+/// A short piece of text around where reading failed, for error messages
+fn truncate(before: &str, after: &str) -> String {
+    format!("{before}{}", after.chars().take(10).collect::<String>())
+}
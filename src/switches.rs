@@ -0,0 +1,146 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings;
+
+/// The colors telling apart the switch arrays of a level, and the vaults they open
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SwitchColor {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
+
+impl SwitchColor {
+    /// All switch colors
+    pub const ALL: [SwitchColor; 4] = [
+        SwitchColor::Red,
+        SwitchColor::Green,
+        SwitchColor::Blue,
+        SwitchColor::Yellow,
+    ];
+
+    /// The name of the color in level files
+    pub fn name(&self) -> &'static str {
+        match self {
+            SwitchColor::Red => "red",
+            SwitchColor::Green => "green",
+            SwitchColor::Blue => "blue",
+            SwitchColor::Yellow => "yellow",
+        }
+    }
+
+    /// The color with the given name
+    pub fn from_name(name: &str) -> Option<SwitchColor> {
+        SwitchColor::ALL
+            .into_iter()
+            .find(|color| color.name() == name)
+    }
+
+    /// The color the switches, the wiring and the vault door are drawn in
+    pub fn color(&self) -> Color {
+        match self {
+            SwitchColor::Red => settings::bad(),
+            SwitchColor::Green => settings::good(),
+            SwitchColor::Blue => SKYBLUE,
+            SwitchColor::Yellow => YELLOW,
+        }
+    }
+}
+
+/// A switch lit by bumping into it with the pod or by dropping an item onto it,
+/// staying lit for a while before it goes out again
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Switch {
+    pub position: Vec2,
+    /// The time in seconds the switch stays lit, 0.0 once it is out
+    pub timer: f32,
+}
+
+/// Switches of the same color that open a vault once they are all lit at the same time
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SwitchArray {
+    pub color: SwitchColor,
+    pub switches: Vec<Switch>,
+    /// The time in seconds a switch stays lit, in which the others have to be lit as well
+    pub window: f32,
+    /// The index of the vault's door, unlocked for good when the vault opens
+    pub door: Option<usize>,
+    /// Whether the vault has been opened
+    pub open: bool,
+}
+
+impl SwitchArray {
+    /// The distance at which the pod or an item presses a switch
+    pub const REACH: f32 = 16.0;
+    /// The time window of an array, unless the level sets one
+    pub const WINDOW: f32 = 10.0;
+
+    /// Create an array of the given color without any switches or a vault to open
+    pub fn new(color: SwitchColor) -> Self {
+        SwitchArray {
+            color,
+            switches: vec![],
+            window: Self::WINDOW,
+            door: None,
+            open: false,
+        }
+    }
+
+    /// Add a switch at the given position
+    pub fn add_switch(&mut self, position: Vec2) {
+        self.switches.push(Switch {
+            position,
+            timer: 0.0,
+        });
+    }
+
+    /// Light the switches pressed by anything at the given positions and let the
+    /// others run down. Returns true if all switches are lit and the vault just opened.
+    pub fn update(&mut self, seconds: f32, pressing: &[Vec2]) -> bool {
+        if self.open {
+            return false;
+        }
+        for switch in self.switches.iter_mut() {
+            let pressed = pressing
+                .iter()
+                .any(|p| (*p - switch.position).length() < Self::REACH);
+            switch.timer = if pressed {
+                self.window
+            } else {
+                (switch.timer - seconds).max(0.0)
+            };
+        }
+        self.open = !self.switches.is_empty() && self.switches.iter().all(|s| s.timer > 0.0);
+        self.open
+    }
+
+    /// Draw the switches with the time they stay lit, the wiring to the vault
+    /// and the frame of the vault door, in the given area, while it is closed
+    pub fn draw(&self, door: Rect) {
+        let color = self.color.color();
+        let dim = Color::new(color.r, color.g, color.b, 0.3);
+        let center = door.center();
+        for switch in &self.switches {
+            let (x, y) = (switch.position.x, switch.position.y);
+            let lit = switch.timer > 0.0 || self.open;
+            if !self.open {
+                draw_line(x, y, center.x, center.y, 1.0, if lit { color } else { dim });
+            }
+            draw_rectangle(x - 10.0, y - 4.0, 20.0, 8.0, DARKGRAY);
+            draw_circle(x, y - 4.0, 6.0, if lit { color } else { dim });
+            // the countdown runs round the switch and shows the seconds left
+            if switch.timer > 0.0 && !self.open {
+                let left = switch.timer / self.window;
+                draw_arc(x, y - 4.0, 24, 9.0, -90.0, 2.0, 360.0 * left, color);
+                let text = format!("{:.0}", switch.timer.ceil());
+                draw_text(&text, x - 4.0, y - 16.0, 16.0, WHITE);
+            }
+        }
+        if !self.open {
+            let Rect { x, y, w, h } = door;
+            draw_rectangle_lines(x, y, w, h, 3.0, color);
+        }
+    }
+}
@@ -0,0 +1,1371 @@
+use ::rand::Rng;
+use macroquad::prelude::*;
+
+use crate::camera::CameraShake;
+use crate::ecs::Entity;
+use crate::enemy::{Enemy, Target, Turret};
+use crate::physics::*;
+use crate::pickups::Pickup;
+use crate::projectiles::{Owner, ProjectilePool};
+use crate::route::find_route;
+use crate::scripting::Facts;
+use crate::terrain::{Terrain, TerrainHazard, check_collision};
+use crate::triggers::{Occupant, TriggerAction};
+use crate::ui::InputState;
+use crate::world::World;
+
+/// What a system gets to work with during one update of the world
+pub struct Step<'a> {
+    /// The player's input
+    pub input: &'a InputState,
+    /// The simulated time in seconds since the last update
+    pub seconds: f32,
+    /// The time step of the physics simulation
+    pub dt: f32,
+}
+
+/// A system updates one aspect of the world, usually all entities with a given set of components
+pub type System = fn(&mut World, &Step);
+
+/// The systems run on every update of the world, in order
+pub const SCHEDULE: &[System] = &[
+    control,
+    forces,
+    anomalies,
+    assist,
+    delivery,
+    fuses,
+    livestock,
+    effects,
+    timer,
+    weather,
+    escort,
+    waves,
+    hazards,
+    drones,
+    turrets,
+    projectiles,
+    funnels,
+    linking,
+    throw,
+    sever,
+    beam,
+    chain,
+    rope,
+    partners,
+    water,
+    physics,
+    collision,
+    loose_items,
+    constraints,
+    terrain_hazards,
+    practice_route,
+    refuel,
+    pickups,
+    oxygen,
+    checkpoints,
+    switches,
+    doors,
+    triggers,
+    scripts,
+];
+
+/// The built-in schedule, as a list new systems can be added to
+pub fn schedule() -> Vec<System> {
+    SCHEDULE.to_vec()
+}
+
+/// Steer Jetman, work the winch and fire the laser as the player says,
+/// and the partner pods as the other players say
+pub fn control(world: &mut World, step: &Step) {
+    let input = step.input;
+    world.jetman.towed_mass = world.towed_mass();
+    for partner in world.partners.iter_mut() {
+        partner.towed_mass = partner
+            .linked_item
+            .and_then(|entity| world.entities.bodies.get(entity))
+            .map_or(0.0, |body| body.mass);
+    }
+    if let Some((position, velocity)) = steer(&mut world.jetman, input, step.dt) {
+        world.projectiles.fire(position, velocity, Owner::Player);
+    }
+    for (partner, input) in world.partners.iter_mut().zip(&input.partners) {
+        if let Some((position, velocity)) = steer(partner, input, step.dt) {
+            world.projectiles.fire(position, velocity, Owner::Player);
+        }
+    }
+    if input.toggle_practice {
+        world.practice = !world.practice;
+        world.route_timer = 0.0;
+    }
+    if input.toggle_assist {
+        world.assist = !world.assist;
+    }
+    if input.grapple {
+        world.jetman.grapple = throw_grapple(world, &world.jetman);
+    }
+    for index in 0..world.partners.len() {
+        if input.partners.get(index).is_some_and(|input| input.grapple) {
+            world.partners[index].grapple = throw_grapple(world, &world.partners[index]);
+        }
+    }
+}
+
+/// The grapple a pod holds after the player works it: one stuck where the hook hits
+/// along the pod's heading, or none if the pod was already hanging from one
+fn throw_grapple(world: &World, pod: &Jetman) -> Option<Grapple> {
+    if pod.grapple.is_some() {
+        return None;
+    }
+    let direction = vector_from_angle(pod.heading);
+    world
+        .raycast(pod.position(), direction, Jetman::GRAPPLE_RANGE)
+        .map(|hit| Grapple {
+            anchor: hit.point,
+            length: hit.distance,
+        })
+}
+
+/// Steer a pod and work its winch, returning where the laser fires from and how fast,
+/// if it fires
+fn steer(jetman: &mut Jetman, input: &InputState, dt: f32) -> Option<(Vec2, Vec2)> {
+    if input.thrust {
+        jetman.apply_thrust();
+    }
+    if input.turn_left {
+        jetman.turn_left();
+    }
+    if input.turn_right {
+        jetman.turn_right();
+    }
+    if input.auto_level {
+        jetman.level_out();
+    }
+    if let Some(aim) = input.aim {
+        jetman.turn_towards(aim);
+    }
+    if input.brake {
+        jetman.brake(dt);
+    }
+    if input.strafe_left {
+        jetman.strafe(-1.0, dt);
+    }
+    if input.strafe_right {
+        jetman.strafe(1.0, dt);
+    }
+    // The winch reels the grappling rope while the hook is stuck, and otherwise
+    // only operates while something hangs from the beam
+    if jetman.grapple.is_some() {
+        if input.reel_in {
+            jetman.reel_in_rope(dt);
+        }
+        if input.reel_out {
+            jetman.reel_out_rope(dt);
+        }
+    } else if jetman.linked_item.is_some() {
+        if input.reel_in {
+            jetman.reel_in(dt);
+        }
+        if input.reel_out {
+            jetman.reel_out(dt);
+        }
+    }
+    if input.fire { jetman.fire() } else { None }
+}
+
+/// Apply gravity to Jetman and the chain he tows, which weighs him down through the
+/// links, and to the items let go of in flight, and let the wind blow him and the items
+/// around
+pub fn forces(world: &mut World, _step: &Step) {
+    world.jetman.apply_force(world.gravity);
+    world.jetman.apply_force(world.wind);
+    for partner in world.partners.iter_mut() {
+        partner.apply_force(world.gravity);
+        partner.apply_force(world.wind);
+    }
+    for entity in world.entities.towables.entities() {
+        if let Some(body) = world.entities.bodies.get_mut(entity) {
+            body.apply_force(world.wind);
+        }
+    }
+    let towed = world.towed();
+    for &entity in &towed {
+        if let Some(body) = world.entities.bodies.get_mut(entity) {
+            body.apply_force(world.gravity * body.mass);
+        }
+    }
+    for (entity, towable) in world.entities.towables.iter() {
+        if !towable.loose || towed.contains(&entity) {
+            continue;
+        }
+        if let Some(body) = world.entities.bodies.get_mut(entity) {
+            let drag = -body.velocity * Towable::AIR_DRAG;
+            body.apply_force((world.gravity + drag) * body.mass);
+        }
+    }
+}
+
+/// The items gravity pulls down: those towed by the pods and those let go of in flight
+fn weighed_down(world: &World) -> Vec<Entity> {
+    let mut items = world.towed();
+    let loose = world
+        .entities
+        .towables
+        .iter()
+        .filter(|(entity, towable)| towable.loose && !items.contains(entity))
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+    items.extend(loose);
+    items
+}
+
+/// Let the black holes pull in every body and swallow whatever crosses their event
+/// horizon, and cancel out the gravity on the bodies inside anti-grav bubbles
+pub fn anomalies(world: &mut World, step: &Step) {
+    let mut anomalies = vec![];
+    for (_, anomaly) in world.entities.anomalies.iter_mut() {
+        anomaly.update(step.dt);
+        anomalies.push(*anomaly);
+    }
+    if anomalies.is_empty() {
+        return;
+    }
+    let pull = |position| anomalies.iter().map(|a| a.pull(position)).sum::<Vec2>();
+    let lifts = |position| anomalies.iter().any(|a| a.lifts(position));
+    let swallows = |position| anomalies.iter().any(|a| a.swallows(position));
+
+    // gravity only weighs down the pods and the items towed or let go of, so only
+    // they are lifted
+    let gravity = world.gravity;
+    let pods = std::iter::once(&mut world.jetman).chain(world.partners.iter_mut());
+    let mut swallowed = vec![];
+    for pod in pods {
+        let position = pod.position();
+        pod.body.apply_force(pull(position) * pod.body.mass);
+        if lifts(position) {
+            pod.apply_force(-gravity);
+        }
+        if swallows(position) && !pod.invulnerable && pod.health > 0.0 {
+            pod.health = 0.0;
+            swallowed.push(position);
+        }
+    }
+    let weighed_down = weighed_down(world);
+    let mut gone = vec![];
+    for (entity, body) in world.entities.bodies.iter_mut() {
+        body.apply_force(pull(body.position) * body.mass);
+        if weighed_down.contains(&entity) && lifts(body.position) {
+            body.apply_force(-gravity * body.mass);
+        }
+        if swallows(body.position) {
+            gone.push(entity);
+            swallowed.push(body.position);
+        }
+    }
+    for projectile in world.projectiles.iter_mut() {
+        if swallows(projectile.body.position) {
+            projectile.active = false;
+        }
+    }
+    for entity in gone {
+        if world.entities.towables.contains(entity) {
+            world.destroy_item(entity);
+        } else {
+            world.entities.despawn(entity);
+        }
+    }
+    for position in swallowed {
+        world.particles.burst(position, 20, 1.5, VIOLET);
+    }
+}
+
+/// Let the stability assist steady Jetman, if it's on
+pub fn assist(world: &mut World, step: &Step) {
+    if world.assist {
+        world.jetman.stabilize(world.gravity, step.dt);
+    }
+}
+
+/// Check if an item has been dropped into a teleporter that accepts it, and count
+/// the cargo left to deliver
+pub fn delivery(world: &mut World, _step: &Step) {
+    let entities = &world.entities;
+    let delivered = entities
+        .items()
+        .find(|(_, towable, position)| {
+            entities.teleporters.iter().any(|(_, teleport)| {
+                teleport.accepts(towable.kind) && (*position - teleport.position).length() < 10.0
+            })
+        })
+        .map(|(entity, towable, _)| (entity, *towable));
+    if let Some((entity, towable)) = delivered {
+        match towable.kind {
+            ItemKind::Cargo | ItemKind::Livestock => {
+                world.progress.delivered += 1;
+                if towable.is_intact() {
+                    world.progress.intact += 1;
+                }
+            }
+            ItemKind::Bomb { .. } => world.progress.defused += 1,
+            ItemKind::Key { .. } => {}
+        }
+        world.remove_item(entity);
+    }
+    world.progress.cargo_left = world
+        .entities
+        .items()
+        .filter(|(_, towable, _)| towable.kind.is_cargo())
+        .count() as u32;
+}
+
+/// Tick the bombs' fuses and detonate the ones that ran out
+pub fn fuses(world: &mut World, step: &Step) {
+    let detonated = world
+        .entities
+        .towables
+        .iter_mut()
+        .filter_map(|(entity, towable)| towable.tick(step.seconds).then_some(entity))
+        .collect::<Vec<_>>();
+    for entity in detonated {
+        // an earlier blast may already have destroyed the bomb
+        let Some(position) = world.entities.position(entity) else {
+            continue;
+        };
+        world.remove_item(entity);
+        world.explode(position, 60.0);
+    }
+}
+
+/// Let the restless items push themselves about now and then. Left alone, they
+/// wander off a bit and come to a stop again, but hanging from the beam they
+/// keep tugging at it.
+pub fn livestock(world: &mut World, step: &Step) {
+    let (low, high) = Restless::INTERVAL;
+    let towed = world.chain();
+    for (entity, restless) in world.entities.restless.iter_mut() {
+        let Some(body) = world.entities.bodies.get_mut(entity) else {
+            continue;
+        };
+        if !towed.contains(&entity) {
+            body.velocity *= (1.0 - Restless::DRAG).powf(step.dt);
+        }
+        restless.timer -= step.seconds;
+        if restless.timer > 0.0 {
+            continue;
+        }
+        restless.timer = world.rng.gen_range(low..high);
+        let angle = world.rng.gen_range(0.0..std::f32::consts::TAU);
+        let strength = world.rng.gen_range(0.5..1.0) * Restless::STRENGTH;
+        body.velocity += Vec2::from_angle(angle) * strength / body.mass;
+    }
+}
+
+/// Age the particles, explosions, animations and on-screen reminders
+pub fn effects(world: &mut World, step: &Step) {
+    world.reminder_timer = (world.reminder_timer - step.seconds).max(0.0);
+    world.particles.update(step.dt, step.seconds);
+    // a firing thruster leaves the picked trail behind
+    if world.jetman.thrusting != 0
+        && let Some(color) = world.trail.color(world.clock)
+    {
+        let back = -vector_from_angle(world.jetman.heading);
+        let nozzle = world.jetman.position() + back * 20.0;
+        world.particles.spray(nozzle, back * 1.5, color);
+    }
+    for explosion in world.explosions.iter_mut() {
+        explosion.age += step.seconds;
+    }
+    for (_, animator) in world.entities.animators.iter_mut() {
+        animator.update(step.seconds);
+    }
+    world.jetman.flame.update(step.seconds);
+    world
+        .explosions
+        .retain(|explosion| !explosion.is_finished());
+}
+
+/// Run the level timer until the objectives are complete
+pub fn timer(world: &mut World, step: &Step) {
+    if !world.objectives_complete() {
+        world.timer += step.seconds;
+    }
+}
+
+/// Let the weather change
+pub fn weather(world: &mut World, step: &Step) {
+    world.fog.update(world.clock, step.seconds, &mut world.rng);
+}
+
+/// Move the escorted craft along its path
+pub fn escort(world: &mut World, step: &Step) {
+    if let Some(escort) = world.escort.as_mut() {
+        escort.update(step.dt);
+        world.progress.escorted = escort.progress();
+        world.progress.escort_lost = escort.is_destroyed();
+    }
+}
+
+/// Advance the level clock and send in the enemy waves whose time has come,
+/// be they set in the level or sent by the director
+pub fn waves(world: &mut World, step: &Step) {
+    let previous = world.clock;
+    world.clock += step.seconds;
+
+    // enemies appear on a ring around whatever they are going after
+    let center = world.escort_target().unwrap_or(world.jetman.position());
+    for wave in &world.waves {
+        if wave.time > previous && wave.time <= world.clock {
+            for _ in 0..wave.count {
+                let angle = world.rng.gen_range(0.0..std::f32::consts::TAU);
+                world
+                    .entities
+                    .spawn_drone(center + Vec2::from_angle(angle) * 450.0);
+            }
+        }
+    }
+    if let Some(director) = world.director.as_mut()
+        && world.clock >= director.next_wave()
+    {
+        for (position, enemy) in director.send(center, &mut world.rng) {
+            world.entities.spawn_enemy(position, enemy);
+        }
+        world.progress.waves = director.waves;
+    }
+    world.progress.hostiles = world.entities.enemies.len() as u32;
+}
+
+/// Damage Jetman's hull where he touches a hazard, destroying the hazards that only hit once
+pub fn hazards(world: &mut World, _step: &Step) {
+    let pod = world.jetman.position();
+    let mut spent = vec![];
+    for (entity, hazard) in world.entities.hazards.iter() {
+        let Some(position) = world.entities.position(entity) else {
+            continue;
+        };
+        if (position - pod).length() < hazard.reach {
+            world.jetman.damage(hazard.damage);
+            world.shake.add_trauma(hazard.damage * 2.0);
+            if hazard.single_use {
+                spent.push((entity, position));
+            }
+        }
+    }
+    for (entity, position) in spent {
+        world.entities.despawn(entity);
+        world.particles.burst(position, 16, 2.0, RED);
+    }
+}
+
+/// Steer the drones towards Jetman or the escorted craft,
+/// letting them ram the escort or the items they run into
+pub fn drones(world: &mut World, _step: &Step) {
+    let jetman = world.jetman.position();
+    let escort = world.escort_target();
+    let items = world
+        .entities
+        .items()
+        .map(|(_, _, position)| position)
+        .collect::<Vec<_>>();
+
+    let entities = &mut world.entities;
+    let mut destroyed = vec![];
+    for (entity, enemy) in entities.enemies.iter_mut() {
+        let Some(body) = entities.bodies.get_mut(entity) else {
+            continue;
+        };
+        enemy.choose_target(body.position, jetman, escort);
+        let target = match enemy.target {
+            Target::Escort => escort.unwrap_or(jetman),
+            Target::Jetman => jetman,
+        };
+        enemy.steer(body, target);
+
+        let position = body.position;
+        if let Some(craft) = world.escort.as_mut()
+            && enemy.target == Target::Escort
+            && (position - craft.position()).length() < Enemy::HIT_DISTANCE + 8.0
+        {
+            craft.damage(0.2);
+            destroyed.push((entity, position));
+        } else if items
+            .iter()
+            .any(|item| (position - *item).length() < Enemy::HIT_DISTANCE)
+        {
+            destroyed.push((entity, position));
+        }
+    }
+    for (entity, position) in destroyed {
+        world.entities.despawn(entity);
+        world.particles.burst(position, 16, 2.0, RED);
+    }
+}
+
+/// Swing the turrets towards Jetman if they can see him, fire at him,
+/// and crush turrets that items fall onto
+pub fn turrets(world: &mut World, step: &Step) {
+    let pod = world.jetman.position();
+    let falling = world
+        .entities
+        .towables
+        .entities()
+        .filter_map(|entity| world.entities.bodies.get(entity))
+        .filter(|body| body.velocity.length() > 1.0)
+        .map(|body| body.position)
+        .collect::<Vec<_>>();
+    let turrets = world.entities.turrets.entities().collect::<Vec<_>>();
+    for entity in turrets {
+        let Some(muzzle) = world.entities.turrets.get(entity).map(Turret::muzzle) else {
+            continue;
+        };
+        let target = world.line_of_sight(muzzle, pod).then_some(pod);
+        let Some(turret) = world.entities.turrets.get_mut(entity) else {
+            continue;
+        };
+        if let Some(velocity) = turret.update(target, step.seconds, world.fire_rate) {
+            if turret.launcher {
+                world.projectiles.launch(turret.muzzle(), velocity);
+            } else {
+                world
+                    .projectiles
+                    .fire(turret.muzzle(), velocity, Owner::Enemy);
+            }
+        }
+        // heavy items dropped on a turret flatten it
+        let crushed = falling
+            .iter()
+            .any(|item| (*item - turret.position).length() < Turret::HIT_DISTANCE + 6.0);
+        if crushed {
+            turret.damage(1.0);
+        }
+    }
+    world.remove_destroyed_turrets();
+}
+
+/// Steer the missiles, move the projectiles and resolve their hits on terrain,
+/// enemies and Jetman. Missiles blow up where they hit, pushing everything
+/// around them away.
+pub fn projectiles(world: &mut World, step: &Step) {
+    let pod = world.jetman.position();
+    for projectile in world.projectiles.iter_mut() {
+        projectile.home(pod, world.gravity, step.dt, step.seconds);
+    }
+    let mut blasts = world.projectiles.update(step.dt, step.seconds);
+    let entities = &mut world.entities;
+    let mut destroyed = vec![];
+    for projectile in world.projectiles.iter_mut() {
+        let position = projectile.body.position;
+        let hit_terrain = world.terrain.iter().any(|t| t.contains(position));
+        let hit = match projectile.owner {
+            Owner::Player => {
+                let turret = entities.turrets.iter_mut().find(|(_, turret)| {
+                    (turret.position - position).length() < Turret::HIT_DISTANCE
+                });
+                if let Some((_, turret)) = turret {
+                    turret.damage(0.34);
+                    projectile.active = false;
+                    world.particles.burst(position, 6, 1.0, WHITE);
+                    continue;
+                }
+                let enemy = entities.enemies.entities().find(|&enemy| {
+                    entities.bodies.get(enemy).is_some_and(|body| {
+                        (body.position - position).length() < ProjectilePool::HIT_DISTANCE
+                    })
+                });
+                if let Some(enemy) = enemy
+                    && !destroyed.contains(&enemy)
+                {
+                    destroyed.push(enemy);
+                }
+                enemy.is_some()
+            }
+            Owner::Enemy => {
+                let hit =
+                    (world.jetman.position() - position).length() < ProjectilePool::HIT_DISTANCE;
+                if hit && projectile.fuel.is_none() {
+                    world.jetman.damage(0.1);
+                    world.shake.add_trauma(0.3);
+                }
+                hit
+            }
+        };
+        if hit || hit_terrain {
+            projectile.active = false;
+            if projectile.fuel.is_some() {
+                blasts.push(position);
+            } else {
+                world.particles.burst(position, 6, 1.0, WHITE);
+            }
+        }
+    }
+    for position in blasts {
+        world.blast(position, ProjectilePool::MISSILE_BLAST);
+    }
+    for enemy in destroyed {
+        if let Some(position) = world.entities.position(enemy) {
+            world.particles.burst(position, 16, 2.0, RED);
+        }
+        world.entities.despawn(enemy);
+    }
+    world.remove_destroyed_turrets();
+}
+
+/// Guide items that fell into a funnel towards its throat. Items still on a beam
+/// go where the pods tow them, so they can be lifted back out.
+pub fn funnels(world: &mut World, _step: &Step) {
+    let towed = world.towed();
+    for funnel in &world.funnels {
+        for entity in world.entities.towables.entities() {
+            if towed.contains(&entity) {
+                continue;
+            }
+            if let Some(body) = world.entities.bodies.get_mut(entity) {
+                funnel.guide(body);
+            }
+        }
+    }
+}
+
+/// Check for linking with items: the first item hooks onto Jetman,
+/// further items hook onto the last item of the chain
+pub fn linking(world: &mut World, _step: &Step) {
+    let chain = world.chain();
+    // a drained beam has to recharge a bit before it can take on another item
+    if world.jetman.beam_energy < Jetman::BEAM_ENGAGE {
+        return;
+    }
+    let (anchor, reach) = match chain.last() {
+        Some(&tail) => (
+            world.entities.position(tail).unwrap_or_default(),
+            world
+                .entities
+                .towables
+                .get(tail)
+                .map_or(0.0, |towable| towable.link_distance),
+        ),
+        None => (world.jetman.position(), world.jetman.reach()),
+    };
+    let wall = world.jetman.wall_reach();
+    let candidate = world
+        .entities
+        .items()
+        .find(|(entity, _, position)| {
+            !chain.contains(entity)
+                && (*position - anchor).length() < reach
+                && wall.is_none_or(|wall| world.solid_between(anchor, *position) <= wall)
+        })
+        .map(|(entity, _, _)| entity);
+    if let Some(entity) = candidate {
+        // the beam grabs the item by the edge facing it
+        if let (Some(towable), Some(position)) = (
+            world.entities.towables.get_mut(entity),
+            world.entities.bodies.get(entity).map(|body| body.position),
+        ) {
+            towable.attach_towards(position, anchor);
+        }
+        match chain.last() {
+            Some(&tail) => world.entities.links.insert(tail, Link { next: entity }),
+            None => world.jetman.linked_item = Some(entity),
+        }
+    }
+}
+
+/// Wind up a throw while the throw key is held, whirling the towed chain faster and
+/// faster along its swing, and let go of the chain once the key is released
+pub fn throw(world: &mut World, step: &Step) {
+    let jetman = &mut world.jetman;
+    if jetman.linked_item.is_none() {
+        jetman.throw_charge = 0.0;
+        return;
+    }
+    if !step.input.throw {
+        if jetman.throw_charge > 0.0 {
+            jetman.throw_charge = 0.0;
+            world.release_chain();
+        }
+        return;
+    }
+    jetman.throw_charge = (jetman.throw_charge + step.seconds / Jetman::THROW_WINDUP).min(1.0);
+    let force = Jetman::THROW_FORCE * jetman.throw_charge;
+    let pivot = jetman.body;
+    for entity in world.chain() {
+        if let Some(body) = world.entities.bodies.get_mut(entity) {
+            let tangent = (body.position - pivot.position).perp().normalize_or_zero();
+            // push the way the chain already swings, so a throw never stalls it
+            let swing = (body.velocity - pivot.velocity).dot(tangent);
+            let direction = if swing < 0.0 { -tangent } else { tangent };
+            body.apply_force(direction * force * body.mass);
+        }
+    }
+}
+
+/// Check for severing link, which releases the whole chain
+pub fn sever(world: &mut World, step: &Step) {
+    if step.input.sever_link {
+        world.release_chain();
+    }
+}
+
+/// Collect the pickups the jet pod touches into the inventory, and empty a collected
+/// fuel cell or oxygen canister into the tanks whenever there's room for all of it
+pub fn pickups(world: &mut World, _step: &Step) {
+    let position = world.jetman.position();
+    let collected = world
+        .entities
+        .pickups
+        .iter()
+        .filter(|(_, pickup)| (pickup.position - position).length() < Pickup::REACH)
+        .map(|(entity, pickup)| (entity, *pickup))
+        .collect::<Vec<_>>();
+    for (entity, pickup) in collected {
+        if let Some(modifier) = pickup.kind.modifier() {
+            world.jetman.modifiers.add(modifier, modifier.duration());
+        }
+        world.inventory.add(pickup.kind);
+        world
+            .particles
+            .burst(pickup.position, 12, 1.5, pickup.kind.color());
+        world.entities.despawn(entity);
+    }
+    let jetman = &mut world.jetman;
+    if let Some(fuel) = world.inventory.use_fuel_cell(jetman.fuel, jetman.max_fuel) {
+        jetman.fuel += fuel;
+    }
+    if let Some(oxygen) = jetman.oxygen.as_mut()
+        && let Some(air) = world.inventory.use_oxygen_canister(oxygen)
+    {
+        oxygen.refill(air);
+    }
+}
+
+/// Let the pilot breathe, if the level makes them, telling them once the air runs out.
+/// Suffocating costs the pod like having it destroyed.
+pub fn oxygen(world: &mut World, step: &Step) {
+    let Some(oxygen) = world.jetman.oxygen.as_mut() else {
+        return;
+    };
+    let had_air = !oxygen.is_empty();
+    oxygen.breathe(step.seconds);
+    if had_air && oxygen.is_empty() {
+        world.notice = Some(("Out of oxygen!".to_string(), World::NOTICE_SECONDS));
+    }
+}
+
+/// Keep the pods on the ropes of their grappling hooks, if they're stuck in the terrain.
+/// A hook comes loose once what it was stuck in moves or is gone.
+pub fn rope(world: &mut World, _step: &Step) {
+    let loose = std::iter::once(&world.jetman)
+        .chain(&world.partners)
+        .map(|pod| {
+            pod.grapple
+                .is_some_and(|grapple| !world.solid_at(grapple.anchor))
+        })
+        .collect::<Vec<_>>();
+    let pods = std::iter::once(&mut world.jetman).chain(&mut world.partners);
+    for (pod, loose) in pods.zip(loose) {
+        if loose {
+            pod.grapple = None;
+        }
+        pod.hang();
+    }
+}
+
+/// Enforce rigid connections along the chain of linked items, starting at Jetman
+/// and working down to the last item, and snap the most strained link if the chain
+/// stays overloaded for too long
+pub fn chain(world: &mut World, step: &Step) {
+    let mut strained: Option<(Option<Entity>, f32)> = None;
+    for (anchor, impulse) in pull_chain(world) {
+        // remember the link under the highest tension
+        let tension = if step.dt > 0.0 {
+            impulse / step.dt
+        } else {
+            0.0
+        };
+        if strained.is_none_or(|(_, highest)| tension > highest) {
+            strained = Some((anchor, tension));
+        }
+    }
+
+    world.jetman.link_tension = strained.map_or(0.0, |(_, tension)| tension);
+    if world.jetman.link_tension > world.jetman.link_strength {
+        world.jetman.overload_frames += 1;
+    } else {
+        world.jetman.overload_frames = 0;
+    }
+    if world.jetman.overload_frames >= Jetman::OVERLOAD_FRAMES_TO_SNAP
+        && let Some((anchor, _)) = strained
+    {
+        world.snap_link(anchor);
+    }
+}
+
+/// Pull the links of the chain towed by Jetman to their lengths, from the pod down
+/// to the last item. Returns the impulse each link applied, along with the item it
+/// hangs from, None for the pod.
+fn pull_chain(world: &mut World) -> Vec<(Option<Entity>, f32)> {
+    let mut anchor = (world.jetman.body, world.jetman.link_distance);
+    let mut previous: Option<Entity> = None;
+    let mut impulses = vec![];
+    for entity in world.chain() {
+        let (mut anchor_body, rest_length) = anchor;
+        let (Some(mut body), Some(mut towable)) = (
+            world.entities.bodies.get(entity).copied(),
+            world.entities.towables.get(entity).copied(),
+        ) else {
+            break;
+        };
+        let impulse = resolve_link_at(&mut anchor_body, &mut body, &mut towable, rest_length);
+        match previous {
+            Some(prev) => world.entities.bodies.insert(prev, anchor_body),
+            None => world.jetman.body = anchor_body,
+        }
+        world.entities.bodies.insert(entity, body);
+        world.entities.towables.insert(entity, towable);
+        impulses.push((previous, impulse));
+
+        let link_distance = towable.link_distance;
+        anchor = (body, link_distance);
+        previous = Some(entity);
+    }
+    impulses
+}
+
+/// Let the partner pods lock their beams onto an item within reach, even one Jetman
+/// or another partner is towing already, so the pods can share the load of a heavy
+/// item, and pull on it. Their beams drain and recharge like Jetman's.
+pub fn partners(world: &mut World, step: &Step) {
+    for (index, partner) in world.partners.iter_mut().enumerate() {
+        partner.relink_cooldown = (partner.relink_cooldown - step.seconds).max(0.0);
+        let mass = partner
+            .linked_item
+            .and_then(|entity| world.entities.bodies.get(entity))
+            .map(|body| body.mass);
+        let drained = charge_beam(partner, mass, &world.terrain, step.dt);
+        let severed = step
+            .input
+            .partners
+            .get(index)
+            .is_some_and(|input| input.sever_link);
+        if let Some(entity) = partner.linked_item
+            && (severed || drained)
+        {
+            partner.linked_item = None;
+            partner.relink_cooldown = Jetman::RELINK_DELAY;
+            if let Some(towable) = world.entities.towables.get_mut(entity) {
+                towable.loose = true;
+            }
+        }
+        if partner.linked_item.is_none()
+            && partner.relink_cooldown <= 0.0
+            && partner.beam_energy >= Jetman::BEAM_ENGAGE
+        {
+            let position = partner.position();
+            let found = world
+                .entities
+                .items()
+                .find(|(_, _, item)| (*item - position).length() < partner.link_distance);
+            if let Some((entity, _, item)) = found {
+                // the beam grabs the item by the edge facing the pod, as Jetman's does
+                if let Some(towable) = world.entities.towables.get_mut(entity) {
+                    towable.attach_towards(item, position);
+                }
+                partner.linked_item = Some(entity);
+            }
+        }
+        let Some(entity) = partner.linked_item else {
+            continue;
+        };
+        let (Some(mut body), Some(mut towable)) = (
+            world.entities.bodies.get(entity).copied(),
+            world.entities.towables.get(entity).copied(),
+        ) else {
+            partner.linked_item = None;
+            continue;
+        };
+        let impulse = resolve_link_at(
+            &mut partner.body,
+            &mut body,
+            &mut towable,
+            partner.link_distance,
+        );
+        world.entities.bodies.insert(entity, body);
+        world.entities.towables.insert(entity, towable);
+        partner.link_tension = if step.dt > 0.0 {
+            impulse / step.dt
+        } else {
+            0.0
+        };
+        if partner.link_tension > partner.link_strength {
+            partner.overload_frames += 1;
+        } else {
+            partner.overload_frames = 0;
+        }
+        if partner.overload_frames >= Jetman::OVERLOAD_FRAMES_TO_SNAP {
+            partner.overload_frames = 0;
+            partner.linked_item = None;
+            partner.relink_cooldown = Jetman::RELINK_DELAY;
+            if let Some(towable) = world.entities.towables.get_mut(entity) {
+                towable.loose = true;
+            }
+            let middle = (partner.position() + body.position) / 2.0;
+            world.particles.burst(middle, 24, 2.0, GREEN);
+        }
+    }
+}
+
+/// Apply buoyancy and drag to all bodies under water, splashing where they dive in.
+/// Only the pods and the items towed or let go of are pulled down by gravity, so
+/// only they are lifted up by buoyancy.
+pub fn water(world: &mut World, step: &Step) {
+    let gravity = world.gravity;
+    let weighed_down = weighed_down(world);
+    let pods = std::iter::once(&mut world.jetman).chain(world.partners.iter_mut());
+    let bodies =
+        pods.map(|pod| (&mut pod.body, gravity))
+            .chain(world.entities.bodies.iter_mut().map(|(entity, body)| {
+                let gravity = if weighed_down.contains(&entity) {
+                    gravity
+                } else {
+                    Vec2::ZERO
+                };
+                (body, gravity)
+            }));
+    let mut splashes = vec![];
+    for (body, gravity) in bodies {
+        for water in &world.water {
+            if water.entered(body, step.dt) {
+                splashes.push(Vec2::new(body.position.x, water.rect.y));
+            }
+            water.apply(body, gravity);
+        }
+    }
+    for splash in splashes {
+        world
+            .particles
+            .burst(splash, 20, 1.5, Color::new(0.6, 0.8, 1.0, 1.0));
+    }
+    let pod = world.jetman.position();
+    world.jetman.submerged = world.water.iter().any(|water| water.contains(pod));
+}
+
+/// Move Jetman and all other bodies according to the forces acting on them
+pub fn physics(world: &mut World, step: &Step) {
+    world.jetman.update(step.dt);
+    for partner in world.partners.iter_mut() {
+        partner.update(step.dt);
+    }
+    for (_, body) in world.entities.bodies.iter_mut() {
+        body.update(step.dt);
+    }
+    for (_, towable) in world.entities.towables.iter_mut() {
+        towable.turn(step.dt);
+    }
+}
+
+/// Bring the items let go of in flight to rest once they lie still on the ground, and
+/// count those that fell out of the level as lost
+pub fn loose_items(world: &mut World, _step: &Step) {
+    let towed = world.towed();
+    let loose = world
+        .entities
+        .towables
+        .iter()
+        .filter(|(entity, towable)| towable.loose && !towed.contains(entity))
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+    if loose.is_empty() {
+        return;
+    }
+    let extent = world.extent();
+    let mut lost = vec![];
+    for entity in loose {
+        let (Some(body), Some(towable)) = (
+            world.entities.bodies.get_mut(entity),
+            world.entities.towables.get_mut(entity),
+        ) else {
+            continue;
+        };
+        if !extent.contains(body.position) {
+            lost.push(entity);
+            continue;
+        }
+        let ground = towable.size.y / 2.0 + 2.0;
+        let resting = body.velocity.length() < Towable::REST_SPEED
+            && world
+                .terrain
+                .iter()
+                .any(|terrain| terrain.raycast(body.position, Vec2::Y, ground).is_some());
+        if resting {
+            body.velocity = Vec2::ZERO;
+            towable.loose = false;
+        }
+    }
+    for entity in lost {
+        world.destroy_item(entity);
+        world.notice = Some(("Cargo lost!".to_string(), World::NOTICE_SECONDS));
+    }
+}
+
+/// Bounce Jetman and all other bodies off the terrain
+pub fn collision(world: &mut World, _step: &Step) {
+    let velocity = world.jetman.body.velocity;
+    let towed = world
+        .chain()
+        .into_iter()
+        .filter_map(|entity| Some((entity, world.entities.bodies.get(entity)?.velocity)))
+        .collect::<Vec<_>>();
+    let doors = world
+        .doors
+        .iter()
+        .filter_map(|door| door.terrain())
+        .collect::<Vec<_>>();
+    world.jetman.contacts = 0;
+    for terrain in world.terrain.iter().chain(&doors) {
+        if check_collision(&mut world.jetman.body, terrain) {
+            world.jetman.contacts += 1;
+        }
+        for partner in world.partners.iter_mut() {
+            check_collision(&mut partner.body, terrain);
+        }
+        for (entity, body) in world.entities.bodies.iter_mut() {
+            check_collision(body, terrain);
+            if let Some(towable) = world.entities.towables.get_mut(entity) {
+                collide_ends(body, towable, terrain);
+            }
+        }
+    }
+    // hard landings shake the camera, the harder the more
+    let impact = (world.jetman.body.velocity - velocity).length();
+    if impact > CameraShake::HARD_LANDING {
+        world
+            .shake
+            .add_trauma((impact - CameraShake::HARD_LANDING) * 0.4);
+    }
+    // hard impacts while hanging from the beam crack fragile items, or break them apart
+    for (entity, velocity) in towed {
+        let entities = &mut world.entities;
+        let (Some(body), Some(towable)) = (
+            entities.bodies.get(entity),
+            entities.towables.get_mut(entity),
+        ) else {
+            continue;
+        };
+        let position = body.position;
+        if towable.crack((body.velocity - velocity).length()) {
+            world.destroy_item(entity);
+            world.progress.wrecked += 1;
+            world.particles.burst(position, 20, 2.0, LIGHTGRAY);
+        }
+    }
+}
+
+/// Bounce the ends of a long item off the terrain as its middle is, so it can't swing
+/// an end into the ground. The push on an end both moves the item and turns it.
+fn collide_ends(body: &mut Body, towable: &mut Towable, terrain: &Terrain) {
+    for end in towable.ends() {
+        if end == Vec2::ZERO {
+            continue;
+        }
+        let velocity = body.velocity + end.perp() * towable.spin;
+        let mut probe = Body {
+            position: body.position + end,
+            velocity,
+            ..*body
+        };
+        if !check_collision(&mut probe, terrain) {
+            continue;
+        }
+        body.position = probe.position - end;
+        // the change of the end's velocity, shared between moving and turning the item
+        // by how hard each is
+        let change = probe.velocity - velocity;
+        let direction = change.normalize_or_zero();
+        let lever = end.perp_dot(direction);
+        let inertia = towable.inertia(body.mass);
+        let impulse = change.length() / (1.0 / body.mass + lever * lever / inertia);
+        body.velocity += direction * impulse / body.mass;
+        towable.spin += lever * impulse / inertia;
+    }
+}
+
+/// Solve the links and the collisions with the terrain again, as many times more as the
+/// world's solver iterations call for. Solved once each, a link pulls its item into the
+/// ground the collision just pushed it out of, which makes heavy cargo jitter; solved
+/// together a few times, both settle.
+pub fn constraints(world: &mut World, _step: &Step) {
+    let towed = world.towed();
+    if towed.is_empty() && world.jetman.grapple.is_none() {
+        return;
+    }
+    let doors = world
+        .doors
+        .iter()
+        .filter_map(|door| door.terrain())
+        .collect::<Vec<_>>();
+    for _ in 1..world.solver_iterations {
+        world.jetman.hang();
+        pull_chain(world);
+        for partner in world.partners.iter_mut() {
+            if let Some(entity) = partner.linked_item
+                && let (Some(body), Some(towable)) = (
+                    world.entities.bodies.get_mut(entity),
+                    world.entities.towables.get_mut(entity),
+                )
+            {
+                resolve_link_at(&mut partner.body, body, towable, partner.link_distance);
+            }
+        }
+        for terrain in world.terrain.iter().chain(&doors) {
+            check_collision(&mut world.jetman.body, terrain);
+            for partner in world.partners.iter_mut() {
+                check_collision(&mut partner.body, terrain);
+            }
+            for entity in &towed {
+                if let Some(body) = world.entities.bodies.get_mut(*entity) {
+                    check_collision(body, terrain);
+                    if let Some(towable) = world.entities.towables.get_mut(*entity) {
+                        collide_ends(body, towable, terrain);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Let hazardous terrain hurt whatever touches it: the pods take damage for as long
+/// as they touch it, items are destroyed and bombs go off
+pub fn terrain_hazards(world: &mut World, step: &Step) {
+    let damage = |position: Vec2| {
+        let touched = world.query_circle(position, TerrainHazard::REACH).terrain;
+        touched
+            .into_iter()
+            .filter_map(|index| world.terrain[index].hazard)
+            .map(|hazard| hazard.damage())
+            .fold(0.0, f32::max)
+    };
+    let pod = damage(world.jetman.position());
+    let partners = world
+        .partners
+        .iter()
+        .map(|partner| damage(partner.position()))
+        .collect::<Vec<_>>();
+    let destroyed = world
+        .entities
+        .items()
+        .filter(|(_, _, position)| damage(*position) > 0.0)
+        .map(|(entity, towable, _)| (entity, towable.kind))
+        .collect::<Vec<_>>();
+
+    world.jetman.damage(pod * step.seconds);
+    for (partner, damage) in world.partners.iter_mut().zip(partners) {
+        partner.damage(damage * step.seconds);
+    }
+    for (entity, kind) in destroyed {
+        // an earlier blast may already have destroyed the item
+        let Some(position) = world.entities.position(entity) else {
+            continue;
+        };
+        world.destroy_item(entity);
+        match kind {
+            ItemKind::Bomb { .. } => world.explode(position, 60.0),
+            _ => world.particles.burst(position, 20, 2.0, ORANGE),
+        }
+    }
+}
+
+/// Keep the hint line up to date as things move around
+pub fn practice_route(world: &mut World, step: &Step) {
+    if world.practice {
+        world.route_timer -= step.seconds;
+        if world.route_timer <= 0.0 {
+            world.route = find_route(&world.terrain, &world.route_stops()).unwrap_or_default();
+            world.route_timer = 1.0;
+        }
+    }
+}
+
+/// Fill up the tank on any fuel pad Jetman lands on
+pub fn refuel(world: &mut World, step: &Step) {
+    let pods = std::iter::once(&mut world.jetman).chain(world.partners.iter_mut());
+    for jetman in pods {
+        if world.fuel_pads.iter().any(|pad| pad.serves(&jetman.body)) {
+            jetman.refuel(FuelPad::FLOW * step.seconds);
+            if let Some(oxygen) = jetman.oxygen.as_mut() {
+                oxygen.refill(Oxygen::FLOW * step.seconds);
+            }
+        }
+    }
+}
+
+/// Record checkpoints Jetman passes and respawn him if his pod got destroyed
+pub fn checkpoints(world: &mut World, _step: &Step) {
+    let pod = world.jetman.position();
+    for checkpoint in world.checkpoints.iter_mut() {
+        if !checkpoint.reached && (checkpoint.position - pod).length() < Checkpoint::RADIUS {
+            checkpoint.reached = true;
+            world.respawn_point = checkpoint.position;
+        }
+    }
+    if world.jetman.is_destroyed() {
+        world.lose_life();
+    }
+}
+
+/// Drain the beam's energy by the weight of the towed chain, dropping the chain once
+/// it runs out, and recharge it while idle, faster with the pod on the ground
+pub fn beam(world: &mut World, step: &Step) {
+    let mass = world.jetman.linked_item.map(|_| world.towed_mass());
+    if charge_beam(&mut world.jetman, mass, &world.terrain, step.dt) {
+        world.release_chain();
+    }
+}
+
+/// Drain a pod's beam by the mass it tows, if it tows any, or recharge it while idle.
+/// Returns whether the beam ran out of energy and has to let go.
+fn charge_beam(pod: &mut Jetman, towed: Option<f32>, terrain: &[Terrain], dt: f32) -> bool {
+    let Some(mass) = towed else {
+        let landed = pod.velocity().length() < 0.2
+            && terrain
+                .iter()
+                .any(|terrain| terrain.raycast(pod.position(), Vec2::Y, 16.0).is_some());
+        let rate = if landed {
+            Jetman::LANDED_RECHARGE
+        } else {
+            Jetman::BEAM_RECHARGE
+        };
+        pod.beam_energy = (pod.beam_energy + rate * dt).min(pod.max_beam_energy);
+        return false;
+    };
+    pod.beam_energy = (pod.beam_energy - mass * Jetman::BEAM_DRAIN * dt).max(0.0);
+    pod.beam_energy <= 0.0
+}
+
+/// Light the switches the pod bumps into and the items rest on, and unlock the doors
+/// of the vaults whose switches are all lit at once
+pub fn switches(world: &mut World, step: &Step) {
+    let pressing = std::iter::once(world.jetman.position())
+        .chain(world.entities.items().map(|(_, _, position)| position))
+        .collect::<Vec<_>>();
+    let mut opened = vec![];
+    for array in world.switch_arrays.iter_mut() {
+        if array.update(step.seconds, &pressing) {
+            opened.push((array.door, array.color));
+        }
+    }
+    for (door, color) in opened {
+        let Some(door) = door.and_then(|door| world.doors.get_mut(door)) else {
+            continue;
+        };
+        door.unlocked = true;
+        world
+            .particles
+            .burst(door.area.center(), 40, 2.0, color.color());
+        world.shake.add_trauma(0.4);
+    }
+}
+
+/// Unlock the doors whose keys are carried to them, then let the plates, levers and
+/// keys slide the doors open or shut
+pub fn doors(world: &mut World, step: &Step) {
+    let delivered = world
+        .entities
+        .items()
+        .filter_map(|(entity, towable, position)| match towable.kind {
+            ItemKind::Key { door } if world.doors.get(door)?.reaches(position) => {
+                Some((entity, door, position))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    for (entity, door, position) in delivered {
+        world.remove_item(entity);
+        world.doors[door].unlocked = true;
+        world.particles.burst(position, 20, 1.5, GOLD);
+    }
+    let pod = world.jetman.position();
+    let items = world
+        .entities
+        .items()
+        .map(|(_, _, position)| position)
+        .collect::<Vec<_>>();
+    for door in world.doors.iter_mut() {
+        door.update(step.seconds, pod, &items);
+    }
+}
+
+/// Track Jetman and the items passing through the triggers, and carry out the actions
+/// of the triggers firing
+pub fn triggers(world: &mut World, step: &Step) {
+    world.notice = world
+        .notice
+        .take()
+        .map(|(text, time)| (text, time - step.seconds))
+        .filter(|(_, time)| *time > 0.0);
+    world.trigger_events.clear();
+    let mut occupants = vec![(Occupant::Jetman, world.jetman.position())];
+    occupants.extend(
+        world
+            .entities
+            .items()
+            .map(|(entity, _, position)| (Occupant::Item(entity), position)),
+    );
+    let mut actions = vec![];
+    for (index, trigger) in world.triggers.iter_mut().enumerate() {
+        for (occupant, event) in trigger.update(&occupants) {
+            world.trigger_events.push((index, occupant, event));
+            if event == trigger.event && !trigger.fired {
+                trigger.fired = true;
+                actions.push(trigger.action.clone());
+            }
+        }
+    }
+    for action in actions {
+        match action {
+            TriggerAction::OpenDoor(door) => {
+                if let Some(door) = world.doors.get_mut(door) {
+                    door.unlocked = true;
+                    world.particles.burst(door.area.center(), 30, 2.0, GRAY);
+                }
+            }
+            TriggerAction::SpawnDrones(count) => {
+                let center = world.jetman.position();
+                for _ in 0..count {
+                    let angle = world.rng.gen_range(0.0..std::f32::consts::TAU);
+                    world
+                        .entities
+                        .spawn_drone(center + Vec2::from_angle(angle) * 450.0);
+                }
+            }
+            TriggerAction::ShowText(text) => world.notice = Some((text, World::NOTICE_SECONDS)),
+            TriggerAction::Script => {}
+        }
+    }
+}
+
+/// Run the level's scripts: their top level on the first update, then their event
+/// functions for what passed through the triggers and got delivered, and their timers
+pub fn scripts(world: &mut World, step: &Step) {
+    let facts = Facts {
+        time: world.clock,
+        delivered: world.progress.delivered,
+        pod: world.jetman.position(),
+    };
+    let mut commands = vec![];
+    for script in world.scripts.iter_mut() {
+        let mut run = || -> Result<(), String> {
+            commands.extend(script.start(facts)?);
+            for &(trigger, _, event) in &world.trigger_events {
+                commands.extend(script.on_trigger(trigger, event, facts)?);
+            }
+            commands.extend(script.update(step.seconds, facts)?);
+            Ok(())
+        };
+        // a broken script shows what's wrong instead of bringing down the game
+        if let Err(error) = run() {
+            world.notice = Some((error, World::NOTICE_SECONDS));
+        }
+    }
+    for command in commands {
+        world.carry_out(command);
+    }
+}
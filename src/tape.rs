@@ -0,0 +1,283 @@
+use std::path::{Path, PathBuf};
+
+use crate::persistence;
+use crate::replay::Frame;
+use crate::ui::{Action, InputState};
+use crate::world::World;
+
+/// The actions performed on every frame of a run, without anything else a replay keeps,
+/// so it can be written by hand or edited as text and played into a world as its input.
+///
+/// As text, each line names the frames an action is performed on and the action,
+/// like `120 fire` for a single frame or `0-59 thrust` for the first second.
+/// Frames count from 0 and ranges include both ends. Lines for the players flying the
+/// partner pods start with the player, like `p2 0-59 thrust`. The heading the first
+/// player aims the pod at with the mouse follows `aim`, in radians, like `120 aim 1.57`.
+/// Empty lines and lines starting with `#` are skipped. A line with only a frame number
+/// makes the tape at least that long, for waiting with nothing pressed. Tapes recorded
+/// from a run start with the level file and the seed it was flown with, like
+/// `level levels/01-first-delivery.level` and `seed 3`. Tapes run for at most
+/// `MAX_FRAMES` frames.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct InputTape {
+    /// The input of each frame
+    frames: Vec<Frame>,
+    /// The level file the run was flown in, if it came from one
+    level: Option<PathBuf>,
+    /// The seed the run's level was generated from
+    seed: Option<u64>,
+}
+
+impl InputTape {
+    /// The length of every frame on a tape, in seconds
+    pub const FRAME_SECONDS: f32 = World::FIXED_STEP;
+    /// The file the tape of the last completed run is kept in
+    pub const LAST_RUN: &str = "last_run.tape";
+    /// The most players a tape holds the actions of, Jetman's and the partners'
+    const PLAYERS: usize = 4;
+    /// The most frames a tape runs for, an hour's worth
+    pub const MAX_FRAMES: usize = 60 * 60 * 60;
+
+    /// Create an empty tape
+    pub fn new() -> Self {
+        InputTape {
+            frames: vec![],
+            level: None,
+            seed: None,
+        }
+    }
+
+    /// Create an empty tape for a run of the world, noting its level file and seed
+    pub fn for_run(world: &World) -> Self {
+        InputTape {
+            frames: vec![],
+            level: world.level_path().map(Path::to_path_buf),
+            seed: Some(world.seed()),
+        }
+    }
+
+    /// The level file the run was flown in, if the tape notes one
+    pub fn level(&self) -> Option<&Path> {
+        self.level.as_deref()
+    }
+
+    /// The seed the run's level was generated from, if the tape notes one
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Record the actions the player performs in a frame. Tapes play every frame
+    /// at `FRAME_SECONDS`, so runs recorded with steps of other lengths play out
+    /// differently. Frames past `MAX_FRAMES` are left off.
+    pub fn record(&mut self, input: &InputState) {
+        if self.frames.len() < Self::MAX_FRAMES {
+            self.frames.push(Frame::capture(input, Self::FRAME_SECONDS));
+        }
+    }
+
+    /// The number of frames on the tape
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Check whether the tape has no frames
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The input of the given frame, with nothing pressed past the end of the tape
+    pub fn input(&self, frame: usize) -> InputState {
+        self.frames.get(frame).map(Frame::input).unwrap_or_default()
+    }
+
+    /// Play the whole tape into the world, a frame at a time
+    pub fn play(&self, world: &mut World) {
+        for frame in 0..self.len() {
+            world.step(&self.input(frame), Self::FRAME_SECONDS);
+        }
+    }
+
+    /// Read a tape from its text
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut tape = InputTape::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            tape.parse_line(line)
+                .map_err(|error| format!("line {}: {error}", index + 1))?;
+        }
+        Ok(tape)
+    }
+
+    /// Add the actions a single line describes
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        if let Some(path) = line.strip_prefix("level ") {
+            self.level = Some(PathBuf::from(path.trim()));
+            return Ok(());
+        }
+        if let Some(seed) = line.strip_prefix("seed ") {
+            let seed = seed.trim();
+            self.seed = Some(
+                seed.parse()
+                    .map_err(|_| format!("'{seed}' is not a seed"))?,
+            );
+            return Ok(());
+        }
+        // the players count from 1, the first flying Jetman's pod
+        let (player, line) = match line.strip_prefix('p') {
+            Some(rest) => {
+                let (player, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+                match player.parse::<usize>() {
+                    Ok(player @ 1..=Self::PLAYERS) => (player, rest.trim_start()),
+                    _ => return Err(format!("'p{player}' is not a player")),
+                }
+            }
+            None => (1, line),
+        };
+        let (frames, name) = line.split_once(' ').unwrap_or((line, ""));
+        let number = |text: &str| {
+            text.parse::<usize>()
+                .map_err(|_| format!("'{text}' is not a frame"))
+        };
+        let (start, end) = match frames.split_once('-') {
+            Some((start, end)) => (number(start)?, number(end)?),
+            None => (number(frames)?, number(frames)?),
+        };
+        if end < start {
+            return Err(format!("the frames {start}-{end} run backwards"));
+        }
+        if end >= Self::MAX_FRAMES {
+            return Err(format!(
+                "frame {end} is past the {} frames a tape runs for",
+                Self::MAX_FRAMES
+            ));
+        }
+        if self.frames.len() <= end {
+            let empty = Frame {
+                seconds: Self::FRAME_SECONDS,
+                ..Default::default()
+            };
+            self.frames.resize(end + 1, empty);
+        }
+        let name = name.trim();
+        if name.is_empty() {
+            return Ok(());
+        }
+        if let Some(("aim", heading)) = name.split_once(' ') {
+            if player > 1 {
+                return Err("only the first player aims with the mouse".to_string());
+            }
+            let heading = heading.trim();
+            let aim = heading
+                .parse::<f32>()
+                .ok()
+                .filter(|aim| aim.is_finite())
+                .ok_or(format!("'{heading}' is not a heading"))?;
+            for frame in &mut self.frames[start..=end] {
+                frame.aim = Some(aim);
+            }
+            return Ok(());
+        }
+        let action = Action::from_name(name).ok_or(format!("unknown action '{name}'"))?;
+        let bit = 1 << Action::ALL.iter().position(|a| *a == action).unwrap_or(0);
+        for frame in &mut self.frames[start..=end] {
+            let actions = match player {
+                1 => &mut frame.actions,
+                partner => {
+                    if frame.partners.len() < partner - 1 {
+                        frame.partners.resize(partner - 1, 0);
+                    }
+                    &mut frame.partners[partner - 2]
+                }
+            };
+            *actions |= bit;
+        }
+        Ok(())
+    }
+
+    /// Write the tape as text, each action held over consecutive frames on a line of its own
+    pub fn to_text(&self) -> String {
+        let players = 1 + self
+            .frames
+            .iter()
+            .map(|frame| frame.partners.len())
+            .max()
+            .unwrap_or(0);
+        let mut lines = vec![];
+        for player in 1..=players {
+            let actions = |frame: &Frame| match player {
+                1 => frame.actions,
+                partner => frame.partners.get(partner - 2).copied().unwrap_or(0),
+            };
+            for (index, action) in Action::ALL.iter().enumerate() {
+                let held = |frame: usize| actions(&self.frames[frame]) & 1 << index != 0;
+                let mut frame = 0;
+                while frame < self.len() {
+                    if !held(frame) {
+                        frame += 1;
+                        continue;
+                    }
+                    let start = frame;
+                    while frame < self.len() && held(frame) {
+                        frame += 1;
+                    }
+                    lines.push((player, start, frame - 1, action.name().to_string()));
+                }
+            }
+        }
+        let mut frame = 0;
+        while frame < self.len() {
+            let Some(aim) = self.frames[frame].aim else {
+                frame += 1;
+                continue;
+            };
+            let start = frame;
+            while frame < self.len() && self.frames[frame].aim == Some(aim) {
+                frame += 1;
+            }
+            lines.push((1, start, frame - 1, format!("aim {aim}")));
+        }
+        lines.sort_by_key(|(_, start, _, _)| *start);
+        let mut text = format!(
+            "# {} frames of {} seconds\n",
+            self.len(),
+            Self::FRAME_SECONDS
+        );
+        if let Some(level) = &self.level {
+            text.push_str(&format!("level {}\n", level.display()));
+        }
+        if let Some(seed) = self.seed {
+            text.push_str(&format!("seed {seed}\n"));
+        }
+        for (player, start, end, name) in lines {
+            if player > 1 {
+                text.push_str(&format!("p{player} "));
+            }
+            if start == end {
+                text.push_str(&format!("{start} {name}\n"));
+            } else {
+                text.push_str(&format!("{start}-{end} {name}\n"));
+            }
+        }
+        // the last frame keeps the tape as long as it was, even with nothing pressed
+        if let Some(last) = self.len().checked_sub(1) {
+            text.push_str(&format!("{last}\n"));
+        }
+        text
+    }
+
+    /// Read a tape from a text file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = persistence::read_to_string(path)
+            .map_err(|error| format!("can't read {}: {error}", path.display()))?;
+        InputTape::parse(&source).map_err(|error| format!("{}: {error}", path.display()))
+    }
+
+    /// Write the tape to a text file
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        persistence::write(path, self.to_text())
+    }
+}
@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::world::World;
+
+/// Logs the physics of the jet pod after every step of the simulation as rows of a CSV
+/// file, so the effect of tuning constants like gravity and thrust can be plotted
+pub struct Telemetry {
+    writer: BufWriter<File>,
+}
+
+impl Telemetry {
+    /// The names of the columns, in the order they're written
+    pub const COLUMNS: [&str; 9] = [
+        "t",
+        "x",
+        "y",
+        "vx",
+        "vy",
+        "heading",
+        "fuel",
+        "link_tension",
+        "collisions",
+    ];
+
+    /// Start a log in the given file, replacing whatever was in it
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", Self::COLUMNS.join(","))?;
+        Ok(Telemetry { writer })
+    }
+
+    /// Write a row with the state of the pod after the last step
+    pub fn record(&mut self, world: &World) -> io::Result<()> {
+        let jetman = &world.jetman;
+        let body = &jetman.body;
+        writeln!(
+            self.writer,
+            "{:.4},{:.3},{:.3},{:.5},{:.5},{:.5},{:.3},{:.5},{}",
+            world.clock(),
+            body.position.x,
+            body.position.y,
+            body.velocity.x,
+            body.velocity.y,
+            jetman.heading,
+            jetman.fuel,
+            jetman.link_tension,
+            jetman.contacts,
+        )
+    }
+
+    /// Write out the rows still buffered
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
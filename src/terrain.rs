@@ -1,11 +1,18 @@
+use std::collections::{BTreeSet, HashSet};
+use std::sync::OnceLock;
+
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::physics::Body;
+use crate::physics::{Body, Material};
+use crate::render::ShapeBatch;
+use crate::snapshot::RectDef;
 
 /// Shape of a terrain element
+#[derive(Clone, Serialize, Deserialize)]
 pub enum TerrainShape {
     /// Rectangular terrain shape, axis-aligned
-    Rectangle(Rect),
+    Rectangle(#[serde(with = "RectDef")] Rect),
     /// Linear terrain shape
     Line(Vec2, Vec2),
     /// Circular terrain shape
@@ -14,65 +21,525 @@ pub enum TerrainShape {
     Polygon(Vec<Vec2>),
 }
 
+/// Where a ray hit the terrain
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hit {
+    pub point: Vec2,
+    /// The direction the surface faces at the point, towards where the ray came from
+    pub normal: Vec2,
+    /// How far along the ray the point is
+    pub distance: f32,
+}
+
+/// What a hazardous terrain element does to whatever touches it, on top of being as
+/// solid as any other terrain
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum TerrainHazard {
+    /// A pool of lava, melting items and jet pods in moments
+    Lava,
+    /// A strip of spikes, puncturing items and tearing at the hull
+    Spikes,
+}
+
+impl TerrainHazard {
+    /// How close a body has to come to the hazard to touch it
+    pub const REACH: f32 = 10.0;
+
+    /// The hazard of the given name in level files, if there is one
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lava" => Some(TerrainHazard::Lava),
+            "spikes" => Some(TerrainHazard::Spikes),
+            _ => None,
+        }
+    }
+
+    /// The hull damage per second the hazard deals to a jet pod touching it
+    pub fn damage(&self) -> f32 {
+        match self {
+            TerrainHazard::Lava => 4.0,
+            TerrainHazard::Spikes => 1.0,
+        }
+    }
+}
+
 /// A terrain element. Jetman can collide with these.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Terrain {
     shape: TerrainShape,
+    /// The surface material of the terrain element
+    pub material: Material,
+    /// What the terrain element does to whatever touches it, if it's hazardous
+    pub hazard: Option<TerrainHazard>,
+    /// The triangles a polygon is cut into to fill it, worked out when it's first drawn
+    /// after being created or blasted, so a hail of craters costs a single cut.
+    /// Empty for the other shapes.
+    #[serde(skip)]
+    pieces: OnceLock<Vec<[Vec2; 3]>>,
+    /// The number scripts refer to the terrain element by, its place among the terrain
+    /// of the level, which stays the same as the elements before it get blasted away
+    pub(crate) id: usize,
+    /// The smallest axis-aligned rectangle around the terrain element, kept up to date
+    /// as it moves and gets blasted, so whatever is nowhere near it can skip it cheaply
+    #[serde(with = "RectDef")]
+    bounds: Rect,
 }
 
 impl Terrain {
+    /// How far a curve flattened into a polygon strays from the true curve at most,
+    /// unless the level says otherwise
+    pub const TOLERANCE: f32 = 1.0;
+
+    /// Create a terrain element of the given shape
+    fn new(shape: TerrainShape) -> Self {
+        let mut terrain = Terrain {
+            shape,
+            material: Material::DEFAULT,
+            hazard: None,
+            pieces: OnceLock::new(),
+            id: 0,
+            bounds: Rect::default(),
+        };
+        terrain.bounds = terrain.measure();
+        terrain
+    }
+
     /// Create an axis-aligned rectangular terrain
     pub fn rectangle(x: f32, y: f32, w: f32, h: f32) -> Self {
-        Terrain {
-            shape: TerrainShape::Rectangle(Rect::new(x, y, w, h)),
-        }
+        Terrain::new(TerrainShape::Rectangle(Rect::new(x, y, w, h)))
     }
 
     /// Create a linear terrain
     pub fn line(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
-        Terrain {
-            shape: TerrainShape::Line(Vec2::new(x1, y1), Vec2::new(x2, y2)),
-        }
+        Terrain::new(TerrainShape::Line(Vec2::new(x1, y1), Vec2::new(x2, y2)))
     }
 
     /// Create a circular terrain
     pub fn circle(x: f32, y: f32, r: f32) -> Self {
-        Terrain {
-            shape: TerrainShape::Circle(Vec2::new(x, y), r),
+        Terrain::new(TerrainShape::Circle(Vec2::new(x, y), r))
+    }
+
+    /// Create a polygonal terrain from the points around its outline, in either direction
+    pub fn polygon(points: Vec<Vec2>) -> Self {
+        Terrain::new(TerrainShape::Polygon(points))
+    }
+
+    /// Create a polygonal terrain with holes in it, like caves in a hillside. Each hole
+    /// is joined to the outline by a seam cut in from its rightmost point, so the
+    /// holes become part of the outline. Holes must lie inside the outline and apart
+    /// from each other.
+    pub fn with_holes(outline: Vec<Vec2>, mut holes: Vec<Vec<Vec2>>) -> Self {
+        let rightmost = |hole: &[Vec2]| hole.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+        // the rightmost hole first, so the seams of the others can't cross its own
+        holes.sort_by(|a, b| rightmost(b).total_cmp(&rightmost(a)));
+        let outline = holes
+            .into_iter()
+            .filter(|hole| hole.len() >= 3)
+            .fold(outline, cut_seam);
+        Terrain::polygon(outline)
+    }
+
+    /// Create a polygonal terrain whose outline follows a Catmull-Rom spline running
+    /// smoothly through the given points, then straight back from the last to the first.
+    /// The spline is flattened into straight pieces straying no further from it than
+    /// `tolerance`.
+    ///
+    /// The spline is the centripetal kind, which doesn't loop and overshoots far less
+    /// where the points are spaced unevenly, like at the corners of a hill's foot.
+    pub fn spline(points: &[Vec2], tolerance: f32) -> Self {
+        let mut outline = points.first().copied().into_iter().collect::<Vec<_>>();
+        for i in 1..points.len() {
+            let [p1, p2] = [points[i - 1], points[i]];
+            // past the ends the spline carries straight on
+            let p0 = if i >= 2 { points[i - 2] } else { p1 * 2.0 - p2 };
+            let p3 = points.get(i + 1).copied().unwrap_or(p2 * 2.0 - p1);
+            // the knots are spaced by the square roots of the distances between the points
+            let knot = |from: f32, a: Vec2, b: Vec2| from + (b - a).length().sqrt().max(0.01);
+            let t0 = 0.0;
+            let t1 = knot(t0, p0, p1);
+            let t2 = knot(t1, p1, p2);
+            let t3 = knot(t2, p2, p3);
+            let mix =
+                |a: Vec2, b: Vec2, from: f32, to: f32, t: f32| a.lerp(b, (t - from) / (to - from));
+            flatten(&mut outline, tolerance, |share| {
+                let t = t1 + (t2 - t1) * share;
+                let a1 = mix(p0, p1, t0, t1, t);
+                let a2 = mix(p1, p2, t1, t2, t);
+                let a3 = mix(p2, p3, t2, t3, t);
+                let b1 = mix(a1, a2, t0, t2, t);
+                let b2 = mix(a2, a3, t1, t3, t);
+                mix(b1, b2, t1, t2, t)
+            });
         }
+        Terrain::polygon(outline)
     }
 
-    pub fn polygon(segments: Vec<Vec2>) -> Self {
-        Terrain {
-            shape: TerrainShape::Polygon(segments),
+    /// Create a polygonal terrain whose outline is a chain of cubic Bezier curves, then
+    /// straight back from the end of the last to the start of the first. The points are
+    /// the start followed by two control points and an end for each curve, the end of
+    /// one curve being the start of the next. The curves are flattened into straight
+    /// pieces straying no further from them than `tolerance`. Points left over after
+    /// the last full curve are ignored.
+    pub fn bezier(points: &[Vec2], tolerance: f32) -> Self {
+        let mut outline = points.first().copied().into_iter().collect::<Vec<_>>();
+        for curve in points.windows(4).step_by(3) {
+            let [start, first, second, end] = [curve[0], curve[1], curve[2], curve[3]];
+            flatten(&mut outline, tolerance, |t| {
+                let u = 1.0 - t;
+                start * u * u * u
+                    + first * 3.0 * u * u * t
+                    + second * 3.0 * u * t * t
+                    + end * t * t * t
+            });
         }
+        Terrain::polygon(outline)
+    }
+
+    /// Use the given surface material for the terrain element
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
     }
 
-    /// Draw the terrain element
-    pub fn draw(&self) {
+    /// Make the terrain element hazardous
+    pub fn with_hazard(mut self, hazard: TerrainHazard) -> Self {
+        self.hazard = Some(hazard);
+        self
+    }
+
+    /// Move the terrain element by the given offset
+    pub fn translate(&mut self, offset: Vec2) {
         match self.shape {
-            TerrainShape::Rectangle(rect) => {
-                draw_rectangle(rect.x, rect.y, rect.w, rect.h, DARKGREEN);
+            TerrainShape::Rectangle(ref mut rect) => rect.move_to(rect.point() + offset),
+            TerrainShape::Line(ref mut a, ref mut b) => {
+                *a += offset;
+                *b += offset;
+            }
+            TerrainShape::Circle(ref mut c, _) => *c += offset,
+            TerrainShape::Polygon(ref mut points) => {
+                points.iter_mut().for_each(|point| *point += offset);
+                if let Some(pieces) = self.pieces.get_mut() {
+                    pieces
+                        .iter_mut()
+                        .flatten()
+                        .for_each(|point| *point += offset);
+                }
             }
+        }
+        self.bounds.move_to(self.bounds.point() + offset);
+    }
+
+    /// Check whether a point lies inside the terrain element
+    pub fn contains(&self, point: Vec2) -> bool {
+        match self.shape {
+            TerrainShape::Rectangle(rect) => rect.contains(point),
             TerrainShape::Line(a, b) => {
-                draw_line(a.x, a.y, b.x, b.y, 4.0, DARKGREEN);
+                let line = b - a;
+                let t = ((point - a).dot(line) / line.length_squared().max(f32::EPSILON))
+                    .clamp(0.0, 1.0);
+                (point - (a + line * t)).length() < 2.0
+            }
+            TerrainShape::Circle(c, r) => (point - c).length() < r,
+            TerrainShape::Polygon(ref points) => {
+                self.bounds.contains(point) && point_in_polygon(point, points)
+            }
+        }
+    }
+
+    /// The smallest axis-aligned rectangle around the terrain element
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /// Work out the smallest axis-aligned rectangle around the terrain element
+    fn measure(&self) -> Rect {
+        match self.shape {
+            TerrainShape::Rectangle(rect) => rect,
+            TerrainShape::Circle(c, r) => Rect::new(c.x - r, c.y - r, r * 2.0, r * 2.0),
+            _ => {
+                let points = self.outline().into_iter().map(|(a, _)| a);
+                let (min, max) = points.fold(
+                    (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+                    |(min, max), p| (min.min(p), max.max(p)),
+                );
+                // lines have an outline of a single segment, so its end counts as well
+                let (min, max) = match self.shape {
+                    TerrainShape::Line(_, b) => (min.min(b), max.max(b)),
+                    _ => (min, max),
+                };
+                Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+            }
+        }
+    }
+
+    /// Check whether the terrain element overlaps a circle
+    pub fn overlaps_circle(&self, center: Vec2, radius: f32) -> bool {
+        if let TerrainShape::Circle(c, r) = self.shape {
+            return (center - c).length() < r + radius;
+        }
+        self.contains(center)
+            || self.outline().into_iter().any(|(a, b)| {
+                let edge = b - a;
+                let t = ((center - a).dot(edge) / edge.length_squared().max(f32::EPSILON))
+                    .clamp(0.0, 1.0);
+                (center - (a + edge * t)).length() < radius
+            })
+    }
+
+    /// Cast a ray from `origin` in `direction` and find where it first hits the terrain
+    /// element, if it does within `max_distance`. A ray starting inside the element
+    /// hits right at its origin, facing back along the ray.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<Hit> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec2::ZERO {
+            return None;
+        }
+        if self.contains(origin) {
+            return Some(Hit {
+                point: origin,
+                normal: -direction,
+                distance: 0.0,
+            });
+        }
+        let hit = match self.shape {
+            TerrainShape::Circle(center, radius) => {
+                // solve |origin + direction * t - center| = radius for the nearer t
+                let to_origin = origin - center;
+                let b = to_origin.dot(direction);
+                let c = to_origin.length_squared() - radius * radius;
+                let discriminant = b * b - c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let distance = -b - discriminant.sqrt();
+                let point = origin + direction * distance;
+                (distance >= 0.0).then(|| Hit {
+                    point,
+                    normal: (point - center).normalize_or_zero(),
+                    distance,
+                })
             }
-            TerrainShape::Circle(c, r) => {
-                draw_circle(c.x, c.y, r, DARKGREEN);
+            _ => self
+                .outline()
+                .into_iter()
+                .filter_map(|(a, b)| raycast_segment(origin, direction, a, b))
+                .min_by(|x, y| x.distance.total_cmp(&y.distance)),
+        };
+        hit.filter(|hit| hit.distance <= max_distance)
+    }
+
+    /// The line segments making up the outline of the terrain element
+    pub fn outline(&self) -> Vec<(Vec2, Vec2)> {
+        let close = |points: &[Vec2]| {
+            (0..points.len())
+                .map(|i| (points[i], points[(i + 1) % points.len()]))
+                .collect()
+        };
+        match self.shape {
+            TerrainShape::Rectangle(rect) => close(&[
+                rect.point(),
+                rect.point() + vec2(rect.w, 0.0),
+                rect.point() + rect.size(),
+                rect.point() + vec2(0.0, rect.h),
+            ]),
+            TerrainShape::Line(a, b) => vec![(a, b)],
+            TerrainShape::Circle(c, r) => close(
+                &(0..16)
+                    .map(|i| c + Vec2::from_angle(i as f32 / 16.0 * std::f32::consts::TAU) * r)
+                    .collect::<Vec<_>>(),
+            ),
+            TerrainShape::Polygon(ref points) => close(points),
+        }
+    }
+
+    /// The edges of the outline on the surface of the terrain element, leaving out the
+    /// seams joining the holes of a polygon to its outline, which run through its inside
+    /// once in each direction
+    pub fn surface(&self) -> Vec<(Vec2, Vec2)> {
+        let outline = self.outline();
+        let key = |a: Vec2, b: Vec2| [a.x, a.y, b.x, b.y].map(f32::to_bits);
+        let edges = outline
+            .iter()
+            .map(|(a, b)| key(*a, *b))
+            .collect::<HashSet<_>>();
+        outline
+            .into_iter()
+            .filter(|(a, b)| !edges.contains(&key(*b, *a)))
+            .collect()
+    }
+
+    /// Blast a crater into the terrain element.
+    /// Returns true if the element has been destroyed entirely.
+    pub fn damage(&mut self, center: Vec2, radius: f32) -> bool {
+        let inside = |p: Vec2| (p - center).length() < radius;
+        match self.shape {
+            TerrainShape::Rectangle(rect) => {
+                inside(rect.point()) && inside(rect.point() + rect.size())
+            }
+            TerrainShape::Line(a, b) => inside(a) && inside(b),
+            TerrainShape::Circle(c, r) => (c - center).length() + r < radius,
+            TerrainShape::Polygon(ref mut points) => {
+                // refine the edges crossing the blast so the crater gets a round shape
+                let mut refined = vec![];
+                for i in 0..points.len() {
+                    let a = points[i];
+                    let b = points[(i + 1) % points.len()];
+                    refined.push(a);
+                    let length = (b - a).length();
+                    let closest =
+                        a + (b - a) * ((center - a).dot(b - a) / (length * length)).clamp(0.0, 1.0);
+                    if inside(closest) {
+                        let steps = (length / 8.0) as usize;
+                        for step in 1..steps {
+                            refined.push(a.lerp(b, step as f32 / steps as f32));
+                        }
+                    }
+                }
+                // push every vertex caught in the blast out to its rim
+                for point in refined.iter_mut() {
+                    if inside(*point) {
+                        let direction = (*point - center).try_normalize().unwrap_or(Vec2::Y);
+                        *point = center + direction * radius;
+                    }
+                }
+                self.pieces = OnceLock::new();
+                *points = refined;
+                self.bounds = self.measure();
+                false
             }
+        }
+    }
+
+    /// The fill and outline colors hinting at the terrain's material
+    fn colors(&self) -> (Color, Color) {
+        if self.hazard == Some(TerrainHazard::Lava) {
+            (Color::from_hex(0xD83A10), ORANGE)
+        } else if self.hazard == Some(TerrainHazard::Spikes) {
+            (DARKGRAY, LIGHTGRAY)
+        } else if self.material == Material::ICE {
+            (Color::from_hex(0x9FD8F0), Color::from_hex(0xCFF0FF))
+        } else if self.material == Material::RUBBER {
+            (Color::from_hex(0xC0306A), PINK)
+        } else if self.material == Material::MUD {
+            (Color::from_hex(0x5A3A1A), BROWN)
+        } else if self.material.is_springy() {
+            (Color::from_hex(0x2060C0), SKYBLUE)
+        } else if self.material.conveyor != 0.0 {
+            (Color::from_hex(0x404040), YELLOW)
+        } else {
+            (DARKGREEN, LIME)
+        }
+    }
+
+    /// Check whether anything drawn of the terrain element reaches into an area of the world
+    pub fn reaches_into(&self, area: Rect) -> bool {
+        // the spikes and the chevrons stick out of the bounds a little
+        let bounds = self.bounds;
+        Rect::new(
+            bounds.x - 10.0,
+            bounds.y - 10.0,
+            bounds.w + 20.0,
+            bounds.h + 20.0,
+        )
+        .overlaps(&area)
+    }
+
+    /// Add the terrain element to a batch of shapes to draw, unless it lies entirely
+    /// outside the given area of the world. Of polygons only the triangles and edges
+    /// reaching into the area are added, as the ground of large levels is one polygon
+    /// running their whole width.
+    pub fn draw(&self, area: Rect, batch: &mut ShapeBatch) {
+        if !self.reaches_into(area) {
+            return;
+        }
+        let seen = |points: &[Vec2]| {
+            let (min, max) = points.iter().fold(
+                (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+                |(min, max), p| (min.min(*p), max.max(*p)),
+            );
+            area.overlaps(&Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+        };
+        let (fill, outline) = self.colors();
+        match self.shape {
+            TerrainShape::Rectangle(rect) => batch.rectangle(rect, fill),
+            TerrainShape::Line(a, b) => batch.line(a, b, 4.0, fill),
+            TerrainShape::Circle(c, r) => batch.circle(c, r, 32, fill),
             TerrainShape::Polygon(ref points) => {
+                let pieces = self.pieces.get_or_init(|| triangulate(points));
+                for [a, b, c] in pieces.iter().filter(|piece| seen(&piece[..])) {
+                    batch.triangle(*a, *b, *c, fill);
+                }
                 for i in 0..points.len() {
                     let a = points[i];
                     let b = points[(i + 1) % points.len()];
-                    draw_line(a.x, a.y, b.x, b.y, 2.0, LIME);
+                    if seen(&[a, b]) {
+                        batch.line(a, b, 2.0, outline);
+                    }
                 }
             }
         }
+        if self.material.conveyor != 0.0 {
+            // chevrons along the top pointing the way the belt runs
+            let bounds = self.bounds();
+            let direction = self.material.conveyor.signum();
+            let count = (bounds.w / 20.0) as usize;
+            for index in 0..count {
+                let x = bounds.x + (index as f32 + 0.5) * 20.0;
+                let y = bounds.y + 4.0;
+                let tip = vec2(x + 4.0 * direction, y);
+                batch.line(vec2(x - 4.0 * direction, y - 3.0), tip, 1.5, outline);
+                batch.line(vec2(x - 4.0 * direction, y + 3.0), tip, 1.5, outline);
+            }
+        }
+        if self.hazard == Some(TerrainHazard::Spikes) {
+            // a row of teeth along the top
+            let bounds = self.bounds();
+            let teeth = (bounds.w / 10.0).max(1.0) as usize;
+            let width = bounds.w / teeth as f32;
+            for tooth in 0..teeth {
+                let left = vec2(bounds.x + tooth as f32 * width, bounds.y);
+                let tip = left + vec2(width / 2.0, -8.0);
+                batch.triangle(left, left + vec2(width, 0.0), tip, outline);
+            }
+        }
     }
 }
 
+/// Turn a grid of cells into terrain, one rectangle for each row of solid cells
+/// of the same material next to each other. The grid is laid out row by row,
+/// `width` cells to a row, with `None` for empty cells.
+pub fn grid(
+    cells: &[Option<Material>],
+    width: usize,
+    cell_size: Vec2,
+    origin: Vec2,
+) -> Vec<Terrain> {
+    let mut terrain = vec![];
+    for (row, cells) in cells.chunks(width.max(1)).enumerate() {
+        let mut column = 0;
+        while column < cells.len() {
+            let Some(material) = cells[column] else {
+                column += 1;
+                continue;
+            };
+            let start = column;
+            while column < cells.len() && cells[column] == Some(material) {
+                column += 1;
+            }
+            let position = origin + vec2(start as f32, row as f32) * cell_size;
+            let size = vec2((column - start) as f32, 1.0) * cell_size;
+            terrain.push(
+                Terrain::rectangle(position.x, position.y, size.x, size.y).with_material(material),
+            );
+        }
+    }
+    terrain
+}
+
 /// Check for collisions between a body and a terrain
-/// and alter the body's position and velocity on collision
-pub fn check_collision(body: &mut Body, terrain: &Terrain) {
+/// and alter the body's position and velocity on collision.
+/// Returns whether the body hit the terrain.
+pub fn check_collision(body: &mut Body, terrain: &Terrain) -> bool {
     match terrain.shape {
         TerrainShape::Rectangle(rect) => {
             let pos = body.position;
@@ -82,7 +549,8 @@ pub fn check_collision(body: &mut Body, terrain: &Terrain) {
                 && pos.y < rect.y + rect.h
             {
                 body.position.y = rect.y - 1.0;
-                body.velocity.y = -body.velocity.y * 0.5;
+                body.bounce(Vec2::NEG_Y, terrain.material);
+                return true;
             }
         }
         TerrainShape::Line(p1, p2) => {
@@ -91,7 +559,7 @@ pub fn check_collision(body: &mut Body, terrain: &Terrain) {
             let to_pos = pos - p1;
             let len_sq = line.length_squared();
             if len_sq == 0.0 {
-                return;
+                return false;
             }
 
             let t = (to_pos.dot(line) / len_sq).clamp(0.0, 1.0);
@@ -101,8 +569,8 @@ pub fn check_collision(body: &mut Body, terrain: &Terrain) {
             if dist < 10.0 {
                 let normal = (pos - closest).normalize();
                 body.position = closest + normal * 10.0;
-                body.velocity -= 2.0 * body.velocity.dot(normal) * normal;
-                body.velocity *= 0.5;
+                body.bounce(normal, terrain.material);
+                return true;
             }
         }
         TerrainShape::Circle(center, radius) => {
@@ -114,31 +582,332 @@ pub fn check_collision(body: &mut Body, terrain: &Terrain) {
             if dist < min_dist {
                 let normal = delta.normalize();
                 body.position = center + normal * min_dist;
-                body.velocity -= 2.0 * body.velocity.dot(normal) * normal;
-                body.velocity *= 0.5;
+                body.bounce(normal, terrain.material);
+                return true;
             }
         }
         TerrainShape::Polygon(ref vertices) => {
-            if point_in_polygon(body.position, vertices) {
-                body.position.y -= 2.0; // crude correction
-                body.velocity.y = -body.velocity.y * 0.5;
+            if terrain.contains(body.position) {
+                // out through the nearest edge, which under an overhang is the one above
+                // and in a cave the one of the cave, never through a seam into the inside
+                let outward = if signed_area(vertices) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                let pos = body.position;
+                let closest = terrain
+                    .surface()
+                    .into_iter()
+                    .map(|(a, b)| {
+                        let edge = b - a;
+                        let t = ((pos - a).dot(edge) / edge.length_squared().max(f32::EPSILON))
+                            .clamp(0.0, 1.0);
+                        let normal = vec2(edge.y, -edge.x).normalize_or_zero() * outward;
+                        (a + edge * t, normal)
+                    })
+                    .min_by(|(x, _), (y, _)| (*x - pos).length().total_cmp(&(*y - pos).length()));
+                if let Some((point, normal)) = closest {
+                    body.position = point + normal;
+                    body.bounce(normal, terrain.material);
+                    return true;
+                }
             }
         }
     }
+    false
+}
+
+/// Add the points along a curve, given as a function from 0.0 at its start to 1.0 at
+/// its end, halving the pieces until none strays further from the curve than `tolerance`
+fn flatten(points: &mut Vec<Vec2>, tolerance: f32, curve: impl Fn(f32) -> Vec2) {
+    /// How often a piece is halved at most, making for up to 1024 pieces per curve
+    const MAX_DEPTH: u32 = 10;
+    /// How often a piece is halved at least, so S-shaped curves that happen to cross
+    /// the straight line between their ends in the middle still get bent
+    const MIN_DEPTH: u32 = 2;
+
+    fn piece(
+        points: &mut Vec<Vec2>,
+        tolerance: f32,
+        curve: &impl Fn(f32) -> Vec2,
+        (from, to): (f32, f32),
+        depth: u32,
+    ) {
+        let (a, b) = (curve(from), curve(to));
+        let middle = (from + to) / 2.0;
+        let chord = b - a;
+        let strays = |t: f32| {
+            let point = curve(t);
+            let along =
+                ((point - a).dot(chord) / chord.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+            (point - (a + chord * along)).length() > tolerance
+        };
+        let bent = depth < MIN_DEPTH
+            || [0.25, 0.5, 0.75]
+                .into_iter()
+                .any(|share| strays(from + (to - from) * share));
+        if depth < MAX_DEPTH && bent {
+            piece(points, tolerance, curve, (from, middle), depth + 1);
+            piece(points, tolerance, curve, (middle, to), depth + 1);
+        } else {
+            points.push(b);
+        }
+    }
+
+    piece(points, tolerance.max(0.01), &curve, (0.0, 1.0), 0);
+}
+
+/// Where a ray hits the line segment from `a` to `b`, if it does
+fn raycast_segment(origin: Vec2, direction: Vec2, a: Vec2, b: Vec2) -> Option<Hit> {
+    let edge = b - a;
+    let denominator = direction.perp_dot(edge);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let to_a = a - origin;
+    let distance = to_a.perp_dot(edge) / denominator;
+    let along = to_a.perp_dot(direction) / denominator;
+    if distance < 0.0 || !(0.0..=1.0).contains(&along) {
+        return None;
+    }
+    // of the two sides of the segment, the one facing the ray
+    let mut normal = edge.perp().normalize_or_zero();
+    if normal.dot(direction) > 0.0 {
+        normal = -normal;
+    }
+    Some(Hit {
+        point: origin + direction * distance,
+        normal,
+        distance,
+    })
 }
 
-fn point_in_polygon(point: Vec2, polygon: &[Vec2]) -> bool {
+/// Twice the area enclosed by a polygon, positive or negative depending on which
+/// way its outline runs
+fn signed_area(polygon: &[Vec2]) -> f32 {
+    (0..polygon.len())
+        .map(|i| polygon[i].perp_dot(polygon[(i + 1) % polygon.len()]))
+        .sum()
+}
+
+/// Check whether a point lies inside a polygon, by whether a ray from it crosses the
+/// outline an odd number of times. Whichever way the outline runs and however its
+/// corners turn, and the seams joining holes to the outline cancel out, being crossed
+/// once in each direction.
+pub(crate) fn point_in_polygon(point: Vec2, polygon: &[Vec2]) -> bool {
     let mut inside = false;
-    let mut j = polygon.len() - 1;
     for i in 0..polygon.len() {
-        let pi = polygon[i];
-        let pj = polygon[j];
-        if ((pi.y > point.y) != (pj.y > point.y))
-            && (point.x < (pj.x - pi.x) * (point.y - pi.y) / (pj.y - pi.y + 0.00001) + pi.x)
+        let a = polygon[i];
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (b.x - a.x) * (point.y - a.y) / (b.y - a.y)
         {
             inside = !inside;
         }
-        j = i;
     }
     inside
 }
+
+/// Check whether a point lies inside a triangle or on its edges, whichever way it winds
+fn point_in_triangle(point: Vec2, [a, b, c]: &[Vec2; 3]) -> bool {
+    let sides = [
+        (*b - *a).perp_dot(point - *a),
+        (*c - *b).perp_dot(point - *b),
+        (*a - *c).perp_dot(point - *c),
+    ];
+    !(sides.iter().any(|side| *side < 0.0) && sides.iter().any(|side| *side > 0.0))
+}
+
+/// Join a hole to the outline around it by a seam from the hole's rightmost point to
+/// a point of the outline it can see, going around the hole the other way than around
+/// the outline
+fn cut_seam(mut outline: Vec<Vec2>, mut hole: Vec<Vec2>) -> Vec<Vec2> {
+    if signed_area(&outline) < 0.0 {
+        outline.reverse();
+    }
+    if signed_area(&hole) > 0.0 {
+        hole.reverse();
+    }
+    let start = (0..hole.len())
+        .max_by(|a, b| hole[*a].x.total_cmp(&hole[*b].x))
+        .unwrap_or_default();
+    let from = hole[start];
+    // the nearest edge of the outline straight to the right
+    let count = outline.len();
+    let mut nearest: Option<(f32, usize)> = None;
+    for i in 0..count {
+        let (a, b) = (outline[i], outline[(i + 1) % count]);
+        if (a.y > from.y) == (b.y > from.y) {
+            continue;
+        }
+        let x = a.x + (b.x - a.x) * (from.y - a.y) / (b.y - a.y);
+        if x >= from.x && nearest.is_none_or(|(nearest, _)| x < nearest) {
+            nearest = Some((x, i));
+        }
+    }
+    let Some((x, edge)) = nearest else {
+        return outline;
+    };
+    let hit = vec2(x, from.y);
+    // the end of the edge further right, unless a corner of the outline pokes into the
+    // triangle between the two and blocks the view; then the corner closest in angle
+    let far = if outline[edge].x > outline[(edge + 1) % count].x {
+        edge
+    } else {
+        (edge + 1) % count
+    };
+    let slope = |p: Vec2| (p.y - from.y).abs() / (p.x - from.x).max(f32::EPSILON);
+    let to = (0..count)
+        .filter(|i| {
+            *i != far
+                && outline[*i].x > from.x
+                && point_in_triangle(outline[*i], &[from, hit, outline[far]])
+        })
+        .min_by(|a, b| slope(outline[*a]).total_cmp(&slope(outline[*b])))
+        .unwrap_or(far);
+    let mut joined = outline[..=to].to_vec();
+    joined.extend((0..=hole.len()).map(|i| hole[(start + i) % hole.len()]));
+    joined.extend_from_slice(&outline[to..]);
+    joined
+}
+
+/// Cut a polygon into triangles by clipping ears, the corners whose triangle has no other
+/// point of the polygon in it, one after the other. Only the corners turning the other
+/// way can lie inside an ear, so only those are checked. Self-intersecting outlines, like
+/// the ones craters leave behind, don't always have an ear left; then the next corner
+/// gets clipped anyway, so the polygon is still covered, just less exactly.
+fn triangulate(polygon: &[Vec2]) -> Vec<[Vec2; 3]> {
+    let mut points = polygon.to_vec();
+    points.dedup();
+    while points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    let count = points.len();
+    if count < 3 {
+        return vec![];
+    }
+    // counterclockwise, so ears are the corners turning left
+    if signed_area(&points) < 0.0 {
+        points.reverse();
+    }
+    // the corners left, linked both ways around the outline so clipping one is cheap
+    let mut next = (0..count).map(|i| (i + 1) % count).collect::<Vec<_>>();
+    let mut previous = (0..count)
+        .map(|i| (i + count - 1) % count)
+        .collect::<Vec<_>>();
+    let convex = |previous: &[usize], next: &[usize], corner: usize| {
+        let (a, b, c) = (
+            points[previous[corner]],
+            points[corner],
+            points[next[corner]],
+        );
+        (b - a).perp_dot(c - b) > 0.0
+    };
+    let mut reflex = (0..count)
+        .filter(|corner| !convex(&previous, &next, *corner))
+        .collect::<BTreeSet<_>>();
+    let mut triangles = vec![];
+    let mut left = count;
+    let mut corner = 0;
+    let mut misses = 0;
+    while left > 3 {
+        let (before, after) = (previous[corner], next[corner]);
+        let [a, b, c] = [points[before], points[corner], points[after]];
+        let ear = !reflex.contains(&corner)
+            && !reflex.iter().any(|other| {
+                let p = points[*other];
+                p != a && p != b && p != c && point_in_triangle(p, &[a, b, c])
+            });
+        if ear || misses >= left {
+            triangles.push([a, b, c]);
+            next[before] = after;
+            previous[after] = before;
+            reflex.remove(&corner);
+            left -= 1;
+            for neighbour in [before, after] {
+                if convex(&previous, &next, neighbour) {
+                    reflex.remove(&neighbour);
+                } else {
+                    reflex.insert(neighbour);
+                }
+            }
+            misses = 0;
+        } else {
+            misses += 1;
+        }
+        corner = after;
+    }
+    triangles.push([
+        points[previous[corner]],
+        points[corner],
+        points[next[corner]],
+    ]);
+    triangles
+}
+
+/// A funnel-shaped receiver that guides dropped items down to its throat,
+/// where a teleporter picks them up.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Funnel {
+    /// The center of the funnel's narrow bottom opening
+    pub throat: Vec2,
+    /// The width of the wide top opening
+    pub mouth_width: f32,
+    /// The width of the narrow bottom opening
+    pub throat_width: f32,
+    /// The vertical distance between mouth and throat
+    pub depth: f32,
+}
+
+impl Funnel {
+    /// Create a new funnel whose throat is centered at the given position
+    pub fn new(throat: Vec2, mouth_width: f32, throat_width: f32, depth: f32) -> Self {
+        Funnel {
+            throat,
+            mouth_width,
+            throat_width,
+            depth,
+        }
+    }
+
+    /// Create the terrain elements forming the funnel's walls,
+    /// made of ice so items slide down instead of bouncing out
+    pub fn walls(&self) -> Vec<Terrain> {
+        let top = self.throat.y - self.depth;
+        let bottom = self.throat.y;
+        let mouth = self.mouth_width / 2.0;
+        let throat = self.throat_width / 2.0;
+        vec![
+            Terrain::line(self.throat.x - mouth, top, self.throat.x - throat, bottom)
+                .with_material(Material::ICE),
+            Terrain::line(self.throat.x + mouth, top, self.throat.x + throat, bottom)
+                .with_material(Material::ICE),
+        ]
+    }
+
+    /// Check whether a point lies between the funnel's walls
+    pub fn contains(&self, point: Vec2) -> bool {
+        let top = self.throat.y - self.depth;
+        if point.y < top || point.y > self.throat.y {
+            return false;
+        }
+        // interpolate the half-width between mouth and throat
+        let t = (point.y - top) / self.depth;
+        let half_width = (self.mouth_width + (self.throat_width - self.mouth_width) * t) / 2.0;
+        (point.x - self.throat.x).abs() < half_width
+    }
+
+    /// Nudge a body inside the funnel towards the throat and damp its sideways motion,
+    /// so that items slide down the walls rather than bouncing back out
+    pub fn guide(&self, body: &mut Body) {
+        if !self.contains(body.position) {
+            return;
+        }
+        let offset = self.throat.x - body.position.x;
+        body.apply_force(Vec2::new(offset * 0.002, 0.005) * body.mass);
+        body.velocity.x *= 0.9;
+        // never let the walls bounce an item back up through the mouth
+        body.velocity.y = body.velocity.y.max(0.0);
+    }
+}
@@ -0,0 +1,211 @@
+use macroquad::prelude::*;
+use roxmltree::{Document, Node};
+
+use crate::level::{Level, Placement, Properties, Registry};
+use crate::level_file;
+use crate::physics::Material;
+use crate::terrain::{self, Terrain};
+
+/// The bits of a tile id telling how the tile is flipped, rather than which tile it is
+const FLIP_FLAGS: u32 = 0xF000_0000;
+
+/// Parse a map made with the Tiled editor, saved as a `.tmx` file.
+///
+/// The tiles of every tile layer are solid terrain, made of the layer's `material`
+/// property if it has one. Objects are placed by their class, as known to the
+/// level `Registry`; any other rectangle, ellipse, polygon or polyline is terrain,
+/// again of the object's `material`. Objects are taken as they are, without rotation.
+/// Hidden layers are left out, and infinite maps aren't supported.
+/// The map's properties set up the rest of the level, see `Level::set_properties`.
+pub fn parse(source: &str, seed: u64) -> Result<Level, String> {
+    let document = Document::parse(source).map_err(|error| error.to_string())?;
+    let map = document.root_element();
+    if !map.has_tag_name("map") {
+        return Err("not a Tiled map".to_string());
+    }
+    if map.attribute("infinite") == Some("1") {
+        return Err(
+            "infinite maps aren't supported, turn 'Infinite' off in the map's properties"
+                .to_string(),
+        );
+    }
+    let mut level = Level::new("Untitled");
+    level.seed = seed;
+    level.set_properties(&properties(map))?;
+
+    let registry = Registry::new();
+    let tile_size = vec2(attribute(map, "tilewidth")?, attribute(map, "tileheight")?);
+    add_layers(&mut level, &registry, map, tile_size)?;
+    Ok(level)
+}
+
+/// Add the visible layers of the map, or of a group of layers, to the level. Only the
+/// layers themselves are read, so the collision shapes of the tiles in the map's
+/// tilesets don't turn into terrain.
+fn add_layers(
+    level: &mut Level,
+    registry: &Registry,
+    parent: Node,
+    tile_size: Vec2,
+) -> Result<(), String> {
+    let visible = |node: &Node| node.is_element() && node.attribute("visible") != Some("0");
+    for layer in parent.children().filter(visible) {
+        match layer.tag_name().name() {
+            "layer" => add_tiles(level, layer, tile_size)
+                .map_err(|error| format!("layer '{}': {error}", name(layer)))?,
+            "objectgroup" => {
+                for object in layer.children().filter(|node| node.has_tag_name("object")) {
+                    add_object(level, registry, object)
+                        .map_err(|error| format!("object '{}': {error}", name(object)))?;
+                }
+            }
+            "group" => add_layers(level, registry, layer, tile_size)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Add the solid tiles of a tile layer to the level
+fn add_tiles(level: &mut Level, layer: Node, tile_size: Vec2) -> Result<(), String> {
+    let width = attribute(layer, "width")? as usize;
+    let material = material(layer)?;
+    let data = layer
+        .children()
+        .find(|node| node.has_tag_name("data"))
+        .ok_or("no tile data")?;
+    if data.children().any(|node| node.has_tag_name("chunk")) {
+        return Err("tiles in chunks, as infinite maps have them, aren't supported".to_string());
+    }
+    let tiles = match data.attribute("encoding") {
+        Some("csv") => data
+            .text()
+            .unwrap_or_default()
+            .split(',')
+            .map(|tile| tile.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| error.to_string())?,
+        None => data
+            .children()
+            .filter(|node| node.has_tag_name("tile"))
+            .map(|tile| tile.attribute("gid").map_or(Ok(0), str::parse::<u32>))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|error| error.to_string())?,
+        Some(other) => {
+            return Err(format!(
+                "tiles encoded as {other} aren't supported, save the map with CSV layers"
+            ));
+        }
+    };
+    let cells = tiles
+        .iter()
+        .map(|tile| (tile & !FLIP_FLAGS != 0).then_some(material))
+        .collect::<Vec<_>>();
+    level
+        .terrain
+        .extend(terrain::grid(&cells, width, tile_size, Vec2::ZERO));
+    Ok(())
+}
+
+/// Add whatever an object stands for to the level
+fn add_object(level: &mut Level, registry: &Registry, object: Node) -> Result<(), String> {
+    let placement = Placement {
+        position: vec2(attribute(object, "x")?, attribute(object, "y")?),
+        size: vec2(
+            attribute(object, "width").unwrap_or(0.0),
+            attribute(object, "height").unwrap_or(0.0),
+        ),
+        properties: properties(object),
+    };
+    let class = object
+        .attribute("class")
+        .or_else(|| object.attribute("type"))
+        .unwrap_or_default();
+    if !registry.place(level, class, &placement)? {
+        let material = material(object)?;
+        for terrain in shapes(object, placement.position, placement.size)? {
+            level.terrain.push(terrain.with_material(material));
+        }
+    }
+    Ok(())
+}
+
+/// The terrain making up an object's shape
+fn shapes(object: Node, position: Vec2, size: Vec2) -> Result<Vec<Terrain>, String> {
+    let points = |node: Node| -> Result<Vec<Vec2>, String> {
+        node.attribute("points")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|point| {
+                let (x, y) = point.split_once(',').ok_or("malformed points")?;
+                Ok(position + vec2(parse_number("x", x)?, parse_number("y", y)?))
+            })
+            .collect()
+    };
+    for shape in object.children().filter(Node::is_element) {
+        match shape.tag_name().name() {
+            "ellipse" => {
+                let center = position + size / 2.0;
+                return Ok(vec![Terrain::circle(center.x, center.y, size.x / 2.0)]);
+            }
+            "polygon" => return Ok(vec![Terrain::polygon(points(shape)?)]),
+            "polyline" => {
+                let points = points(shape)?;
+                return Ok(points
+                    .windows(2)
+                    .map(|line| Terrain::line(line[0].x, line[0].y, line[1].x, line[1].y))
+                    .collect());
+            }
+            // points and text don't take up any space
+            "point" | "text" => return Ok(vec![]),
+            _ => {}
+        }
+    }
+    Ok(vec![Terrain::rectangle(
+        position.x, position.y, size.x, size.y,
+    )])
+}
+
+/// The custom properties of a map, layer or object
+fn properties(node: Node) -> Properties {
+    node.children()
+        .filter(|child| child.has_tag_name("properties"))
+        .flat_map(|properties| properties.children())
+        .filter(|property| property.has_tag_name("property"))
+        .filter_map(|property| {
+            let name = property.attribute("name")?;
+            let value = property.attribute("value").or_else(|| property.text())?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// The terrain material set by the `material` property, or the default one
+fn material(node: Node) -> Result<Material, String> {
+    properties(node)
+        .get("material")
+        .map_or(Ok(Material::DEFAULT), |name| level_file::material(name))
+}
+
+/// The number in an attribute of a node
+fn attribute(node: Node, name: &str) -> Result<f32, String> {
+    let value = node
+        .attribute(name)
+        .ok_or_else(|| format!("missing '{name}'"))?;
+    parse_number(name, value)
+}
+
+/// Parse a number, complaining about the named value if it isn't one
+fn parse_number(name: &str, value: &str) -> Result<f32, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{name}' is not a number: '{value}'"))
+}
+
+/// The name of a layer or object, or its id if it doesn't have a name
+fn name<'a>(node: Node<'a, '_>) -> &'a str {
+    node.attribute("name")
+        .or_else(|| node.attribute("id"))
+        .unwrap_or_default()
+}
@@ -0,0 +1,90 @@
+use macroquad::prelude::*;
+
+/// The speeds the simulation can run at, relative to real time
+const SCALES: [f32; 7] = [0.125, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// The index of normal speed in `SCALES`
+const NORMAL: usize = 3;
+
+/// The simulated time of a single stepped frame, in seconds
+const FRAME_SECONDS: f32 = 1.0 / 60.0;
+
+/// Debugging controls over how fast the simulation runs, independent of rendering:
+/// `[` and `]` slow it down and speed it up, `\` freezes it and `.` steps a frozen
+/// simulation by a single frame
+pub struct TimeControl {
+    /// The index of the current speed in `SCALES`
+    scale: usize,
+    /// Whether the simulation stands still until stepped
+    frozen: bool,
+    /// Whether the frozen simulation advances a frame this time
+    step: bool,
+}
+
+impl TimeControl {
+    /// Create time controls running the simulation at normal speed
+    pub fn new() -> Self {
+        TimeControl {
+            scale: NORMAL,
+            frozen: false,
+            step: false,
+        }
+    }
+
+    /// Read the keyboard for changes to the speed and frame steps
+    pub fn update(&mut self) {
+        if is_key_pressed(KeyCode::LeftBracket) {
+            self.scale = self.scale.saturating_sub(1);
+        }
+        if is_key_pressed(KeyCode::RightBracket) {
+            self.scale = (self.scale + 1).min(SCALES.len() - 1);
+        }
+        if is_key_pressed(KeyCode::Backslash) {
+            self.frozen = !self.frozen;
+        }
+        self.step = self.frozen && is_key_pressed(KeyCode::Period);
+    }
+
+    /// The simulated seconds for a rendered frame of the given duration,
+    /// or `None` if the simulation stands still this frame
+    pub fn seconds(&self, frame_seconds: f32) -> Option<f32> {
+        let scale = SCALES[self.scale];
+        if self.step {
+            Some(FRAME_SECONDS * scale)
+        } else if self.frozen {
+            None
+        } else {
+            Some(frame_seconds * scale)
+        }
+    }
+
+    /// Whether the simulation runs as it normally would
+    pub fn is_normal(&self) -> bool {
+        self.scale == NORMAL && !self.frozen
+    }
+
+    /// Show the speed in the bottom right corner, unless it is normal
+    pub fn draw(&self) {
+        if self.is_normal() {
+            return;
+        }
+        let mut text = format!("Speed x{}", SCALES[self.scale]);
+        if self.frozen {
+            text.push_str("  FROZEN - press . to step");
+        }
+        let width = measure_text(&text, None, 20, 1.0).width;
+        draw_text(
+            &text,
+            screen_width() - width - 20.0,
+            screen_height() - 20.0,
+            20.0,
+            ORANGE,
+        );
+    }
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
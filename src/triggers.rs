@@ -0,0 +1,111 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::Entity;
+use crate::snapshot::RectDef;
+
+/// Something passing through the region of a trigger
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Occupant {
+    Jetman,
+    Item(Entity),
+}
+
+/// What a trigger reacts to
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum TriggerEvent {
+    /// Jetman or an item came into the region
+    Enter,
+    /// Jetman or an item left the region
+    Exit,
+}
+
+impl TriggerEvent {
+    /// The name of the event in level files
+    pub fn name(&self) -> &'static str {
+        match self {
+            TriggerEvent::Enter => "enter",
+            TriggerEvent::Exit => "exit",
+        }
+    }
+
+    /// The event with the given name
+    pub fn from_name(name: &str) -> Option<TriggerEvent> {
+        [TriggerEvent::Enter, TriggerEvent::Exit]
+            .into_iter()
+            .find(|event| event.name() == name)
+    }
+}
+
+/// What happens when a trigger fires
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum TriggerAction {
+    /// Unlock the door with the given index for good
+    OpenDoor(usize),
+    /// Send the given number of drones after Jetman
+    SpawnDrones(usize),
+    /// Show a line of text in the middle of the screen for a while
+    ShowText(String),
+    /// Nothing but what the level's scripts do, as they hear of every trigger
+    /// Jetman or an item passes through
+    Script,
+}
+
+/// A region nothing collides with, firing its action once when Jetman or an item
+/// first enters or leaves it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Trigger {
+    #[serde(with = "RectDef")]
+    pub area: Rect,
+    pub event: TriggerEvent,
+    pub action: TriggerAction,
+    /// Whether the action has been carried out
+    pub fired: bool,
+    /// What is inside the region right now
+    inside: Vec<Occupant>,
+}
+
+impl Trigger {
+    /// Create a trigger carrying out `action` on `event` in the given region
+    pub fn new(area: Rect, event: TriggerEvent, action: TriggerAction) -> Self {
+        Trigger {
+            area,
+            event,
+            action,
+            fired: false,
+            inside: vec![],
+        }
+    }
+
+    /// Track the occupants at the given positions, returning the events of those that
+    /// came in or went out since the last update
+    pub fn update(&mut self, occupants: &[(Occupant, Vec2)]) -> Vec<(Occupant, TriggerEvent)> {
+        let mut events = vec![];
+        let inside = occupants
+            .iter()
+            .filter(|(_, position)| self.area.contains(*position))
+            .map(|(occupant, _)| *occupant)
+            .collect::<Vec<_>>();
+        for occupant in &inside {
+            if !self.inside.contains(occupant) {
+                events.push((*occupant, TriggerEvent::Enter));
+            }
+        }
+        // whatever is gone from the world altogether counts as having left
+        for occupant in &self.inside {
+            if !inside.contains(occupant) {
+                events.push((*occupant, TriggerEvent::Exit));
+            }
+        }
+        self.inside = inside;
+        events
+    }
+
+    /// Outline the region, dimmed once the trigger has fired. Triggers are only drawn
+    /// in practice mode, so level designers can see them.
+    pub fn draw(&self) {
+        let alpha = if self.fired { 0.2 } else { 0.5 };
+        let Rect { x, y, w, h } = self.area;
+        draw_rectangle_lines(x, y, w, h, 1.0, Color::new(1.0, 0.0, 1.0, alpha));
+    }
+}
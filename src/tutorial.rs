@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+
+use macroquad::prelude::*;
+
+use crate::hud;
+use crate::ui::{Action, Bindings, InputState};
+use crate::world::World;
+
+/// A tooltip teaching one of the game's controls
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Tip {
+    /// Shown when a level starts
+    Fly,
+    /// Shown once the player fired the jetpack
+    Steer,
+    /// Shown when the tractor beam first grabs an item
+    Sever,
+    /// Shown along with the tip on severing
+    Reel,
+    /// Shown when the first enemy shows up
+    Fire,
+}
+
+impl Tip {
+    /// The action that shows the player got the tip
+    fn action(&self) -> Action {
+        match self {
+            Tip::Fly => Action::Thrust,
+            Tip::Steer => Action::TurnLeft,
+            Tip::Sever => Action::Sever,
+            Tip::Reel => Action::ReelIn,
+            Tip::Fire => Action::Fire,
+        }
+    }
+
+    /// The text of the tooltip, naming the keys currently bound to its actions
+    fn text(&self, bindings: &Bindings) -> String {
+        match self {
+            Tip::Fly => format!(
+                "Press {} to fire the jetpack",
+                bindings.label(Action::Thrust)
+            ),
+            Tip::Steer => format!(
+                "Press {} and {} to turn",
+                bindings.label(Action::TurnLeft),
+                bindings.label(Action::TurnRight)
+            ),
+            Tip::Sever => format!("Press {} to sever", bindings.label(Action::Sever)),
+            Tip::Reel => format!(
+                "Hold {} to reel in, {} to reel out",
+                bindings.label(Action::ReelIn),
+                bindings.label(Action::ReelOut)
+            ),
+            Tip::Fire => format!("Press {} to fire the laser", bindings.label(Action::Fire)),
+        }
+    }
+}
+
+/// Shows each tip once, when the game first gets to the situation it is about
+pub struct Tutorial {
+    /// Whether tips are shown at all
+    pub enabled: bool,
+    /// The tips already triggered this session
+    triggered: Vec<Tip>,
+    /// The tips waiting for their turn
+    queue: VecDeque<Tip>,
+    /// The tip shown right now, with the time in seconds it has been shown
+    current: Option<(Tip, f32)>,
+}
+
+impl Tutorial {
+    /// The time in seconds a tip stays up if the player doesn't act on it
+    const DURATION: f32 = 8.0;
+    /// The time in seconds a tip stays up at least
+    const MIN_DURATION: f32 = 1.0;
+
+    /// Create a tutorial that hasn't shown any tips yet
+    pub fn new() -> Self {
+        Tutorial {
+            enabled: true,
+            triggered: vec![],
+            queue: VecDeque::new(),
+            current: None,
+        }
+    }
+
+    /// Queue a tip, unless it has been triggered before
+    fn trigger(&mut self, tip: Tip) {
+        if !self.triggered.contains(&tip) {
+            self.triggered.push(tip);
+            self.queue.push_back(tip);
+        }
+    }
+
+    /// Trigger the tips for what happens in the world and retire the shown tip
+    /// once the player acted on it or it has been up long enough
+    pub fn observe(&mut self, world: &World, input: &InputState, seconds: f32) {
+        if !self.enabled {
+            return;
+        }
+        self.trigger(Tip::Fly);
+        if input.thrust {
+            self.trigger(Tip::Steer);
+        }
+        if world.jetman.linked_item.is_some() {
+            self.trigger(Tip::Sever);
+            self.trigger(Tip::Reel);
+        }
+        if !world.entities.enemies.is_empty() || !world.entities.turrets.is_empty() {
+            self.trigger(Tip::Fire);
+        }
+
+        if let Some((tip, shown)) = self.current.as_mut() {
+            *shown += seconds;
+            let acted = *shown > Self::MIN_DURATION && input.performs(tip.action());
+            if acted || *shown > Self::DURATION {
+                self.current = None;
+            }
+        }
+        if self.current.is_none() {
+            self.current = self.queue.pop_front().map(|tip| (tip, 0.0));
+        }
+    }
+
+    /// Draw the shown tip, if any, looking up the keys bound to it now
+    /// so rebinding shows right away
+    pub fn draw(&self, bindings: &Bindings) {
+        let Some((tip, _)) = self.current.filter(|_| self.enabled) else {
+            return;
+        };
+        let text = tip.text(bindings);
+        let size = measure_text(&text, None, 22, 1.0);
+        let zone = hud::safe_zone();
+        let x = zone.center().x - size.width / 2.0;
+        let y = screen_height() - 70.0;
+        draw_rectangle(
+            x - 10.0,
+            y - size.height - 8.0,
+            size.width + 20.0,
+            size.height + 16.0,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+        draw_text(&text, x, y, 22.0, YELLOW);
+    }
+}
+
+impl Default for Tutorial {
+    fn default() -> Self {
+        Tutorial::new()
+    }
+}
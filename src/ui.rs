@@ -1,6 +1,13 @@
-use macroquad::input::{KeyCode, is_key_down, is_key_pressed};
+use std::collections::HashMap;
+
+use macroquad::input::{
+    KeyCode, MouseButton, is_key_down, is_key_pressed, is_mouse_button_pressed, mouse_position,
+    mouse_wheel,
+};
+use macroquad::math::Vec2;
 
 /// The state of the player's input.
+#[derive(Clone, Default)]
 pub struct InputState {
     /// Whether the player is thrusting.
     pub thrust: bool,
@@ -8,18 +15,488 @@ pub struct InputState {
     pub turn_left: bool,
     /// Whether the player is turning right.
     pub turn_right: bool,
+    /// Whether the player is firing the retro thruster, braking against the heading.
+    pub brake: bool,
+    /// Whether the player is firing the side thruster pushing the pod to its left.
+    pub strafe_left: bool,
+    /// Whether the player is firing the side thruster pushing the pod to its right.
+    pub strafe_right: bool,
     /// Whether the player is severing the link between Jetman and the Item he's linked with.
     pub sever_link: bool,
+    /// Whether the player is reeling the tractor beam in.
+    pub reel_in: bool,
+    /// Whether the player is reeling the tractor beam out.
+    pub reel_out: bool,
+    /// Whether the player is firing the laser.
+    pub fire: bool,
+    /// Whether the player is switching practice mode on or off.
+    pub toggle_practice: bool,
+    /// Whether the player is switching the stability assist on or off.
+    pub toggle_assist: bool,
+    /// Whether the player is turning the pod back upright.
+    pub auto_level: bool,
+    /// Whether the player is shooting the grappling hook or letting go of it.
+    pub grapple: bool,
+    /// Whether the player is winding up a throw of the towed chain, which flies off
+    /// once the key is let go.
+    pub throw: bool,
+    /// The heading the pod turns towards, when the player aims it with the mouse.
+    pub aim: Option<f32>,
+    /// Whether the player is answering yes to a prompt.
+    pub confirm: bool,
+    /// Whether the player is answering no to a prompt.
+    pub decline: bool,
+    /// Whether the player is moving up in a menu.
+    pub menu_up: bool,
+    /// Whether the player is moving down in a menu.
+    pub menu_down: bool,
+    /// Whether the player is picking the highlighted menu entry.
+    pub select: bool,
+    /// Whether the player is going back, pausing the game or leaving a screen.
+    pub back: bool,
+    /// Whether the player is moving left in a menu or dialog.
+    pub menu_left: bool,
+    /// Whether the player is moving right in a menu or dialog.
+    pub menu_right: bool,
+    /// How many lines the player is scrolling down, negative for up.
+    pub scroll: f32,
+    /// How many steps the player is zooming the camera in, negative for out.
+    pub zoom: f32,
+    /// Where the mouse pointer is on the screen.
+    pub pointer: Vec2,
+    /// Whether the player is clicking the left mouse button.
+    pub click: bool,
+    /// Whether the player is jumping back to the previous event of a replay.
+    pub jump_back: bool,
+    /// Whether the player is jumping ahead to the next event of a replay.
+    pub jump_forward: bool,
+    /// The input of the other players flying the partner pods in co-op, in the order of the pods.
+    pub partners: Vec<InputState>,
 }
 
 impl InputState {
-    /// Create an `InputState` from the current state of the keyboard.
+    /// Create an `InputState` from the current state of the keyboard, using the default bindings.
     pub fn from_raylib() -> Self {
+        InputState::from_bindings(&Bindings::new())
+    }
+
+    /// Forget the actions taken once per key press, of all players, so a frame simulated
+    /// in several steps takes them only on the first.
+    pub fn release_presses(&mut self) {
+        self.sever_link = false;
+        self.toggle_practice = false;
+        self.toggle_assist = false;
+        self.grapple = false;
+        for partner in self.partners.iter_mut() {
+            partner.release_presses();
+        }
+    }
+
+    /// Create an `InputState` from the current state of the keyboard and the given bindings.
+    pub fn from_bindings(bindings: &Bindings) -> Self {
         InputState {
-            thrust: is_key_down(KeyCode::Up) || is_key_down(KeyCode::W),
-            turn_left: is_key_down(KeyCode::Left) || is_key_down(KeyCode::A),
-            turn_right: is_key_down(KeyCode::Right) || is_key_down(KeyCode::D),
-            sever_link: is_key_pressed(KeyCode::S) || is_key_pressed(KeyCode::S),
+            thrust: bindings.is_down(Action::Thrust),
+            turn_left: bindings.is_down(Action::TurnLeft),
+            turn_right: bindings.is_down(Action::TurnRight),
+            brake: bindings.is_down(Action::Brake),
+            strafe_left: bindings.is_down(Action::StrafeLeft),
+            strafe_right: bindings.is_down(Action::StrafeRight),
+            sever_link: bindings.is_pressed(Action::Sever),
+            reel_in: bindings.is_down(Action::ReelIn),
+            reel_out: bindings.is_down(Action::ReelOut),
+            fire: bindings.is_down(Action::Fire),
+            toggle_practice: bindings.is_pressed(Action::TogglePractice),
+            toggle_assist: bindings.is_pressed(Action::ToggleAssist),
+            auto_level: bindings.is_down(Action::AutoLevel),
+            grapple: bindings.is_pressed(Action::Grapple),
+            throw: bindings.is_down(Action::Throw),
+            aim: None,
+            confirm: is_key_pressed(KeyCode::Y),
+            decline: is_key_pressed(KeyCode::N),
+            menu_up: is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::W),
+            menu_down: is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::S),
+            select: is_key_pressed(KeyCode::Enter),
+            back: is_key_pressed(KeyCode::Escape),
+            menu_left: is_key_pressed(KeyCode::Left) || is_key_pressed(KeyCode::A),
+            menu_right: is_key_pressed(KeyCode::Right)
+                || is_key_pressed(KeyCode::D)
+                || is_key_pressed(KeyCode::Tab),
+            scroll: scroll_input(),
+            zoom: zoom_input(),
+            pointer: mouse_position().into(),
+            click: is_mouse_button_pressed(MouseButton::Left),
+            jump_back: is_key_pressed(KeyCode::PageUp),
+            jump_forward: is_key_pressed(KeyCode::PageDown),
+            partners: vec![],
+        }
+    }
+
+    /// Whether the player is performing the given action.
+    pub fn performs(&self, action: Action) -> bool {
+        match action {
+            Action::Thrust => self.thrust,
+            Action::TurnLeft => self.turn_left,
+            Action::TurnRight => self.turn_right,
+            Action::Sever => self.sever_link,
+            Action::ReelIn => self.reel_in,
+            Action::ReelOut => self.reel_out,
+            Action::Fire => self.fire,
+            Action::TogglePractice => self.toggle_practice,
+            Action::ToggleAssist => self.toggle_assist,
+            Action::AutoLevel => self.auto_level,
+            Action::Brake => self.brake,
+            Action::StrafeLeft => self.strafe_left,
+            Action::StrafeRight => self.strafe_right,
+            Action::Grapple => self.grapple,
+            Action::Throw => self.throw,
+        }
+    }
+
+    /// Set whether the player is performing the given action.
+    pub fn set(&mut self, action: Action, active: bool) {
+        let field = match action {
+            Action::Thrust => &mut self.thrust,
+            Action::TurnLeft => &mut self.turn_left,
+            Action::TurnRight => &mut self.turn_right,
+            Action::Sever => &mut self.sever_link,
+            Action::ReelIn => &mut self.reel_in,
+            Action::ReelOut => &mut self.reel_out,
+            Action::Fire => &mut self.fire,
+            Action::TogglePractice => &mut self.toggle_practice,
+            Action::ToggleAssist => &mut self.toggle_assist,
+            Action::AutoLevel => &mut self.auto_level,
+            Action::Brake => &mut self.brake,
+            Action::StrafeLeft => &mut self.strafe_left,
+            Action::StrafeRight => &mut self.strafe_right,
+            Action::Grapple => &mut self.grapple,
+            Action::Throw => &mut self.throw,
+        };
+        *field = active;
+    }
+}
+
+/// The actions of the game the player can bind keys to.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    /// Fire the jetpack.
+    Thrust,
+    /// Turn to the left.
+    TurnLeft,
+    /// Turn to the right.
+    TurnRight,
+    /// Sever the tractor beam.
+    Sever,
+    /// Reel the tractor beam in.
+    ReelIn,
+    /// Reel the tractor beam out.
+    ReelOut,
+    /// Fire the laser.
+    Fire,
+    /// Switch practice mode on or off.
+    TogglePractice,
+    /// Switch the stability assist on or off.
+    ToggleAssist,
+    /// Turn the pod back upright.
+    AutoLevel,
+    /// Fire the retro thruster.
+    Brake,
+    /// Fire the side thruster pushing to the left.
+    StrafeLeft,
+    /// Fire the side thruster pushing to the right.
+    StrafeRight,
+    /// Shoot the grappling hook or let go of it.
+    Grapple,
+    /// Wind up a throw of the towed chain, letting go once released.
+    Throw,
+}
+
+impl Action {
+    /// All actions.
+    pub const ALL: [Action; 15] = [
+        Action::Thrust,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Sever,
+        Action::ReelIn,
+        Action::ReelOut,
+        Action::Fire,
+        Action::TogglePractice,
+        Action::ToggleAssist,
+        Action::AutoLevel,
+        Action::Brake,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::Grapple,
+        Action::Throw,
+    ];
+
+    /// The name of the action in text files, like `turn-left`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Thrust => "thrust",
+            Action::TurnLeft => "turn-left",
+            Action::TurnRight => "turn-right",
+            Action::Sever => "sever",
+            Action::ReelIn => "reel-in",
+            Action::ReelOut => "reel-out",
+            Action::Fire => "fire",
+            Action::TogglePractice => "toggle-practice",
+            Action::ToggleAssist => "toggle-assist",
+            Action::AutoLevel => "auto-level",
+            Action::Brake => "brake",
+            Action::StrafeLeft => "strafe-left",
+            Action::StrafeRight => "strafe-right",
+            Action::Grapple => "grapple",
+            Action::Throw => "throw",
+        }
+    }
+
+    /// The action of the given name, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// The keys bound to each action.
+pub struct Bindings {
+    keys: HashMap<Action, Vec<KeyCode>>,
+}
+
+impl Bindings {
+    /// Create the default bindings.
+    pub fn new() -> Self {
+        let keys = HashMap::from([
+            (Action::Thrust, vec![KeyCode::Up, KeyCode::W]),
+            (Action::TurnLeft, vec![KeyCode::Left, KeyCode::A]),
+            (Action::TurnRight, vec![KeyCode::Right, KeyCode::D]),
+            (Action::Sever, vec![KeyCode::S]),
+            (Action::ReelIn, vec![KeyCode::Q]),
+            (Action::ReelOut, vec![KeyCode::E]),
+            (Action::Fire, vec![KeyCode::Space]),
+            (Action::TogglePractice, vec![KeyCode::P]),
+            (Action::ToggleAssist, vec![KeyCode::Z]),
+            (Action::AutoLevel, vec![KeyCode::X]),
+            (Action::Brake, vec![KeyCode::Down, KeyCode::B]),
+            (Action::StrafeLeft, vec![KeyCode::Comma]),
+            (Action::StrafeRight, vec![KeyCode::Period]),
+            (Action::Grapple, vec![KeyCode::G]),
+            (Action::Throw, vec![KeyCode::T]),
+        ]);
+        Bindings { keys }
+    }
+
+    /// The bindings of the first player in co-op, on the arrow keys.
+    pub fn arrows() -> Self {
+        let keys = HashMap::from([
+            (Action::Thrust, vec![KeyCode::Up]),
+            (Action::TurnLeft, vec![KeyCode::Left]),
+            (Action::TurnRight, vec![KeyCode::Right]),
+            (Action::Sever, vec![KeyCode::Down]),
+            (Action::ReelIn, vec![KeyCode::Comma]),
+            (Action::ReelOut, vec![KeyCode::Period]),
+            (Action::Fire, vec![KeyCode::RightControl]),
+            (Action::TogglePractice, vec![KeyCode::P]),
+            (Action::ToggleAssist, vec![KeyCode::Z]),
+            (Action::AutoLevel, vec![KeyCode::Slash]),
+            (Action::Brake, vec![KeyCode::Kp5]),
+            (Action::StrafeLeft, vec![KeyCode::Kp4]),
+            (Action::StrafeRight, vec![KeyCode::Kp6]),
+            (Action::Grapple, vec![KeyCode::Kp0]),
+            (Action::Throw, vec![KeyCode::KpEnter]),
+        ]);
+        Bindings { keys }
+    }
+
+    /// The bindings of the second player in co-op, on the left of the keyboard.
+    pub fn wasd() -> Self {
+        let keys = HashMap::from([
+            (Action::Thrust, vec![KeyCode::W]),
+            (Action::TurnLeft, vec![KeyCode::A]),
+            (Action::TurnRight, vec![KeyCode::D]),
+            (Action::Sever, vec![KeyCode::S]),
+            (Action::ReelIn, vec![KeyCode::Q]),
+            (Action::ReelOut, vec![KeyCode::E]),
+            (Action::Fire, vec![KeyCode::LeftShift]),
+            (Action::AutoLevel, vec![KeyCode::G]),
+            (Action::Brake, vec![KeyCode::C]),
+            (Action::StrafeLeft, vec![KeyCode::X]),
+            (Action::StrafeRight, vec![KeyCode::V]),
+            (Action::Grapple, vec![KeyCode::F]),
+            (Action::Throw, vec![KeyCode::R]),
+        ]);
+        Bindings { keys }
+    }
+
+    /// The keys bound to an action.
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Bind an action to the given keys, replacing its previous keys.
+    pub fn bind(&mut self, action: Action, keys: Vec<KeyCode>) {
+        self.keys.insert(action, keys);
+    }
+
+    /// Whether any key bound to the action is held down.
+    pub fn is_down(&self, action: Action) -> bool {
+        self.keys(action).iter().any(|&key| is_key_down(key))
+    }
+
+    /// Whether any key bound to the action was pressed this frame.
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.keys(action).iter().any(|&key| is_key_pressed(key))
+    }
+
+    /// The keys bound to an action as shown to the player, like `[Up]/[W]`.
+    pub fn label(&self, action: Action) -> String {
+        let keys = self.keys(action);
+        if keys.is_empty() {
+            return "[unbound]".to_string();
         }
+        keys.iter()
+            .map(|key| format!("[{key:?}]"))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings::new()
+    }
+}
+
+/// The keys actions can be bound to, named in settings files as they are shown, like `Up`.
+/// Escape and F11 are left out, as they always go back and switch to fullscreen.
+const KEYS: [KeyCode; 94] = [
+    KeyCode::Space,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Right,
+    KeyCode::Left,
+    KeyCode::Down,
+    KeyCode::Up,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F12,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::KpDecimal,
+    KeyCode::KpDivide,
+    KeyCode::KpMultiply,
+    KeyCode::KpSubtract,
+    KeyCode::KpAdd,
+    KeyCode::KpEnter,
+    KeyCode::LeftShift,
+    KeyCode::LeftControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::RightAlt,
+];
+
+/// The key of the given name, as shown to the player, if actions can be bound to it.
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEYS.into_iter().find(|key| format!("{key:?}") == name)
+}
+
+/// The steps to zoom in by from the mouse wheel and the `=` and `-` keys, negative for out
+fn zoom_input() -> f32 {
+    let mut steps = mouse_wheel().1.signum();
+    if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+        steps += 1.0;
+    }
+    if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+        steps -= 1.0;
+    }
+    steps
+}
+
+/// The lines to scroll by from the arrow keys and the mouse wheel
+fn scroll_input() -> f32 {
+    let wheel = mouse_wheel().1;
+    let mut lines = if wheel > 0.0 {
+        -1.0
+    } else if wheel < 0.0 {
+        1.0
+    } else {
+        0.0
+    };
+    if is_key_pressed(KeyCode::Down) {
+        lines += 1.0;
+    }
+    if is_key_pressed(KeyCode::Up) {
+        lines -= 1.0;
     }
+    lines
 }
@@ -0,0 +1,230 @@
+use macroquad::prelude::*;
+
+#[cfg(feature = "sound")]
+use macroquad::audio::{PlaySoundParams, Sound, load_sound_from_bytes, play_sound};
+
+use crate::camera::CameraShake;
+use crate::hud::{self, Anchor};
+use crate::physics::Bodied;
+use crate::settings;
+use crate::world::World;
+
+/// The share of the tank below which the fuel runs low
+const LOW_FUEL: f32 = 0.2;
+/// The height above the terrain below which sinking fast is dangerous
+const SINK_ALTITUDE: f32 = 120.0;
+/// The share of the air left below which the pilot is about to suffocate
+pub const LOW_OXYGEN: f32 = 0.25;
+/// The share of the link's strength above which the towed chain is about to snap
+const STRAIN: f32 = 0.8;
+
+/// Something about the flight the pilot should see to right away
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Warning {
+    LowFuel,
+    /// The pilot close to running out of air
+    LowOxygen,
+    /// Sinking fast close to the ground, as the radar altimeter tells
+    SinkRate,
+    /// The towed chain close to snapping
+    LinkStrain,
+}
+
+impl Warning {
+    /// All warnings, the most urgent first
+    pub const ALL: [Warning; 4] = [
+        Warning::SinkRate,
+        Warning::LowOxygen,
+        Warning::LinkStrain,
+        Warning::LowFuel,
+    ];
+
+    /// The alert shown while the warning is on
+    pub fn message(&self) -> &'static str {
+        match self {
+            Warning::LowFuel => "LOW FUEL",
+            Warning::LowOxygen => "LOW OXYGEN",
+            Warning::SinkRate => "SINK RATE - PULL UP",
+            Warning::LinkStrain => "LINK STRAIN",
+        }
+    }
+
+    /// Check whether the warning applies to the world as it is
+    pub fn applies(&self, world: &World) -> bool {
+        let jetman = &world.jetman;
+        match self {
+            Warning::LowFuel => jetman.fuel < jetman.max_fuel * LOW_FUEL,
+            Warning::LowOxygen => jetman
+                .oxygen
+                .is_some_and(|oxygen| oxygen.share() < LOW_OXYGEN),
+            // screen y points down, so a positive vertical speed means sinking
+            Warning::SinkRate => {
+                jetman.velocity().y > CameraShake::HARD_LANDING
+                    && world
+                        .altitude()
+                        .is_some_and(|altitude| altitude < SINK_ALTITUDE)
+            }
+            Warning::LinkStrain => {
+                jetman.linked_item.is_some() && jetman.link_tension > jetman.link_strength * STRAIN
+            }
+        }
+    }
+
+    /// The pitch of the warning's beep in hertz, so the pilot tells them apart by ear
+    #[cfg_attr(not(feature = "sound"), allow(dead_code))]
+    fn pitch(&self) -> f32 {
+        match self {
+            Warning::LowFuel => 440.0,
+            Warning::LowOxygen => 784.0,
+            Warning::SinkRate => 988.0,
+            Warning::LinkStrain => 660.0,
+        }
+    }
+}
+
+/// The warnings that apply to the world as it is, the most urgent first
+pub fn check(world: &World) -> Vec<Warning> {
+    Warning::ALL
+        .into_iter()
+        .filter(|warning| warning.applies(world))
+        .collect()
+}
+
+/// Flashes the warnings that are on at the top of the screen and beeps for them:
+/// once when a warning comes on, and again every so often for the most urgent one
+/// while it stays on. Beeps are only heard with the `sound` feature.
+pub struct Alerts {
+    /// The warnings on, the most urgent first
+    active: Vec<Warning>,
+    /// The time in seconds the alerts have been flashing
+    time: f32,
+    /// The time in seconds until the most urgent warning beeps again
+    repeat: f32,
+    /// The beep of each warning
+    #[cfg(feature = "sound")]
+    beeps: Vec<(Warning, Sound)>,
+}
+
+impl Alerts {
+    /// The time in seconds between the beeps of a warning staying on
+    const REPEAT: f32 = 1.5;
+    /// How often the alerts flash per second
+    const FLASH_RATE: f32 = 2.5;
+
+    /// Create the alerts, making up the beeps of the warnings
+    pub async fn load() -> Self {
+        #[cfg(feature = "sound")]
+        let mut beeps = vec![];
+        #[cfg(feature = "sound")]
+        for warning in Warning::ALL {
+            if let Ok(sound) = load_sound_from_bytes(&beep(warning.pitch(), 0.15)).await {
+                beeps.push((warning, sound));
+            }
+        }
+        Alerts {
+            active: vec![],
+            time: 0.0,
+            repeat: 0.0,
+            #[cfg(feature = "sound")]
+            beeps,
+        }
+    }
+
+    /// Turn all alerts off, as when a new level starts
+    pub fn clear(&mut self) {
+        self.active.clear();
+        self.repeat = 0.0;
+    }
+
+    /// Take in the warnings on after a frame of the given length in seconds,
+    /// beeping at the given volume from 0.0 to 1.0 where it's due
+    pub fn update(&mut self, warnings: Vec<Warning>, seconds: f32, volume: f32) {
+        let new = warnings
+            .iter()
+            .copied()
+            .filter(|warning| !self.active.contains(warning))
+            .collect::<Vec<_>>();
+        if self.active.is_empty() {
+            self.time = 0.0;
+        }
+        self.time += seconds;
+        self.repeat -= seconds;
+        self.active = warnings;
+        let due = new.first().copied().or_else(|| {
+            let urgent = self.active.first().copied();
+            urgent.filter(|_| self.repeat <= 0.0)
+        });
+        if let Some(warning) = due {
+            self.repeat = Self::REPEAT;
+            self.beep(warning, volume);
+        }
+    }
+
+    /// Play the beep of a warning
+    #[cfg(feature = "sound")]
+    fn beep(&self, warning: Warning, volume: f32) {
+        if let Some((_, sound)) = self.beeps.iter().find(|(w, _)| *w == warning) {
+            let params = PlaySoundParams {
+                looped: false,
+                volume,
+            };
+            play_sound(sound, params);
+        }
+    }
+
+    /// Play the beep of a warning, which nobody hears without the `sound` feature
+    #[cfg(not(feature = "sound"))]
+    fn beep(&self, _warning: Warning, _volume: f32) {}
+
+    /// Draw the alerts of the warnings on below the time, flashing
+    pub fn draw(&self) {
+        let lit = (self.time * Self::FLASH_RATE).fract() < 0.6;
+        let mut y = 120.0;
+        for warning in &self.active {
+            let text = warning.message();
+            let width = measure_text(text, None, 24, 1.0).width;
+            let at = hud::anchor(Anchor::Top, vec2(-width / 2.0, y));
+            let (background, color) = if lit {
+                (settings::bad(), WHITE)
+            } else {
+                (Color::new(0.0, 0.0, 0.0, 0.6), settings::bad())
+            };
+            draw_rectangle(at.x - 8.0, at.y - 20.0, width + 16.0, 28.0, background);
+            draw_text(text, at.x, at.y, 24.0, color);
+            y += 34.0;
+        }
+    }
+}
+
+/// A short beep of the given pitch in hertz, as a WAV file of 16-bit samples
+#[cfg(feature = "sound")]
+fn beep(pitch: f32, seconds: f32) -> Vec<u8> {
+    const RATE: u32 = 22_050;
+    let count = (RATE as f32 * seconds) as u32;
+    let samples = (0..count).map(|index| {
+        let t = index as f32 / RATE as f32;
+        // fading in and out keeps the beep from clicking
+        let fade = (t / 0.01).min((seconds - t) / 0.01).clamp(0.0, 1.0);
+        let wave = (t * pitch * std::f32::consts::TAU).sin();
+        (wave * fade * 0.5 * i16::MAX as f32) as i16
+    });
+    let data = count * 2;
+    let mut wav = Vec::with_capacity(44 + data as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // uncompressed samples, one channel
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&RATE.to_le_bytes());
+    wav.extend_from_slice(&(RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
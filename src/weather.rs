@@ -0,0 +1,129 @@
+use ::rand::Rng;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A scripted change of the fog density
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct FogKeyframe {
+    /// The time in seconds after the start of the level at which the change begins
+    pub time: f32,
+    /// The density the fog moves towards, from 0.0 (clear) to 1.0 (pea soup)
+    pub density: f32,
+}
+
+/// Fog limiting how far the pilot can see around the jet pod
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Fog {
+    /// The current density, from 0.0 (clear) to 1.0 (pea soup)
+    pub density: f32,
+    /// The density the fog is currently moving towards
+    pub target: f32,
+    /// How fast the density changes, per second
+    pub rate: f32,
+    /// Scripted density changes, ordered by time
+    pub keyframes: Vec<FogKeyframe>,
+    /// Whether the weather picks new random densities on its own
+    pub drifting: bool,
+    /// The time in seconds until the drifting weather picks a new density
+    next_drift: f32,
+}
+
+impl Fog {
+    /// The distance the pilot can see in clear weather
+    pub const MAX_VISIBILITY: f32 = 1200.0;
+    /// The distance the pilot can see in the thickest fog
+    pub const MIN_VISIBILITY: f32 = 60.0;
+    /// The width of the band in which objects fade into the fog
+    const FADE: f32 = 120.0;
+
+    /// Create clear weather without any fog
+    pub fn clear() -> Self {
+        Fog {
+            density: 0.0,
+            target: 0.0,
+            rate: 0.1,
+            keyframes: vec![],
+            drifting: false,
+            next_drift: 0.0,
+        }
+    }
+
+    /// Create fog following the given scripted density changes
+    pub fn scripted(keyframes: Vec<FogKeyframe>) -> Self {
+        Fog {
+            keyframes,
+            ..Fog::clear()
+        }
+    }
+
+    /// Create fog whose density changes randomly over time
+    pub fn drifting() -> Self {
+        Fog {
+            drifting: true,
+            ..Fog::clear()
+        }
+    }
+
+    /// Move the density towards its target and pick up scripted or random changes
+    pub fn update(&mut self, clock: f32, seconds: f32, rng: &mut impl Rng) {
+        if let Some(keyframe) = self.keyframes.iter().rev().find(|k| k.time <= clock) {
+            self.target = keyframe.density;
+        }
+        if self.drifting {
+            self.next_drift -= seconds;
+            if self.next_drift <= 0.0 {
+                self.target = rng.gen_range(0.0..0.9);
+                self.next_drift = rng.gen_range(10.0..30.0);
+            }
+        }
+        let step = self.rate * seconds;
+        self.density += (self.target - self.density).clamp(-step, step);
+    }
+
+    /// The distance the pilot can currently see
+    pub fn visibility(&self) -> f32 {
+        Self::MAX_VISIBILITY + (Self::MIN_VISIBILITY - Self::MAX_VISIBILITY) * self.density
+    }
+
+    /// Check whether something at the given distance from the pilot can still be made out
+    pub fn is_visible(&self, distance: f32) -> bool {
+        self.density <= 0.0 || distance < self.visibility() + Self::FADE
+    }
+
+    /// Draw the fog around the given point in world coordinates,
+    /// thickening with distance until it hides everything beyond the visibility
+    pub fn draw(&self, center: Vec2) {
+        if self.density <= 0.0 {
+            return;
+        }
+        let color = Color::from_hex(0x8C9199);
+        let visibility = self.visibility();
+        let bands = 12;
+        let band = Self::FADE / bands as f32;
+        for i in 0..bands {
+            let alpha = (i + 1) as f32 / bands as f32;
+            draw_circle_lines(
+                center.x,
+                center.y,
+                visibility + band * (i as f32 + 0.5),
+                band + 1.0,
+                Color { a: alpha, ..color },
+            );
+        }
+        // everything further out is hidden completely
+        let outer = 4000.0;
+        draw_circle_lines(
+            center.x,
+            center.y,
+            visibility + Self::FADE + outer / 2.0,
+            outer,
+            color,
+        );
+    }
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Fog::clear()
+    }
+}
@@ -1,207 +1,1458 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ::rand::SeedableRng;
 use macroquad::prelude::*;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
 
+use crate::analysis::Heatmap;
+use crate::camera::{self, AutoZoom, CameraFraming, CameraOptions, CameraShake};
+use crate::cosmetics::Trail;
+use crate::decoration::Decoration;
+use crate::difficulty::Modifiers;
+use crate::director::Director;
+use crate::doors::Door;
+use crate::ecs::Entity;
+use crate::enemy::Wave;
+use crate::entities::Entities;
+use crate::escort::Escort;
+use crate::fluids::Water;
+use crate::ghost::Ghost;
+use crate::hud::{self, Anchor, Contact, FlightReadings};
+use crate::level::Level;
+use crate::level_file;
+use crate::lighting::{self, Light};
+use crate::objective::{Objective, Progress};
+use crate::particles::ParticleSystem;
 use crate::physics::*;
-use crate::terrain::{Terrain, check_collision};
+use crate::pickups::Inventory;
+use crate::post::{self, Effects, ScreenFlash};
+use crate::projectiles::ProjectilePool;
+use crate::query::{Overlaps, circle_overlaps_rect};
+use crate::render::{DrawList, Drawer, Layer, ShapeBatch};
+use crate::route;
+use crate::scripting::{Command, Script};
+use crate::settings;
+use crate::switches::SwitchArray;
+use crate::systems::{self, Step, System};
+use crate::terrain::{Funnel, Hit, Terrain};
+use crate::triggers::{Occupant, Trigger, TriggerEvent};
 use crate::ui::InputState;
-
-fn generate_ground_poly(width: i32, height: i32, segments: usize) -> Vec<Vec2> {
-    let base_y = 500.0;
-    let mut top = vec![];
-
-    for i in 0..=segments {
-        let x = i as f32 * (width as f32 / segments as f32);
-        let y = base_y - macroquad::rand::gen_range(0.0, 80.0);
-        top.push(Vec2::new(x, y));
-    }
-
-    let bottom = (0..=segments)
-        .rev()
-        .map(|i| {
-            let x = i as f32 * (width as f32 / segments as f32);
-            Vec2::new(x, height as f32)
-        })
-        .collect::<Vec<_>>();
-
-    top.extend(bottom);
-    top
-}
+use crate::weather::Fog;
 
 /// The game world containing physics bodies and terrains
+#[derive(Clone, Serialize, Deserialize)]
 pub struct World {
+    /// The name of the level the world was created from
+    level_name: String,
+    /// The file the level was loaded from, if it was. Only the designers editing
+    /// the level need it, so snapshots leave it out.
+    #[serde(skip)]
+    level_path: Option<PathBuf>,
+    /// The seed the world was generated with, shown so runs can be shared and reproduced
+    seed: u64,
+    /// The random generator for everything that happens by chance during play.
+    /// This is the generator behind `StdRng`, named so it can be saved in snapshots.
+    pub(crate) rng: ChaCha12Rng,
     pub jetman: Jetman,
-    items: Vec<Item>,
-    teleports: Vec<Teleporter>,
-    gravity: Vec2,
-    terrain: Vec<Terrain>,
-    camera: Camera2D,
+    /// The other pods in co-op, each flown by a player of its own
+    pub partners: Vec<Jetman>,
+    /// The items, teleporters and hostiles, stored by component
+    pub(crate) entities: Entities,
+    /// The systems updating the world, in the order they run. Snapshots can't hold
+    /// functions, so restored worlds start out with the built-in schedule.
+    #[serde(skip, default = "systems::schedule")]
+    systems: Vec<System>,
+    /// The extra drawers queuing things to draw, in the order they run.
+    /// Snapshots can't hold functions, so restored worlds start out without any.
+    #[serde(skip)]
+    drawers: Vec<Drawer>,
+    pub(crate) funnels: Vec<Funnel>,
+    pub(crate) checkpoints: Vec<Checkpoint>,
+    /// The switches opening vaults, one array per color
+    pub(crate) switch_arrays: Vec<SwitchArray>,
+    /// The doors opened by pressure plates, levers and keys
+    pub(crate) doors: Vec<Door>,
+    /// The scripts running the level's custom logic
+    pub(crate) scripts: Vec<Script>,
+    pub(crate) triggers: Vec<Trigger>,
+    /// What passed in and out of which trigger during the last update
+    pub(crate) trigger_events: Vec<(usize, Occupant, TriggerEvent)>,
+    /// A line of text shown in the middle of the screen, with the seconds it stays up
+    pub(crate) notice: Option<(String, f32)>,
+    pub(crate) fuel_pads: Vec<FuelPad>,
+    /// Where Jetman reappears after losing a jet pod
+    pub(crate) respawn_point: Vec2,
+    /// The number of jet pods the player may still lose before the game is over
+    lives: u32,
+    /// The number of jet pods lost so far
+    deaths: u32,
+    pub(crate) water: Vec<Water>,
+    pub(crate) explosions: Vec<Explosion>,
+    pub(crate) escort: Option<Escort>,
+    pub(crate) waves: Vec<Wave>,
+    pub(crate) director: Option<Director>,
+    /// The time in seconds since the start of the level
+    pub(crate) clock: f32,
+    /// The time in seconds the player has taken so far, stopped once the objectives are complete
+    pub(crate) timer: f32,
+    /// The real time in seconds passed but not simulated yet, short of a fixed step
+    lag: f32,
+    /// The particles are only for show, so snapshots leave them out
+    #[serde(skip)]
+    pub(crate) particles: ParticleSystem,
+    pub(crate) projectiles: ProjectilePool,
+    pub(crate) fog: Fog,
+    objectives: Vec<Objective>,
+    pub(crate) progress: Progress,
+    /// The gems, fuel cells and oxygen canisters Jetman has collected
+    pub(crate) inventory: Inventory,
+    /// Whether the world is hidden and only the instruments are shown
+    instrument_only: bool,
+    /// Whether the view is cut down to the safe zone, so wider screens don't see more
+    competitive: bool,
+    /// Whether the level is dark, so only what the headlamp and the lights reach is seen
+    dark: bool,
+    /// The time in seconds a good run of the level takes, if the level sets one
+    par_time: Option<f32>,
+    /// Whether practice mode is on, showing a hint line along the route to fly
+    pub practice: bool,
+    /// Whether the stability assist is on, counter-thrusting against drift and fast descents
+    pub assist: bool,
+    /// The route to fly, as shown in practice mode
+    pub(crate) route: Vec<Vec2>,
+    /// The time in seconds until the route is recomputed
+    pub(crate) route_timer: f32,
+    /// How fast the simulation runs compared to real time
+    pub time_scale: f32,
+    /// The time in seconds the objectives stay displayed in the middle of the screen
+    pub(crate) reminder_timer: f32,
+    pub(crate) gravity: Vec2,
+    /// How many times per step the links and the collisions are solved together.
+    /// More iterations keep heavy cargo dragged through terrain from jittering.
+    pub solver_iterations: u32,
+    /// The wind as designed for the level
+    base_wind: Vec2,
+    /// The wind after applying the difficulty modifiers
+    pub(crate) wind: Vec2,
+    /// How fast enemies fire, after applying the difficulty modifiers
+    pub(crate) fire_rate: f32,
+    pub(crate) terrain: Vec<Terrain>,
+    /// The scenery drawn in front of the action
+    decorations: Vec<Decoration>,
+    /// The shake of the camera after hard landings, explosions and hits
+    pub(crate) shake: CameraShake,
+    /// The white flash of the screen after explosions
+    pub(crate) flash: ScreenFlash,
+    /// The framing of the pod and the receiver on the final approach
+    framing: CameraFraming,
+    /// How the player likes the camera, which is only for show
+    #[serde(skip)]
+    camera_options: CameraOptions,
+    /// The camera pulling back to keep the cargo in view
+    auto_zoom: AutoZoom,
+    /// Where the widgets of the HUD are drawn, which is only for show
+    #[serde(skip)]
+    hud_layout: hud::Layout,
+    /// A previous run flown alongside this one. The ghost is only for show,
+    /// so snapshots leave it out.
+    #[serde(skip)]
+    ghost: Option<Arc<Ghost>>,
+    /// Where pods crashed in the analyzer's runs, shown over the level to the
+    /// designers. Only for show, so snapshots leave it out.
+    #[serde(skip)]
+    crash_map: Option<Arc<Heatmap>>,
+    /// The trail the thruster leaves behind, which is only for show
+    #[serde(skip)]
+    pub(crate) trail: Trail,
 }
 
+/// The state of a world at one moment, saved with `World::snapshot` to go back to later,
+/// like predicting ahead and rolling back once the actual input is known
+#[derive(Clone)]
+pub struct WorldState(World);
+
 impl World {
-    /// Create a new game world
+    /// The time in seconds a trigger's text stays on the screen
+    pub const NOTICE_SECONDS: f32 = 4.0;
+    /// The time in seconds simulated by every step of `update`, so worlds given the same
+    /// input play out the same however fast they are rendered
+    pub const FIXED_STEP: f32 = 1.0 / 60.0;
+    /// The most steps `update` takes for one frame, so a long stall doesn't leave the
+    /// simulation ever further behind
+    const MAX_STEPS: usize = 10;
+    /// How many times per step the links and the collisions are solved together,
+    /// unless the level says otherwise
+    pub const SOLVER_ITERATIONS: u32 = 4;
+    /// The most times per step a level may have the links and the collisions solved,
+    /// so a slip of the finger can't bring the game to a crawl
+    pub const MAX_SOLVER_ITERATIONS: u32 = 16;
+    /// The room around the terrain that still belongs to the level
+    const EXTENT_MARGIN: f32 = 400.0;
+
+    /// Create a new game world with a seed taken from the clock
     pub fn new() -> Self {
-        let terrain = vec![Terrain::polygon(generate_ground_poly(
-            screen_width() as i32,
-            screen_height() as i32,
-            12,
-        ))];
-        let camera = Camera2D {
-            zoom: vec2(2.0 / screen_width(), 2.0 / screen_height()),
-            target: vec2(0.0, 0.0),
-            ..Default::default()
-        };
+        World::new_with_seed(random_seed())
+    }
+
+    /// Create a new game world generated from the given seed. Worlds created with
+    /// the same seed and played with the same input turn out the same.
+    pub fn new_with_seed(seed: u64) -> Self {
+        World::from_level(Level::demo(seed))
+    }
+
+    /// Create a game world from a level description
+    pub fn from_level(level: Level) -> Self {
+        let mut jetman = Jetman::new();
+        level.upgrades.fit(&mut jetman);
+        jetman.body.position = level.spawn;
+        jetman.oxygen = level.oxygen.map(Oxygen::new);
+
+        let mut entities = Entities::new();
+        for teleporter in level.teleports {
+            entities.spawn_teleporter(teleporter);
+        }
+        for item in level.items {
+            entities.spawn_item(item);
+        }
+        for turret in level.turrets {
+            entities.spawn_turret(turret);
+        }
+        for pickup in level.pickups {
+            entities.spawn_pickup(pickup);
+        }
+        for anomaly in level.anomalies {
+            entities.spawn_anomaly(anomaly);
+        }
+        let mut terrain = level.terrain;
+        for (id, element) in terrain.iter_mut().enumerate() {
+            element.id = id;
+        }
 
         World {
-            jetman: Jetman::new(),
-            items: vec![Item::new(100.0, 200.0)],
-            teleports: vec![Teleporter::new(Vec2::new(400.0, 300.0))],
-            gravity: Vec2::new(0.0, 0.01),
+            level_name: level.name,
+            level_path: level.path,
+            seed: level.seed,
+            rng: ChaCha12Rng::seed_from_u64(level.seed),
+            jetman,
+            partners: vec![],
+            entities,
+            systems: systems::schedule(),
+            drawers: vec![],
+            funnels: level.funnels,
+            checkpoints: level.checkpoints,
+            switch_arrays: level.switch_arrays,
+            doors: level.doors,
+            scripts: level.scripts,
+            triggers: level.triggers,
+            trigger_events: vec![],
+            notice: None,
+            fuel_pads: level.fuel_pads,
+            respawn_point: level.spawn,
+            lives: level.lives,
+            deaths: 0,
+            water: level.water,
+            explosions: vec![],
+            escort: level.escort_path.map(Escort::new),
+            waves: level.waves,
+            director: level.director,
+            clock: 0.0,
+            timer: 0.0,
+            lag: 0.0,
+            // the particles get a stream of their own, so effects don't shift the gameplay
+            particles: ParticleSystem::new(level.seed.wrapping_add(1)),
+            projectiles: ProjectilePool::new(),
+            fog: level.fog,
+            objectives: level.objectives,
+            progress: Progress::default(),
+            inventory: Inventory::default(),
+            instrument_only: level.instrument_only,
+            competitive: level.competitive,
+            dark: level.dark,
+            par_time: level.par_time,
+            practice: false,
+            assist: false,
+            route: vec![],
+            route_timer: 0.0,
+            time_scale: 1.0,
+            reminder_timer: 0.0,
+            gravity: level.gravity,
+            solver_iterations: level
+                .solver_iterations
+                .clamp(1, Self::MAX_SOLVER_ITERATIONS),
+            base_wind: level.wind,
+            wind: level.wind,
+            fire_rate: 1.0,
             terrain,
-            camera,
+            decorations: level.decorations,
+            shake: CameraShake::new(),
+            flash: ScreenFlash::default(),
+            framing: CameraFraming::new(),
+            camera_options: CameraOptions::default(),
+            auto_zoom: AutoZoom::new(),
+            hud_layout: hud::Layout::default(),
+            ghost: None,
+            crash_map: None,
+            trail: Trail::Plain,
         }
     }
 
-    /// Update the game world
-    pub fn update(&mut self, input: &InputState) {
-        let dt = get_frame_time() * 20.0;
+    /// Run an extra system on every update, after the built-in ones
+    pub fn add_system(&mut self, system: System) {
+        self.systems.push(system);
+    }
 
-        if input.thrust {
-            self.jetman.apply_thrust();
+    /// Queue extra things to draw on every draw, after the built-in ones
+    pub fn add_drawer(&mut self, drawer: Drawer) {
+        self.drawers.push(drawer);
+    }
+
+    /// Update the game world for a frame of the given length in seconds, stepping it
+    /// by `FIXED_STEP` as often as the time passed calls for. The time left over is
+    /// carried into the next update.
+    pub fn update(&mut self, input: &InputState, frame_seconds: f32) {
+        let mut input = input.clone();
+        for _ in 0..self.steps_due(frame_seconds) {
+            self.step(&input, Self::FIXED_STEP);
+            input.release_presses();
         }
-        if input.turn_left {
-            self.jetman.turn_left();
+    }
+
+    /// Take in a frame of the given length in seconds and count the fixed steps now due.
+    /// Updating the world by hand, take that many steps of `FIXED_STEP`.
+    pub fn steps_due(&mut self, frame_seconds: f32) -> usize {
+        self.lag += frame_seconds;
+        let steps = (self.lag / Self::FIXED_STEP) as usize;
+        self.lag -= steps as f32 * Self::FIXED_STEP;
+        if steps > Self::MAX_STEPS {
+            self.lag = 0.0;
         }
-        if input.turn_right {
-            self.jetman.turn_right();
+        steps.min(Self::MAX_STEPS)
+    }
+
+    /// Save the state of the world, to go back to it with `restore`
+    pub fn snapshot(&self) -> WorldState {
+        WorldState(self.clone())
+    }
+
+    /// Go back to a saved state of the world. What is only for show, like the particles,
+    /// the ghost, the trail, the camera options and the HUD layout, stays as it is,
+    /// as do the systems and the drawers.
+    pub fn restore(&mut self, state: &WorldState) {
+        let current = std::mem::replace(self, state.0.clone());
+        self.systems = current.systems;
+        self.drawers = current.drawers;
+        self.particles = current.particles;
+        self.ghost = current.ghost;
+        self.crash_map = current.crash_map;
+        self.trail = current.trail;
+        self.camera_options = current.camera_options;
+        self.hud_layout = current.hud_layout;
+    }
+
+    /// Advance the game world by a frame of the given length in seconds, as it passed
+    /// in real time. Stepping worlds doesn't need a window, so they can run headless.
+    pub fn step(&mut self, input: &InputState, frame_seconds: f32) {
+        if self.is_game_over() {
+            return;
+        }
+        let seconds = frame_seconds * self.time_scale;
+        let step = Step {
+            input,
+            seconds,
+            dt: seconds * 20.0,
+        };
+        for index in 0..self.systems.len() {
+            let system = self.systems[index];
+            system(self, &step);
         }
+        self.shake.update(seconds);
+        self.flash.update(seconds);
+        let receiver = self.approached_receiver();
+        self.framing.update(seconds, receiver);
+        let cargo = self
+            .jetman
+            .linked_item
+            .and_then(|entity| self.entities.position(entity))
+            .filter(|_| self.camera_options.auto_zoom)
+            .map(|position| position - self.jetman.position());
+        let view = camera::VIRTUAL_RESOLUTION / self.camera_options.zoom;
+        self.auto_zoom.update(seconds, cargo, view);
+    }
 
-        // Apply gravity to Jetman
-        self.jetman.apply_force(self.gravity);
+    /// Add a partner pod next to the others, for another player to fly
+    pub fn add_partner(&mut self) {
+        let mut partner = Jetman::new();
+        let offset = 40.0 * (self.partners.len() + 1) as f32;
+        partner.body.position = self.jetman.position() + vec2(offset, 0.0);
+        self.partners.push(partner);
+    }
 
-        // Check if item has been dropped into teleporter
-        if let Some(item_id) = self.jetman.linked_item {
-            let item = &mut self.items[item_id.0];
-            let mut teleporting = false;
-            for teleport in &self.teleports {
-                let diff = item.position() - teleport.position;
-                let distance = diff.length();
-                if distance < 10.0 {
-                    item.body_mut().position = Vec2::new(100.0, 200.0);
-                    item.clear_forces();
-                    teleporting = true;
-                    break;
+    /// Carry out what a script asked for
+    pub(crate) fn carry_out(&mut self, command: Command) {
+        match command {
+            Command::SpawnItem(position) => {
+                self.entities.spawn_item(Item::new(position.x, position.y));
+            }
+            Command::SpawnBomb(position, fuse) => {
+                self.entities
+                    .spawn_item(Item::bomb(position.x, position.y, fuse));
+            }
+            Command::MoveTerrain(id, offset) => {
+                match self.terrain.iter_mut().find(|terrain| terrain.id == id) {
+                    Some(terrain) => terrain.translate(offset),
+                    None => {
+                        let text = format!("script error: there is no terrain {id} left to move");
+                        self.notice = Some((text, World::NOTICE_SECONDS));
+                    }
                 }
             }
-            if teleporting {
-                self.jetman.linked_item = None;
-                self.items.remove(item_id.0);
+            Command::SetGravity(strength) => self.gravity = vec2(0.0, strength),
+            Command::ShowText(text) => self.notice = Some((text, World::NOTICE_SECONDS)),
+            // scripts keep their timers to themselves
+            Command::Every(..) => {}
+        }
+    }
+
+    /// The receiver of the towed item, if the item has come within range of it
+    fn approached_receiver(&self) -> Option<Vec2> {
+        let entity = self.jetman.linked_item?;
+        let towable = self.entities.towables.get(entity)?;
+        let position = self.entities.position(entity)?;
+        self.teleporter_for(towable.kind, position)
+            .filter(|receiver| (*receiver - position).length() < CameraFraming::RANGE)
+    }
+
+    /// The point in the world shown at the given point on the screen
+    pub fn screen_to_world(&self, point: Vec2) -> Vec2 {
+        self.camera().screen_to_world(point)
+    }
+
+    /// The camera centered on the jet pod, or framing it with the receiver on the final
+    /// approach, and shaken by any recent impacts, scaled so the virtual resolution fits
+    /// the current screen size. Wider screens see more of the world to the sides, except
+    /// in competitive levels, where the view is cut down to the safe zone so nobody sees
+    /// further than anybody else.
+    fn camera(&self) -> Camera2D {
+        let view = self.view();
+        let scale = camera::pixels_per_unit(view.size());
+        let mut camera = Camera2D {
+            zoom: vec2(2.0 / view.w, 2.0 / view.h) * scale,
+            viewport: self
+                .competitive
+                .then_some((view.x as i32, 0, view.w as i32, view.h as i32)),
+            ..Default::default()
+        };
+        let pod = self.jetman_position();
+        let pods = self
+            .partners
+            .iter()
+            .map(|partner| partner.position())
+            .fold(Rect::new(pod.x, pod.y, 0.0, 0.0), |pods, other| {
+                pods.combine_with(Rect::new(other.x, other.y, 0.0, 0.0))
+            });
+        // zooming in shows less of the world, which framing has to fit into
+        let zoom = self.camera_options.zoom / self.auto_zoom.scale();
+        camera.zoom *= zoom;
+        self.framing.apply(
+            &mut camera,
+            pods,
+            view.size() / (scale * zoom),
+            !self.competitive,
+        );
+        if self.camera_options.shake {
+            self.shake.apply(&mut camera);
+        }
+        camera
+    }
+
+    /// The part of the world the camera shows, as the smallest axis-aligned rectangle
+    /// around it, so a shaking camera's tilt is covered as well
+    fn seen_area(&self, camera: &Camera2D) -> Rect {
+        let view = self.view();
+        let corners = [
+            view.point(),
+            view.point() + vec2(view.w, 0.0),
+            view.point() + view.size(),
+            view.point() + vec2(0.0, view.h),
+        ]
+        .map(|corner| camera.screen_to_world(corner));
+        let (min, max) = corners.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    /// The part of the screen the world is shown in
+    fn view(&self) -> Rect {
+        if self.competitive {
+            hud::safe_zone()
+        } else {
+            Rect::new(0.0, 0.0, screen_width(), screen_height())
+        }
+    }
+
+    /// Draw the game world, layer by layer, with the HUD on top
+    pub fn draw(&self, input: &InputState) {
+        // clear the screen
+        clear_background(BLACK);
+
+        let camera = self.camera();
+        let mut list = DrawList::new();
+        self.queue_world(&mut list, self.seen_area(&camera));
+        list.push(Layer::Hud, || self.draw_hud(input));
+        for drawer in &self.drawers {
+            drawer(self, &mut list);
+        }
+
+        // flying by instruments, the pilot doesn't get to see the world
+        if !self.instrument_only {
+            let effects = self.effects();
+            if effects.any() {
+                post::draw(
+                    &camera,
+                    self.view(),
+                    effects,
+                    &self.glows(),
+                    |camera, target| {
+                        list.draw(..Layer::Hud);
+                        if self.dark {
+                            lighting::draw(camera, self.view(), &self.lights(), Some(target));
+                        }
+                    },
+                );
+            } else {
+                set_camera(&camera);
+                list.draw(..Layer::Hud);
+                if self.dark {
+                    lighting::draw(&camera, self.view(), &self.lights(), None);
+                }
             }
         }
 
-        // Check for linking with items
-        let jetman_pos = self.jetman.position();
-        for (id, item) in self.items.iter_mut().enumerate() {
-            let diff = item.position() - jetman_pos;
-            let distance = diff.length();
-            if distance < self.jetman.link_distance {
-                self.jetman.linked_item = Some(ItemId(id));
+        set_default_camera();
+        list.draw(Layer::Hud..);
+    }
+
+    /// Draw the heads-up display in screen coordinates
+    fn draw_hud(&self, input: &InputState) {
+        if self.fog.density > 0.0 {
+            let text = format!("Visibility: {:.0}", self.fog.visibility());
+            let at = hud::anchor(Anchor::Top, vec2(-70.0, 30.0));
+            draw_text(&text, at.x, at.y, 20.0, GRAY);
+        }
+        let time = hud::format_time(self.timer);
+        let color = if self.objectives_complete() {
+            settings::good()
+        } else {
+            WHITE
+        };
+        let at = hud::anchor(Anchor::Top, vec2(-30.0, 70.0));
+        draw_text(&time, at.x, at.y, 24.0, color);
+        if let Some(par_time) = self.par_time {
+            let text = format!("Par {}", hud::format_time(par_time));
+            let color = if self.timer > par_time {
+                settings::bad()
+            } else {
+                GRAY
+            };
+            draw_text(&text, at.x, at.y + 20.0, 18.0, color);
+        }
+        if self.practice {
+            let at = hud::anchor(Anchor::Top, vec2(-40.0, 50.0));
+            draw_text("PRACTICE", at.x, at.y, 20.0, YELLOW);
+        }
+        if self.assist {
+            let at = hud::anchor(Anchor::Top, vec2(60.0, 50.0));
+            draw_text("ASSIST", at.x, at.y, 20.0, SKYBLUE);
+        }
+        if self.instrument_only || self.fog.density > 0.3 {
+            self.draw_instruments();
+        }
+        let layout = &self.hud_layout;
+        if layout.controls.shown {
+            hud::draw_controls(layout.controls.position(), input, &self.jetman);
+        }
+        if layout.flight.shown {
+            hud::draw_flight_panel(layout.flight.position(), &self.flight_readings());
+        }
+        if layout.objectives.shown {
+            let at = layout.objectives.position();
+            hud::draw_objectives(at, &self.objectives, &self.progress, &self.jetman);
+        }
+        let at = hud::anchor(Anchor::Bottom, vec2(-30.0, -20.0));
+        draw_text(&format!("Lives: {}", self.lives), at.x, at.y, 20.0, WHITE);
+        if self.inventory.count() > 0 || !self.entities.pickups.is_empty() {
+            self.inventory
+                .draw(hud::anchor(Anchor::Bottom, vec2(60.0, -20.0)));
+        }
+        let at = hud::anchor(Anchor::BottomLeft, vec2(10.0, -20.0));
+        draw_text(&format!("Seed: {}", self.seed), at.x, at.y, 20.0, DARKGRAY);
+        // lines of text centered on the screen
+        let centered = |text: &str, y: f32, size: u16, color: Color| {
+            let width = measure_text(text, None, size, 1.0).width;
+            let at = hud::anchor(Anchor::Center, vec2(-width / 2.0, y));
+            draw_text(text, at.x, at.y, size as f32, color);
+        };
+        if self.reminder_timer > 0.0 {
+            let mut y = -120.0;
+            for objective in &self.objectives {
+                centered(&objective.describe(&self.progress), y, 26, YELLOW);
+                y += 30.0;
             }
         }
+        if let Some((text, _)) = &self.notice {
+            centered(text, -160.0, 26, WHITE);
+        }
+        if self.is_game_over() {
+            centered("GAME OVER - press Enter to try again", 0.0, 30, RED);
+        }
+    }
 
-        // Check for severing link
-        if input.sever_link {
-            if let Some(item_id) = self.jetman.linked_item {
-                self.jetman.linked_item = None;
-                self.items[item_id.0].clear_forces();
+    /// Queue the terrain and all entities of the game world on their layers, leaving
+    /// out the terrain outside the given area, which the camera doesn't show
+    fn queue_world<'a>(&'a self, list: &mut DrawList<'a>, area: Rect) {
+        // all in one batch, drawn in a few goes rather than shape by shape
+        list.push(Layer::Terrain, move || {
+            let mut batch = ShapeBatch::new();
+            for terrain in self.terrain.iter().filter(|t| t.reaches_into(area)) {
+                terrain.draw(area, &mut batch);
+            }
+            batch.draw();
+        });
+        for door in &self.doors {
+            list.push(Layer::Terrain, || door.draw());
+        }
+        // only draw what can be made out through the fog
+        let jetman_position = self.jetman.position();
+        let visible =
+            move |position: Vec2| self.fog.is_visible((position - jetman_position).length());
+        for pad in self.fuel_pads.iter().filter(|p| visible(p.position)) {
+            list.push(Layer::Items, || pad.draw());
+        }
+        for checkpoint in self.checkpoints.iter().filter(|c| visible(c.position)) {
+            list.push(Layer::Items, || checkpoint.draw());
+        }
+        if let Some(director) = &self.director {
+            list.push(Layer::Terrain, || director.draw());
+        }
+        if self.practice {
+            for trigger in &self.triggers {
+                list.push(Layer::Foreground, || trigger.draw());
+            }
+        }
+        // the switches are wired to their vaults, so they are drawn even in the fog
+        for array in &self.switch_arrays {
+            if let Some(door) = array.door.and_then(|door| self.doors.get(door)) {
+                list.push(Layer::Items, move || array.draw(door.area));
+            }
+        }
+        // the teleporters, items and hostiles
+        self.entities.queue_draws(list, visible);
+        if let Some(escort) = self.escort.as_ref().filter(|e| visible(e.position())) {
+            list.push(Layer::Actors, || escort.draw());
+        }
+        // the crashes counted by the analyzer, over the terrain they happened at
+        if let Some(crash_map) = &self.crash_map {
+            list.push(Layer::Foreground, || crash_map.draw());
+        }
+        // the ghost of a previous run flies beneath the real pod
+        if let Some(ghost) = &self.ghost {
+            list.push_at(Layer::Actors, -1, || ghost.draw(self.clock));
+        }
+        // the Jetman, unless his last pod is gone
+        if !self.is_game_over() {
+            list.push(Layer::Actors, || self.jetman.draw());
+        }
+        // the partner pods, ringed to tell them apart, with their beams
+        for partner in &self.partners {
+            list.push(Layer::Actors, || {
+                partner.draw();
+                let position = partner.position();
+                draw_circle_lines(position.x, position.y, 18.0, 1.5, ORANGE);
+            });
+            list.push_at(Layer::Actors, 1, || {
+                let from = partner.position();
+                if let Some(to) = partner
+                    .linked_item
+                    .and_then(|e| self.entities.attachment(e))
+                {
+                    draw_line(from.x, from.y, to.x, to.y, 3.0, ORANGE);
+                }
+            });
+        }
+        // the links between Jetman and the chain of items he's towing
+        list.push_at(Layer::Actors, 1, || {
+            let mut from = self.jetman.position();
+            for entity in self.chain() {
+                let (Some(to), Some(center)) = (
+                    self.entities.attachment(entity),
+                    self.entities.position(entity),
+                ) else {
+                    break;
+                };
+                draw_line(from.x, from.y, to.x, to.y, 3.0, GREEN);
+                from = center;
             }
+        });
+        list.push(Layer::Particles, || self.particles.draw());
+        list.push(Layer::Particles, || self.projectiles.draw());
+        for explosion in &self.explosions {
+            list.push(Layer::Particles, || explosion.draw());
+        }
+        // the hint line in practice mode
+        if self.practice {
+            list.push_at(Layer::Particles, 1, || route::draw_route(&self.route));
+        }
+        // the water in front of anything submerged, the decorations in front of
+        // the water, and the fog in front of everything
+        for water in &self.water {
+            list.push_at(Layer::Foreground, -1, || water.draw());
+        }
+        for decoration in &self.decorations {
+            list.push(Layer::Foreground, || decoration.draw(self.clock));
+        }
+        list.push_at(Layer::Foreground, i32::MAX, move || {
+            self.fog.draw(jetman_position)
+        });
+    }
+
+    /// The lights shining in a dark level: the jet pod's headlamp and the glow
+    /// around it, the teleporters and any explosions
+    fn lights(&self) -> Vec<Light> {
+        let mut lights = vec![];
+        if !self.is_game_over() {
+            let pod = self.jetman.position();
+            lights.push(Light::Cone {
+                origin: pod,
+                angle: self.jetman.heading,
+                spread: 0.45,
+                range: 360.0,
+                color: Color::new(1.0, 0.95, 0.8, 1.0),
+            });
+            lights.push(Light::Glow {
+                center: pod,
+                radius: 60.0,
+                color: Color::new(1.0, 0.95, 0.8, 0.6),
+            });
+        }
+        for (_, teleporter) in self.entities.teleporters.iter() {
+            let color = match teleporter.kind {
+                TeleporterKind::Goal => YELLOW,
+                TeleporterKind::Disposal => ORANGE,
+            };
+            lights.push(Light::Glow {
+                center: teleporter.position,
+                radius: 120.0,
+                color,
+            });
+        }
+        for explosion in &self.explosions {
+            let fade = 1.0 - explosion.age / Explosion::DURATION;
+            lights.push(Light::Glow {
+                center: explosion.position,
+                radius: explosion.radius * 2.0,
+                color: Color::new(1.0, 0.6, 0.2, fade.max(0.0)),
+            });
         }
+        lights
+    }
 
-        // Enforce rigid connection if Jetman is linked to an item
-        if let Some(ItemId(id)) = self.jetman.linked_item {
-            let item = &mut self.items[id];
-            let item_pos = item.position();
-            let delta = item_pos - jetman_pos;
-            let distance = delta.length();
+    /// The post-processing effects the picture of the world goes through, as the
+    /// camera options allow
+    fn effects(&self) -> Effects {
+        let options = &self.camera_options;
+        Effects {
+            crt: options.crt,
+            bloom: options.bloom,
+            flash: if options.flash {
+                self.flash.strength()
+            } else {
+                0.0
+            },
+        }
+    }
 
-            let rest_length = self.jetman.link_distance;
-            if distance != 0.0 {
-                let direction = delta / distance;
-                let correction = direction * (distance - rest_length);
+    /// The bright things that glow with bloom: the flames of the thrusting pods,
+    /// the teleporters and any explosions
+    fn glows(&self) -> Vec<Light> {
+        let pods = std::iter::once(&self.jetman).filter(|_| !self.is_game_over());
+        let mut glows = pods
+            .chain(&self.partners)
+            .filter(|pod| pod.thrusting != 0)
+            .map(|pod| Light::Glow {
+                center: pod.position() - Vec2::from_angle(pod.heading) * 24.0,
+                radius: 28.0,
+                color: Color::new(1.0, 0.6, 0.2, 0.8),
+            })
+            .collect::<Vec<_>>();
+        for (_, teleporter) in self.entities.teleporters.iter() {
+            let color = match teleporter.kind {
+                TeleporterKind::Goal => YELLOW,
+                TeleporterKind::Disposal => ORANGE,
+            };
+            glows.push(Light::Glow {
+                center: teleporter.position,
+                radius: 50.0,
+                color: Color { a: 0.6, ..color },
+            });
+        }
+        for explosion in &self.explosions {
+            let fade = 1.0 - explosion.age / Explosion::DURATION;
+            glows.push(Light::Glow {
+                center: explosion.position,
+                radius: explosion.radius * 1.5,
+                color: Color::new(1.0, 0.7, 0.3, fade.max(0.0)),
+            });
+        }
+        glows
+    }
 
-                // Calculate correction ratio based on masses
-                let total_mass = self.jetman.mass() + item.mass();
-                let jetman_ratio = item.mass() / total_mass;
-                let item_ratio = self.jetman.mass() / total_mass;
+    /// Draw the instrument panel: radar, attitude indicator, altimeter and waypoint compass
+    fn draw_instruments(&self) {
+        let pod = self.jetman.position();
+        let terrain = self
+            .terrain
+            .iter()
+            .flat_map(|terrain| terrain.outline())
+            .collect::<Vec<_>>();
+        let mut contacts = self
+            .entities
+            .renderables
+            .iter()
+            .filter_map(|(entity, renderable)| {
+                Some(Contact {
+                    position: self.entities.position(entity)?,
+                    color: renderable.radar_color(),
+                })
+            })
+            .collect::<Vec<_>>();
+        contacts.extend(self.escort.iter().map(|escort| Contact {
+            position: escort.position(),
+            color: SKYBLUE,
+        }));
 
-                // Correct positions
-                self.jetman.body_mut().position += correction * jetman_ratio;
-                item.body_mut().position -= correction * item_ratio;
+        let corner = hud::anchor(Anchor::BottomRight, vec2(0.0, -100.0));
+        let (right, bottom) = (corner.x, corner.y);
+        hud::draw_radar(
+            vec2(right - 100.0, bottom),
+            80.0,
+            pod,
+            500.0,
+            &terrain,
+            &contacts,
+        );
+        hud::draw_attitude_indicator(
+            vec2(right - 240.0, bottom),
+            40.0,
+            self.jetman.heading,
+            self.gravity,
+        );
+        hud::draw_altimeter(
+            vec2(right - 370.0, bottom - 30.0),
+            self.altitude(),
+            self.jetman.velocity().y,
+        );
+        hud::draw_waypoint_compass(vec2(right - 430.0, bottom), 30.0, pod, self.waypoint());
+    }
 
-                // Optional: also correct velocity along the axis to enforce rigid link
-                let relative_velocity = item.velocity() - self.jetman.velocity();
-                let projected_velocity = relative_velocity.dot(direction);
-                let velocity_correction = direction * projected_velocity;
+    /// What the flight instruments read for the pod
+    fn flight_readings(&self) -> FlightReadings {
+        let jetman = &self.jetman;
+        FlightReadings {
+            speed: jetman.velocity().length(),
+            vertical_speed: jetman.velocity().y,
+            heading: jetman.heading,
+            altitude: self.altitude(),
+            tension: jetman
+                .linked_item
+                .map(|_| (jetman.link_tension, jetman.link_strength)),
+        }
+    }
+
+    /// The height of the pod above the terrain directly below it, if there is any in range
+    pub fn altitude(&self) -> Option<f32> {
+        self.distance_to_terrain(self.jetman.position(), Vec2::Y, 1000.0)
+    }
+
+    /// How far from a point the terrain is when looking in a direction,
+    /// if there is any within `range`
+    pub fn distance_to_terrain(&self, from: Vec2, direction: Vec2, range: f32) -> Option<f32> {
+        self.raycast(from, direction, range).map(|hit| hit.distance)
+    }
+
+    /// Find what overlaps a point
+    pub fn query_point(&self, point: Vec2) -> Overlaps {
+        self.query_circle(point, 0.0)
+    }
 
-                self.jetman.body_mut().velocity += velocity_correction * jetman_ratio;
-                item.body_mut().velocity -= velocity_correction * item_ratio;
+    /// Find what overlaps a circle. Terrain is first sifted by its bounds, so only
+    /// the elements near the circle are tested exactly.
+    pub fn query_circle(&self, center: Vec2, radius: f32) -> Overlaps {
+        let near = |position: Vec2| (position - center).length() < radius + Overlaps::ENTITY_RADIUS;
+        Overlaps {
+            terrain: self
+                .terrain
+                .iter()
+                .enumerate()
+                .filter(|(_, terrain)| circle_overlaps_rect(center, radius, terrain.bounds()))
+                .filter(|(_, terrain)| terrain.overlaps_circle(center, radius))
+                .map(|(index, _)| index)
+                .collect(),
+            items: self
+                .entities
+                .items()
+                .filter(|(_, _, position)| near(*position))
+                .map(|(entity, _, _)| entity)
+                .collect(),
+            teleporters: self
+                .entities
+                .teleporters
+                .iter()
+                .filter(|(_, teleporter)| near(teleporter.position))
+                .map(|(entity, _)| entity)
+                .collect(),
+            water: self
+                .water
+                .iter()
+                .enumerate()
+                .filter(|(_, water)| circle_overlaps_rect(center, radius, water.rect))
+                .map(|(index, _)| index)
+                .collect(),
+        }
+    }
+
+    /// Cast a ray from `origin` in `direction` and find where it first hits any terrain
+    /// or shut door, if it does within `max_distance`
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<Hit> {
+        let doors = self.doors.iter().filter_map(|door| door.terrain());
+        self.terrain
+            .iter()
+            .filter_map(|terrain| terrain.raycast(origin, direction, max_distance))
+            .chain(doors.filter_map(|door| door.raycast(origin, direction, max_distance)))
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// The position of the nearest teleporter accepting the given kind of item,
+    /// or of the door a key opens
+    fn teleporter_for(&self, kind: ItemKind, position: Vec2) -> Option<Vec2> {
+        if let ItemKind::Key { door } = kind {
+            return self.doors.get(door).map(|door| door.area.center());
+        }
+        self.entities
+            .teleporters
+            .iter()
+            .filter(|(_, teleport)| teleport.accepts(kind))
+            .map(|(_, teleport)| teleport.position)
+            .min_by(|a, b| {
+                (*a - position)
+                    .length()
+                    .total_cmp(&(*b - position).length())
+            })
+    }
+
+    /// The stops of the route through the remaining objectives: from the pod to the
+    /// teleporter for the towed item, then picking up and delivering each remaining item
+    pub(crate) fn route_stops(&self) -> Vec<Vec2> {
+        let mut stops = vec![self.jetman.position()];
+        let chain = self.chain();
+        if let Some(&first) = chain.first()
+            && let Some(towable) = self.entities.towables.get(first)
+            && let Some(position) = self.entities.position(first)
+            && let Some(target) = self.teleporter_for(towable.kind, position)
+        {
+            stops.push(target);
+        }
+        let mut remaining = self
+            .entities
+            .items()
+            .filter(|(entity, _, _)| !chain.contains(entity))
+            .map(|(_, towable, position)| (towable.kind, position))
+            .collect::<Vec<_>>();
+        // greedily pick up the closest item next
+        while !remaining.is_empty() {
+            let here = *stops.last().unwrap();
+            let (index, _) = remaining
+                .iter()
+                .enumerate()
+                .min_by(|(_, (_, a)), (_, (_, b))| {
+                    (*a - here).length().total_cmp(&(*b - here).length())
+                })
+                .unwrap();
+            let (kind, position) = remaining.swap_remove(index);
+            if let Some(target) = self.teleporter_for(kind, position) {
+                stops.push(position);
+                stops.push(target);
             }
         }
+        stops
+    }
 
-        // Update physics
-        self.jetman.update(dt);
-        for item in self.items.iter_mut() {
-            item.update(dt);
+    /// Where the pilot should head next: the nearest teleporter accepting the towed item,
+    /// or the nearest item if nothing is being towed
+    pub fn waypoint(&self) -> Option<Vec2> {
+        let pod = self.jetman.position();
+        match self.jetman.linked_item {
+            Some(entity) => {
+                let towable = self.entities.towables.get(entity)?;
+                self.teleporter_for(towable.kind, pod)
+            }
+            None => self
+                .entities
+                .items()
+                .map(|(_, _, position)| position)
+                .min_by(|a, b| (*a - pod).length().total_cmp(&(*b - pod).length())),
         }
+    }
+
+    pub fn jetman_position(&self) -> Vec2 {
+        self.jetman.position()
+    }
+
+    /// The position of the escorted craft while the drones can still go after it
+    pub(crate) fn escort_target(&self) -> Option<Vec2> {
+        self.escort
+            .as_ref()
+            .filter(|escort| !escort.is_destroyed() && !escort.has_arrived())
+            .map(|escort| escort.position())
+    }
 
-        // Check for terrain collisions
-        for terrain in &self.terrain {
-            check_collision(&mut self.jetman.body, terrain);
-            for item in &mut self.items {
-                check_collision(&mut item.body, terrain);
+    /// The chain of items towed by Jetman, starting with the item linked to the pod
+    pub fn chain(&self) -> Vec<Entity> {
+        let mut chain = vec![];
+        let mut next = self.jetman.linked_item;
+        while let Some(entity) = next {
+            // guard against accidental cycles in the chain
+            if chain.contains(&entity) {
+                break;
             }
+            chain.push(entity);
+            next = self.entities.links.get(entity).map(|link| link.next);
         }
+        chain
+    }
 
-        // center the camera on the jet pod
-        let jetman_position = self.jetman_position();
-        self.camera.target.x = jetman_position.x;
-        self.camera.target.y = jetman_position.y;
-        set_camera(&self.camera);
+    /// The items towed by any pod: Jetman's chain and the items the partners hold
+    pub fn towed(&self) -> Vec<Entity> {
+        let mut towed = self.chain();
+        towed.extend(
+            self.partners
+                .iter()
+                .filter_map(|partner| partner.linked_item),
+        );
+        towed
     }
 
-    /// Draw the game world
-    pub fn draw(&self, input: &InputState) {
-        // clear the screen
-        clear_background(BLACK);
+    /// The area the level spans: all of its terrain and some room around it.
+    /// Whatever leaves it is lost for good.
+    pub fn extent(&self) -> Rect {
+        let margin = Self::EXTENT_MARGIN;
+        self.terrain
+            .iter()
+            .map(Terrain::bounds)
+            .reduce(|extent, bounds| extent.combine_with(bounds))
+            .map_or(Rect::new(0.0, 0.0, 0.0, 0.0), |extent| {
+                Rect::new(
+                    extent.x - margin,
+                    extent.y - margin,
+                    extent.w + margin * 2.0,
+                    extent.h + margin * 2.0,
+                )
+            })
+    }
+
+    /// The total mass of the chain Jetman tows
+    pub fn towed_mass(&self) -> f32 {
+        self.chain()
+            .iter()
+            .filter_map(|entity| self.entities.bodies.get(*entity))
+            .map(|body| body.mass)
+            .sum()
+    }
+
+    /// Check whether all objectives of the world have been achieved
+    pub fn objectives_complete(&self) -> bool {
+        self.objectives
+            .iter()
+            .all(|objective| objective.is_complete(&self.progress))
+    }
+
+    /// The player's progress towards the world's objectives
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Check whether any objective of the world can no longer be achieved
+    pub fn objectives_failed(&self) -> bool {
+        self.objectives
+            .iter()
+            .any(|objective| objective.is_failed(&self.progress))
+    }
+
+    /// Blow up Jetman's pod and put a new one at the last checkpoint, if any lives are left
+    pub(crate) fn lose_life(&mut self) {
+        let pod = self.jetman.position();
+        self.particles
+            .burst(pod, 40, 3.0, Color::from_hex(0x4B3CF4));
+        self.release_chain();
+        self.deaths += 1;
+        self.lives = self.lives.saturating_sub(1);
+        if self.lives > 0 {
+            self.jetman.respawn(self.respawn_point);
+        }
+    }
+
+    /// The number of jet pods the player may still lose
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    /// The gems, fuel cells and oxygen canisters Jetman has collected
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    /// The number of jet pods lost so far
+    pub fn deaths(&self) -> u32 {
+        self.deaths
+    }
+
+    /// The time in seconds the player has taken for the level so far
+    pub fn time(&self) -> f32 {
+        self.timer
+    }
+
+    /// The time in seconds since the start of the level
+    pub fn clock(&self) -> f32 {
+        self.clock
+    }
+
+    /// What passed in and out of the triggers during the last update, by the index
+    /// of the trigger
+    pub fn trigger_events(&self) -> &[(usize, Occupant, TriggerEvent)] {
+        &self.trigger_events
+    }
+
+    /// Race against a previous run, or against nobody
+    pub fn set_ghost(&mut self, ghost: Option<Ghost>) {
+        self.ghost = ghost.map(Arc::new);
+    }
+
+    /// Show where pods crashed over the level, or nothing
+    pub fn set_crash_map(&mut self, crash_map: Option<Arc<Heatmap>>) {
+        self.crash_map = crash_map;
+    }
+
+    /// Pick the trail the thruster leaves behind
+    pub fn set_trail(&mut self, trail: Trail) {
+        self.trail = trail;
+    }
+
+    /// Show the world as the player likes the camera. Competitive levels keep the camera
+    /// at its natural zoom, so nobody sees more of the level than anybody else.
+    pub fn set_camera_options(&mut self, mut options: CameraOptions) {
+        if self.competitive {
+            options.zoom = 1.0;
+            options.auto_zoom = false;
+        }
+        self.camera_options = options;
+    }
+
+    /// Lay the HUD out as the player likes it
+    pub fn set_hud_layout(&mut self, layout: hud::Layout) {
+        self.hud_layout = layout;
+    }
+
+    /// Drive the randomness of the world from a new seed from now on
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self.particles = ParticleSystem::new(seed.wrapping_add(1));
+    }
+
+    /// The seed the world was generated with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The name of the level the world was created from
+    pub fn level_name(&self) -> &str {
+        &self.level_name
+    }
+
+    /// The file the level was loaded from, if it was
+    pub fn level_path(&self) -> Option<&Path> {
+        self.level_path.as_deref()
+    }
 
-        // draw the terrain
-        for terrain in &self.terrain {
-            terrain.draw();
+    /// Load the level file again after it was changed, rebuilding the terrain and
+    /// everything in the level while the pods fly on as they were. The time and the
+    /// pods lost carry over; whatever the pods towed is let go of. Difficulty
+    /// modifiers have to be applied again.
+    pub fn reload(&mut self) -> Result<(), String> {
+        let Some(path) = self.level_path.clone() else {
+            return Err("the level wasn't loaded from a file".to_string());
+        };
+        let mut level = level_file::load(&path, self.seed)?;
+        level.lives = self.lives;
+        let mut reloaded = World::from_level(level);
+        reloaded.jetman = self.jetman.clone();
+        reloaded.partners = self.partners.clone();
+        for pod in std::iter::once(&mut reloaded.jetman).chain(&mut reloaded.partners) {
+            pod.linked_item = None;
+            pod.grapple = None;
+            pod.link_tension = 0.0;
         }
-        // draw the teleporters
-        for teleport in &self.teleports {
-            teleport.draw();
+        reloaded.rng = self.rng.clone();
+        reloaded.deaths = self.deaths;
+        reloaded.clock = self.clock;
+        reloaded.timer = self.timer;
+        reloaded.systems = std::mem::take(&mut self.systems);
+        reloaded.drawers = std::mem::take(&mut self.drawers);
+        reloaded.particles = std::mem::take(&mut self.particles);
+        reloaded.practice = self.practice;
+        reloaded.assist = self.assist;
+        reloaded.time_scale = self.time_scale;
+        reloaded.shake = self.shake.clone();
+        reloaded.flash = self.flash.clone();
+        reloaded.framing = self.framing.clone();
+        reloaded.set_camera_options(self.camera_options);
+        reloaded.auto_zoom = self.auto_zoom.clone();
+        reloaded.hud_layout = self.hud_layout.clone();
+        reloaded.ghost = self.ghost.take();
+        reloaded.crash_map = self.crash_map.take();
+        reloaded.trail = self.trail;
+        let file = path.file_name().unwrap_or(path.as_os_str());
+        let notice = format!("Reloaded {}", file.to_string_lossy());
+        reloaded.notice = Some((notice, Self::NOTICE_SECONDS));
+        *self = reloaded;
+        Ok(())
+    }
+
+    /// Layer difficulty modifiers over the level's own parameters
+    pub fn apply_modifiers(&mut self, modifiers: &Modifiers) {
+        self.fire_rate = modifiers.enemy_fire_rate;
+        self.wind = self.base_wind * modifiers.wind_strength;
+        // spread the pads in service evenly over the level
+        let count = self.fuel_pads.len();
+        for (index, pad) in self.fuel_pads.iter_mut().enumerate() {
+            pad.active = (index as f32 + 0.5) / count as f32 <= modifiers.fuel_pads;
+        }
+    }
+
+    /// Show the objectives in the middle of the screen for a while
+    pub fn remind_objectives(&mut self) {
+        self.reminder_timer = 6.0;
+    }
+
+    /// Check whether the player has run out of lives
+    pub fn is_game_over(&self) -> bool {
+        self.lives == 0
+    }
+
+    /// Let go of all items towed by Jetman. The items fly on with the speed of their
+    /// swing: what each moved across its link is kept, what stretched the link is dropped.
+    pub(crate) fn release_chain(&mut self) {
+        let mut anchor = self.jetman.body;
+        for entity in self.chain() {
+            self.entities.links.remove(entity);
+            if let Some(body) = self.entities.bodies.get_mut(entity) {
+                let direction = (body.position - anchor.position).normalize_or_zero();
+                let stretch = (body.velocity - anchor.velocity).dot(direction);
+                anchor = *body;
+                body.velocity -= direction * stretch;
+                body.acceleration = Vec2::ZERO;
+            }
+            self.let_go(entity);
         }
-        // draw the items
-        for item in &self.items {
-            item.draw();
+        self.jetman.linked_item = None;
+    }
+
+    /// Let an item fall freely until it comes to rest, as it does once no pod holds it
+    pub(crate) fn let_go(&mut self, entity: Entity) {
+        if let Some(towable) = self.entities.towables.get_mut(entity) {
+            towable.loose = true;
         }
-        // draw the Jetman
-        self.jetman.draw();
-        // draw the link between Jetman and the item he's linked with
-        if let Some(item_id) = self.jetman.linked_item {
-            let item = &self.items[item_id.0];
-            let jp = self.jetman.position();
-            let ip = item.position();
-            draw_line(jp.x, jp.y, ip.x, ip.y, 3.0, GREEN);
+    }
+
+    /// Remove destroyed turrets with a burst of debris
+    pub(crate) fn remove_destroyed_turrets(&mut self) {
+        let destroyed = self
+            .entities
+            .turrets
+            .iter()
+            .filter(|(_, turret)| turret.is_destroyed())
+            .map(|(entity, turret)| (entity, turret.position))
+            .collect::<Vec<_>>();
+        for (entity, position) in destroyed {
+            self.particles.burst(position, 24, 2.0, RED);
+            self.entities.despawn(entity);
         }
+    }
 
-        // draw thw HUD
-        set_default_camera();
-        visualize_input(input, &self.jetman);
+    /// Check whether the straight line between two points is free of terrain
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        let steps = ((to - from).length() / 4.0).ceil() as usize;
+        (0..=steps).all(|step| {
+            let point = from.lerp(to, step as f32 / steps.max(1) as f32);
+            !self.terrain.iter().any(|t| t.contains(point))
+        })
     }
 
-    pub fn jetman_position(&self) -> Vec2 {
-        self.jetman.position()
+    /// Check whether terrain or a shut door lies right at the point, within
+    /// `Jetman::GRAPPLE_GRIP`, like where a grappling hook is stuck
+    pub(crate) fn solid_at(&self, point: Vec2) -> bool {
+        let grip = Jetman::GRAPPLE_GRIP;
+        let area = Rect::new(point.x - grip, point.y - grip, grip * 2.0, grip * 2.0);
+        let at = |terrain: &Terrain| {
+            terrain.bounds().overlaps(&area) && terrain.overlaps_circle(point, grip)
+        };
+        self.terrain.iter().any(at)
+            || self
+                .doors
+                .iter()
+                .filter_map(|door| door.terrain())
+                .any(|door| at(&door))
+    }
+
+    /// The length of the straight line between two points that runs through terrain
+    pub fn solid_between(&self, from: Vec2, to: Vec2) -> f32 {
+        // only the terrain near the line, lines being solid a little past their bounds
+        let (min, max) = (from.min(to) - 2.0, from.max(to) + 2.0);
+        let area = Rect::new(min.x, min.y, max.x - min.x, max.y - min.y);
+        let near = self
+            .terrain
+            .iter()
+            .filter(|terrain| terrain.bounds().overlaps(&area))
+            .collect::<Vec<_>>();
+        let steps = ((to - from).length() / 4.0).ceil() as usize;
+        let step_length = (to - from).length() / steps.max(1) as f32;
+        let solid = (0..=steps)
+            .filter(|step| {
+                let point = from.lerp(to, *step as f32 / steps.max(1) as f32);
+                near.iter().any(|terrain| terrain.contains(point))
+            })
+            .count();
+        solid as f32 * step_length
+    }
+
+    /// Break the link hanging from the given anchor (Jetman if `None`),
+    /// releasing the rest of the chain with a burst of sparks
+    pub(crate) fn snap_link(&mut self, anchor: Option<Entity>) {
+        let (from, released) = match anchor {
+            Some(entity) => (
+                self.entities.position(entity).unwrap_or_default(),
+                self.entities.links.remove(entity).map(|link| link.next),
+            ),
+            None => (self.jetman.position(), self.jetman.linked_item.take()),
+        };
+        let Some(released) = released else {
+            return;
+        };
+        let to = self.entities.position(released).unwrap_or(from);
+        self.particles.burst((from + to) / 2.0, 24, 2.0, GREEN);
+        // the pull of the link goes with it, as when the whole chain is let go of
+        if let Some(body) = self.entities.bodies.get_mut(released) {
+            body.acceleration = Vec2::ZERO;
+        }
+        self.let_go(released);
+        self.jetman.overload_frames = 0;
+    }
+
+    /// Detonate a bomb at the given position, pushing away and damaging
+    /// nearby bodies, destroying items close by and blasting a crater into the terrain
+    pub(crate) fn explode(&mut self, center: Vec2, radius: f32) {
+        self.progress.detonated += 1;
+        self.blast(center, radius);
+    }
+
+    /// Set off a blast at the given position that isn't a bomb going off,
+    /// with all the force and damage of one
+    pub(crate) fn blast(&mut self, center: Vec2, radius: f32) {
+        self.explosions.push(Explosion::new(center, radius));
+        self.particles.burst(center, 40, 3.0, ORANGE);
+
+        // the blast reaches further than the crater it leaves
+        let reach = radius * 2.0;
+        let push = |body: &mut Body| {
+            let delta = body.position - center;
+            let falloff = 1.0 - delta.length() / reach;
+            if falloff > 0.0 {
+                let direction = delta.try_normalize().unwrap_or(Vec2::NEG_Y);
+                body.velocity += direction * 4.0 * falloff / body.mass;
+            }
+            falloff.max(0.0)
+        };
+        let falloff = push(&mut self.jetman.body);
+        self.jetman.damage(falloff * 0.6);
+        // even distant blasts rattle the view a little
+        self.shake.add_trauma(0.2 + falloff * 0.8);
+        self.flash.add(0.15 + falloff * 0.45);
+
+        for (_, body) in self.entities.bodies.iter_mut() {
+            push(body);
+        }
+        let destroyed = self
+            .entities
+            .items()
+            .filter(|(_, _, position)| (*position - center).length() < radius * 0.6)
+            .map(|(entity, _, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in destroyed {
+            self.destroy_item(entity);
+        }
+
+        self.terrain
+            .retain_mut(|terrain| !terrain.damage(center, radius));
+    }
+
+    /// Destroy an item, which counts as lost if it's cargo that was still to be delivered
+    pub(crate) fn destroy_item(&mut self, entity: Entity) {
+        if self
+            .entities
+            .towables
+            .get(entity)
+            .is_some_and(|towable| towable.kind.is_cargo())
+        {
+            self.progress.lost += 1;
+        }
+        self.remove_item(entity);
     }
+
+    /// Remove an item from the world, closing the gap in the chain it may have been part of
+    pub(crate) fn remove_item(&mut self, entity: Entity) {
+        let next = self.entities.links.get(entity).map(|link| link.next);
+        if self.jetman.linked_item == Some(entity) {
+            self.jetman.linked_item = next;
+        }
+        for partner in self.partners.iter_mut() {
+            if partner.linked_item == Some(entity) {
+                partner.linked_item = None;
+            }
+        }
+        let previous = self
+            .entities
+            .links
+            .iter()
+            .find(|(_, link)| link.next == entity)
+            .map(|(previous, _)| previous);
+        if let Some(previous) = previous {
+            match next {
+                Some(next) => self.entities.links.insert(previous, Link { next }),
+                None => {
+                    self.entities.links.remove(previous);
+                }
+            }
+        }
+        self.entities.despawn(entity);
+    }
+}
+
+/// A seed taken from the clock, for runs nobody asked to reproduce
+pub fn random_seed() -> u64 {
+    (miniquad::date::now() * 1000.0) as u64
 }
 
 impl Default for World {
@@ -210,40 +1461,3 @@ impl Default for World {
         World::new()
     }
 }
-
-/// Draw an HUD visualizing user input
-fn visualize_input(input: &InputState, jetman: &Jetman) {
-    let mut y = 10.0;
-    let x = 10.0;
-    let spacing = 20.0;
-    y += spacing;
-    draw_text("Press W for", x, y, 20.0, GRAY);
-    draw_text(
-        "THRUST",
-        x + 100.0,
-        y,
-        20.0,
-        if input.thrust { WHITE } else { GRAY },
-    );
-    y += spacing;
-    draw_text("Press A to turn     , D to turn", x, y, 20.0, GRAY);
-    draw_text(
-        "LEFT",
-        x + 140.0,
-        y,
-        20.0,
-        if input.turn_left { WHITE } else { GRAY },
-    );
-    draw_text(
-        "RIGHT",
-        x + 280.0,
-        y,
-        20.0,
-        if input.turn_right { WHITE } else { GRAY },
-    );
-
-    y += spacing;
-    if jetman.linked_item.is_some() {
-        draw_text("Press S to sever the tractor beam", x, y, 20.0, WHITE);
-    }
-}
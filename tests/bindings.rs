@@ -0,0 +1,21 @@
+//! The keys the players fly with
+
+use jetman::ui::{Action, Bindings};
+
+#[test]
+fn no_key_does_two_things_for_the_players_sharing_a_keyboard() {
+    let sets = [Bindings::arrows(), Bindings::wasd()];
+    let mut taken = vec![];
+    for bindings in &sets {
+        for action in Action::ALL {
+            for key in bindings.keys(action) {
+                assert!(
+                    !taken.contains(key),
+                    "{key:?} is bound to {} and something else",
+                    action.name()
+                );
+                taken.push(*key);
+            }
+        }
+    }
+}
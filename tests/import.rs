@@ -0,0 +1,144 @@
+//! Levels and terrain read from the files of other tools
+
+use std::io::Cursor;
+
+use jetman::physics::Body;
+use jetman::terrain::check_collision;
+use jetman::{heightmap, svg, tiled};
+use macroquad::prelude::*;
+
+#[test]
+fn numbers_after_a_close_draw_a_line_from_where_the_path_started() {
+    let terrain = svg::parse(
+        r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0 L10 0 L10 10 z 5 5"/></svg>"#,
+    )
+    .unwrap();
+    assert_eq!(terrain.len(), 2);
+    assert!(terrain[0].contains(vec2(8.0, 2.0)));
+    assert_eq!(terrain[1].outline(), vec![(vec2(0.0, 0.0), vec2(5.0, 5.0))]);
+}
+
+#[test]
+fn path_data_that_does_not_parse_is_an_error() {
+    for data in ["M0 0 L10", "M0 0 # 5", "5 5", "M0 0 X 1 2"] {
+        let source = format!(r#"<svg><path d="{data}"/></svg>"#);
+        assert!(svg::parse(&source).is_err(), "{data}");
+    }
+}
+
+#[test]
+fn rectangles_circles_and_ellipses_become_polygons() {
+    let terrain = svg::parse(
+        r#"<svg>
+            <rect x="0" y="0" width="40" height="20"/>
+            <rect x="100" y="0" width="40" height="20" rx="5"/>
+            <circle cx="200" cy="50" r="10"/>
+            <ellipse cx="300" cy="50" rx="30" ry="10"/>
+        </svg>"#,
+    )
+    .unwrap();
+    assert_eq!(terrain.len(), 4);
+    assert!(terrain[0].contains(vec2(39.0, 19.0)));
+    assert!(terrain[1].contains(vec2(120.0, 10.0)));
+    // the rounded corner cuts the very corner off
+    assert!(!terrain[1].contains(vec2(100.5, 0.5)));
+    assert!(terrain[2].contains(vec2(200.0, 58.0)));
+    assert!(!terrain[2].contains(vec2(208.0, 58.0)));
+    assert!(terrain[3].contains(vec2(325.0, 50.0)));
+    assert!(!terrain[3].contains(vec2(300.0, 62.0)));
+}
+
+#[test]
+fn shapes_without_a_size_are_an_error() {
+    for shape in [
+        r#"<rect width="0" height="10"/>"#,
+        r#"<circle cx="5" cy="5"/>"#,
+        r#"<ellipse rx="5" ry="-1"/>"#,
+    ] {
+        assert!(
+            svg::parse(&format!("<svg>{shape}</svg>")).is_err(),
+            "{shape}"
+        );
+    }
+}
+
+/// A Tiled map with a tileset whose tile has a collision shape, around the given layers
+fn tiled_map(layers: &str) -> String {
+    format!(
+        r#"<map tilewidth="10" tileheight="10" infinite="0">
+            <tileset firstgid="1" name="rock">
+                <tile id="0"><objectgroup><object x="0" y="0" width="10" height="10"/></objectgroup></tile>
+            </tileset>
+            {layers}
+        </map>"#
+    )
+}
+
+#[test]
+fn tiled_maps_read_only_their_visible_layers() {
+    let level = tiled::parse(
+        &tiled_map(
+            r#"<layer name="ground" width="2" height="1"><data encoding="csv">1,0</data></layer>
+            <layer name="hidden" width="2" height="1" visible="0"><data encoding="csv">0,1</data></layer>
+            <group name="more">
+                <objectgroup name="rocks"><object x="100" y="100" width="20" height="20"/></objectgroup>
+                <objectgroup name="drafts" visible="0"><object x="200" y="100" width="20" height="20"/></objectgroup>
+            </group>"#,
+        ),
+        1,
+    )
+    .unwrap();
+    let solid = |point| level.terrain.iter().any(|terrain| terrain.contains(point));
+    assert!(solid(vec2(5.0, 5.0)));
+    assert!(!solid(vec2(15.0, 5.0)));
+    assert!(solid(vec2(110.0, 110.0)));
+    assert!(!solid(vec2(210.0, 110.0)));
+    // the tile's collision shape only says what the tile is like, it isn't in the map
+    assert_eq!(level.terrain.len(), 2);
+}
+
+#[test]
+fn infinite_tiled_maps_are_an_error() {
+    let chunked = tiled_map(
+        r#"<layer name="ground" width="2" height="1"><data encoding="csv">
+            <chunk x="0" y="0" width="2" height="1">1,1</chunk>
+        </data></layer>"#,
+    );
+    assert!(tiled::parse(&chunked, 1).is_err());
+    assert!(tiled::parse(&chunked.replace(r#"infinite="0""#, r#"infinite="1""#), 1).is_err());
+}
+
+/// An 8 by 8 pixel PNG of a white block with a black hole of 2 by 2 pixels in the middle
+fn block_with_a_hole() -> Vec<u8> {
+    let image = image::GrayImage::from_fn(8, 8, |x, y| {
+        let hole = (3..5).contains(&x) && (3..5).contains(&y);
+        image::Luma([if hole { 0 } else { 255 }])
+    });
+    let mut bytes = Cursor::new(vec![]);
+    image
+        .write_to(&mut bytes, image::ImageOutputFormat::Png)
+        .unwrap();
+    bytes.into_inner()
+}
+
+#[test]
+fn images_keep_the_holes_in_their_solid_parts_open() {
+    let terrain = heightmap::parse(&block_with_a_hole(), 10.0, 0.5).unwrap();
+    assert_eq!(terrain.len(), 1);
+    let contains = |x, y| terrain[0].contains(vec2(x, y));
+    assert!(contains(15.0, 15.0) && contains(65.0, 65.0));
+    assert!(!contains(40.0, 40.0), "the hole is open");
+    assert!(!contains(-10.0, 40.0) && !contains(90.0, 40.0));
+
+    // next to the seam cut in to the hole, bodies leave through the side of the block
+    let mut body = Body::new(vec2(66.0, 55.0), 1.0);
+    assert!(check_collision(&mut body, &terrain[0]));
+    assert!(!terrain[0].contains(body.position), "{}", body.position);
+}
+
+#[test]
+fn images_with_a_threshold_outside_0_to_1_or_no_png_are_an_error() {
+    assert!(heightmap::parse(&block_with_a_hole(), 10.0, 1.5).is_err());
+    assert!(heightmap::parse(&block_with_a_hole(), 10.0, -0.1).is_err());
+    assert!(heightmap::parse(b"not a png", 10.0, 0.5).is_err());
+}
@@ -0,0 +1,62 @@
+//! Playing over the network, with a server and its clients on this machine
+
+use std::thread;
+use std::time::Duration;
+
+use jetman::headless::FRAME_SECONDS;
+use jetman::level_file;
+use jetman::net::{Client, Server};
+use jetman::ui::InputState;
+
+/// The most frames to wait for something to come over the network
+const PATIENCE: usize = 2000;
+
+/// Step the server and the client a frame at a time, the client's player thrusting,
+/// until `done` holds. Fails if it never does.
+fn play_until(server: &mut Server, client: &mut Client, done: impl Fn(&Server, &Client) -> bool) {
+    let thrust = InputState {
+        thrust: true,
+        ..Default::default()
+    };
+    for _ in 0..PATIENCE {
+        client.send_input(&thrust).unwrap();
+        client.update(FRAME_SECONDS).unwrap();
+        assert!(server.update(FRAME_SECONDS).is_empty());
+        if done(server, client) {
+            return;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("gave up waiting on the network");
+}
+
+#[test]
+fn clients_join_fly_and_see_the_level_start_over_once_the_run_is_lost() {
+    // a bomb with a short fuse right next to the only crate
+    let source = "spawn 100 300\nground 1200 700 16\nitem 320 380\nbomb 330 380 0.5\n\
+                  goal 940 380\ndeliver 1";
+    let level = level_file::parse(source, 1).unwrap();
+    let mut server = Server::bind("127.0.0.1:0", level).unwrap();
+    let mut client = Client::connect(server.local_addr().unwrap()).unwrap();
+
+    // the client's player flies Jetman, and the server's snapshots show the thrust
+    play_until(&mut server, &mut client, |_, client| {
+        client.player() == Some(0)
+            && client
+                .world()
+                .is_some_and(|world| world.jetman.fuel < world.jetman.max_fuel)
+    });
+    assert_eq!(server.runs(), 0);
+
+    // the crate is lost, and the client is told before the level starts over
+    play_until(&mut server, &mut client, |server, client| {
+        server.runs() == 1 && client.run_over() == Some(false)
+    });
+    assert!(!server.world().objectives_failed());
+    // and the world shown moves on to the new run
+    play_until(&mut server, &mut client, |_, client| {
+        client
+            .world()
+            .is_some_and(|world| world.clock() < 1.0 && !world.objectives_failed())
+    });
+}
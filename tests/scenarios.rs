@@ -0,0 +1,454 @@
+//! End-to-end scenarios played through headless worlds: real level files, flown by
+//! scripted tapes or the autopilot, checked for how the run turns out and for anything
+//! in the world going out of whack along the way
+
+use std::fs;
+use std::path::Path;
+
+use jetman::campaign::LevelSummary;
+use jetman::controller::{Autopilot, Controller, WorldView};
+use jetman::headless::{FRAME_SECONDS, Observation};
+use jetman::hud::format_time;
+use jetman::level::Level;
+use jetman::level_file;
+use jetman::modifiers::Modifier;
+use jetman::physics::Oxygen;
+use jetman::pickups::Inventory;
+use jetman::scripting::Script;
+use jetman::snapshot::{Compression, encode};
+use jetman::tape::InputTape;
+use jetman::world::World;
+use macroquad::prelude::vec2;
+
+/// The campaign every scenario draws its levels from
+const CAMPAIGN: &str = "levels/campaign.txt";
+
+/// Load a level of the campaign into a world
+fn world(file: &str, seed: u64) -> World {
+    let level = level_file::load(Path::new("levels").join(file), seed).unwrap();
+    World::from_level(level)
+}
+
+/// Check the things that must hold for any world on every frame
+fn check_invariants(world: &World) {
+    let jetman = &world.jetman;
+    let body = &jetman.body;
+    assert!(body.position.is_finite(), "the pod left the number line");
+    assert!(body.velocity.is_finite(), "the pod's velocity isn't finite");
+    assert!((0.0..=jetman.max_fuel).contains(&jetman.fuel));
+    assert!((0.0..=jetman.max_beam_energy).contains(&jetman.beam_energy));
+    assert!((0.0..=1.0).contains(&jetman.health));
+    assert!(world.time() <= world.clock() + f32::EPSILON);
+    let chain = world.chain();
+    for (index, entity) in chain.iter().enumerate() {
+        assert!(
+            !chain[..index].contains(entity),
+            "the chain holds {entity:?} more than once"
+        );
+    }
+}
+
+/// Play a tape into the world a frame at a time, checking the invariants after each
+fn play(tape: &InputTape, world: &mut World) {
+    for frame in 0..tape.len() {
+        world.step(&tape.input(frame), FRAME_SECONDS);
+        check_invariants(world);
+    }
+}
+
+/// Let the autopilot fly the world until the run is over or `seconds` have passed
+fn autopilot(world: &mut World, seconds: f32) {
+    let mut pilot = Autopilot::new();
+    for _ in 0..(seconds / FRAME_SECONDS) as usize {
+        if world.objectives_complete() || world.objectives_failed() || world.is_game_over() {
+            break;
+        }
+        let input = pilot.input(&WorldView::new(world));
+        world.step(&input, FRAME_SECONDS);
+        check_invariants(world);
+    }
+}
+
+#[test]
+fn every_campaign_level_loads_and_validates() {
+    let campaign = fs::read_to_string(CAMPAIGN).unwrap();
+    let files = campaign
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    for file in files {
+        let level = level_file::load(Path::new("levels").join(file), 1).unwrap();
+        assert_eq!(level.validate(), Vec::<String>::new(), "{file}");
+    }
+}
+
+#[test]
+fn the_demo_level_is_the_same_for_a_seed_without_a_window() {
+    let (first, second) = (Level::demo(3), Level::demo(3));
+    assert_eq!(first.terrain[0].outline(), second.terrain[0].outline());
+    assert_eq!(first.terrain[0].bounds().right(), 800.0);
+}
+
+#[test]
+fn times_are_rounded_before_the_minutes_are_split_off() {
+    assert_eq!(format_time(59.999), "1:00.00");
+    assert_eq!(format_time(83.456), "1:23.46");
+    assert_eq!(format_time(0.0), "0:00.00");
+    assert_eq!(format_time(600.004), "10:00.00");
+}
+
+#[test]
+fn grounds_without_a_width_or_segments_are_an_error() {
+    assert!(level_file::parse("ground 800 600 0", 1).is_err());
+    assert!(level_file::parse("ground 0 600 10", 1).is_err());
+    assert_eq!(
+        level_file::parse("ground 800 600 1", 1)
+            .unwrap()
+            .terrain
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn oxygen_that_runs_out_at_once_is_an_error() {
+    assert!(level_file::parse("oxygen 0", 1).is_err());
+    assert!(level_file::parse("oxygen -5", 1).is_err());
+    assert_eq!(
+        level_file::parse("oxygen 30", 1).unwrap().oxygen,
+        Some(30.0)
+    );
+}
+
+#[test]
+fn funnels_without_depth_or_narrowing_are_an_error() {
+    assert!(level_file::parse("funnel 400 500 120 40 0", 1).is_err());
+    assert!(level_file::parse("funnel 400 500 40 120 80", 1).is_err());
+    assert!(level_file::parse("funnel 400 500 120 120 80", 1).is_err());
+    assert!(level_file::parse("funnel 400 500 120 -40 80", 1).is_err());
+    assert!(level_file::parse("funnel 400 500 120 40 80", 1).is_ok());
+}
+
+#[test]
+fn canisters_and_cells_top_up_tanks_smaller_than_them() {
+    let mut inventory = Inventory {
+        oxygen_canisters: 1,
+        fuel_cells: 1,
+        ..Default::default()
+    };
+    let mut oxygen = Oxygen::new(20.0);
+    oxygen.breathe(19.0);
+    assert_eq!(inventory.use_oxygen_canister(&oxygen), None);
+    oxygen.breathe(1.0);
+    assert_eq!(inventory.use_oxygen_canister(&oxygen), Some(20.0));
+    assert_eq!(inventory.oxygen_canisters, 0);
+
+    assert_eq!(inventory.use_fuel_cell(5.0, 20.0), None);
+    assert_eq!(inventory.use_fuel_cell(0.0, 20.0), Some(20.0));
+    assert_eq!(inventory.fuel_cells, 0);
+}
+
+#[test]
+fn autopilot_completes_the_first_delivery() {
+    let mut world = world("01-first-delivery.level", 1);
+    autopilot(&mut world, 120.0);
+    let observation = Observation::new(&world);
+    assert!(world.objectives_complete());
+    assert_eq!(observation.delivered, 1);
+    assert_eq!(world.deaths(), 0);
+    assert!(world.time() > 0.0);
+    // a quick, clean run earns the base score and a bonus for the time left over,
+    // less the fuel burnt
+    let summary = LevelSummary::new(&world);
+    assert!(summary.score > 1000, "{}", summary.score);
+    assert!(summary.score < 1000 + 180 * 5, "{}", summary.score);
+}
+
+#[test]
+fn autopilot_completes_the_levels_without_bombs_or_tunnels() {
+    // the autopilot knows nothing of bombs or narrow tunnels, so it only
+    // beats the levels that are about towing cargo through the open
+    for file in ["01-first-delivery.level", "03-blind-flight.level"] {
+        let mut world = world(file, 1);
+        autopilot(&mut world, 120.0);
+        assert!(world.objectives_complete(), "{file}");
+        assert_eq!(world.deaths(), 0, "{file}");
+        assert!(LevelSummary::new(&world).score > 0, "{file}");
+    }
+}
+
+#[test]
+fn runs_with_the_same_seed_and_input_come_out_the_same() {
+    let run = || {
+        let mut world = world("01-first-delivery.level", 7);
+        autopilot(&mut world, 120.0);
+        (
+            world.time(),
+            world.jetman_position(),
+            world.jetman.fuel_used,
+        )
+    };
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn rolling_back_and_playing_the_same_input_again_comes_out_the_same() {
+    let mut world = world("02-bomb-squad.level", 3);
+    let tape = InputTape::parse("0-119 thrust\n30-59 turn-left\n90-149 turn-right").unwrap();
+    // uneven frames, as a rendered game gets them
+    let fly = |world: &mut World| {
+        for frame in 0..tape.len() {
+            world.update(&tape.input(frame), 0.013 + frame as f32 % 3.0 * 0.005);
+        }
+        encode(world, Compression::Fast).unwrap()
+    };
+    fly(&mut world);
+    let state = world.snapshot();
+    let first = fly(&mut world);
+    world.restore(&state);
+    assert_eq!(fly(&mut world), first);
+}
+
+#[test]
+fn tapes_keep_every_player_and_the_aim_through_their_text() {
+    let tape = InputTape::parse(
+        "0-29 thrust\n10-19 aim 1.5707964\n20 aim -0.25\np2 5-9 turn-left\np3 8 sever\n40",
+    )
+    .unwrap();
+    assert_eq!(tape.len(), 41);
+    assert_eq!(tape.input(15).aim, Some(1.5707964));
+    assert_eq!(tape.input(20).aim, Some(-0.25));
+    assert_eq!(tape.input(21).aim, None);
+    assert_eq!(InputTape::parse(&tape.to_text()).unwrap(), tape);
+    assert!(InputTape::parse("p2 0 aim 1").is_err());
+    assert!(InputTape::parse("0 aim up").is_err());
+}
+
+#[test]
+fn tapes_note_the_level_and_seed_of_their_run_and_stop_after_an_hour() {
+    let world = world("01-first-delivery.level", 7);
+    let mut tape = InputTape::for_run(&world);
+    tape.record(&Autopilot::new().input(&WorldView::new(&world)));
+    let text = tape.to_text();
+    let read = InputTape::parse(&text).unwrap();
+    assert_eq!(read, tape);
+    assert_eq!(read.seed(), Some(7));
+    assert_eq!(read.level(), world.level_path());
+    assert!(read.level().is_some());
+
+    let last = InputTape::MAX_FRAMES - 1;
+    assert_eq!(
+        InputTape::parse(&last.to_string()).unwrap().len(),
+        InputTape::MAX_FRAMES
+    );
+    assert!(InputTape::parse(&format!("0-{} thrust", InputTape::MAX_FRAMES)).is_err());
+    assert!(InputTape::parse("18446744073709551615 fire").is_err());
+    assert!(InputTape::parse("seed lots").is_err());
+}
+
+#[test]
+fn an_idle_pod_falls_and_stays_on_the_ground() {
+    let mut world = world("01-first-delivery.level", 1);
+    let start = world.jetman_position();
+    play(&InputTape::parse("599").unwrap(), &mut world);
+    assert!(world.jetman_position().y > start.y);
+    // once down, the pod only bounces about a little
+    for _ in 0..600 {
+        world.step(&Default::default(), FRAME_SECONDS);
+        check_invariants(&world);
+        assert!(world.altitude().is_some_and(|altitude| altitude < 40.0));
+    }
+    assert_eq!(world.deaths(), 0);
+    assert!(!world.objectives_complete());
+}
+
+#[test]
+fn thrusting_burns_fuel_and_pushes_the_pod_along() {
+    let mut world = world("01-first-delivery.level", 1);
+    let start = world.jetman_position();
+    let fuel = world.jetman.fuel;
+    // the pod starts out pointing to the right
+    play(&InputTape::parse("0-59 thrust").unwrap(), &mut world);
+    assert!(world.jetman.fuel < fuel);
+    assert!(world.jetman.fuel_used > 0.0);
+    assert!(world.jetman_position().x > start.x);
+}
+
+#[test]
+fn solving_links_and_collisions_together_keeps_dragged_cargo_on_the_beam() {
+    // a heavy crate dragged along the ground: solved once per step, the link and the
+    // ground fight over the crate, and the jittering tension snaps the beam
+    let drag = |iterations: u32| {
+        let source = format!(
+            "spawn 300 340\nrect 0 400 3000 100\ncrate 300 385 120\niterations {iterations}"
+        );
+        let mut world = World::from_level(level_file::parse(&source, 1).unwrap());
+        assert_eq!(world.solver_iterations, iterations);
+        play(
+            &InputTape::parse("0-5 turn-left\n0-299 thrust").unwrap(),
+            &mut world,
+        );
+        WorldView::new(&world).towed_item().is_some()
+    };
+    assert!(!drag(1));
+    assert!(drag(World::SOLVER_ITERATIONS));
+    // however many a level asks for, a step takes a bounded number
+    let level = level_file::parse("iterations 100000", 1).unwrap();
+    assert_eq!(
+        World::from_level(level).solver_iterations,
+        World::MAX_SOLVER_ITERATIONS
+    );
+}
+
+#[test]
+fn queries_find_what_overlaps_a_point_or_a_circle() {
+    let source = "spawn 100 100\nrect 0 400 200 50\ncircle 600 400 20\nitem 100 380\n\
+                  goal 300 380\nwater 400 300 100 100";
+    let world = World::from_level(level_file::parse(source, 1).unwrap());
+    assert_eq!(world.query_point(vec2(50.0, 420.0)).terrain, [0]);
+    assert_eq!(world.query_point(vec2(300.0, 380.0)).teleporters.len(), 1);
+    assert_eq!(world.query_point(vec2(450.0, 350.0)).water, [0]);
+    assert!(world.query_point(vec2(1000.0, 0.0)).is_empty());
+    let near_item = world.query_circle(vec2(100.0, 360.0), 25.0);
+    assert_eq!(near_item.items.len(), 1);
+    assert!(near_item.terrain.is_empty());
+    // within the bounds of the round terrain, but off its rim
+    assert!(world.query_point(vec2(583.0, 383.0)).terrain.is_empty());
+    assert!(
+        world
+            .query_circle(vec2(590.0, 360.0), 10.0)
+            .terrain
+            .is_empty()
+    );
+    assert_eq!(world.query_circle(vec2(590.0, 360.0), 25.0).terrain, [1]);
+}
+
+#[test]
+fn spikes_hold_up_what_lands_on_them_while_tearing_at_it() {
+    let source = "spawn 200 300\nrect 100 305 300 50 spikes";
+    let mut world = World::from_level(level_file::parse(source, 1).unwrap());
+    play(&InputTape::parse("29").unwrap(), &mut world);
+    assert!(world.jetman.damage_taken > 0.0);
+    // a pod that can't be hurt lies on the spikes for good
+    world.jetman.invulnerable = true;
+    play(&InputTape::parse("299").unwrap(), &mut world);
+    assert!(
+        world.jetman_position().y < 305.0,
+        "the pod rests on the spikes"
+    );
+}
+
+#[test]
+fn a_bomb_left_alone_fails_the_level() {
+    let mut world = world("02-bomb-squad.level", 1);
+    // the fuse burns for a minute
+    play(&InputTape::parse("3659").unwrap(), &mut world);
+    assert!(world.objectives_failed());
+    assert!(!world.objectives_complete());
+}
+
+#[test]
+fn losing_cargo_the_delivery_still_needs_fails_the_level() {
+    // a bomb with a short fuse right next to the only crate
+    let source = "spawn 100 300\nground 1200 700 16\nitem 320 380\nbomb 330 380 0.5\n\
+                  goal 940 380\ndeliver 1";
+    let mut world = World::from_level(level_file::parse(source, 1).unwrap());
+    play(&InputTape::parse("119").unwrap(), &mut world);
+    assert!(world.objectives_failed());
+
+    // with a crate to spare, the level goes on
+    let spare = format!("{source}\nitem 700 380");
+    let mut world = World::from_level(level_file::parse(&spare, 1).unwrap());
+    play(&InputTape::parse("119").unwrap(), &mut world);
+    assert!(!world.objectives_failed());
+}
+
+#[test]
+fn opening_a_vault_slides_its_door_away_and_leaves_the_terrain_behind_it() {
+    // a crate rests on the only red switch, and a block stands in the doorway
+    let source = "spawn 100 300\nrect 0 400 1000 100\nrect 520 330 20 20\n\
+                  vault red 500 300 60 100\nswitch red 200 380\nitem 200 380";
+    let mut world = World::from_level(level_file::parse(source, 1).unwrap());
+    let hit = |world: &World| {
+        let hit = world.raycast(vec2(450.0, 340.0), vec2(1.0, 0.0), 200.0);
+        hit.map(|hit| hit.point.x)
+    };
+    assert_eq!(hit(&world), Some(500.0), "the shut door");
+    play(&InputTape::parse("119").unwrap(), &mut world);
+    assert_eq!(hit(&world), Some(520.0), "the block behind the open door");
+}
+
+#[test]
+fn a_trigger_unlocks_the_door_it_names() {
+    let source = "spawn 100 300\nrect 0 400 1000 100\ndoor gate 500 300 60 100\n\
+                  trigger enter 50 250 100 100 door gate";
+    let mut world = World::from_level(level_file::parse(source, 1).unwrap());
+    let blocked = |world: &World| {
+        world
+            .raycast(vec2(450.0, 340.0), vec2(1.0, 0.0), 200.0)
+            .is_some()
+    };
+    assert!(blocked(&world));
+    play(&InputTape::parse("119").unwrap(), &mut world);
+    assert!(!blocked(&world));
+}
+
+#[test]
+fn scripts_move_the_terrain_they_mean_after_other_terrain_is_blasted_away() {
+    // the first block goes up in the blast of a bomb, then the script moves the second
+    let source = "spawn 100 100\nrect 290 360 20 20\nrect 600 300 40 40\nbomb 300 370 0.1";
+    let mut level = level_file::parse(source, 1).unwrap();
+    let script = "every(1.0, \"lift\");\nfn lift() { move_terrain(1, 0, -100); }";
+    level.scripts.push(Script::new(script).unwrap());
+    let mut world = World::from_level(level);
+    let blocked = |world: &World| {
+        world
+            .raycast(vec2(500.0, 320.0), vec2(1.0, 0.0), 200.0)
+            .is_some()
+    };
+    assert!(blocked(&world));
+    play(&InputTape::parse("89").unwrap(), &mut world);
+    assert!(
+        !blocked(&world),
+        "the second block was lifted out of the way"
+    );
+}
+
+#[test]
+fn every_player_grapples_and_the_hooks_come_loose_when_their_wall_moves() {
+    // a wall ahead of the pods, which face it from the start
+    let source = "spawn 200 300\nrect 350 100 20 400";
+    let mut level = level_file::parse(source, 1).unwrap();
+    let script = "every(1.0, \"shift\");\nfn shift() { move_terrain(0, 100, 0); }";
+    level.scripts.push(Script::new(script).unwrap());
+    let mut world = World::from_level(level);
+    world.add_partner();
+    play(
+        &InputTape::parse("0 grapple\np2 0 grapple").unwrap(),
+        &mut world,
+    );
+    assert!(world.jetman.grapple.is_some());
+    assert!(world.partners[0].grapple.is_some());
+    play(&InputTape::parse("89").unwrap(), &mut world);
+    assert!(world.jetman.grapple.is_none());
+    assert!(world.partners[0].grapple.is_none());
+}
+
+#[test]
+fn only_the_magnet_minds_the_walls_between_the_pod_and_an_item() {
+    // a wall 35 units thick between the pod and a crate within reach
+    let source = "spawn 100 300\nrect 105 250 35 100\nitem 145 300";
+    let links = |magnet: bool| {
+        let mut world = World::from_level(level_file::parse(source, 1).unwrap());
+        if magnet {
+            world.jetman.modifiers.add(Modifier::Magnet, 10.0);
+        }
+        play(&InputTape::parse("0").unwrap(), &mut world);
+        WorldView::new(&world).towed_item().is_some()
+    };
+    assert!(links(false), "the beam locks on as it always has");
+    assert!(
+        !links(true),
+        "the magnet's beam doesn't reach through the wall"
+    );
+}
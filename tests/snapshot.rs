@@ -0,0 +1,125 @@
+use jetman::level_file;
+use jetman::replay::{Replay, ReplayPlayer};
+use jetman::snapshot::{self, Compression};
+use jetman::ui::InputState;
+use jetman::world::World;
+
+#[test]
+fn each_compression_marks_its_bytes_and_decodes_back() {
+    let value = (0..1000u32).map(|n| n % 7).collect::<Vec<_>>();
+    let mut markers = vec![];
+    for compression in [Compression::None, Compression::Fast, Compression::Best] {
+        let encoded = snapshot::encode(&value, compression).unwrap();
+        markers.push(encoded[0]);
+        assert_eq!(snapshot::decode::<Vec<u32>>(&encoded).unwrap(), value);
+    }
+    assert_eq!(markers, [0, 1, 3]);
+}
+
+#[test]
+fn values_larger_than_the_limit_are_an_error_even_uncompressed() {
+    let value = vec![0u8; 2000];
+    for compression in [Compression::None, Compression::Fast] {
+        let encoded = snapshot::encode(&value, compression).unwrap();
+        assert!(snapshot::decode_within::<Vec<u8>>(&encoded, 1000).is_err());
+        assert_eq!(
+            snapshot::decode_within::<Vec<u8>>(&encoded, 4000).unwrap(),
+            value
+        );
+    }
+    // a length far beyond the bytes that follow it
+    let mut encoded = vec![0];
+    encoded.extend(u64::MAX.to_le_bytes());
+    assert!(snapshot::decode_within::<Vec<u8>>(&encoded, 1000).is_err());
+}
+
+#[test]
+fn deltas_between_values_of_any_lengths_decode_back() {
+    let short = vec![1u32, 2, 3];
+    let long = (0..50u32).collect::<Vec<_>>();
+    for (base, value) in [
+        (&short, &long),
+        (&long, &short),
+        (&long, &long),
+        (&short, &vec![]),
+    ] {
+        for compression in [Compression::None, Compression::Fast] {
+            let delta = snapshot::encode_delta(base, value, compression).unwrap();
+            assert_eq!(&snapshot::decode_delta(base, &delta).unwrap(), value);
+        }
+    }
+}
+
+#[test]
+fn deltas_to_values_larger_than_the_limit_are_an_error() {
+    let base = vec![0u8; 10];
+    let value = vec![7u8; 2000];
+    let delta = snapshot::encode_delta(&base, &value, Compression::Fast).unwrap();
+    assert!(snapshot::decode_delta_within(&base, &delta, 1000).is_err());
+    assert_eq!(
+        snapshot::decode_delta_within(&base, &delta, 4000).unwrap(),
+        value
+    );
+}
+
+#[test]
+fn deltas_of_a_world_a_while_later_decode_back_and_take_far_less_than_the_world() {
+    let level = level_file::load("levels/02-bomb-squad.level", 1).unwrap();
+    let mut world = World::from_level(level);
+    let base = world.clone();
+    let input = InputState {
+        thrust: true,
+        turn_left: true,
+        ..Default::default()
+    };
+    for _ in 0..Replay::KEYFRAME_INTERVAL {
+        world.step(&input, World::FIXED_STEP);
+    }
+    let full = snapshot::encode(&world, Compression::Fast).unwrap();
+    let delta = snapshot::encode_delta(&base, &world, Compression::Fast).unwrap();
+    let decoded: World = snapshot::decode_delta(&base, &delta).unwrap();
+    assert_eq!(
+        snapshot::encode(&decoded, Compression::None).unwrap(),
+        snapshot::encode(&world, Compression::None).unwrap()
+    );
+    assert!(delta.len() * 2 < full.len());
+}
+
+#[test]
+fn seeking_back_and_forth_across_keyframes_finds_the_world_played_straight_through() {
+    let level = level_file::load("levels/02-bomb-squad.level", 1).unwrap();
+    let mut world = World::from_level(level);
+    let mut replay = Replay::record(&world).with_keyframe_interval(30);
+    for frame in 0..300 {
+        let input = InputState {
+            thrust: frame % 50 < 30,
+            turn_left: frame % 70 < 10,
+            ..Default::default()
+        };
+        world.step(&input, World::FIXED_STEP);
+        replay.push(&input, World::FIXED_STEP, &world);
+    }
+    // the snapshots go through a file as they would when shared
+    let path = std::env::temp_dir().join(format!("jetman-seek-{}.replay", std::process::id()));
+    replay.save(&path, Compression::Fast).unwrap();
+    let replay = Replay::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let encoded =
+        |player: &ReplayPlayer| snapshot::encode(player.world(), Compression::None).unwrap();
+    let mut straight = ReplayPlayer::new(replay.clone());
+    let mut worlds = vec![encoded(&straight)];
+    while straight.tick() < replay.len() {
+        straight.seek(straight.tick() + 1);
+        worlds.push(encoded(&straight));
+    }
+    let mut player = ReplayPlayer::new(replay);
+    for tick in [250, 45, 299, 0, 61, 60, 59, 300, 1, 181, 95] {
+        player.seek(tick);
+        assert_eq!(player.tick(), tick);
+        assert!(
+            encoded(&player) == worlds[tick],
+            "the world differs at tick {tick}"
+        );
+    }
+}
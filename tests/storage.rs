@@ -0,0 +1,28 @@
+//! Saving and reading back through the storage of the platform
+
+use jetman::persistence::{FileSystem, Storage};
+
+#[test]
+fn files_read_back_what_was_written_and_list_their_directories() {
+    let root = std::env::temp_dir().join(format!("jetman-storage-{}", std::process::id()));
+    let storage = FileSystem;
+    let level = root.join("mods/caves/levels/01.level");
+    storage.write(&level, b"spawn 100 100").unwrap();
+    storage
+        .write(&root.join("mods/caves/mod.toml"), b"")
+        .unwrap();
+    assert_eq!(storage.read(&level).unwrap(), b"spawn 100 100");
+    assert!(storage.is_file(&level) && !storage.is_dir(&level));
+    assert!(storage.is_dir(&root.join("mods/caves")));
+    assert!(storage.read(&root.join("missing")).is_err());
+
+    let mut listed = storage.list(&root.join("mods/caves")).unwrap();
+    listed.sort();
+    let names = listed
+        .iter()
+        .filter_map(|path| path.file_name()?.to_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["levels", "mod.toml"]);
+    assert!(listed.iter().all(|path| path.starts_with(&root)));
+    std::fs::remove_dir_all(root).unwrap();
+}
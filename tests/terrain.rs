@@ -0,0 +1,65 @@
+//! The shapes terrain comes in
+
+use jetman::terrain::Terrain;
+use macroquad::prelude::*;
+
+/// A U opening downwards, 30 wide with a 10 wide notch, its corners listed the given way
+fn u_shape(clockwise: bool) -> Terrain {
+    let mut points = [
+        (0.0, 0.0),
+        (30.0, 0.0),
+        (30.0, 30.0),
+        (20.0, 30.0),
+        (20.0, 10.0),
+        (10.0, 10.0),
+        (10.0, 30.0),
+        (0.0, 30.0),
+    ]
+    .map(|(x, y)| vec2(x, y))
+    .to_vec();
+    if !clockwise {
+        points.reverse();
+    }
+    Terrain::polygon(points)
+}
+
+#[test]
+fn concave_polygons_leave_their_notches_open_whichever_way_they_run() {
+    for clockwise in [true, false] {
+        let terrain = u_shape(clockwise);
+        assert!(terrain.contains(vec2(15.0, 5.0)), "the top");
+        assert!(terrain.contains(vec2(5.0, 25.0)) && terrain.contains(vec2(25.0, 25.0)));
+        assert!(!terrain.contains(vec2(15.0, 20.0)), "the notch");
+        assert!(!terrain.contains(vec2(35.0, 15.0)) && !terrain.contains(vec2(15.0, -5.0)));
+    }
+}
+
+#[test]
+fn corners_in_a_straight_line_or_repeated_change_nothing() {
+    let square = |points: &[(f32, f32)]| {
+        Terrain::polygon(points.iter().map(|(x, y)| vec2(*x, *y)).collect())
+    };
+    let plain = square(&[(0.0, 0.0), (20.0, 0.0), (20.0, 20.0), (0.0, 20.0)]);
+    let cluttered = square(&[
+        (0.0, 0.0),
+        (10.0, 0.0),
+        (10.0, 0.0),
+        (20.0, 0.0),
+        (20.0, 10.0),
+        (20.0, 20.0),
+        (0.0, 20.0),
+        (0.0, 0.0),
+    ]);
+    for point in [vec2(10.0, 10.0), vec2(1.0, 19.0), vec2(19.0, 1.0)] {
+        assert!(
+            plain.contains(point) && cluttered.contains(point),
+            "{point}"
+        );
+    }
+    for point in [vec2(25.0, 10.0), vec2(10.0, -5.0), vec2(-1.0, 10.0)] {
+        assert!(
+            !plain.contains(point) && !cluttered.contains(point),
+            "{point}"
+        );
+    }
+}
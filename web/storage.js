@@ -0,0 +1,62 @@
+// Keeps what the game saves in the page's local storage, for web builds.
+// Load it after macroquad's mq_js_bundle.js and before the game's wasm file.
+miniquad_add_plugin({
+    name: "jetman_storage",
+    version: 1,
+    register_plugin: function (importObject) {
+        const prefix = "jetman/";
+        const bytes = (ptr, len) => new Uint8Array(wasm_memory.buffer, ptr, len);
+        const key = (ptr, len) => prefix + new TextDecoder().decode(bytes(ptr, len));
+
+        // the values are base64 text, as local storage only holds strings
+        const load = (ptr, len) => {
+            const saved = window.localStorage.getItem(key(ptr, len));
+            return saved === null ? null : Uint8Array.from(atob(saved), c => c.charCodeAt(0));
+        };
+
+        importObject.env.jetman_storage_len = function (key_ptr, key_len) {
+            const value = load(key_ptr, key_len);
+            return value === null ? -1 : value.length;
+        };
+        importObject.env.jetman_storage_get = function (key_ptr, key_len, out) {
+            const value = load(key_ptr, key_len);
+            if (value !== null) {
+                bytes(out, value.length).set(value);
+            }
+        };
+        importObject.env.jetman_storage_set = function (key_ptr, key_len, value_ptr, len) {
+            // a chunk of characters at a time, as adding them one by one takes quadratic time
+            const value = bytes(value_ptr, len);
+            const chunks = [];
+            for (let start = 0; start < len; start += 0x8000) {
+                chunks.push(String.fromCharCode.apply(null, value.subarray(start, start + 0x8000)));
+            }
+            try {
+                window.localStorage.setItem(key(key_ptr, key_len), btoa(chunks.join("")));
+                return 1;
+            } catch (error) {
+                return 0;
+            }
+        };
+
+        // the keys starting with a prefix, without the game's own, one per line
+        const keys = (ptr, len) => {
+            const start = key(ptr, len);
+            const found = [];
+            for (let index = 0; index < window.localStorage.length; index++) {
+                const saved = window.localStorage.key(index);
+                if (saved.startsWith(start)) {
+                    found.push(saved.slice(prefix.length));
+                }
+            }
+            return new TextEncoder().encode(found.join("\n"));
+        };
+        importObject.env.jetman_storage_keys_len = function (prefix_ptr, prefix_len) {
+            return keys(prefix_ptr, prefix_len).length;
+        };
+        importObject.env.jetman_storage_keys = function (prefix_ptr, prefix_len, out) {
+            const found = keys(prefix_ptr, prefix_len);
+            bytes(out, found.length).set(found);
+        };
+    },
+});